@@ -8,8 +8,9 @@ use gloo::file::{Blob, ObjectUrl};
 use gloo::timers::future::sleep;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use shared::form::validate_lens_display_name;
 use shared::request::{AskClippyRequest, ClippyContext};
-use shared::response::{ChatUpdate, SearchResult};
+use shared::response::{ChatUpdate, LensCrawlStats, SearchResult};
 use thiserror::Error;
 use yew::platform::pinned::mpsc::UnboundedReceiver;
 
@@ -186,6 +187,42 @@ pub struct Lens {
     pub image: Option<String>,
     pub description: Option<String>,
     pub embedded_configuration: Option<EmbedConfiguration>,
+    #[serde(default)]
+    pub crawl_settings: LensCrawlSettings,
+}
+
+/// Delay between requests used when a lens doesn't override it, the app's
+/// default `domain_crawl_delay_ms`.
+pub const DEFAULT_CRAWL_DELAY_MS: u32 = 0;
+/// Concurrent crawls used when a lens doesn't override it, the app's default
+/// `inflight_domain_limit`.
+pub const DEFAULT_CRAWL_CONCURRENCY: u32 = 2;
+/// Pages crawled per domain when a lens doesn't override it, the app's
+/// default `domain_crawl_limit`.
+pub const DEFAULT_DOMAIN_CRAWL_LIMIT: u32 = 500_000;
+
+/// Per-lens overrides for how its sources are crawled. Unset values inherit
+/// the global defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LensCrawlSettings {
+    pub crawl_delay_ms: Option<u32>,
+    pub concurrency: Option<u32>,
+    pub domain_crawl_limit: Option<u32>,
+}
+
+impl LensCrawlSettings {
+    pub fn effective_crawl_delay_ms(&self) -> u32 {
+        self.crawl_delay_ms.unwrap_or(DEFAULT_CRAWL_DELAY_MS)
+    }
+
+    pub fn effective_concurrency(&self) -> u32 {
+        self.concurrency.unwrap_or(DEFAULT_CRAWL_CONCURRENCY)
+    }
+
+    pub fn effective_domain_crawl_limit(&self) -> u32 {
+        self.domain_crawl_limit
+            .unwrap_or(DEFAULT_DOMAIN_CRAWL_LIMIT)
+    }
 }
 
 /// Chat history for a single chat session
@@ -229,6 +266,14 @@ pub enum LensAddDocType {
     GDrive {
         token: String,
     },
+    /// RSS/Atom feed, each entry in the feed is crawled. Saved to the lens
+    /// definition's `feeds`, which installed lenses fetch again every
+    /// `poll_interval_minutes` to pick up new entries. The feed's own
+    /// summary is only indexed w/ `include_summary`.
+    Feed {
+        include_summary: bool,
+        poll_interval_minutes: u32,
+    },
     RssFeed {
         preferred_model: PreferredModel,
     },
@@ -259,6 +304,23 @@ pub struct LensSource {
     pub url: String,
     pub status: String,
     pub doc_uuid: String,
+    /// Status of the source's URL in the crawl queue (Queued, Processing,
+    /// Completed or Failed), if it has been queued.
+    #[serde(default)]
+    pub crawl_status: Option<String>,
+    /// Search ranking multiplier for the source's documents when searching
+    /// the lens, 1.0 leaves scores unchanged.
+    #[serde(default = "LensSource::default_boost")]
+    pub boost: f32,
+}
+
+impl LensSource {
+    pub const MIN_BOOST: f32 = 0.1;
+    pub const MAX_BOOST: f32 = 5.0;
+
+    pub fn default_boost() -> f32 {
+        1.0
+    }
 }
 
 #[derive(Deserialize)]
@@ -274,14 +336,35 @@ pub struct SourceValidationResponse {
 pub enum ApiError {
     #[error("You need to sign in.")]
     Unauthorized,
+    /// The request never got a response, e.g. the server is down or the
+    /// user is offline.
+    #[error("Can't reach Spyglass server: {0}")]
+    Unreachable(reqwest::Error),
     #[error("Unable to make request: {0}")]
-    RequestError(#[from] reqwest::Error),
+    RequestError(reqwest::Error),
     #[error("Api Error: {0}")]
     ClientError(ApiErrorMessage),
     #[error("Unable to make request: {0}")]
     Other(String),
 }
 
+impl ApiError {
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, ApiError::Unreachable(_))
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        // Transport failures never get a status back from the server
+        if err.status().is_none() && (err.is_request() || err.is_timeout()) {
+            ApiError::Unreachable(err)
+        } else {
+            ApiError::RequestError(err)
+        }
+    }
+}
+
 #[derive(Clone, Deserialize, Debug)]
 pub struct ApiErrorMessage {
     pub code: u16,
@@ -348,7 +431,7 @@ impl ApiClient {
             }
             Err(error) => {
                 log::error!("Error requesting download {:?}", error);
-                Err(ApiError::Unauthorized)
+                Err(error.into())
             }
         }
     }
@@ -498,6 +581,45 @@ impl ApiClient {
         }
     }
 
+    /// Boosts or buries the source's documents when searching the lens. The
+    /// boost is saved in the lens definition's `source_boosts` under the
+    /// source's domain, which is what installed lenses are ranked by.
+    pub async fn lens_update_source_boost(
+        &self,
+        lens: &str,
+        source: &LensSource,
+        boost: f32,
+    ) -> Result<(), ApiError> {
+        let boost = boost.clamp(LensSource::MIN_BOOST, LensSource::MAX_BOOST);
+        let domain = url::Url::parse(&source.url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()))
+            .unwrap_or_default();
+        match &self.token {
+            Some(token) => {
+                let resp = self
+                    .client
+                    .patch(format!(
+                        "{}/user/lenses/{}/source/{}",
+                        self.endpoint, lens, source.doc_uuid
+                    ))
+                    .bearer_auth(token)
+                    .json(&serde_json::json!({ "boost": boost, "domain": domain }))
+                    .send()
+                    .await?;
+
+                match resp.error_for_status_ref() {
+                    Ok(_) => Ok(()),
+                    Err(err) => match resp.json::<ApiErrorMessage>().await {
+                        Ok(msg) => Err(ApiError::ClientError(msg)),
+                        Err(_) => Err(ApiError::RequestError(err)),
+                    },
+                }
+            }
+            None => Err(ApiError::Unauthorized),
+        }
+    }
+
     pub async fn validate_lens_source(
         &self,
         lens: &str,
@@ -532,6 +654,7 @@ impl ApiClient {
     }
 
     pub async fn lens_update(&self, lens: &str, display_name: &str) -> Result<(), ApiError> {
+        let display_name = validate_lens_display_name(display_name).map_err(ApiError::Other)?;
         match &self.token {
             Some(token) => {
                 match self
@@ -551,6 +674,49 @@ impl ApiClient {
         }
     }
 
+    /// Pages, bytes & time spent crawling the lens so far.
+    pub async fn lens_stats(&self, lens: &str) -> Result<LensCrawlStats, ApiError> {
+        match &self.token {
+            Some(token) => Ok(self
+                .client
+                .get(format!("{}/user/lenses/{}/stats", self.endpoint, lens))
+                .bearer_auth(token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<LensCrawlStats>()
+                .await?),
+            None => Err(ApiError::Unauthorized),
+        }
+    }
+
+    /// Saves the lens's crawl delay, concurrency & page limit overrides as the
+    /// lens's `crawl_settings` w/ `PATCH /user/lenses/{lens}`, the route `lens_update`
+    /// uses. Unset values are sent as `null`, clearing the override.
+    pub async fn lens_update_crawl_settings(
+        &self,
+        lens: &str,
+        settings: &LensCrawlSettings,
+    ) -> Result<(), ApiError> {
+        match &self.token {
+            Some(token) => {
+                match self
+                    .client
+                    .patch(format!("{}/user/lenses/{}", self.endpoint, lens))
+                    .bearer_auth(token)
+                    .json(&serde_json::json!({ "crawl_settings": settings }))
+                    .send()
+                    .await?
+                    .error_for_status()
+                {
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(ApiError::RequestError(err)),
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
     pub async fn get_user_data(&self) -> Result<UserData, ApiError> {
         match &self.token {
             Some(token) => {
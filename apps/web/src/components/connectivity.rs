@@ -0,0 +1,49 @@
+use ui_components::icons;
+use yew::prelude::*;
+
+/// First retry after a failed request, doubled for every attempt after.
+const RETRY_BASE_MS: u32 = 1_000;
+const RETRY_MAX_MS: u32 = 30_000;
+
+/// Whether the Spyglass server could be reached the last time we tried.
+/// Provided by the app so any page can report on & react to connectivity.
+#[derive(Clone, PartialEq)]
+pub struct ServerStatus {
+    pub is_reachable: bool,
+    /// Pages report whether their last request made it to the server.
+    pub report: Callback<bool>,
+}
+
+impl Default for ServerStatus {
+    fn default() -> Self {
+        Self {
+            is_reachable: true,
+            report: Callback::noop(),
+        }
+    }
+}
+
+/// How long to wait before retrying after `attempt` failed retries.
+pub fn retry_delay_ms(attempt: u32) -> u32 {
+    RETRY_BASE_MS
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(RETRY_MAX_MS)
+}
+
+#[function_component(ServerUnreachableBanner)]
+pub fn server_unreachable_banner() -> Html {
+    let status = use_context::<ServerStatus>().unwrap_or_default();
+    if status.is_reachable {
+        return html! {};
+    }
+
+    html! {
+        <div
+            class="fixed top-0 inset-x-0 z-50 flex flex-row items-center justify-center gap-2 bg-red-700 text-white text-sm font-semibold p-2"
+            role="alert"
+        >
+            <icons::RefreshIcon height="h-4" width="w-4" animate_spin={true} />
+            <span>{"Can't reach Spyglass server. Retrying..."}</span>
+        </div>
+    }
+}
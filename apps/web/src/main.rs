@@ -15,7 +15,10 @@ mod metrics;
 mod pages;
 mod schema;
 mod utils;
-use components::nav::NavBar;
+use components::{
+    connectivity::{ServerStatus, ServerUnreachableBanner},
+    nav::NavBar,
+};
 use pages::{
     dashboard::Dashboard, discover::DiscoverPage, landing::LandingPage,
     lens_editor::CreateLensPage, AppPage,
@@ -105,6 +108,7 @@ pub enum Msg {
     CheckAuth,
     LoadUserData,
     SetSelectedLens(Lens),
+    SetServerReachable(bool),
     LensDeleted(Lens),
     UpdateAuth(AuthStatus),
     UpdateUserData(UserData),
@@ -114,6 +118,7 @@ pub struct App {
     auth_status: AuthStatus,
     current_lens: Option<String>,
     session_uuid: String,
+    server_reachable: bool,
 }
 
 impl Component for App {
@@ -152,6 +157,7 @@ impl Component for App {
             },
             current_lens: None,
             session_uuid: uuid::Uuid::new_v4().hyphenated().to_string(),
+            server_reachable: true,
         }
     }
 
@@ -209,8 +215,12 @@ impl Component for App {
                     let api = auth_status.get_client();
                     if auth_status.is_authenticated {
                         log::info!("grabbing logged in user's data");
-                        if let Ok(user_data) = api.get_user_data().await {
-                            link.send_message(Msg::UpdateUserData(user_data));
+                        match api.get_user_data().await {
+                            Ok(user_data) => link.send_message(Msg::UpdateUserData(user_data)),
+                            Err(err) if err.is_unreachable() => {
+                                link.send_message(Msg::SetServerReachable(false))
+                            }
+                            Err(err) => log::error!("Unable to load user data: {err}"),
                         }
                     }
                 });
@@ -220,6 +230,11 @@ impl Component for App {
                 self.current_lens = Some(lens.name);
                 true
             }
+            Msg::SetServerReachable(is_reachable) => {
+                let changed = self.server_reachable != is_reachable;
+                self.server_reachable = is_reachable;
+                changed
+            }
             Msg::LensDeleted(_lens) => {
                 link.send_message(Msg::LoadUserData);
                 true
@@ -231,6 +246,7 @@ impl Component for App {
             }
             Msg::UpdateUserData(user_data) => {
                 self.auth_status.user_data = Some(user_data);
+                self.server_reachable = true;
                 true
             }
         }
@@ -307,29 +323,40 @@ impl Component for App {
             }
         };
 
+        let server_status = ServerStatus {
+            is_reachable: self.server_reachable,
+            report: link.callback(Msg::SetServerReachable),
+        };
+
         if embedded {
             log::error!("rendering embedded");
             html! {
                 <ContextProvider<AuthStatus> context={self.auth_status.clone()}>
-                    <BrowserRouter>
-                        <div class="flex flex-col sm:flex-row">
-                            <Switch<EmbeddedRoute> render={switch_embedded} />
-                        </div>
-                    </BrowserRouter>
+                    <ContextProvider<ServerStatus> context={server_status}>
+                        <BrowserRouter>
+                            <ServerUnreachableBanner />
+                            <div class="flex flex-col sm:flex-row">
+                                <Switch<EmbeddedRoute> render={switch_embedded} />
+                            </div>
+                        </BrowserRouter>
+                    </ContextProvider<ServerStatus>>
                 </ContextProvider<AuthStatus>>
             }
         } else {
             html! {
                 <ContextProvider<AuthStatus> context={self.auth_status.clone()}>
-                    <BrowserRouter>
-                        <div class="flex flex-col sm:flex-row">
-                            <NavBar
-                                current_lens={self.current_lens.clone()}
-                                session_uuid={self.session_uuid.clone()}
-                            />
-                            <Switch<Route> render={switch} />
-                        </div>
-                    </BrowserRouter>
+                    <ContextProvider<ServerStatus> context={server_status}>
+                        <BrowserRouter>
+                            <ServerUnreachableBanner />
+                            <div class="flex flex-col sm:flex-row">
+                                <NavBar
+                                    current_lens={self.current_lens.clone()}
+                                    session_uuid={self.session_uuid.clone()}
+                                />
+                                <Switch<Route> render={switch} />
+                            </div>
+                        </BrowserRouter>
+                    </ContextProvider<ServerStatus>>
                 </ContextProvider<AuthStatus>>
             }
         }
@@ -21,9 +21,13 @@ extern "C" {
     pub async fn create_picker(cb: &Closure<dyn Fn(JsValue, JsValue)>) -> Result<(), JsValue>;
 }
 
+/// How often a new feed is fetched for new entries, unless changed.
+const DEFAULT_POLL_INTERVAL_MINUTES: &str = "60";
+
 #[derive(Clone, EnumIter, Display, PartialEq, Eq)]
 pub enum AddSourceTabs {
     Website,
+    Feed,
     Podcast,
     GDrive,
     File,
@@ -35,6 +39,9 @@ pub struct AddSourceComponent {
     selected_tab: AddSourceTabs,
     _context_listener: ContextHandle<AuthStatus>,
     _feed_input_ref: NodeRef,
+    _rss_input_ref: NodeRef,
+    _rss_poll_ref: NodeRef,
+    _rss_summary_ref: NodeRef,
     _url_input_ref: NodeRef,
     _url_crawl_ref: NodeRef,
 }
@@ -42,6 +49,7 @@ pub struct AddSourceComponent {
 pub enum Msg {
     AddUrl,
     AddFeed,
+    AddRssFeed,
     ChangeToTab(AddSourceTabs),
     EmitError(String),
     EmitUpdate,
@@ -57,6 +65,9 @@ pub struct AddSourceComponentProps {
     pub on_error: Callback<String>,
     #[prop_or_default]
     pub on_update: Callback<()>,
+    /// When set, adding sources is disabled & this is shown instead.
+    #[prop_or_default]
+    pub disabled_reason: Option<String>,
 }
 
 impl Component for AddSourceComponent {
@@ -78,6 +89,9 @@ impl Component for AddSourceComponent {
             selected_tab: AddSourceTabs::Website,
             _context_listener: context_listener,
             _feed_input_ref: NodeRef::default(),
+            _rss_input_ref: NodeRef::default(),
+            _rss_poll_ref: NodeRef::default(),
+            _rss_summary_ref: NodeRef::default(),
             _url_input_ref: NodeRef::default(),
             _url_crawl_ref: NodeRef::default(),
         }
@@ -111,6 +125,43 @@ impl Component for AddSourceComponent {
                 }
                 true
             }
+            Msg::AddRssFeed => {
+                if let (Some(rss_input), Some(poll_input), Some(summary_checkbox)) = (
+                    self._rss_input_ref.cast::<HtmlInputElement>(),
+                    self._rss_poll_ref.cast::<HtmlInputElement>(),
+                    self._rss_summary_ref.cast::<HtmlInputElement>(),
+                ) {
+                    let url = match url::Url::parse(&rss_input.value()) {
+                        Ok(url) => url,
+                        Err(_) => {
+                            link.send_message(Msg::EmitError("Invalid URL".into()));
+                            return false;
+                        }
+                    };
+
+                    let poll_interval_minutes = match poll_input.value().trim().parse::<u32>() {
+                        Ok(minutes) if minutes > 0 => minutes,
+                        _ => {
+                            link.send_message(Msg::EmitError(
+                                "Poll interval must be a number of minutes".into(),
+                            ));
+                            return false;
+                        }
+                    };
+
+                    let source = LensAddDocument {
+                        url: url.to_string(),
+                        doc_type: LensAddDocType::Feed {
+                            include_summary: summary_checkbox.checked(),
+                            poll_interval_minutes,
+                        },
+                    };
+
+                    self.adding_in_progress = true;
+                    self.add_source(&props.lens_identifier, source, link, true);
+                }
+                true
+            }
             Msg::AddUrl => {
                 if let (Some(url_input), Some(crawl_checkbox)) = (
                     self._url_input_ref.cast::<HtmlInputElement>(),
@@ -164,6 +215,16 @@ impl Component for AddSourceComponent {
                     input.set_value("");
                 }
 
+                if let Some(input) = self._rss_input_ref.cast::<HtmlInputElement>() {
+                    input.set_value("");
+                }
+                if let Some(input) = self._rss_poll_ref.cast::<HtmlInputElement>() {
+                    input.set_value(DEFAULT_POLL_INTERVAL_MINUTES);
+                }
+                if let Some(input) = self._rss_summary_ref.cast::<HtmlInputElement>() {
+                    input.set_checked(false);
+                }
+
                 props.on_update.emit(());
                 true
             }
@@ -235,6 +296,8 @@ impl Component for AddSourceComponent {
             })
             .collect::<Html>();
 
+        let disabled = self.adding_in_progress || props.disabled_reason.is_some();
+
         html! {
             <div class="flex flex-col gap-4">
                 <div>
@@ -242,10 +305,17 @@ impl Component for AddSourceComponent {
                         {tabs}
                     </ul>
                     <div class="px-2 md:px-8 py-2">
+                        {if let Some(reason) = &props.disabled_reason {
+                            html! { <div class="text-xs text-red-400 pb-2">{reason}</div> }
+                        } else {
+                            html! {}
+                        }}
                         {match self.selected_tab {
-                            AddSourceTabs::Website => self.view_website_tab(link),
-                            AddSourceTabs::Podcast => self.view_podcast_tab(link),
-                            AddSourceTabs::GDrive => self.view_gdrive_tab(link),
+                            AddSourceTabs::Website => self.view_website_tab(link, disabled),
+                            AddSourceTabs::Feed => self.view_feed_tab(link, disabled),
+                            AddSourceTabs::Podcast => self.view_podcast_tab(link, disabled),
+                            AddSourceTabs::GDrive => self.view_gdrive_tab(link, disabled),
+                            AddSourceTabs::File if props.disabled_reason.is_some() => html! {},
                             AddSourceTabs::File => html! {
                                 <FileUpload lens_identifier={props.lens_identifier.clone()} on_upload={ctx.link().callback(|_| Msg::EmitUpdate)}/>
                             }
@@ -258,7 +328,7 @@ impl Component for AddSourceComponent {
 }
 
 impl AddSourceComponent {
-    fn view_website_tab(&self, link: &Scope<AddSourceComponent>) -> Html {
+    fn view_website_tab(&self, link: &Scope<AddSourceComponent>, disabled: bool) -> Html {
         html! {
             <div>
                 <div class="text-xs text-neutral-400 pb-2">
@@ -284,7 +354,7 @@ impl AddSourceComponent {
                         size={BtnSize::Sm}
                         _type={BtnType::Primary}
                         onclick={link.callback(|_| Msg::AddUrl)}
-                        disabled={self.adding_in_progress}
+                        disabled={disabled}
                     >
                         {if self.adding_in_progress {
                             html! {
@@ -303,7 +373,64 @@ impl AddSourceComponent {
         }
     }
 
-    fn view_podcast_tab(&self, link: &Scope<AddSourceComponent>) -> Html {
+    fn view_feed_tab(&self, link: &Scope<AddSourceComponent>, disabled: bool) -> Html {
+        html! {
+            <div>
+                <div class="text-xs text-neutral-400 pb-2">
+                    {"Add every entry from an RSS or Atom feed"}
+                </div>
+                <div class="flex flex-row gap-4 items-center">
+                    <input
+                        ref={self._rss_input_ref.clone()}
+                        type="text"
+                        class="rounded p-2 text-sm text-neutral-800 flex-grow"
+                        placeholder="https://example.com/atom.xml"
+                    />
+                    <div>
+                        <label class="flex flex-row gap-2 items-center text-sm">
+                            {"Poll every"}
+                            <input
+                                ref={self._rss_poll_ref.clone()}
+                                type="number"
+                                min="1"
+                                value={DEFAULT_POLL_INTERVAL_MINUTES}
+                                class="rounded p-2 text-sm text-neutral-800 w-20"
+                            />
+                            {"min"}
+                        </label>
+                    </div>
+                    <div>
+                        <label class="flex flex-row gap-2 text-sm">
+                            <input
+                                ref={self._rss_summary_ref.clone()}
+                                type="checkbox"
+                            />
+                            {"Index summaries"}
+                        </label>
+                    </div>
+                    <Btn
+                        disabled={disabled}
+                        size={BtnSize::Sm}
+                        _type={BtnType::Primary}
+                        onclick={link.callback(|_| Msg::AddRssFeed)}>
+                        {if self.adding_in_progress {
+                            html! {
+                                <icons::RefreshIcon
+                                    width="w-4"
+                                    height="h-4"
+                                    animate_spin={self.adding_in_progress}
+                                />
+                            }
+                        } else {
+                            html! { <div>{"Add RSS feed"}</div> }
+                        }}
+                    </Btn>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_podcast_tab(&self, link: &Scope<AddSourceComponent>, disabled: bool) -> Html {
         html! {
             <div>
                 <div class="text-xs text-neutral-400 pb-2">
@@ -317,7 +444,7 @@ impl AddSourceComponent {
                         placeholder="https://example.com/feed.rss"
                     />
                     <Btn
-                        disabled={self.adding_in_progress}
+                        disabled={disabled}
                         size={BtnSize::Sm}
                         _type={BtnType::Primary}
                         onclick={link.callback(|_| Msg::AddFeed)}>
@@ -338,12 +465,12 @@ impl AddSourceComponent {
         }
     }
 
-    fn view_gdrive_tab(&self, link: &Scope<AddSourceComponent>) -> Html {
+    fn view_gdrive_tab(&self, link: &Scope<AddSourceComponent>, disabled: bool) -> Html {
         html! {
             <div>
                 <Btn
                     _type={BtnType::Primary}
-                    disabled={self.adding_in_progress}
+                    disabled={disabled}
                     onclick={link.callback(|_| Msg::OpenCloudFilePicker)}
                     size={BtnSize::Sm}
                 >
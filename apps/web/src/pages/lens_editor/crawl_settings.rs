@@ -0,0 +1,166 @@
+use ui_components::btn::{Btn, BtnSize, BtnType};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::client::{
+    LensCrawlSettings, DEFAULT_CRAWL_CONCURRENCY, DEFAULT_CRAWL_DELAY_MS,
+    DEFAULT_DOMAIN_CRAWL_LIMIT,
+};
+
+const MAX_CRAWL_DELAY_MS: u32 = 60_000;
+const MAX_CRAWL_CONCURRENCY: u32 = 10;
+const MAX_DOMAIN_CRAWL_LIMIT: u32 = 1_000_000;
+
+#[derive(Properties, PartialEq)]
+pub struct CrawlSettingsProps {
+    pub settings: LensCrawlSettings,
+    #[prop_or_default]
+    pub is_saving: bool,
+    #[prop_or_default]
+    pub on_save: Callback<LensCrawlSettings>,
+}
+
+/// Parses an optional override, an empty input inherits the default.
+fn parse_override(value: &str, label: &str, min: u32, max: u32) -> Result<Option<u32>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    match value.parse::<u32>() {
+        Ok(num) if (min..=max).contains(&num) => Ok(Some(num)),
+        _ => Err(format!("{label} must be a number between {min} and {max}")),
+    }
+}
+
+#[function_component(CrawlSettingsComponent)]
+pub fn crawl_settings(props: &CrawlSettingsProps) -> Html {
+    let delay_ref = use_node_ref();
+    let concurrency_ref = use_node_ref();
+    let limit_ref = use_node_ref();
+    let error = use_state_eq(|| None::<String>);
+
+    let onsave = {
+        let delay_ref = delay_ref.clone();
+        let concurrency_ref = concurrency_ref.clone();
+        let limit_ref = limit_ref.clone();
+        let error = error.clone();
+        let on_save = props.on_save.clone();
+        Callback::from(move |_: MouseEvent| {
+            let delay = delay_ref
+                .cast::<HtmlInputElement>()
+                .map(|el| el.value())
+                .unwrap_or_default();
+            let concurrency = concurrency_ref
+                .cast::<HtmlInputElement>()
+                .map(|el| el.value())
+                .unwrap_or_default();
+
+            let limit = limit_ref
+                .cast::<HtmlInputElement>()
+                .map(|el| el.value())
+                .unwrap_or_default();
+
+            let settings = parse_override(&delay, "Crawl delay", 0, MAX_CRAWL_DELAY_MS).and_then(
+                |crawl_delay_ms| {
+                    let concurrency =
+                        parse_override(&concurrency, "Concurrency", 1, MAX_CRAWL_CONCURRENCY)?;
+                    let domain_crawl_limit =
+                        parse_override(&limit, "Pages per domain", 1, MAX_DOMAIN_CRAWL_LIMIT)?;
+                    Ok(LensCrawlSettings {
+                        crawl_delay_ms,
+                        concurrency,
+                        domain_crawl_limit,
+                    })
+                },
+            );
+
+            match settings {
+                Ok(settings) => {
+                    error.set(None);
+                    on_save.emit(settings);
+                }
+                Err(msg) => error.set(Some(msg)),
+            }
+        })
+    };
+
+    let settings = &props.settings;
+    let input_styles = "rounded p-2 text-sm text-neutral-800 w-32";
+    let help_styles = "text-xs text-neutral-400";
+
+    html! {
+        <details class="rounded-md bg-neutral-700 p-4">
+            <summary class="cursor-pointer font-semibold">{"Advanced"}</summary>
+            <div class="flex flex-col gap-4 mt-4">
+                <div class="flex flex-col gap-1">
+                    <label class="text-sm">{"Crawl delay (ms)"}</label>
+                    <input
+                        ref={delay_ref}
+                        class={input_styles}
+                        type="number"
+                        min="0"
+                        max={MAX_CRAWL_DELAY_MS.to_string()}
+                        placeholder={DEFAULT_CRAWL_DELAY_MS.to_string()}
+                        value={settings.crawl_delay_ms.map(|v| v.to_string()).unwrap_or_default()}
+                    />
+                    <div class={help_styles}>
+                        {if settings.crawl_delay_ms.is_some() {
+                            format!("{}ms between requests, set for this lens.", settings.effective_crawl_delay_ms())
+                        } else {
+                            format!("{}ms between requests, inherited from the default.", settings.effective_crawl_delay_ms())
+                        }}
+                    </div>
+                </div>
+                <div class="flex flex-col gap-1">
+                    <label class="text-sm">{"Concurrent crawls"}</label>
+                    <input
+                        ref={concurrency_ref}
+                        class={input_styles}
+                        type="number"
+                        min="1"
+                        max={MAX_CRAWL_CONCURRENCY.to_string()}
+                        placeholder={DEFAULT_CRAWL_CONCURRENCY.to_string()}
+                        value={settings.concurrency.map(|v| v.to_string()).unwrap_or_default()}
+                    />
+                    <div class={help_styles}>
+                        {if settings.concurrency.is_some() {
+                            format!("{} at a time, set for this lens.", settings.effective_concurrency())
+                        } else {
+                            format!("{} at a time, inherited from the default.", settings.effective_concurrency())
+                        }}
+                    </div>
+                </div>
+                <div class="flex flex-col gap-1">
+                    <label class="text-sm">{"Pages per domain"}</label>
+                    <input
+                        ref={limit_ref}
+                        class={input_styles}
+                        type="number"
+                        min="1"
+                        max={MAX_DOMAIN_CRAWL_LIMIT.to_string()}
+                        placeholder={DEFAULT_DOMAIN_CRAWL_LIMIT.to_string()}
+                        value={settings.domain_crawl_limit.map(|v| v.to_string()).unwrap_or_default()}
+                    />
+                    <div class={help_styles}>
+                        {if settings.domain_crawl_limit.is_some() {
+                            format!("Up to {} pages per domain, set for this lens.", settings.effective_domain_crawl_limit())
+                        } else {
+                            format!("Up to {} pages per domain, inherited from the default.", settings.effective_domain_crawl_limit())
+                        }}
+                    </div>
+                </div>
+                {if let Some(msg) = &*error {
+                    html! { <div class="text-sm text-red-400">{msg}</div> }
+                } else {
+                    html! {}
+                }}
+                <div>
+                    <Btn size={BtnSize::Sm} _type={BtnType::Primary} onclick={onsave} disabled={props.is_saving}>
+                        {if props.is_saving { "Saving..." } else { "Save crawl settings" }}
+                    </Btn>
+                </div>
+            </div>
+        </details>
+    }
+}
@@ -5,21 +5,28 @@ use ui_components::{
     icons,
     results::Paginator,
 };
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
 use wasm_bindgen_futures::spawn_local;
-use web_sys::HtmlInputElement;
+use web_sys::{Element, HtmlElement, HtmlInputElement};
 use yew::prelude::*;
 use yew_router::scope_ext::RouterScopeExt;
 
 use crate::{
-    client::{ApiError, Lens, LensDocType, LensSource},
+    client::{ApiError, Lens, LensCrawlSettings, LensDocType, LensSource},
+    components::connectivity::{retry_delay_ms, ServerStatus},
     download_file,
     schema::{GetLensSourceResponse, LensSourceQueryFilter},
+    utils::{format_bytes, format_duration_ms},
     AuthStatus,
 };
+use shared::constants::{DEFAULT_MAX_SOURCES_PER_LENS, LENS_DISPLAY_NAME_MAX_LEN};
+use shared::form::validate_lens_display_name;
+use shared::response::LensCrawlStats;
 
 mod add_source;
+mod crawl_settings;
 use add_source::AddSourceComponent;
+use crawl_settings::CrawlSettingsComponent;
 
 const QUERY_DEBOUNCE_MS: u32 = 1_000;
 const REFRESH_INTERVAL_MS: u32 = 5_000;
@@ -43,6 +50,7 @@ pub struct CreateLensPage {
     pub error_msg: Option<String>,
     pub lens_identifier: String,
     pub lens_data: Option<Lens>,
+    pub lens_stats: Option<LensCrawlStats>,
 
     pub source_filter: LensSourceQueryFilter,
     pub lens_sources: Option<Vec<LensSource>>,
@@ -50,11 +58,20 @@ pub struct CreateLensPage {
 
     pub is_loading_lens_sources: bool,
     pub is_saving_name: bool,
+    pub is_saving_crawl_settings: bool,
+    /// Set when the last request couldn't reach the server, requests are
+    /// retried with a backoff until it's back.
+    pub server_unreachable: bool,
+    pub retry_attempt: u32,
 
     pub auth_status: AuthStatus,
     pub add_url_error: Option<String>,
+    /// Set while the display name being typed is invalid, nothing is saved
+    /// until it's fixed.
+    pub name_error: Option<String>,
 
     pub _refresh_interval: Option<Interval>,
+    pub _retry_timeout: Option<Timeout>,
     pub _context_listener: ContextHandle<AuthStatus>,
     pub _query_debounce: Option<JsValue>,
     pub _name_input_ref: NodeRef,
@@ -67,6 +84,7 @@ pub struct CreateLensProps {
 
 pub enum Msg {
     ClearError,
+    CrawlSettingsSaved(Result<LensCrawlSettings, String>),
     DeleteLensSource(LensSource),
     Reload,
     ReloadCurrentSources,
@@ -74,16 +92,73 @@ pub enum Msg {
         page: usize,
         filter: LensSourceQueryFilter,
     },
+    Retry,
     Save {
         display_name: String,
     },
     SaveDone,
+    ServerUnreachable,
     SetError(String),
     SetFilter(LensSourceQueryFilter),
     SetLensData(Lens),
     SetLensSources(GetLensSourceResponse),
+    SetLensStats(LensCrawlStats),
     UpdateContext(AuthStatus),
+    UpdateCrawlSettings(LensCrawlSettings),
     UpdateDisplayName,
+    UpdateSourceBoost(LensSource, f32),
+}
+
+impl CreateLensPage {
+    /// Tracks whether the server could be reached & lets the rest of the app
+    /// know so it can show/hide the offline banner.
+    fn set_server_reachable(&mut self, ctx: &Context<Self>, is_reachable: bool) {
+        self.server_unreachable = !is_reachable;
+        if is_reachable {
+            self.retry_attempt = 0;
+            self._retry_timeout = None;
+        }
+
+        if let Some((status, _)) = ctx.link().context::<ServerStatus>(Callback::noop()) {
+            status.report.emit(is_reachable);
+        }
+    }
+
+    /// Source count & crawl usage shown under the lens name.
+    fn view_stats(&self) -> Html {
+        let mut parts = Vec::new();
+        if let Some(paginator) = &self.lens_source_paginator {
+            if self.source_filter == LensSourceQueryFilter::All {
+                parts.push(format!("{} sources", paginator.num_items));
+            }
+        }
+
+        if let Some(stats) = &self.lens_stats {
+            parts.push(format!("{} pages indexed", stats.pages_indexed));
+            parts.push(format!("{} fetched", format_bytes(stats.bytes_fetched)));
+            parts.push(format!(
+                "{} spent crawling",
+                format_duration_ms(stats.crawl_time_ms)
+            ));
+            if let Some(last_crawl) = stats
+                .last_crawl_at
+                .and_then(|at| chrono::NaiveDateTime::from_timestamp_opt(at, 0))
+            {
+                parts.push(format!(
+                    "last crawled {}",
+                    last_crawl.format("%Y-%m-%d %H:%M UTC")
+                ));
+            }
+        }
+
+        if parts.is_empty() {
+            return html! {};
+        }
+
+        html! {
+            <div class="mt-2 text-sm text-neutral-400">{parts.join(" · ")}</div>
+        }
+    }
 }
 
 impl Component for CreateLensPage {
@@ -108,14 +183,20 @@ impl Component for CreateLensPage {
             error_msg: None,
             lens_identifier: ctx.props().lens.clone(),
             lens_data: None,
+            lens_stats: None,
             lens_sources: None,
             lens_source_paginator: None,
             source_filter: LensSourceQueryFilter::default(),
             is_saving_name: false,
+            is_saving_crawl_settings: false,
             is_loading_lens_sources: false,
+            server_unreachable: false,
+            retry_attempt: 0,
             auth_status,
             add_url_error: None,
+            name_error: None,
             _refresh_interval: None,
+            _retry_timeout: None,
             _context_listener: context_listener,
             _query_debounce: None,
             _name_input_ref: NodeRef::default(),
@@ -153,6 +234,18 @@ impl Component for CreateLensPage {
                 self.error_msg = None;
                 true
             }
+            Msg::CrawlSettingsSaved(result) => {
+                self.is_saving_crawl_settings = false;
+                match result {
+                    Ok(settings) => {
+                        if let Some(lens_data) = &mut self.lens_data {
+                            lens_data.crawl_settings = settings;
+                        }
+                    }
+                    Err(err) => self.error_msg = Some(err),
+                }
+                true
+            }
             Msg::DeleteLensSource(source) => {
                 // Add to lens
                 let auth_status = self.auth_status.clone();
@@ -182,6 +275,12 @@ impl Component for CreateLensPage {
                 let link = link.clone();
                 spawn_local(async move {
                     let api = auth_status.get_client();
+                    // Stats are nice to have, the page works fine without them.
+                    match api.lens_stats(&identifier).await {
+                        Ok(stats) => link.send_message(Msg::SetLensStats(stats)),
+                        Err(err) => log::warn!("unable to load lens stats: {err}"),
+                    }
+
                     match api.lens_retrieve(&identifier).await {
                         Ok(lens) => link.send_message_batch(vec![
                             Msg::SetLensData(lens),
@@ -196,6 +295,10 @@ impl Component for CreateLensPage {
 
                             log::error!("error retrieving lens: {msg}");
                         }
+                        Err(err) if err.is_unreachable() => {
+                            log::error!("error retrieving lens: {err}");
+                            link.send_message(Msg::ServerUnreachable);
+                        }
                         Err(err) => log::error!("error retrieving lens: {err}"),
                     }
                 });
@@ -229,12 +332,32 @@ impl Component for CreateLensPage {
 
                             log::error!("error retrieving lens: {msg}");
                         }
+                        Err(err) if err.is_unreachable() => {
+                            log::error!("error retrieving lens: {err}");
+                            link.send_message(Msg::ServerUnreachable);
+                        }
                         Err(err) => log::error!("error retrieving lens: {err}"),
                     }
                 });
 
                 true
             }
+            Msg::Retry => {
+                self._retry_timeout = None;
+                let page = self
+                    .lens_source_paginator
+                    .as_ref()
+                    .map(|x| x.page)
+                    .unwrap_or(0);
+                link.send_message_batch(vec![
+                    Msg::Reload,
+                    Msg::ReloadSources {
+                        page,
+                        filter: self.source_filter,
+                    },
+                ]);
+                false
+            }
             Msg::Save { display_name } => {
                 if let Some(lens_data) = &mut self.lens_data {
                     let auth_status = self.auth_status.clone();
@@ -257,6 +380,19 @@ impl Component for CreateLensPage {
                 self.is_saving_name = false;
                 true
             }
+            Msg::ServerUnreachable => {
+                self.set_server_reachable(ctx, false);
+                // Both the lens & its sources fail when the server is down,
+                // only schedule one retry.
+                if self._retry_timeout.is_none() {
+                    let link = link.clone();
+                    let delay = retry_delay_ms(self.retry_attempt);
+                    self.retry_attempt += 1;
+                    self._retry_timeout =
+                        Some(Timeout::new(delay, move || link.send_message(Msg::Retry)));
+                }
+                true
+            }
             Msg::SetError(err) => {
                 self.error_msg = Some(err);
                 true
@@ -272,10 +408,16 @@ impl Component for CreateLensPage {
                 true
             }
             Msg::SetLensData(lens_data) => {
+                self.set_server_reachable(ctx, true);
                 self.lens_data = Some(lens_data);
                 true
             }
+            Msg::SetLensStats(stats) => {
+                self.lens_stats = Some(stats);
+                true
+            }
             Msg::SetLensSources(sources) => {
+                self.set_server_reachable(ctx, true);
                 self.is_loading_lens_sources = false;
                 self.lens_source_paginator = Some(LensSourcePaginator {
                     page: sources.page,
@@ -283,6 +425,21 @@ impl Component for CreateLensPage {
                     num_pages: sources.num_pages,
                 });
 
+                // Only the unfiltered list tells us how many sources the lens has.
+                // The API enforces its own limit, the editor stops offering to
+                // add sources at the app's default one.
+                if self.source_filter == LensSourceQueryFilter::All {
+                    self.add_url_error = if sources.num_items
+                        >= DEFAULT_MAX_SOURCES_PER_LENS as usize
+                    {
+                        Some(format!(
+                            "This lens has reached the limit of {DEFAULT_MAX_SOURCES_PER_LENS} sources. Remove some sources to add more."
+                        ))
+                    } else {
+                        None
+                    };
+                }
+
                 let has_processing = sources.results.iter().any(|x| x.status == "Processing");
 
                 if has_processing && self._refresh_interval.is_none() {
@@ -315,6 +472,44 @@ impl Component for CreateLensPage {
                 ]);
                 true
             }
+            Msg::UpdateCrawlSettings(settings) => {
+                let auth_status = self.auth_status.clone();
+                let identifier = self.lens_identifier.clone();
+                let link = link.clone();
+                self.is_saving_crawl_settings = true;
+                spawn_local(async move {
+                    let api = auth_status.get_client();
+                    let result = api
+                        .lens_update_crawl_settings(&identifier, &settings)
+                        .await
+                        .map(|_| settings)
+                        .map_err(|err| {
+                            log::error!("Error saving crawl settings: {err}");
+                            err.to_string()
+                        });
+                    link.send_message(Msg::CrawlSettingsSaved(result));
+                });
+                true
+            }
+            Msg::UpdateSourceBoost(source, boost) => {
+                let auth_status = self.auth_status.clone();
+                let identifier = self.lens_identifier.clone();
+                let link = link.clone();
+                spawn_local(async move {
+                    let api = auth_status.get_client();
+                    match api
+                        .lens_update_source_boost(&identifier, &source, boost)
+                        .await
+                    {
+                        Ok(_) => link.send_message(Msg::ReloadCurrentSources),
+                        Err(err) => {
+                            log::error!("Error updating source boost: {err}");
+                            link.send_message(Msg::SetError(err.to_string()));
+                        }
+                    }
+                });
+                false
+            }
             Msg::UpdateDisplayName => {
                 if let Some(timeout_id) = &self._query_debounce {
                     clear_timeout(timeout_id.clone());
@@ -323,7 +518,26 @@ impl Component for CreateLensPage {
 
                 {
                     if let Some(node) = self._name_input_ref.cast::<HtmlInputElement>() {
-                        let display_name = node.value();
+                        let display_name = match validate_lens_display_name(&node.value()) {
+                            Ok(name) => name,
+                            Err(msg) => {
+                                let changed = self.name_error.as_ref() != Some(&msg);
+                                self.name_error = Some(msg);
+                                return changed;
+                            }
+                        };
+
+                        let had_error = self.name_error.take().is_some();
+                        // Nothing to save if the name was reverted back to the saved one.
+                        let is_unchanged = self
+                            .lens_data
+                            .as_ref()
+                            .map(|lens| lens.display_name == display_name)
+                            .unwrap_or_default();
+                        if is_unchanged {
+                            return had_error;
+                        }
+
                         let link = link.clone();
                         let handle = Timeout::new(QUERY_DEBOUNCE_MS, move || {
                             link.send_message(Msg::Save { display_name })
@@ -331,6 +545,7 @@ impl Component for CreateLensPage {
 
                         let id = handle.forget();
                         self._query_debounce = Some(id);
+                        return had_error;
                     }
                 }
 
@@ -364,6 +579,7 @@ impl Component for CreateLensPage {
                 <div>
                 {if let Some(lens_data) = self.lens_data.as_ref() {
                     html! {
+                        <>
                         <div class="flex flex-row items-center">
                             <input
                                 class="border-b-4 border-neutral-600 pt-3 pb-1 bg-neutral-800 text-white text-2xl outline-none active:outline-none focus:outline-none caret-white"
@@ -371,6 +587,7 @@ impl Component for CreateLensPage {
                                 spellcheck="false"
                                 tabindex="-1"
                                 value={lens_data.display_name.to_string()}
+                                maxlength={LENS_DISPLAY_NAME_MAX_LEN.to_string()}
                                 oninput={link.callback(|_| Msg::UpdateDisplayName)}
                                 ref={self._name_input_ref.clone()}
                             />
@@ -387,6 +604,22 @@ impl Component for CreateLensPage {
                                 html! {}
                             }}
                         </div>
+                        {if let Some(msg) = &self.name_error {
+                            html! { <div class="text-sm text-red-400 mt-1">{msg}</div> }
+                        } else {
+                            html! {}
+                        }}
+                        {self.view_stats()}
+                        </>
+                    }
+                } else if self.server_unreachable {
+                    html! {
+                        <div class="flex flex-col gap-1">
+                            <h2 class="bold text-xl">{"Can't reach Spyglass server"}</h2>
+                            <div class="text-sm text-neutral-400">
+                                {"Check your connection, we'll keep trying in the background."}
+                            </div>
+                        </div>
                     }
                 } else {
                     html! {
@@ -394,11 +627,23 @@ impl Component for CreateLensPage {
                     }
                 }}
                 </div>
+                {if let Some(lens_data) = self.lens_data.as_ref() {
+                    html! {
+                        <CrawlSettingsComponent
+                            settings={lens_data.crawl_settings.clone()}
+                            is_saving={self.is_saving_crawl_settings}
+                            on_save={link.callback(Msg::UpdateCrawlSettings)}
+                        />
+                    }
+                } else {
+                    html! {}
+                }}
                 <div class="mt-4">
                     <AddSourceComponent
                         on_error={link.callback(Msg::SetError)}
                         on_update={link.callback(|_| Msg::Reload)}
                         lens_identifier={self.lens_identifier.clone()}
+                        disabled_reason={self.add_url_error.clone()}
                     />
                 </div>
                 <div class="mt-8">
@@ -411,6 +656,7 @@ impl Component for CreateLensPage {
                                 selected_filter={self.source_filter}
                                 is_loading={self.is_loading_lens_sources}
                                 on_delete={link.callback(Msg::DeleteLensSource)}
+                                on_boost={link.callback(|(source, boost)| Msg::UpdateSourceBoost(source, boost))}
                                 on_refresh={link.callback(move |_| Msg::ReloadSources { page: paginator.page, filter })}
                                 on_select_page={link.callback(move |page| Msg::ReloadSources { page, filter })}
                                 on_select_filter={link.callback(Msg::SetFilter)}
@@ -423,10 +669,56 @@ impl Component for CreateLensPage {
     }
 }
 
+/// Where a source is in the pipeline, from being added until it's searchable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SourceStage {
+    Queued,
+    Crawling,
+    /// Crawled, but waiting on the index to commit before it's searchable.
+    Indexing,
+    Deployed,
+    Failed,
+}
+
+impl SourceStage {
+    fn from_source(source: &LensSource) -> Self {
+        match source.status.as_ref() {
+            "Deployed" => return SourceStage::Deployed,
+            "Failed" | "Unknown" => return SourceStage::Failed,
+            _ => {}
+        }
+
+        match source.crawl_status.as_deref() {
+            Some("Processing") => SourceStage::Crawling,
+            Some("Completed") => SourceStage::Indexing,
+            Some("Failed") => SourceStage::Failed,
+            _ => SourceStage::Queued,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            SourceStage::Queued => "Queued",
+            SourceStage::Crawling => "Crawling",
+            SourceStage::Indexing => "Indexing",
+            SourceStage::Deployed => "Deployed",
+            SourceStage::Failed => "Failed",
+        }
+    }
+}
+
+/// Moves focus to another source row, if there is one.
+fn focus_row(row: Option<Element>) {
+    if let Some(row) = row.and_then(|row| row.dyn_into::<HtmlElement>().ok()) {
+        let _ = row.focus();
+    }
+}
+
 #[derive(Properties, PartialEq)]
 struct LensSourceComponentProps {
     source: LensSource,
     on_delete: Callback<LensSource>,
+    on_boost: Callback<(LensSource, f32)>,
 }
 
 #[function_component(LensSourceComponent)]
@@ -434,6 +726,8 @@ fn lens_source_comp(props: &LensSourceComponentProps) -> Html {
     let source = props.source.clone();
     let callback = props.on_delete.clone();
     let is_deleting = use_state_eq(|| false);
+    let row_ref = use_node_ref();
+    let link_ref = use_node_ref();
     let auth_status = use_context::<AuthStatus>().expect("Ctxt not set up");
     let ext = props
         .source
@@ -453,19 +747,104 @@ fn lens_source_comp(props: &LensSourceComponentProps) -> Html {
         }
     };
 
-    let status_icon = match source.status.as_ref() {
-        "Deployed" => html! { <icons::BadgeCheckIcon classes="fill-green-500" /> },
+    let stage = SourceStage::from_source(&source);
+    let status_icon = match stage {
+        SourceStage::Deployed => html! { <icons::BadgeCheckIcon classes="fill-green-500" /> },
         // todo: show error message in tooltip?
-        "Failed" | "Unknown" => html! { <icons::Warning classes="text-yellow-500" /> },
-        _ => html! { <icons::RefreshIcon animate_spin={true} /> },
+        SourceStage::Failed => html! { <icons::Warning classes="text-yellow-500" /> },
+        // Still being worked on, the label says which stage it's in
+        SourceStage::Queued | SourceStage::Crawling | SourceStage::Indexing => {
+            html! { <icons::RefreshIcon animate_spin={true} /> }
+        }
     };
 
-    let on_delete: Callback<MouseEvent> = {
+    let delete_source: Callback<()> = {
         let source = source.clone();
         let is_deleting = is_deleting.clone();
-        Callback::from(move |_e: MouseEvent| {
-            is_deleting.set(true);
-            callback.emit(source.clone());
+        Callback::from(move |_| {
+            if *is_deleting {
+                return;
+            }
+
+            let msg = format!("Remove \"{}\" from this lens?", source.display_name);
+            if gloo::dialogs::confirm(&msg) {
+                is_deleting.set(true);
+                callback.emit(source.clone());
+            }
+        })
+    };
+    let on_delete = delete_source.reform(|_: MouseEvent| ());
+
+    let on_boost = {
+        let source = source.clone();
+        let callback = props.on_boost.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Ok(boost) = input.value().parse::<f32>() {
+                callback.emit((source.clone(), boost));
+            }
+        })
+    };
+
+    // Arrow keys move between rows, Enter opens the source & Delete removes it.
+    let on_keydown = {
+        let row_ref = row_ref.clone();
+        let link_ref = link_ref.clone();
+        Callback::from(move |evt: KeyboardEvent| {
+            let row = match row_ref.cast::<Element>() {
+                Some(row) => row,
+                None => return,
+            };
+            let target = evt
+                .target()
+                .and_then(|target| target.dyn_into::<Element>().ok());
+            // Leave keys pressed in form controls, e.g. the boost slider, to
+            // the browser.
+            if target
+                .as_ref()
+                .map_or(false, |target| target.is_instance_of::<HtmlInputElement>())
+            {
+                return;
+            }
+            // Leave keys pressed on the link & delete button to the browser.
+            let on_row = target.map_or(false, |target| target == row);
+
+            match evt.key().as_str() {
+                "ArrowDown" => {
+                    evt.prevent_default();
+                    focus_row(row.next_element_sibling());
+                }
+                "ArrowUp" => {
+                    evt.prevent_default();
+                    focus_row(row.previous_element_sibling());
+                }
+                "Home" => {
+                    evt.prevent_default();
+                    focus_row(
+                        row.parent_element()
+                            .and_then(|rows| rows.first_element_child()),
+                    );
+                }
+                "End" => {
+                    evt.prevent_default();
+                    focus_row(
+                        row.parent_element()
+                            .and_then(|rows| rows.last_element_child()),
+                    );
+                }
+                "Enter" if on_row => {
+                    evt.prevent_default();
+                    if let Some(link) = link_ref.cast::<HtmlElement>() {
+                        link.click();
+                    }
+                }
+                // Mac keyboards label Backspace as "delete"
+                "Delete" | "Backspace" if on_row => {
+                    evt.prevent_default();
+                    delete_source.emit(());
+                }
+                _ => {}
+            }
         })
     };
 
@@ -504,38 +883,68 @@ fn lens_source_comp(props: &LensSourceComponentProps) -> Html {
         });
 
         html! {
-            <a onclick={download} href="" target="_blank" class="text-cyan-500 underline">
+            <a ref={link_ref} onclick={download} href="" target="_blank" class="text-cyan-500 underline">
                 {source.display_name.clone()}
             </a>
         }
     } else {
         html! {
-            <a href={source.url.clone()} target="_blank" class="text-cyan-500 underline">
+            <a ref={link_ref} href={source.url.clone()} target="_blank" class="text-cyan-500 underline">
                 {source.display_name.clone()}
             </a>
         }
     };
 
+    let row_label = format!("{}, {}, {}", source.display_name, source.url, stage.label());
+
     html! {
-        <tr>
+        <tr
+            ref={row_ref}
+            tabindex="0"
+            aria-label={row_label}
+            aria-keyshortcuts="Enter Delete"
+            class="focus:outline-none focus-visible:bg-neutral-800"
+            onkeydown={on_keydown}
+        >
             <td class={cell_styles.clone()}>
-                <div class="flex flex-row justify-center">{doc_type_icon}</div>
+                <div class="flex flex-row justify-center" aria-hidden="true">{doc_type_icon}</div>
             </td>
             <td class={cell_styles.clone()}>
                 {url_link}
                 <div class="text-sm text-neutral-600">{source.url.clone()}</div>
             </td>
-            <td class={cell_styles.clone()}>{status_icon}</td>
+            <td class={cell_styles.clone()}>
+                <div class="flex flex-row items-center gap-1" title={stage.label()}>
+                    <span aria-hidden="true">{status_icon}</span>
+                    <span class="text-xs">{stage.label()}</span>
+                </div>
+            </td>
             <td class={cell_styles}>
-                <Btn size={BtnSize::Xs} onclick={on_delete} _type={BtnType::Danger} disabled={*is_deleting}>
-                    {if *is_deleting {
-                        html! {<icons::RefreshIcon height="h-4" width="h-4" animate_spin={true} />}
-                    } else {
-                        html! {
-                            <icons::TrashIcon height="h-4" width="h-4" />
-                        }
-                    }}
-                </Btn>
+                <div class="flex flex-row items-center gap-2">
+                    <label class="flex flex-row items-center gap-1 text-xs text-neutral-400" title="Boost or bury this source when searching the lens">
+                        <span class="sr-only">{format!("Search boost for {}", source.display_name)}</span>
+                        <input
+                            type="range"
+                            class="w-20"
+                            min={LensSource::MIN_BOOST.to_string()}
+                            max={LensSource::MAX_BOOST.to_string()}
+                            step="0.1"
+                            value={source.boost.to_string()}
+                            onchange={on_boost}
+                        />
+                        {format!("{:.1}x", source.boost)}
+                    </label>
+                    <Btn size={BtnSize::Xs} onclick={on_delete} _type={BtnType::Danger} disabled={*is_deleting}>
+                        {if *is_deleting {
+                            html! {<icons::RefreshIcon height="h-4" width="h-4" animate_spin={true} />}
+                        } else {
+                            html! {
+                                <icons::TrashIcon height="h-4" width="h-4" />
+                            }
+                        }}
+                        <span class="sr-only">{format!("Delete {}", source.display_name)}</span>
+                    </Btn>
+                </div>
             </td>
         </tr>
     }
@@ -550,6 +959,8 @@ pub struct SourceTableProps {
     #[prop_or_default]
     on_delete: Callback<LensSource>,
     #[prop_or_default]
+    on_boost: Callback<(LensSource, f32)>,
+    #[prop_or_default]
     on_refresh: Callback<MouseEvent>,
     #[prop_or_default]
     on_select_page: Callback<usize>,
@@ -570,7 +981,7 @@ pub fn source_table(props: &SourceTableProps) -> Html {
     } else {
         props.sources
             .iter()
-            .map(|x| html! { <LensSourceComponent on_delete={props.on_delete.clone()} source={x.clone()} /> })
+            .map(|x| html! { <LensSourceComponent on_delete={props.on_delete.clone()} on_boost={props.on_boost.clone()} source={x.clone()} /> })
             .collect::<Html>()
     };
 
@@ -637,13 +1048,16 @@ pub fn source_table(props: &SourceTableProps) -> Html {
             } else {
                 html! {
                     <>
-                        <table class="table-auto text-sm border-collapse">
+                        <table class="table-auto text-sm border-collapse" aria-label="Data sources">
+                            <caption class="sr-only">
+                                {"Use the arrow keys to move between sources, Enter to open a source and Delete to remove it."}
+                            </caption>
                             <thead>
                                 <tr>
-                                    <th class={header_styles.clone()}></th>
+                                    <th class={header_styles.clone()}><span class="sr-only">{"Type"}</span></th>
                                     <th class={header_styles.clone()}>{"Document"}</th>
-                                    <th class={header_styles.clone()}></th>
-                                    <th class={header_styles}></th>
+                                    <th class={header_styles.clone()}><span class="sr-only">{"Status"}</span></th>
+                                    <th class={header_styles}><span class="sr-only">{"Actions"}</span></th>
                                 </tr>
                             </thead>
                             <tbody>{source_html}</tbody>
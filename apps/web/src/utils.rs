@@ -24,3 +24,35 @@ pub fn validate_hex_color(hex_color: &str) -> Result<(), String> {
     // The hex color is valid.
     Ok(())
 }
+
+/// Formats a byte count for display, e.g. "1.5 MB".
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in UNITS.iter().skip(1) {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+
+    format!("{value:.1} {unit}")
+}
+
+/// Formats a duration for display, e.g. "2h 5m".
+pub fn format_duration_ms(ms: u64) -> String {
+    let secs = ms / 1000;
+    if secs < 60 {
+        format!("{secs}s")
+    } else if secs < 60 * 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
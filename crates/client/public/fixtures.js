@@ -493,8 +493,8 @@ export let listen = async (event, callback) => {
   return {};
 };
 
-export async function deleteDoc(id) {
-  return await invoke("delete_doc", { id });
+export async function deleteDoc(id, block) {
+  return await invoke("delete_doc", { id, block });
 }
 
 export async function network_change(isOffline) {
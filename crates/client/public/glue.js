@@ -5,8 +5,8 @@ if (window.__TAURI__) {
     listen = window.__TAURI__.event.listen;
 }
 
-export async function deleteDoc(id) {
-    return await invoke('delete_doc', { id });
+export async function deleteDoc(id, block) {
+    return await invoke('delete_doc', { id, block });
 }
 
 export async function network_change(isOffline) {
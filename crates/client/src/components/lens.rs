@@ -7,16 +7,26 @@ use crate::{
 };
 use num_format::{Buffer, Locale};
 use shared::response::{InstallStatus, LensResult, LensType};
+use web_sys::HtmlInputElement;
 use yew::function_component;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
 #[derive(PartialEq, Eq)]
 pub enum LensEvent {
+    AddFolder { name: String },
     Install { name: String },
+    Pause { name: String, is_paused: bool },
+    Reset { name: String },
+    SetWeight { name: String, weight: u32 },
     Uninstall { name: String },
 }
 
+/// Range of the priority slider, as a percentage of a lens' search scores.
+/// Matches the range weights are clamped to when saved.
+const MIN_LENS_WEIGHT: u32 = 10;
+const MAX_LENS_WEIGHT: u32 = 1000;
+
 #[derive(Properties, PartialEq)]
 struct LensActionBarProps {
     pub result: LensResult,
@@ -63,6 +73,10 @@ fn lens_action_bar(props: &LensActionBarProps) -> Html {
         }
         InstallStatus::Finished { num_docs: _ } => {
             let name = lens_name.clone();
+            let pause_onclick = onclick.clone();
+            let weight_onchange = onclick.clone();
+            let folder_onclick = onclick.clone();
+            let reset_onclick = onclick.clone();
             let uninstall_cb =
                 Callback::from(move |_| onclick.emit(LensEvent::Uninstall { name: name.clone() }));
 
@@ -118,7 +132,97 @@ fn lens_action_bar(props: &LensActionBarProps) -> Html {
                 _ => html! {},
             };
 
-            html! { <>{view_btn}{uninstall_btn}</> }
+            let pause_btn = match result.lens_type {
+                LensType::Lens => {
+                    let name = lens_name.clone();
+                    let is_paused = result.is_paused;
+                    let pause_cb = Callback::from(move |_| {
+                        pause_onclick.emit(LensEvent::Pause {
+                            name: name.clone(),
+                            is_paused: !is_paused,
+                        })
+                    });
+
+                    html! {
+                        <Btn size={BtnSize::Xs} onclick={pause_cb}>
+                            {if is_paused {
+                                html! { <><icons::PlayIcon width={icon_w} height={icon_h} />{"Resume"}</> }
+                            } else {
+                                html! { <><icons::PauseIcon width={icon_w} height={icon_h} />{"Pause"}</> }
+                            }}
+                        </Btn>
+                    }
+                }
+                _ => html! {},
+            };
+
+            let folder_btn = match result.lens_type {
+                LensType::Lens => {
+                    let name = lens_name.clone();
+                    let folder_cb = Callback::from(move |_| {
+                        folder_onclick.emit(LensEvent::AddFolder { name: name.clone() })
+                    });
+
+                    html! {
+                        <Btn size={BtnSize::Xs} onclick={folder_cb}>
+                            <icons::FolderPlusIcon width={icon_w} height={icon_h} />
+                            {"Add folder"}
+                        </Btn>
+                    }
+                }
+                _ => html! {},
+            };
+
+            let reset_btn = match result.lens_type {
+                LensType::Lens => {
+                    let name = lens_name.clone();
+                    let reset_cb = Callback::from(move |_| {
+                        reset_onclick.emit(LensEvent::Reset { name: name.clone() })
+                    });
+
+                    html! {
+                        <Btn _type={BtnType::Danger} size={BtnSize::Xs} onclick={reset_cb}>
+                            <icons::RefreshIcon width={icon_w} height={icon_h} />
+                            {"Reset crawl"}
+                        </Btn>
+                    }
+                }
+                _ => html! {},
+            };
+
+            let weight_slider = match (&result.lens_type, result.ranking_weight) {
+                (LensType::Lens, Some(weight)) => {
+                    let name = lens_name.clone();
+                    let onchange = Callback::from(move |e: Event| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        if let Ok(weight) = input.value().parse::<u32>() {
+                            weight_onchange.emit(LensEvent::SetWeight {
+                                name: name.clone(),
+                                weight,
+                            });
+                        }
+                    });
+
+                    html! {
+                        <label class="flex flex-row items-center gap-1 text-xs text-neutral-400" title="Boost or bury this lens in search results">
+                            {"Priority"}
+                            <input
+                                type="range"
+                                class="w-20"
+                                min={MIN_LENS_WEIGHT.to_string()}
+                                max={MAX_LENS_WEIGHT.to_string()}
+                                step="10"
+                                value={weight.to_string()}
+                                {onchange}
+                            />
+                            {format!("{:.1}x", weight as f32 / 100.0)}
+                        </label>
+                    }
+                }
+                _ => html! {},
+            };
+
+            html! { <>{view_btn}{pause_btn}{folder_btn}{reset_btn}{uninstall_btn}{weight_slider}</> }
         }
         InstallStatus::Installing { percent, status } => match result.lens_type {
             LensType::Lens | LensType::Internal => {
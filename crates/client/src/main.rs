@@ -27,7 +27,7 @@ extern "C" {
     ) -> Result<JsValue, JsValue>;
 
     #[wasm_bindgen(js_name = "deleteDoc", catch)]
-    pub async fn delete_doc(id: String) -> Result<(), JsValue>;
+    pub async fn delete_doc(id: String, block: bool) -> Result<(), JsValue>;
 
     #[wasm_bindgen(catch)]
     pub async fn save_user_settings(settings: JsValue, restart: bool) -> Result<JsValue, JsValue>;
@@ -69,7 +69,7 @@ extern "C" {
 #[wasm_bindgen(module = "/public/glue.js")]
 extern "C" {
     #[wasm_bindgen(js_name = "deleteDoc", catch)]
-    pub async fn delete_doc(id: String) -> Result<(), JsValue>;
+    pub async fn delete_doc(id: String, block: bool) -> Result<(), JsValue>;
 
     #[wasm_bindgen(catch)]
     pub async fn save_user_settings(settings: JsValue, restart: bool) -> Result<JsValue, JsValue>;
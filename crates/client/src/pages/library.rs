@@ -12,8 +12,11 @@ use crate::components::{
 use crate::utils::RequestState;
 use crate::{invoke, listen, tauri_invoke};
 use shared::event::ClientInvoke;
-use shared::event::{ClientEvent, UninstallLensParams};
-use shared::response::LensResult;
+use shared::event::{
+    AddLensFolderParams, ClientEvent, ListenPayload, PauseLensParams, ResetLensParams,
+    SetLensWeightParams, UninstallLensParams,
+};
+use shared::response::{LensResetResult, LensResult};
 use ui_components::icons;
 
 async fn fetch_user_installed_lenses() -> Option<Vec<LensResult>> {
@@ -38,8 +41,11 @@ pub struct LensManagerPage {
     user_installed: Vec<LensResult>,
     uninstalling: HashSet<String>,
     update_interval_handle: Option<Interval>,
+    // Lens waiting on the folder picker
+    adding_folder_to: Option<String>,
 }
 pub enum Msg {
+    AddFolder(String),
     HandleLensEvent(LensEvent),
     RunLensUpdate,
     RunOpenFolder,
@@ -81,6 +87,23 @@ impl Component for LensManagerPage {
             });
         }
 
+        // Listen for folders chosen to add to a lens
+        {
+            let link = link.clone();
+            spawn_local(async move {
+                let cb = Closure::wrap(Box::new(move |payload: JsValue| {
+                    if let Ok(res) =
+                        serde_wasm_bindgen::from_value::<ListenPayload<String>>(payload)
+                    {
+                        link.send_message(Msg::AddFolder(res.payload));
+                    }
+                }) as Box<dyn Fn(JsValue)>);
+
+                let _ = listen(ClientEvent::FolderChosen.as_ref(), &cb).await;
+                cb.forget();
+            });
+        }
+
         let interval = {
             let link = link.clone();
             Interval::new(5_000, move || link.send_message(Msg::RunRefresher))
@@ -92,6 +115,7 @@ impl Component for LensManagerPage {
             user_installed: Vec::new(),
             uninstalling: HashSet::new(),
             update_interval_handle: Some(interval),
+            adding_folder_to: None,
         }
     }
 
@@ -105,19 +129,89 @@ impl Component for LensManagerPage {
         let link = ctx.link();
         match msg {
             Msg::HandleLensEvent(event) => {
-                if let LensEvent::Uninstall { name } = event {
-                    self.uninstalling.insert(name.clone());
+                match event {
+                    LensEvent::Uninstall { name } => {
+                        self.uninstalling.insert(name.clone());
+                        spawn_local(async move {
+                            let res = tauri_invoke::<_, ()>(
+                                ClientInvoke::UninstallLens,
+                                &UninstallLensParams { name },
+                            )
+                            .await;
+                            log::info!("{:?}", res);
+                        });
+                    }
+                    LensEvent::Pause { name, is_paused } => {
+                        spawn_local(async move {
+                            if let Err(err) = tauri_invoke::<_, ()>(
+                                ClientInvoke::PauseLens,
+                                &PauseLensParams { name, is_paused },
+                            )
+                            .await
+                            {
+                                log::error!("Unable to pause lens: {:?}", err);
+                            }
+                        });
+                    }
+                    LensEvent::Reset { name } => {
+                        spawn_local(async move {
+                            if let Err(err) = tauri_invoke::<_, LensResetResult>(
+                                ClientInvoke::ResetLens,
+                                &ResetLensParams {
+                                    name,
+                                    remove_documents: false,
+                                },
+                            )
+                            .await
+                            {
+                                log::error!("Unable to reset lens: {:?}", err);
+                            }
+                        });
+                    }
+                    LensEvent::SetWeight { name, weight } => {
+                        spawn_local(async move {
+                            if let Err(err) = tauri_invoke::<_, ()>(
+                                ClientInvoke::SetLensWeight,
+                                &SetLensWeightParams { name, weight },
+                            )
+                            .await
+                            {
+                                log::error!("Unable to set lens weight: {:?}", err);
+                            }
+                        });
+                    }
+                    LensEvent::AddFolder { name } => {
+                        self.adding_folder_to = Some(name);
+                        spawn_local(async {
+                            let _ =
+                                invoke(ClientInvoke::ChooseFolder.as_ref(), JsValue::NULL).await;
+                        });
+                    }
+                    LensEvent::Install { .. } => {}
+                }
+
+                true
+            }
+            Msg::AddFolder(path) => {
+                // Folder picker may have been opened by another page
+                if let Some(name) = self.adding_folder_to.take() {
                     spawn_local(async move {
-                        let res = tauri_invoke::<_, ()>(
-                            ClientInvoke::UninstallLens,
-                            &UninstallLensParams { name },
+                        if let Err(err) = tauri_invoke::<_, ()>(
+                            ClientInvoke::AddLensFolder,
+                            &AddLensFolderParams {
+                                name,
+                                path,
+                                extensions: Vec::new(),
+                            },
                         )
-                        .await;
-                        log::info!("{:?}", res);
+                        .await
+                        {
+                            log::error!("Unable to add folder to lens: {:?}", err);
+                        }
                     });
                 }
 
-                true
+                false
             }
             Msg::RunOpenFolder => {
                 spawn_local(async {
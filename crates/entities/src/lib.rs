@@ -51,7 +51,7 @@ pub async fn get_library_stats(
             "Completed" => {
                 entry.crawled += count.count;
             }
-            "Failed" => {
+            "Failed" | "Expired" => {
                 entry.crawled += count.count;
                 entry.failed += count.count;
             }
@@ -1,8 +1,10 @@
+use addr::parse_domain_name;
 use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
 use sea_orm::entity::prelude::*;
 use sea_orm::sea_query::{OnConflict, Query, SqliteQueryBuilder};
 use sea_orm::{
-    sea_query, ConnectionTrait, FromQueryResult, InsertResult, QueryTrait, Set, Statement,
+    sea_query, Condition, ConnectionTrait, FromQueryResult, InsertResult, QueryOrder, QueryTrait,
+    Set, Statement, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -11,12 +13,25 @@ use url::Url;
 
 use super::crawl_tag;
 use super::indexed_document;
+use super::lens;
 use super::tag::{self, get_or_create, TagPair};
+use super::{url_blocklist, url_pattern};
 use crate::BATCH_SIZE;
-use shared::config::{LensConfig, LensRule, Limit, UrlSanitizeConfig, UserSettings};
+use shared::collapse_equivalent_url;
+use shared::config::{
+    LensConfig, LensRule, Limit, QueryStringUrls, UrlSanitizeConfig, UserSettings,
+};
 use shared::regex::{regex_for_domain, regex_for_prefix};
 
-const MAX_RETRIES: u8 = 5;
+/// Retry delays stop growing after this many retries.
+const MAX_RETRY_BACKOFF: u8 = 8;
+
+/// Priority of tasks queued w/o one, e.g. links found while crawling.
+pub const PRIORITY_DEFAULT: i32 = 0;
+/// Priority of URLs the user asked for directly, e.g. added from the browser
+/// extension. These are crawled before anything at a lower priority. Plugins
+/// can queue URLs at either priority, see `spyglass_plugin::PRIORITY_USER`.
+pub const PRIORITY_USER: i32 = 100;
 
 #[derive(Debug, Error)]
 pub enum EnqueueError {
@@ -47,6 +62,23 @@ pub struct TaskError {
     msg: String,
 }
 
+impl TaskError {
+    pub fn new(error_type: TaskErrorType, msg: &str) -> Self {
+        TaskError {
+            error_type,
+            msg: msg.to_string(),
+        }
+    }
+
+    pub fn error_type(&self) -> &TaskErrorType {
+        &self.error_type
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum, Serialize, Eq)]
 #[sea_orm(rs_type = "String", db_type = "String(None)")]
 pub enum CrawlStatus {
@@ -60,6 +92,9 @@ pub enum CrawlStatus {
     Completed,
     #[sea_orm(string_value = "Failed")]
     Failed,
+    /// Sat in the queue longer than the configured TTL without being crawled.
+    #[sea_orm(string_value = "Expired")]
+    Expired,
 }
 
 #[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum, Serialize, Eq, Default)]
@@ -93,6 +128,8 @@ pub struct Model {
     /// Number of retries for this task.
     #[sea_orm(default_value = 0)]
     pub num_retries: u8,
+    /// When a task queued again after failing can be retried.
+    pub next_retry_at: Option<DateTimeUtc>,
     /// Crawl Type
     pub crawl_type: CrawlType,
     /// When this was first added to the crawl queue.
@@ -100,6 +137,20 @@ pub struct Model {
     /// When this task was last updated.
     pub updated_at: DateTimeUtc,
     pub pipeline: Option<String>,
+    /// Number of links followed from a seed URL to reach this URL.
+    #[sea_orm(default_value = 0)]
+    pub depth: i32,
+    /// Also queue the pages listed in the site's sitemap when crawling this.
+    #[sea_orm(default_value = false)]
+    pub use_sitemap: bool,
+    /// Tasks w/ a higher priority are crawled first, see `PRIORITY_USER`.
+    #[sea_orm(default_value = 0)]
+    pub priority: i32,
+    /// Queued over https in place of the http link that was found, see
+    /// `UserSettings::upgrade_insecure_links`. Only these tasks fall back to
+    /// http when the https fetch fails.
+    #[sea_orm(default_value = false)]
+    pub upgraded_from_http: bool,
 }
 
 impl Related<super::tag::Entity> for Entity {
@@ -152,6 +203,14 @@ impl ActiveModelBehavior for ActiveModel {
 }
 
 impl Model {
+    /// Canonical URL found the last time this task was crawled, if it pointed
+    /// somewhere else.
+    pub fn canonical_url(&self) -> Option<String> {
+        let data = self.data.as_ref()?;
+        let data: HashMap<String, String> = serde_json::from_str(data).ok()?;
+        data.get("canonical_url").cloned()
+    }
+
     pub async fn insert_tags<C: ConnectionTrait>(
         &self,
         db: &C,
@@ -214,16 +273,57 @@ pub async fn num_queued(
     Ok(res)
 }
 
-fn gen_dequeue_sql(db: &DatabaseConnection, user_settings: &UserSettings) -> Statement {
+fn gen_dequeue_sql(
+    db: &DatabaseConnection,
+    user_settings: &UserSettings,
+    skip_domains: &[String],
+    // Tasks waiting to be retried until after this are skipped
+    now: chrono::DateTime<chrono::Utc>,
+) -> Statement {
+    // Passed as a JSON array, the number of domains varies
+    let skip_domains = serde_json::to_string(skip_domains).unwrap_or_else(|_| "[]".into());
     Statement::from_sql_and_values(
         db.get_database_backend(),
         include_str!("sql/dequeue.sqlx"),
         vec![
             user_settings.domain_crawl_limit.value().into(),
             user_settings.inflight_domain_limit.value().into(),
+            skip_domains.into(),
+            now.into(),
         ],
     )
 }
+
+/// Skips tasks waiting to be retried.
+fn ready_to_retry() -> Condition {
+    Condition::any()
+        .add(Column::NextRetryAt.is_null())
+        .add(Column::NextRetryAt.lte(chrono::Utc::now()))
+}
+
+/// Ids of the tasks that belong to a paused lens.
+fn paused_lens_tasks() -> sea_query::SelectStatement {
+    Query::select()
+        .column((crawl_tag::Entity, crawl_tag::Column::CrawlQueueId))
+        .from(crawl_tag::Entity)
+        .inner_join(
+            tag::Entity,
+            sea_query::Expr::col((tag::Entity, tag::Column::Id))
+                .equals((crawl_tag::Entity, crawl_tag::Column::TagId)),
+        )
+        .inner_join(
+            lens::Entity,
+            sea_query::Expr::col((lens::Entity, lens::Column::Name))
+                .equals((tag::Entity, tag::Column::Value)),
+        )
+        .and_where(
+            sea_query::Expr::col((tag::Entity, tag::Column::Label))
+                .eq(tag::TagType::Lens.to_string()),
+        )
+        .and_where(sea_query::Expr::col((lens::Entity, lens::Column::IsPaused)).eq(true))
+        .to_owned()
+}
+
 struct LensRuleSets {
     // Allow if any URLs match
     allow_list: Vec<String>,
@@ -285,10 +385,65 @@ pub async fn num_of_files_in_progress(db: &DatabaseConnection) -> anyhow::Result
         .await
 }
 
+/// Marks queued tasks older than `ttl_days` as expired so they're no longer
+/// retried. Returns the number of expired tasks.
+pub async fn expire_tasks(db: &DatabaseConnection, ttl_days: u32) -> anyhow::Result<u64, DbErr> {
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(ttl_days as i64);
+    let res = Entity::update_many()
+        .col_expr(Column::Status, sea_query::Expr::value(CrawlStatus::Expired))
+        .col_expr(
+            Column::UpdatedAt,
+            sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(Column::Status.eq(CrawlStatus::Queued))
+        .filter(Column::CreatedAt.lt(cutoff))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
+
+/// When the oldest queued task was added to the queue.
+pub async fn oldest_queued(db: &DatabaseConnection) -> anyhow::Result<Option<DateTimeUtc>, DbErr> {
+    let task = Entity::find()
+        .filter(Column::Status.eq(CrawlStatus::Queued))
+        .order_by_asc(Column::CreatedAt)
+        .one(db)
+        .await?;
+
+    Ok(task.map(|task| task.created_at))
+}
+
+/// When the longest running task started processing.
+pub async fn oldest_processing(
+    db: &DatabaseConnection,
+) -> anyhow::Result<Option<DateTimeUtc>, DbErr> {
+    // Tasks are updated when marked as processing, so updated_at is when
+    // processing started.
+    let task = Entity::find()
+        .filter(Column::Status.eq(CrawlStatus::Processing))
+        .order_by_asc(Column::UpdatedAt)
+        .one(db)
+        .await?;
+
+    Ok(task.map(|task| task.updated_at))
+}
+
 /// Get the next url in the crawl queue
 pub async fn dequeue(
     db: &DatabaseConnection,
     user_settings: &UserSettings,
+) -> anyhow::Result<Option<Model>, sea_orm::DbErr> {
+    dequeue_except(db, user_settings, &[]).await
+}
+
+/// Same as `dequeue`, leaving the tasks of `skip_domains` in the queue, e.g.
+/// domains fetched from too recently. Bootstrap tasks are fetched from the
+/// Internet Archive, so they're not skipped.
+pub async fn dequeue_except(
+    db: &DatabaseConnection,
+    user_settings: &UserSettings,
+    skip_domains: &[String],
 ) -> anyhow::Result<Option<Model>, sea_orm::DbErr> {
     // Check for inflight limits
     if let Limit::Finite(inflight_crawl_limit) = user_settings.inflight_crawl_limit {
@@ -305,6 +460,8 @@ pub async fn dequeue(
         let result = Entity::find()
             .filter(Column::CrawlType.eq(CrawlType::Bootstrap))
             .filter(Column::Status.eq(CrawlStatus::Queued))
+            .filter(ready_to_retry())
+            .filter(Column::Id.not_in_subquery(paused_lens_tasks()))
             .one(db)
             .await?;
 
@@ -313,7 +470,12 @@ pub async fn dequeue(
         } else {
             // Otherwise, grab a URL off the stack & send it back.
             Entity::find()
-                .from_raw_sql(gen_dequeue_sql(db, user_settings))
+                .from_raw_sql(gen_dequeue_sql(
+                    db,
+                    user_settings,
+                    skip_domains,
+                    chrono::Utc::now(),
+                ))
                 .one(db)
                 .await?
         }
@@ -354,6 +516,8 @@ pub async fn dequeue_files(
     let entity = Entity::find()
         .filter(Column::Status.eq(CrawlStatus::Queued))
         .filter(Column::Url.starts_with("file:"))
+        .filter(ready_to_retry())
+        .order_by_desc(Column::Priority)
         .one(db)
         .await?;
 
@@ -388,6 +552,71 @@ pub struct EnqueueSettings {
     pub tags: Vec<TagPair>,
     pub force_allow: bool,
     pub is_recrawl: bool,
+    /// Depth from the seed URL assigned to the enqueued URLs.
+    pub depth: i32,
+    /// Only enqueue URLs that have not been seen before, skipping anything
+    /// already indexed or in the queue, even when recrawling.
+    pub new_only: bool,
+    /// Have the enqueued URLs pick up their site's sitemap when crawled, see
+    /// `Model::use_sitemap`.
+    pub use_sitemap: bool,
+    /// Priority the URLs are crawled at, see `PRIORITY_DEFAULT` &
+    /// `PRIORITY_USER`.
+    pub priority: i32,
+    /// Host of the lens source the URLs were found crawling. URLs on other
+    /// sites are dropped, see `UserSettings::stay_on_domain`.
+    pub seed_host: Option<String>,
+    /// The URLs were upgraded from http to https, see
+    /// `Model::upgraded_from_http`.
+    pub upgraded_from_http: bool,
+}
+
+/// Host of the lens source that crawls `url` along w/ all of its sub-pages,
+/// i.e. a URL prefix rule. Domain & exact URL rules have no such source.
+pub fn suburl_seed_host(lenses: &[LensConfig], url: &str) -> Option<String> {
+    lenses
+        .iter()
+        .flat_map(|lens| lens.urls.iter())
+        .filter(|prefix| !prefix.ends_with('$') && url.starts_with(prefix.as_str()))
+        .find_map(|prefix| {
+            Url::parse(prefix)
+                .ok()
+                .and_then(|prefix| prefix.host_str().map(|host| host.to_string()))
+        })
+}
+
+/// Whether `link` is on the same site as `seed_host`. Only web links are
+/// checked, anything else is considered on-site. With `allow_subdomains`, any
+/// host under the same root domain (e.g. docs.example.com & example.com)
+/// counts as the same site.
+fn is_same_site(seed_host: &str, link: &Url, allow_subdomains: bool) -> bool {
+    if !matches!(link.scheme(), "http" | "https") {
+        return true;
+    }
+
+    let link_host = match link.host_str() {
+        Some(link_host) => link_host,
+        None => return true,
+    };
+
+    if seed_host.eq_ignore_ascii_case(link_host) {
+        return true;
+    }
+
+    if !allow_subdomains {
+        return false;
+    }
+
+    let root_domain = |host: &str| {
+        parse_domain_name(host)
+            .ok()
+            .and_then(|name| name.root().map(|root| root.to_lowercase()))
+    };
+
+    match (root_domain(seed_host), root_domain(link_host)) {
+        (Some(seed_root), Some(link_root)) => seed_root == link_root,
+        _ => false,
+    }
 }
 
 fn url_is_allowed(
@@ -469,6 +698,10 @@ fn filter_urls(
             }
         })
         .filter_map(|mut url| {
+            if settings.collapse_equivalent_urls {
+                collapse_equivalent_url(&mut url);
+            }
+
             if overrides.force_allow {
                 return Some(url.to_string());
             }
@@ -484,6 +717,10 @@ fn filter_urls(
                 }
             }
 
+            if !query_string_allowed(&url, settings) {
+                return None;
+            }
+
             let normalized = url.to_string();
             let no_end_slash = if normalized.ends_with('/') {
                 Some(normalized.trim_end_matches('/').to_string())
@@ -518,6 +755,202 @@ fn filter_urls(
     Ok(res)
 }
 
+/// Domains crawled by the lenses, any other domain is considered external.
+fn seed_domains(lenses: &[LensConfig]) -> Vec<String> {
+    let mut seeds = Vec::new();
+    for lens in lenses {
+        seeds.extend(lens.domains.iter().cloned());
+        seeds.extend(
+            lens.urls
+                .iter()
+                .filter_map(|prefix| Url::parse(prefix.trim_end_matches('$')).ok())
+                .filter_map(|url| url.host_str().map(|host| host.to_string())),
+        );
+    }
+
+    seeds
+}
+
+fn is_seed_domain(domain: &str, seeds: &[String]) -> bool {
+    seeds.iter().any(|seed| match seed.strip_prefix("*.") {
+        Some(base) => domain == base || domain.ends_with(&format!(".{base}")),
+        None => domain == seed,
+    })
+}
+
+#[derive(Debug, FromQueryResult)]
+struct QueuedDomain {
+    domain: String,
+}
+
+/// Distinct web domains in the crawl queue that aren't crawled by any of the
+/// lenses.
+pub async fn external_domains<C: ConnectionTrait>(
+    db: &C,
+    lenses: &[LensConfig],
+) -> Result<HashSet<String>, DbErr> {
+    let seeds = seed_domains(lenses);
+    // Domains come off the domain index, the url check only has to find one
+    // web URL per domain rather than looking at every queued row.
+    let domains = QueuedDomain::find_by_statement(Statement::from_string(
+        db.get_database_backend(),
+        r#"
+        SELECT queued.domain
+        FROM (SELECT DISTINCT domain FROM crawl_queue) AS queued
+        WHERE EXISTS (
+            SELECT 1
+            FROM crawl_queue cq
+            WHERE cq.domain = queued.domain
+                AND (cq.url LIKE 'http://%' OR cq.url LIKE 'https://%')
+        )"#
+        .to_string(),
+    ))
+    .all(db)
+    .await?;
+
+    Ok(domains
+        .into_iter()
+        .map(|row| row.domain)
+        .filter(|domain| !is_seed_domain(domain, &seeds))
+        .collect())
+}
+
+/// Drops URLs that would take the crawl into a new external domain once
+/// `limit` external domains have been reached.
+async fn limit_external_domains<C: ConnectionTrait>(
+    db: &C,
+    lenses: &[LensConfig],
+    urls: Vec<String>,
+    limit: usize,
+) -> Result<Vec<String>, DbErr> {
+    let seeds = seed_domains(lenses);
+    let external = |url: &String| match Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => parsed
+            .host_str()
+            .map(|host| host.to_string())
+            .filter(|host| !is_seed_domain(host, &seeds)),
+        _ => None,
+    };
+
+    // Nothing in this batch can hit the limit, skip looking up the queue.
+    if !urls.iter().any(|url| external(url).is_some()) {
+        return Ok(urls);
+    }
+
+    let mut known = external_domains(db, lenses).await?;
+
+    Ok(urls
+        .into_iter()
+        .filter(|url| {
+            let domain = match external(url) {
+                Some(domain) => domain,
+                None => return true,
+            };
+
+            if known.contains(&domain) {
+                true
+            } else if known.len() < limit {
+                known.insert(domain);
+                true
+            } else {
+                log::debug!("external domain limit reached, skipping {}", url);
+                false
+            }
+        })
+        .collect())
+}
+
+/// Running count for a path template while filtering a batch of URLs.
+struct PatternCount {
+    num_urls: i64,
+    /// New URLs kept from this batch, added to the stored count.
+    added: i64,
+    is_trap: bool,
+    changed: bool,
+}
+
+/// Drops URLs matching a path template (see `url_pattern::signature`) that has
+/// already produced `limit` distinct URLs on its domain. Calendars & faceted
+/// search pages can generate endless unique URLs that would otherwise keep the
+/// crawler busy forever.
+async fn limit_crawl_traps<C: ConnectionTrait>(
+    db: &C,
+    urls: Vec<String>,
+    limit: usize,
+) -> Result<Vec<String>, DbErr> {
+    // URLs that are already queued don't count as new
+    let mut queued: HashSet<String> = HashSet::new();
+    for chunk in urls.chunks(BATCH_SIZE) {
+        for task in Entity::find()
+            .filter(Column::Url.is_in(chunk.to_vec()))
+            .all(db)
+            .await?
+        {
+            queued.insert(task.url);
+        }
+    }
+
+    let mut patterns: HashMap<(String, String), PatternCount> = HashMap::new();
+    let mut kept = Vec::with_capacity(urls.len());
+    for url in urls {
+        let key = Url::parse(&url).ok().and_then(|parsed| {
+            let domain = parsed.host_str()?.to_string();
+            Some((domain, url_pattern::signature(&parsed)?))
+        });
+
+        let key = match key {
+            Some(key) if !queued.contains(&url) => key,
+            _ => {
+                kept.push(url);
+                continue;
+            }
+        };
+
+        if !patterns.contains_key(&key) {
+            let existing = url_pattern::find(db, &key.0, &key.1).await?;
+            patterns.insert(
+                key.clone(),
+                PatternCount {
+                    num_urls: existing.as_ref().map_or(0, |pattern| pattern.num_urls),
+                    added: 0,
+                    is_trap: existing.map_or(false, |pattern| pattern.is_trap),
+                    changed: false,
+                },
+            );
+        }
+
+        let count = patterns.get_mut(&key).expect("pattern count missing");
+        if count.is_trap {
+            log::debug!("skipping {}, matches crawl trap {}", url, key.1);
+            continue;
+        }
+
+        if count.num_urls + count.added >= limit as i64 {
+            log::warn!(
+                "possible crawl trap on {}: over {} URLs like {}, no more will be queued",
+                key.0,
+                limit,
+                key.1
+            );
+            count.is_trap = true;
+            count.changed = true;
+            continue;
+        }
+
+        count.added += 1;
+        count.changed = true;
+        kept.push(url);
+    }
+
+    for ((domain, signature), count) in patterns {
+        if count.changed {
+            url_pattern::add_urls(db, &domain, &signature, count.added, count.is_trap).await?;
+        }
+    }
+
+    Ok(kept)
+}
+
 pub async fn enqueue_local_files(
     db: &DatabaseConnection,
     urls: &[String],
@@ -581,10 +1014,44 @@ pub async fn enqueue_all<C: ConnectionTrait>(
 ) -> anyhow::Result<(), EnqueueError> {
     // Filter URLs
     let urls = filter_urls(lenses, settings, overrides, urls).unwrap_or_default();
+    // Keep crawls of a single site from wandering off to other sites
+    let urls = match &overrides.seed_host {
+        Some(seed_host) if !overrides.force_allow => urls
+            .into_iter()
+            .filter(|url| {
+                let keep = Url::parse(url).map_or(true, |link| {
+                    is_same_site(seed_host, &link, settings.stay_on_domain_subdomains)
+                });
+                if !keep {
+                    log::debug!("skipping external link {} from {}", url, seed_host);
+                }
+                keep
+            })
+            .collect(),
+        _ => urls,
+    };
+    // Never queue URLs the user has blocked
+    let urls = url_blocklist::remove_blocked(db, urls).await?;
+
+    // Bound how many different external domains crawls can expand into
+    let urls = match settings.total_external_domain_limit {
+        Limit::Finite(limit) if !overrides.force_allow => {
+            limit_external_domains(db, lenses, urls, limit as usize).await?
+        }
+        _ => urls,
+    };
+
+    // Stop following URL patterns that look like crawl traps
+    let urls = match settings.crawl_trap_limit {
+        Limit::Finite(limit) if !overrides.force_allow => {
+            limit_crawl_traps(db, urls, limit as usize).await?
+        }
+        _ => urls,
+    };
 
     // Ignore urls already indexed
     let mut is_indexed: HashSet<String> = HashSet::with_capacity(urls.len());
-    if !overrides.is_recrawl {
+    if !overrides.is_recrawl || overrides.new_only {
         for chunk in urls.chunks(BATCH_SIZE) {
             let chunk = chunk.iter().map(|url| url.to_string()).collect::<Vec<_>>();
             for entry in indexed_document::Entity::find()
@@ -614,6 +1081,10 @@ pub async fn enqueue_all<C: ConnectionTrait>(
                         crawl_type: Set(overrides.crawl_type.clone()),
                         url: Set(url.to_string()),
                         pipeline: Set(pipeline.clone()),
+                        depth: Set(overrides.depth),
+                        use_sitemap: Set(overrides.use_sitemap),
+                        priority: Set(overrides.priority),
+                        upgraded_from_http: Set(overrides.upgraded_from_http),
                         ..Default::default()
                     });
                 }
@@ -642,9 +1113,9 @@ pub async fn enqueue_all<C: ConnectionTrait>(
         return Ok(());
     }
 
-    let on_conflict = if overrides.is_recrawl {
+    let on_conflict = if overrides.is_recrawl && !overrides.new_only {
         OnConflict::column(Column::Url)
-            .update_column(Column::Status)
+            .update_columns([Column::Status, Column::Priority])
             .to_owned()
     } else {
         OnConflict::column(Column::Url).do_nothing().to_owned()
@@ -698,6 +1169,8 @@ pub async fn mark_done(
 
         let mut updated: ActiveModel = crawl.into();
         updated.status = Set(CrawlStatus::Completed);
+        updated.error = Set(None);
+        updated.next_retry_at = Set(None);
         updated.updated_at = Set(chrono::Utc::now());
         updated.update(db).await.ok()
     } else {
@@ -705,19 +1178,40 @@ pub async fn mark_done(
     }
 }
 
-pub async fn mark_failed(db: &DatabaseConnection, id: i64, retry: bool) {
+/// How long to wait before retrying a task that's been retried `num_retries`
+/// times already: 1 minute, then 4, 16, etc.
+pub fn retry_delay(num_retries: u8) -> chrono::Duration {
+    chrono::Duration::minutes(4_i64.pow(num_retries.min(MAX_RETRY_BACKOFF) as u32))
+}
+
+/// Marks a task as failed w/ the reason why. Until it's been retried
+/// `max_retries` times the task is queued again instead, to be retried after
+/// a delay. Returns true if it was queued again.
+pub async fn mark_failed(
+    db: &DatabaseConnection,
+    id: i64,
+    max_retries: u8,
+    error: TaskError,
+) -> bool {
     if let Ok(Some(crawl)) = Entity::find_by_id(id).one(db).await {
         let mut updated: ActiveModel = crawl.clone().into();
+        updated.error = Set(Some(error));
 
         // Bump up number of retries if this failed
-        if retry && crawl.num_retries <= MAX_RETRIES {
+        let requeued = crawl.num_retries < max_retries;
+        if requeued {
             updated.num_retries = Set(crawl.num_retries + 1);
+            updated.next_retry_at = Set(Some(chrono::Utc::now() + retry_delay(crawl.num_retries)));
             // Queue again
             updated.status = Set(CrawlStatus::Queued);
         } else {
+            updated.next_retry_at = Set(None);
             updated.status = Set(CrawlStatus::Failed);
         }
         let _ = updated.update(db).await;
+        requeued
+    } else {
+        false
     }
 }
 
@@ -879,10 +1373,37 @@ pub async fn delete_by_lens(db: DatabaseConnection, name: &str) -> Result<(), se
     Ok(())
 }
 
+/// Deletes a single crawl entry & its tags unless it's being crawled. Checked
+/// in the same statements that delete it, so a worker can't pick the entry up
+/// in between. Returns the number of entries deleted.
+pub async fn delete_unless_processing(db: &DatabaseConnection, id: i64) -> Result<u64, DbErr> {
+    let tx = db.begin().await?;
+    let deletable = Query::select()
+        .column(Column::Id)
+        .from(Entity)
+        .and_where(Column::Id.eq(id))
+        .and_where(Column::Status.ne(CrawlStatus::Processing))
+        .to_owned();
+
+    crawl_tag::Entity::delete_many()
+        .filter(crawl_tag::Column::CrawlQueueId.in_subquery(deletable))
+        .exec(&tx)
+        .await?;
+
+    let res = Entity::delete_many()
+        .filter(Column::Id.eq(id))
+        .filter(Column::Status.ne(CrawlStatus::Processing))
+        .exec(&tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(res.rows_affected)
+}
+
 /// Helper method used to delete multiple crawl entries by id. This method will first
 /// delete all related tag references before deleting the crawl entries
-pub async fn delete_many_by_id(
-    db: &DatabaseConnection,
+pub async fn delete_many_by_id<C: ConnectionTrait>(
+    db: &C,
     dbids: &[i64],
 ) -> Result<u64, sea_orm::DbErr> {
     let mut rows_affected = 0;
@@ -956,6 +1477,27 @@ pub struct CrawlTaskIdsUrls {
     pub url: String,
 }
 
+/// Tasks for a lens that are still waiting to be crawled.
+pub async fn queued_by_lens<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+) -> Result<Vec<CrawlTaskIdsUrls>, sea_orm::DbErr> {
+    CrawlTaskIdsUrls::find_by_statement(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        r#"
+        SELECT
+            crawl_queue.id,
+            crawl_queue.url
+        FROM crawl_queue
+        LEFT JOIN crawl_tag on crawl_queue.id = crawl_tag.crawl_queue_id
+        LEFT JOIN tags on tags.id = crawl_tag.tag_id
+        WHERE tags.label = "lens" AND tags.value = $1 AND crawl_queue.status = "Queued""#,
+        vec![name.into()],
+    ))
+    .all(db)
+    .await
+}
+
 /// Helper method used to get the details for the task. This method will return the associated task and any
 /// associated tags
 pub async fn get_task_details(
@@ -998,6 +1540,25 @@ pub async fn copy_table(
     Ok(())
 }
 
+/// Whether the user's query string settings allow crawling this URL. URLs
+/// w/o a query string are always allowed.
+fn query_string_allowed(url: &Url, settings: &UserSettings) -> bool {
+    if url.query().map(|query| query.is_empty()).unwrap_or(true) {
+        return true;
+    }
+
+    match settings.index_query_string_urls {
+        QueryStringUrls::Always => true,
+        QueryStringUrls::Never => false,
+        QueryStringUrls::OnlyWhitelisted => url.query_pairs().all(|(name, _)| {
+            settings
+                .query_string_whitelist
+                .iter()
+                .any(|allowed| *allowed == name)
+        }),
+    }
+}
+
 // Helper method used to process the url sanitization configuration for
 // the provided url
 fn sanitize_url(url: &mut Url, config: &UrlSanitizeConfig) {
@@ -1009,17 +1570,18 @@ fn sanitize_url(url: &mut Url, config: &UrlSanitizeConfig) {
 #[cfg(test)]
 mod test {
     use sea_orm::prelude::*;
-    use sea_orm::{ActiveModelTrait, Set};
+    use sea_orm::{sea_query, ActiveModelTrait, ConnectionTrait, Set, Statement};
     use url::Url;
 
-    use shared::config::{LensConfig, LensRule, Limit, UserSettings};
+    use shared::config::{LensConfig, LensRule, Limit, QueryStringUrls, UserSettings};
     use shared::regex::{regex_for_robots, WildcardType};
 
     use crate::models::crawl_queue::{CrawlStatus, CrawlType};
-    use crate::models::{crawl_queue, indexed_document};
+    use crate::models::tag::TagType;
+    use crate::models::{crawl_queue, indexed_document, lens, url_blocklist};
     use crate::test::setup_test_db;
 
-    use super::{filter_urls, gen_dequeue_sql, EnqueueSettings};
+    use super::{filter_urls, gen_dequeue_sql, is_same_site, suburl_seed_host, EnqueueSettings};
 
     #[tokio::test]
     async fn test_insert() {
@@ -1050,10 +1612,14 @@ mod test {
         let db = setup_test_db().await;
 
         let settings = UserSettings::default();
-        let sql = gen_dequeue_sql(&db, &settings);
+        let now = chrono::Utc::now();
+        let sql = gen_dequeue_sql(&db, &settings, &[], now);
+        // Dates are formatted however the backend formats them
+        let now = Statement::from_sql_and_values(db.get_database_backend(), "?", vec![now.into()])
+            .to_string();
         assert_eq!(
             sql.to_string(),
-            "WITH\nindexed AS (\n    SELECT\n        domain,\n        count(*) as count\n    FROM indexed_document\n    GROUP BY domain\n),\ninflight AS (\n    SELECT\n        domain,\n        count(*) as count\n    FROM crawl_queue\n    WHERE status = \"Processing\"\n    GROUP BY domain\n)\nSELECT\n    cq.*\nFROM crawl_queue cq\nLEFT JOIN indexed ON indexed.domain = cq.domain\nLEFT JOIN inflight ON inflight.domain = cq.domain\nWHERE\n    COALESCE(indexed.count, 0) < 500000 AND\n    COALESCE(inflight.count, 0) < 2 AND\n    status = \"Queued\" and\n    url not like \"file%\"\nORDER BY\n    cq.updated_at ASC"
+            format!("WITH\nindexed AS (\n    SELECT\n        domain,\n        count(*) as count\n    FROM indexed_document\n    GROUP BY domain\n),\ninflight AS (\n    SELECT\n        domain,\n        count(*) as count\n    FROM crawl_queue\n    WHERE status = \"Processing\"\n    GROUP BY domain\n),\nserved AS (\n    SELECT\n        domain,\n        max(updated_at) as last_served\n    FROM crawl_queue\n    WHERE status IN (\"Processing\", \"Completed\", \"Failed\")\n    GROUP BY domain\n),\nlens_limits AS (\n    SELECT\n        crawl_tag.crawl_queue_id,\n        max(lens.domain_crawl_limit) as crawl_limit\n    FROM crawl_tag\n    JOIN tags ON tags.id = crawl_tag.tag_id\n    JOIN lens ON lens.name = tags.value\n    WHERE tags.label = \"lens\" AND lens.domain_crawl_limit IS NOT NULL\n    GROUP BY crawl_tag.crawl_queue_id\n)\nSELECT\n    cq.*\nFROM crawl_queue cq\nLEFT JOIN indexed ON indexed.domain = cq.domain\nLEFT JOIN inflight ON inflight.domain = cq.domain\nLEFT JOIN served ON served.domain = cq.domain\nLEFT JOIN lens_limits ON lens_limits.crawl_queue_id = cq.id\nWHERE\n    COALESCE(indexed.count, 0) < COALESCE(lens_limits.crawl_limit, 500000) AND\n    COALESCE(inflight.count, 0) < 2 AND\n    status = \"Queued\" and\n    url not like \"file%\" and\n    cq.domain NOT IN (SELECT value FROM json_each('[]')) and\n    (cq.next_retry_at IS NULL OR cq.next_retry_at <= {now}) and\n    cq.id NOT IN (\n        SELECT crawl_tag.crawl_queue_id\n        FROM crawl_tag\n        JOIN tags ON tags.id = crawl_tag.tag_id\n        JOIN lens ON lens.name = tags.value\n        WHERE tags.label = \"lens\" AND lens.is_paused\n    )\nORDER BY\n    cq.priority DESC,\n    served.last_served IS NOT NULL,\n    served.last_served ASC,\n    cq.updated_at ASC")
         );
     }
 
@@ -1087,6 +1653,30 @@ mod test {
         assert_eq!(crawl.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_enqueue_use_sitemap() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let urls = vec!["https://example.com/".to_string()];
+        let overrides = crawl_queue::EnqueueSettings {
+            force_allow: true,
+            use_sitemap: true,
+            ..Default::default()
+        };
+
+        crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, Option::None)
+            .await
+            .unwrap();
+
+        let crawl = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Url.eq(urls[0].to_string()))
+            .one(&db)
+            .await
+            .unwrap()
+            .expect("Task should be queued");
+        assert!(crawl.use_sitemap);
+    }
+
     #[tokio::test]
     async fn test_enqueue_with_recrawl() {
         let settings = UserSettings::default();
@@ -1130,52 +1720,82 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_enqueue_with_rules() {
+    async fn test_enqueue_new_only() {
         let settings = UserSettings::default();
         let db = setup_test_db().await;
-        let url = vec!["https://oldschool.runescape.wiki/w/Worn_Equipment?veaction=edit".into()];
-        let lens = LensConfig {
-            domains: vec!["oldschool.runescape.wiki".into()],
-            rules: vec![LensRule::SkipURL(
-                "https://oldschool.runescape.wiki/*veaction=*".into(),
-            )],
+        let indexed = "https://oldschool.runescape.wiki/".to_owned();
+        let new_url = "https://oldschool.runescape.wiki/w/Worn_Equipment".to_owned();
+
+        let _ = crawl_queue::Entity::insert(crawl_queue::ActiveModel {
+            domain: Set("oldschool.runescape.wiki".into()),
+            url: Set(indexed.clone()),
+            status: Set(crawl_queue::CrawlStatus::Completed),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await;
+
+        indexed_document::ActiveModel {
+            domain: Set("oldschool.runescape.wiki".into()),
+            url: Set(indexed.clone()),
+            doc_id: Set("docid".into()),
+            ..Default::default()
+        }
+        .save(&db)
+        .await
+        .unwrap();
+
+        let overrides = crawl_queue::EnqueueSettings {
+            force_allow: true,
+            is_recrawl: true,
+            new_only: true,
             ..Default::default()
         };
 
         crawl_queue::enqueue_all(
             &db,
-            &url,
-            &[lens],
+            &[indexed, new_url.clone()],
+            &[],
             &settings,
-            &Default::default(),
+            &overrides,
             Option::None,
         )
         .await
         .unwrap();
 
-        let crawl = crawl_queue::Entity::find()
-            .filter(crawl_queue::Column::Url.eq(url[0].to_string()))
+        // Only the new URL should be queued, the indexed one is left alone.
+        let res = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Status.eq(crawl_queue::CrawlStatus::Queued))
             .all(&db)
             .await
             .unwrap();
-
-        assert_eq!(crawl.len(), 0);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].url, new_url);
     }
 
     #[tokio::test]
-    async fn test_dequeue() {
-        let settings = UserSettings::default();
+    async fn test_enqueue_external_domain_limit() {
+        let settings = UserSettings {
+            total_external_domain_limit: Limit::Finite(2),
+            ..Default::default()
+        };
         let db = setup_test_db().await;
-        let url = vec!["https://oldschool.runescape.wiki/".into()];
         let lens = LensConfig {
-            domains: vec!["oldschool.runescape.wiki".into()],
+            domains: vec!["*".into()],
+            urls: vec!["https://example.com/".into()],
             ..Default::default()
         };
+        let urls = vec![
+            "https://example.com/page".to_string(),
+            "https://one.com/".to_string(),
+            "https://two.com/".to_string(),
+            "https://three.com/".to_string(),
+        ];
 
         crawl_queue::enqueue_all(
             &db,
-            &url,
-            &[lens],
+            &urls,
+            &[lens.clone()],
             &settings,
             &Default::default(),
             Option::None,
@@ -1183,42 +1803,507 @@ mod test {
         .await
         .unwrap();
 
-        let queue = crawl_queue::dequeue(&db, &settings).await.unwrap();
+        let queued = crawl_queue::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|task| task.url)
+            .collect::<Vec<String>>();
+        assert_eq!(queued.len(), 3);
+        assert!(!queued.contains(&"https://three.com/".to_string()));
 
-        assert!(queue.is_some());
-        assert_eq!(queue.unwrap().url, url[0]);
+        let external = crawl_queue::external_domains(&db, &[lens]).await.unwrap();
+        assert_eq!(external.len(), 2);
+        assert!(external.contains("one.com"));
     }
 
     #[tokio::test]
-    async fn test_dequeue_with_limit() {
+    async fn test_enqueue_crawl_trap() {
         let settings = UserSettings {
-            domain_crawl_limit: Limit::Finite(2),
+            crawl_trap_limit: Limit::Finite(3),
             ..Default::default()
         };
         let db = setup_test_db().await;
-        let url: Vec<String> = vec!["https://oldschool.runescape.wiki/".into()];
-        let parsed = Url::parse(&url[0]).unwrap();
         let lens = LensConfig {
-            domains: vec!["oldschool.runescape.wiki".into()],
+            domains: vec!["example.com".into()],
             ..Default::default()
         };
+        let mut urls = (1..=5)
+            .map(|day| format!("https://example.com/calendar?date=2099-12-{day:02}"))
+            .collect::<Vec<_>>();
+        urls.push("https://example.com/about".to_string());
 
         crawl_queue::enqueue_all(
             &db,
-            &url,
-            &[lens],
+            &urls,
+            &[lens.clone()],
             &settings,
             &Default::default(),
             Option::None,
         )
         .await
         .unwrap();
-        let doc = indexed_document::ActiveModel {
-            domain: Set(parsed.host_str().unwrap().to_string()),
-            url: Set(url[0].clone()),
-            doc_id: Set("docid".to_string()),
-            ..Default::default()
-        };
+
+        let queued = crawl_queue::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|task| task.url)
+            .collect::<Vec<String>>();
+        assert_eq!(queued.len(), 4);
+        assert!(queued.contains(&"https://example.com/about".to_string()));
+
+        // Trap is remembered for later crawls
+        let more = vec!["https://example.com/calendar?date=2100-01-01".to_string()];
+        crawl_queue::enqueue_all(
+            &db,
+            &more,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(crawl_queue::Entity::find().all(&db).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_blocked_url() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let lens = LensConfig {
+            domains: vec!["example.com".into()],
+            ..Default::default()
+        };
+        url_blocklist::add(&db, "https://example.com/tracking")
+            .await
+            .unwrap();
+
+        let urls = vec![
+            "https://example.com/".to_string(),
+            "https://example.com/tracking".to_string(),
+        ];
+        crawl_queue::enqueue_all(
+            &db,
+            &urls,
+            &[lens],
+            &settings,
+            &EnqueueSettings {
+                force_allow: true,
+                ..Default::default()
+            },
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        let queued = crawl_queue::Entity::find().all(&db).await.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].url, "https://example.com/");
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_stay_on_domain() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let lens = LensConfig {
+            urls: vec![
+                "https://example.com/docs/".into(),
+                "https://other.com/".into(),
+            ],
+            ..Default::default()
+        };
+
+        let seed_host = suburl_seed_host(&[lens.clone()], "https://example.com/docs/intro");
+        assert_eq!(seed_host, Some("example.com".to_string()));
+
+        let urls = vec![
+            "https://example.com/docs/install".to_string(),
+            "https://other.com/page".to_string(),
+        ];
+        crawl_queue::enqueue_all(
+            &db,
+            &urls,
+            &[lens],
+            &settings,
+            &EnqueueSettings {
+                seed_host,
+                ..Default::default()
+            },
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        let queued = crawl_queue::Entity::find().all(&db).await.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].url, "https://example.com/docs/install");
+    }
+
+    #[test]
+    fn test_is_same_site() {
+        let check = |link: &str, allow_subdomains: bool| {
+            is_same_site("example.com", &Url::parse(link).unwrap(), allow_subdomains)
+        };
+
+        assert!(check("https://example.com/about", false));
+        assert!(check("http://EXAMPLE.com/about", false));
+        assert!(!check("https://docs.example.com/", false));
+        assert!(check("https://docs.example.com/", true));
+        assert!(!check("https://other.com/", true));
+        assert!(!check("https://example.com.evil.org/", true));
+        assert!(check("mailto:hello@other.com", false));
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_with_rules() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let url = vec!["https://oldschool.runescape.wiki/w/Worn_Equipment?veaction=edit".into()];
+        let lens = LensConfig {
+            domains: vec!["oldschool.runescape.wiki".into()],
+            rules: vec![LensRule::SkipURL(
+                "https://oldschool.runescape.wiki/*veaction=*".into(),
+            )],
+            ..Default::default()
+        };
+
+        crawl_queue::enqueue_all(
+            &db,
+            &url,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        let crawl = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Url.eq(url[0].to_string()))
+            .all(&db)
+            .await
+            .unwrap();
+
+        assert_eq!(crawl.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dequeue() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let url = vec!["https://oldschool.runescape.wiki/".into()];
+        let lens = LensConfig {
+            domains: vec!["oldschool.runescape.wiki".into()],
+            ..Default::default()
+        };
+
+        crawl_queue::enqueue_all(
+            &db,
+            &url,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        let queue = crawl_queue::dequeue(&db, &settings).await.unwrap();
+
+        assert!(queue.is_some());
+        assert_eq!(queue.unwrap().url, url[0]);
+    }
+
+    #[tokio::test]
+    async fn test_retry_backoff() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let url = vec!["https://oldschool.runescape.wiki/".into()];
+        let lens = LensConfig {
+            domains: vec!["oldschool.runescape.wiki".into()],
+            ..Default::default()
+        };
+
+        crawl_queue::enqueue_all(
+            &db,
+            &url,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        let task = crawl_queue::dequeue(&db, &settings).await.unwrap().unwrap();
+        let error = crawl_queue::TaskError::new(crawl_queue::TaskErrorType::Fetch, "timed out");
+        assert!(crawl_queue::mark_failed(&db, task.id, 2, error.clone()).await);
+
+        // Queued again, but not ready to be retried yet
+        let task = crawl_queue::Entity::find_by_id(task.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.status, CrawlStatus::Queued);
+        assert_eq!(task.num_retries, 1);
+        assert!(task.next_retry_at.unwrap() > chrono::Utc::now());
+        assert!(crawl_queue::dequeue(&db, &settings)
+            .await
+            .unwrap()
+            .is_none());
+
+        let mut update: crawl_queue::ActiveModel = task.into();
+        update.next_retry_at = Set(Some(chrono::Utc::now() - chrono::Duration::minutes(1)));
+        update.update(&db).await.unwrap();
+        let task = crawl_queue::dequeue(&db, &settings).await.unwrap().unwrap();
+
+        assert!(crawl_queue::mark_failed(&db, task.id, 2, error.clone()).await);
+        let task = crawl_queue::Entity::find_by_id(task.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.num_retries, 2);
+
+        // Out of retries
+        assert!(!crawl_queue::mark_failed(&db, task.id, 2, error).await);
+        let task = crawl_queue::Entity::find_by_id(task.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.status, CrawlStatus::Failed);
+        assert_eq!(task.next_retry_at, None);
+
+        assert_eq!(crawl_queue::retry_delay(0), chrono::Duration::minutes(1));
+        assert_eq!(crawl_queue::retry_delay(1), chrono::Duration::minutes(4));
+        assert_eq!(crawl_queue::retry_delay(2), chrono::Duration::minutes(16));
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_across_domains() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let urls: Vec<String> = vec![
+            "https://oldschool.runescape.wiki/".into(),
+            "https://oldschool.runescape.wiki/w/Quests".into(),
+            "https://example.com/".into(),
+        ];
+        let lens = LensConfig {
+            domains: vec!["oldschool.runescape.wiki".into(), "example.com".into()],
+            ..Default::default()
+        };
+
+        crawl_queue::enqueue_all(
+            &db,
+            &urls,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        // Domains take turns, one with more queued tasks doesn't go twice in
+        // a row.
+        let first = crawl_queue::dequeue(&db, &settings).await.unwrap().unwrap();
+        let second = crawl_queue::dequeue(&db, &settings).await.unwrap().unwrap();
+        assert_ne!(first.domain, second.domain);
+
+        let third = crawl_queue::dequeue(&db, &settings).await.unwrap().unwrap();
+        assert_eq!(third.domain, "oldschool.runescape.wiki");
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_except() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let urls: Vec<String> = vec![
+            "https://oldschool.runescape.wiki/".into(),
+            "https://example.com/".into(),
+        ];
+        let lens = LensConfig {
+            domains: vec!["oldschool.runescape.wiki".into(), "example.com".into()],
+            ..Default::default()
+        };
+
+        crawl_queue::enqueue_all(
+            &db,
+            &urls,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        let skip = vec!["oldschool.runescape.wiki".to_string()];
+        let task = crawl_queue::dequeue_except(&db, &settings, &skip)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.domain, "example.com");
+
+        // Only the skipped domain is left
+        let task = crawl_queue::dequeue_except(&db, &settings, &skip)
+            .await
+            .unwrap();
+        assert!(task.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_paused_lens() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let url = vec!["https://oldschool.runescape.wiki/".into()];
+        let lens_config = LensConfig {
+            name: "runescape".into(),
+            domains: vec!["oldschool.runescape.wiki".into()],
+            ..Default::default()
+        };
+
+        lens::add_or_enable(&db, &lens_config, lens::LensType::Simple)
+            .await
+            .unwrap();
+        crawl_queue::enqueue_all(
+            &db,
+            &url,
+            &[lens_config.clone()],
+            &settings,
+            &EnqueueSettings {
+                tags: vec![(TagType::Lens, lens_config.name.clone())],
+                ..Default::default()
+            },
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        // Tasks for a paused lens stay in the queue
+        assert!(lens::set_paused(&db, "runescape", true).await.unwrap());
+        let queue = crawl_queue::dequeue(&db, &settings).await.unwrap();
+        assert!(queue.is_none());
+
+        assert!(lens::set_paused(&db, "runescape", false).await.unwrap());
+        let queue = crawl_queue::dequeue(&db, &settings).await.unwrap();
+        assert_eq!(queue.unwrap().url, url[0]);
+    }
+
+    #[tokio::test]
+    async fn test_oldest_tasks() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let url = vec!["https://oldschool.runescape.wiki/".into()];
+        let lens = LensConfig {
+            domains: vec!["oldschool.runescape.wiki".into()],
+            ..Default::default()
+        };
+
+        assert!(crawl_queue::oldest_queued(&db).await.unwrap().is_none());
+        assert!(crawl_queue::oldest_processing(&db).await.unwrap().is_none());
+
+        crawl_queue::enqueue_all(
+            &db,
+            &url,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        assert!(crawl_queue::oldest_queued(&db).await.unwrap().is_some());
+        assert!(crawl_queue::oldest_processing(&db).await.unwrap().is_none());
+
+        crawl_queue::dequeue(&db, &settings).await.unwrap();
+        assert!(crawl_queue::oldest_queued(&db).await.unwrap().is_none());
+        assert!(crawl_queue::oldest_processing(&db).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_expire_tasks() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let url = vec!["https://oldschool.runescape.wiki/".into()];
+        let lens = LensConfig {
+            domains: vec!["oldschool.runescape.wiki".into()],
+            ..Default::default()
+        };
+
+        crawl_queue::enqueue_all(
+            &db,
+            &url,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        // Nothing is old enough to expire yet
+        assert_eq!(crawl_queue::expire_tasks(&db, 1).await.unwrap(), 0);
+
+        crawl_queue::Entity::update_many()
+            .col_expr(
+                crawl_queue::Column::CreatedAt,
+                sea_query::Expr::value(chrono::Utc::now() - chrono::Duration::days(2)),
+            )
+            .exec(&db)
+            .await
+            .unwrap();
+
+        assert_eq!(crawl_queue::expire_tasks(&db, 1).await.unwrap(), 1);
+        assert_eq!(
+            crawl_queue::num_queued(&db, CrawlStatus::Expired)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(crawl_queue::dequeue(&db, &settings)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_with_limit() {
+        let settings = UserSettings {
+            domain_crawl_limit: Limit::Finite(2),
+            ..Default::default()
+        };
+        let db = setup_test_db().await;
+        let url: Vec<String> = vec!["https://oldschool.runescape.wiki/".into()];
+        let parsed = Url::parse(&url[0]).unwrap();
+        let lens = LensConfig {
+            domains: vec!["oldschool.runescape.wiki".into()],
+            ..Default::default()
+        };
+
+        crawl_queue::enqueue_all(
+            &db,
+            &url,
+            &[lens],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+        let doc = indexed_document::ActiveModel {
+            domain: Set(parsed.host_str().unwrap().to_string()),
+            url: Set(url[0].clone()),
+            doc_id: Set("docid".to_string()),
+            ..Default::default()
+        };
         doc.save(&db).await.unwrap();
         let queue = crawl_queue::dequeue(&db, &settings).await.unwrap();
         assert!(queue.is_some());
@@ -1231,6 +2316,110 @@ mod test {
         assert!(queue.is_none());
     }
 
+    #[tokio::test]
+    async fn test_dequeue_priority() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let discovered: Vec<String> = vec!["https://example.com/".into()];
+        let added: Vec<String> = vec!["https://other.com/".into()];
+
+        crawl_queue::enqueue_all(
+            &db,
+            &discovered,
+            &[],
+            &settings,
+            &EnqueueSettings {
+                force_allow: true,
+                ..Default::default()
+            },
+            Option::None,
+        )
+        .await
+        .unwrap();
+        crawl_queue::enqueue_all(
+            &db,
+            &added,
+            &[],
+            &settings,
+            &EnqueueSettings {
+                force_allow: true,
+                priority: crawl_queue::PRIORITY_USER,
+                ..Default::default()
+            },
+            Option::None,
+        )
+        .await
+        .unwrap();
+
+        // Queued later, but crawled first
+        let task = crawl_queue::dequeue(&db, &settings).await.unwrap().unwrap();
+        assert_eq!(task.url, added[0]);
+        assert_eq!(task.priority, crawl_queue::PRIORITY_USER);
+        let task = crawl_queue::dequeue(&db, &settings).await.unwrap().unwrap();
+        assert_eq!(task.url, discovered[0]);
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_with_lens_limit() {
+        let settings = UserSettings {
+            domain_crawl_limit: Limit::Finite(1),
+            ..Default::default()
+        };
+        let db = setup_test_db().await;
+        let url: Vec<String> = vec!["https://oldschool.runescape.wiki/".into()];
+        let lens_config = LensConfig {
+            name: "runescape".into(),
+            domains: vec!["oldschool.runescape.wiki".into()],
+            domain_crawl_limit: Some(Limit::Finite(5)),
+            ..Default::default()
+        };
+
+        lens::add_or_enable(&db, &lens_config, lens::LensType::Simple)
+            .await
+            .unwrap();
+        indexed_document::ActiveModel {
+            domain: Set("oldschool.runescape.wiki".to_string()),
+            url: Set("https://oldschool.runescape.wiki/w/Tree".to_string()),
+            doc_id: Set("docid".to_string()),
+            ..Default::default()
+        }
+        .save(&db)
+        .await
+        .unwrap();
+
+        // Over the global limit, but not the lens'
+        crawl_queue::enqueue_all(
+            &db,
+            &url,
+            &[lens_config.clone()],
+            &settings,
+            &EnqueueSettings {
+                tags: vec![(TagType::Lens, lens_config.name.clone())],
+                ..Default::default()
+            },
+            Option::None,
+        )
+        .await
+        .unwrap();
+        let queue = crawl_queue::dequeue(&db, &settings).await.unwrap();
+        assert_eq!(queue.unwrap().url, url[0]);
+
+        // Tasks w/o the lens still use the global limit
+        let other: Vec<String> = vec!["https://oldschool.runescape.wiki/w/Rune".into()];
+        crawl_queue::enqueue_all(
+            &db,
+            &other,
+            &[lens_config],
+            &settings,
+            &Default::default(),
+            Option::None,
+        )
+        .await
+        .unwrap();
+        let queue = crawl_queue::dequeue(&db, &settings).await.unwrap();
+        assert!(queue.is_none());
+    }
+
     #[tokio::test]
     async fn test_remove_by_rule() {
         let settings = UserSettings::default();
@@ -1349,6 +2538,73 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_filter_urls_collapse_equivalent() {
+        let settings = UserSettings {
+            collapse_equivalent_urls: true,
+            ..Default::default()
+        };
+        let overrides = EnqueueSettings::default();
+        let lens = LensConfig {
+            domains: vec!["example.com".into()],
+            ..Default::default()
+        };
+
+        let to_enqueue = vec![
+            "https://example.com/docs".into(),
+            "https://example.com/docs/".into(),
+            "https://example.com/docs/index.html".into(),
+            "https://example.com/docs/index.htm".into(),
+        ];
+
+        let filtered = filter_urls(&[lens], &settings, &overrides, &to_enqueue)
+            .expect("Unable to filter urls");
+        assert_eq!(filtered.len(), 4);
+        assert!(filtered.iter().all(|url| url == "https://example.com/docs"));
+    }
+
+    #[test]
+    fn test_filter_urls_query_strings() {
+        let overrides = EnqueueSettings::default();
+        let lens = LensConfig {
+            domains: vec!["example.com".into()],
+            ..Default::default()
+        };
+        let to_enqueue = vec![
+            "https://example.com/docs".into(),
+            "https://example.com/docs?page=2".into(),
+            "https://example.com/docs?page=2&utm_source=feed".into(),
+        ];
+
+        let settings = UserSettings::default();
+        let filtered = filter_urls(&[lens.clone()], &settings, &overrides, &to_enqueue)
+            .expect("Unable to filter urls");
+        assert_eq!(filtered.len(), 3);
+
+        let settings = UserSettings {
+            index_query_string_urls: QueryStringUrls::Never,
+            ..Default::default()
+        };
+        let filtered = filter_urls(&[lens.clone()], &settings, &overrides, &to_enqueue)
+            .expect("Unable to filter urls");
+        assert_eq!(filtered, vec!["https://example.com/docs".to_string()]);
+
+        let settings = UserSettings {
+            index_query_string_urls: QueryStringUrls::OnlyWhitelisted,
+            query_string_whitelist: vec!["page".into()],
+            ..Default::default()
+        };
+        let filtered = filter_urls(&[lens], &settings, &overrides, &to_enqueue)
+            .expect("Unable to filter urls");
+        assert_eq!(
+            filtered,
+            vec![
+                "https://example.com/docs".to_string(),
+                "https://example.com/docs?page=2".to_string()
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_update_or_remove_task() {
         let db = setup_test_db().await;
@@ -1384,6 +2640,10 @@ mod test {
             .expect("should exist");
         // Old task should be marked as failed
         assert_eq!(task.status, CrawlStatus::Failed);
+        assert_eq!(
+            task.canonical_url(),
+            Some("https://example.com".to_string())
+        );
         // New model should have the canonical URL.
         assert_eq!(res.url, "https://example.com");
         assert_eq!(res.id, first.id);
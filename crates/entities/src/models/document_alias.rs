@@ -0,0 +1,176 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::Set;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// URLs that weren't indexed because their content is identical to an already
+/// indexed document, e.g. the same page served w/ & w/o a trailing slash.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "document_alias")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub url: String,
+    /// URL of the indexed document w/ the same content.
+    pub canonical_url: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Hash stored as `indexed_document.content_hash` to spot the same page
+/// served under different URLs. Covers the title as well as the text, so
+/// pages that only share boilerplate (e.g. a login wall) aren't collapsed.
+pub fn content_hash(title: &str, content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(title.trim().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(content.trim().as_bytes());
+    hex::encode(&hasher.finalize()[..])
+}
+
+/// Site a page belongs to for aliasing, its domain w/o a leading `www.`.
+/// Pages are only collapsed into documents from the same site.
+pub fn alias_site(domain: &str) -> &str {
+    domain.strip_prefix("www.").unwrap_or(domain)
+}
+
+/// Records `url` as an alias of `canonical_url`, replacing any previous alias.
+pub async fn add<C>(db: &C, url: &str, canonical_url: &str) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let model = ActiveModel {
+        url: Set(url.to_string()),
+        canonical_url: Set(canonical_url.to_string()),
+        created_at: Set(chrono::Utc::now()),
+        ..Default::default()
+    };
+
+    Entity::insert(model)
+        .on_conflict(
+            OnConflict::column(Column::Url)
+                .update_column(Column::CanonicalUrl)
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Drops aliases for URLs that are now indexed themselves.
+pub async fn remove_many<C>(db: &C, urls: &[String]) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if urls.is_empty() {
+        return Ok(0);
+    }
+
+    let res = Entity::delete_many()
+        .filter(Column::Url.is_in(urls.to_vec()))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
+/// Number of URLs collapsed into another document.
+pub async fn count<C>(db: &C) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find().count(db).await
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::document_alias;
+    use crate::test::setup_test_db;
+
+    #[test]
+    fn test_content_hash() {
+        let hash = document_alias::content_hash("Title", "some content");
+        assert_eq!(
+            hash,
+            document_alias::content_hash(" Title", "some content\n")
+        );
+        assert_ne!(hash, document_alias::content_hash("Other", "some content"));
+        assert_eq!(document_alias::alias_site("www.example.com"), "example.com");
+        assert_eq!(
+            document_alias::alias_site("blog.example.com"),
+            "blog.example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aliases() {
+        let db = setup_test_db().await;
+
+        document_alias::add(&db, "https://example.com/a/", "https://example.com/a")
+            .await
+            .unwrap();
+        document_alias::add(&db, "https://www.example.com/a", "https://example.com/a")
+            .await
+            .unwrap();
+        // Re-adding an alias doesn't count it twice
+        document_alias::add(&db, "https://example.com/a/", "https://example.com/b")
+            .await
+            .unwrap();
+        assert_eq!(document_alias::count(&db).await.unwrap(), 2);
+
+        let removed = document_alias::remove_many(&db, &["https://example.com/a/".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(document_alias::count(&db).await.unwrap(), 1);
+    }
+}
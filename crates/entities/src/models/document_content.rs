@@ -0,0 +1,178 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+use serde::{Deserialize, Serialize};
+
+/// Code blocks of a document, stored as a JSON list.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct CodeBlocks(pub Vec<String>);
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "document_content")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// URL of the indexed document.
+    #[sea_orm(unique)]
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    /// Text content of the document, as it was last indexed.
+    pub content: String,
+    /// Main article text, if it could be extracted.
+    pub body: Option<String>,
+    /// Code blocks indexed w/ the document.
+    pub code: CodeBlocks,
+    /// When the document was published, if known.
+    pub published_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+        self.updated_at = Set(chrono::Utc::now());
+
+        Ok(self)
+    }
+}
+
+/// Returns the stored content of the document at `url`, if any.
+pub async fn get<C>(db: &C, url: &str) -> Result<Option<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find().filter(Column::Url.eq(url)).one(db).await
+}
+
+/// Content of a document to store, see `save`.
+#[derive(Clone, Debug, Default)]
+pub struct NewContent<'a> {
+    pub title: &'a str,
+    pub description: &'a str,
+    pub content: &'a str,
+    pub body: Option<&'a str>,
+    pub code: &'a [String],
+    pub published_at: Option<DateTimeUtc>,
+}
+
+/// Stores the content of the document at `url`, replacing what was stored
+/// before.
+pub async fn save<C>(db: &C, url: &str, new_content: &NewContent<'_>) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut stored: ActiveModel = match get(db, url).await? {
+        Some(existing) => existing.into(),
+        None => ActiveModel {
+            url: Set(url.to_string()),
+            ..Default::default()
+        },
+    };
+    stored.title = Set(new_content.title.to_string());
+    stored.description = Set(new_content.description.to_string());
+    stored.content = Set(new_content.content.to_string());
+    stored.body = Set(new_content.body.map(|body| body.to_string()));
+    stored.code = Set(CodeBlocks(new_content.code.to_vec()));
+    stored.published_at = Set(new_content.published_at);
+    stored.save(db).await?;
+
+    Ok(())
+}
+
+/// Removes the stored content of any of the `urls`.
+pub async fn delete_by_urls<C>(db: &C, urls: &[String]) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let res = Entity::delete_many()
+        .filter(Column::Url.is_in(urls.to_vec()))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::document_content::{self, NewContent};
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_save() {
+        let db = setup_test_db().await;
+        let url = "https://example.com/";
+
+        let first = NewContent {
+            title: "title",
+            content: "first",
+            ..Default::default()
+        };
+        document_content::save(&db, url, &first).await.unwrap();
+        let code = vec!["foo.bar()".to_string()];
+        let published_at = "2023-12-01T00:00:00Z".parse().unwrap();
+        let second = NewContent {
+            title: "new title",
+            description: "description",
+            content: "second",
+            body: Some("body"),
+            code: &code,
+            published_at: Some(published_at),
+        };
+        document_content::save(&db, url, &second).await.unwrap();
+
+        let stored = document_content::get(&db, url).await.unwrap().unwrap();
+        assert_eq!(stored.title, "new title");
+        assert_eq!(stored.description, "description");
+        assert_eq!(stored.published_at, Some(published_at));
+        assert_eq!(stored.content, "second");
+        assert_eq!(stored.body.as_deref(), Some("body"));
+        assert_eq!(stored.code.0, code);
+
+        let removed = document_content::delete_by_urls(&db, &[url.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(document_content::get(&db, url).await.unwrap().is_none());
+    }
+}
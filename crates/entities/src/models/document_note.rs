@@ -0,0 +1,152 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "document_notes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Canonical URL of the annotated document. Notes are keyed on the URL rather
+    /// than the document so they persist when the document is recrawled.
+    #[sea_orm(unique)]
+    pub url: String,
+    /// User provided note text
+    pub note: String,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+        self.updated_at = Set(chrono::Utc::now());
+
+        Ok(self)
+    }
+}
+
+/// Returns the note attached to `url`, if any.
+pub async fn get_note<C>(db: &C, url: &str) -> Result<Option<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find().filter(Column::Url.eq(url)).one(db).await
+}
+
+/// Returns the notes attached to any of the `urls`, keyed by URL.
+pub async fn get_notes<C>(db: &C, urls: &[String]) -> Result<HashMap<String, String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let notes = Entity::find()
+        .filter(Column::Url.is_in(urls.to_vec()))
+        .all(db)
+        .await?;
+
+    Ok(notes
+        .into_iter()
+        .map(|model| (model.url, model.note))
+        .collect())
+}
+
+/// Sets the note for `url`, replacing any existing note. An empty note removes
+/// the note entirely.
+pub async fn set_note<C>(db: &C, url: &str, note: &str) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let existing = get_note(db, url).await?;
+    let note = note.trim();
+
+    match existing {
+        Some(existing) if note.is_empty() => {
+            existing.delete(db).await?;
+        }
+        Some(existing) => {
+            let mut update: ActiveModel = existing.into();
+            update.note = Set(note.to_string());
+            update.update(db).await?;
+        }
+        None if note.is_empty() => {}
+        None => {
+            let new_note = ActiveModel {
+                url: Set(url.to_string()),
+                note: Set(note.to_string()),
+                ..Default::default()
+            };
+            new_note.insert(db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::document_note;
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_set_note() {
+        let db = setup_test_db().await;
+        let url = "https://example.com/";
+
+        document_note::set_note(&db, url, "first").await.unwrap();
+        document_note::set_note(&db, url, "second").await.unwrap();
+
+        let note = document_note::get_note(&db, url).await.unwrap();
+        assert_eq!(note.map(|n| n.note), Some("second".to_string()));
+
+        let notes = document_note::get_notes(&db, &[url.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(notes.get(url), Some(&"second".to_string()));
+
+        // Empty notes are removed
+        document_note::set_note(&db, url, "").await.unwrap();
+        let note = document_note::get_note(&db, url).await.unwrap();
+        assert!(note.is_none());
+    }
+}
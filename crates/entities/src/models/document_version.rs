@@ -0,0 +1,146 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "document_versions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Canonical URL of the document. Only the most recent prior version of a
+    /// document is kept.
+    #[sea_orm(unique)]
+    pub url: String,
+    /// Content of the document before it was last recrawled.
+    pub content: String,
+    pub created_at: DateTimeUtc,
+    /// When this version was replaced by the currently indexed content.
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+        self.updated_at = Set(chrono::Utc::now());
+
+        Ok(self)
+    }
+}
+
+/// Returns the previous version of the document at `url`, if any.
+pub async fn get_previous<C>(db: &C, url: &str) -> Result<Option<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find().filter(Column::Url.eq(url)).one(db).await
+}
+
+/// Keeps `content` as the previous version of `url`, replacing any older
+/// version.
+pub async fn save_previous<C>(db: &C, url: &str, content: &str) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    match get_previous(db, url).await? {
+        Some(existing) => {
+            let mut update: ActiveModel = existing.into();
+            update.content = Set(content.to_string());
+            update.update(db).await?;
+        }
+        None => {
+            let version = ActiveModel {
+                url: Set(url.to_string()),
+                content: Set(content.to_string()),
+                ..Default::default()
+            };
+            version.insert(db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the previous versions of any of the `urls`.
+pub async fn delete_by_urls<C>(db: &C, urls: &[String]) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let res = Entity::delete_many()
+        .filter(Column::Url.is_in(urls.to_vec()))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::document_version;
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_save_previous() {
+        let db = setup_test_db().await;
+        let url = "https://example.com/";
+
+        document_version::save_previous(&db, url, "first")
+            .await
+            .unwrap();
+        document_version::save_previous(&db, url, "second")
+            .await
+            .unwrap();
+
+        // Only the most recent version is kept
+        let previous = document_version::get_previous(&db, url).await.unwrap();
+        assert_eq!(previous.map(|v| v.content), Some("second".to_string()));
+
+        let removed = document_version::delete_by_urls(&db, &[url.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(document_version::get_previous(&db, url)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,12 +1,13 @@
 use std::collections::HashSet;
 use std::ops::Sub;
 
-use crate::models::{document_tag, tag};
+use crate::models::{crawl_queue, document_alias, document_content, document_tag, tag};
 use crate::BATCH_SIZE;
 use sea_orm::entity::prelude::*;
-use sea_orm::sea_query::OnConflict;
+use sea_orm::sea_query::{Expr, OnConflict, Query};
 use sea_orm::{
-    ConnectionTrait, FromQueryResult, InsertResult, QuerySelect, QueryTrait, Set, Statement,
+    Condition, ConnectionTrait, FromQueryResult, InsertResult, QueryOrder, QuerySelect, QueryTrait,
+    Set, Statement,
 };
 use serde::Serialize;
 
@@ -30,6 +31,16 @@ pub struct Model {
     pub created_at: DateTimeUtc,
     /// When this was last updated
     pub updated_at: DateTimeUtc,
+    /// Number of links followed from a seed URL when this document was discovered.
+    #[sea_orm(default_value = 0)]
+    pub depth: i32,
+    /// ETag header from the last time this was fetched.
+    pub etag: Option<String>,
+    /// Last-Modified header from the last time this was fetched.
+    pub last_modified: Option<String>,
+    /// SHA-256 of the title & extracted text of web pages, used to spot the
+    /// same page served under different URLs. See `document_alias::content_hash`.
+    pub content_hash: Option<String>,
 }
 
 impl Related<super::tag::Entity> for Entity {
@@ -334,11 +345,11 @@ pub async fn delete_many_by_doc_id(
 }
 
 /// Helper method used to delete multiple documents by id. This method will first
-/// delete all related tag references before deleting the documents
-pub async fn delete_many_by_id(
-    db: &DatabaseConnection,
-    dbids: &[i64],
-) -> Result<u64, sea_orm::DbErr> {
+/// delete all related tag references & stored content before deleting the documents
+pub async fn delete_many_by_id<C>(db: &C, dbids: &[i64]) -> Result<u64, sea_orm::DbErr>
+where
+    C: ConnectionTrait,
+{
     // Delete all associated tags
     document_tag::Entity::delete_many()
         .filter(document_tag::Column::IndexedDocumentId.is_in(dbids.to_owned()))
@@ -348,6 +359,19 @@ pub async fn delete_many_by_id(
     // Delete item
     let mut num_deleted = 0;
     for chunk in dbids.chunks(BATCH_SIZE) {
+        document_content::Entity::delete_many()
+            .filter(
+                document_content::Column::Url.in_subquery(
+                    Query::select()
+                        .column(Column::Url)
+                        .from(Entity)
+                        .and_where(Column::Id.is_in(chunk.to_owned()))
+                        .to_owned(),
+                ),
+            )
+            .exec(db)
+            .await?;
+
         let res = Entity::delete_many()
             .filter(Column::Id.is_in(chunk.to_owned()))
             .exec(db)
@@ -358,6 +382,49 @@ pub async fn delete_many_by_id(
     Ok(num_deleted)
 }
 
+/// Finds the document stored for a crawl of `url`. Pages are indexed under
+/// their canonical URL, so this also checks the URL the page was fetched from
+/// & any duplicate it was merged into.
+pub async fn find_by_crawl_url<C>(db: &C, url: &str) -> Result<Option<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    for column in [Column::Url, Column::OpenUrl] {
+        let doc = Entity::find().filter(column.eq(url)).one(db).await?;
+        if doc.is_some() {
+            return Ok(doc);
+        }
+    }
+
+    let alias = document_alias::Entity::find()
+        .filter(document_alias::Column::Url.eq(url))
+        .one(db)
+        .await?;
+    match alias {
+        Some(alias) => {
+            Entity::find()
+                .filter(Column::Url.eq(alias.canonical_url))
+                .one(db)
+                .await
+        }
+        None => Ok(None),
+    }
+}
+
+/// Bumps `updated_at` for a document that was checked & found unchanged.
+pub async fn touch_by_url(db: &DatabaseConnection, url: &str) -> Result<u64, sea_orm::DbErr> {
+    let res = Entity::update_many()
+        .col_expr(
+            Column::UpdatedAt,
+            sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(Column::Url.eq(url))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
+
 /// Helper method used to delete multiple documents by url. This method will first
 /// delete all related tag references before deleting the documents
 pub async fn delete_many_by_url(
@@ -405,6 +472,55 @@ pub async fn find_by_lens(
     .await
 }
 
+/// Documents that belong to the lens `name` and no other lens.
+pub async fn find_only_in_lens<C>(db: &C, name: &str) -> Result<Vec<IndexedDocumentId>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    IndexedDocumentId::find_by_statement(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        r#"
+        SELECT
+            indexed_document.id,
+            indexed_document.doc_id
+        FROM indexed_document
+        JOIN document_tag on indexed_document.id = document_tag.indexed_document_id
+        JOIN tags on tags.id = document_tag.tag_id
+        WHERE tags.label = "lens" AND tags.value = $1
+        AND indexed_document.id NOT IN (
+            SELECT document_tag.indexed_document_id
+            FROM document_tag
+            JOIN tags on tags.id = document_tag.tag_id
+            WHERE tags.label = "lens" AND tags.value != $1
+        )"#,
+        vec![name.into()],
+    ))
+    .all(db)
+    .await
+}
+
+/// Removes the lens `name` from any documents tagged with it, returning the
+/// number of documents untagged.
+pub async fn remove_lens_tag<C>(db: &C, name: &str) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let lens_tag = tag::Entity::find()
+        .filter(tag::Column::Label.eq(tag::TagType::Lens.to_string()))
+        .filter(tag::Column::Value.eq(name))
+        .one(db)
+        .await?;
+
+    match lens_tag {
+        Some(lens_tag) => Ok(document_tag::Entity::delete_many()
+            .filter(document_tag::Column::TagId.eq(lens_tag.id))
+            .exec(db)
+            .await?
+            .rows_affected),
+        None => Ok(0),
+    }
+}
+
 /// Helper method used to access the documents database id field from the
 /// string document id
 pub async fn find_by_doc_ids(
@@ -485,6 +601,92 @@ pub async fn get_document_details(
     Ok(None)
 }
 
+/// Most recently indexed or recrawled documents, newest first. Optionally
+/// limited to a lens, a domain & documents updated after `after`. With `after`
+/// they're returned oldest first instead, so polling by moving `after` up to
+/// the last document returned doesn't skip any when more than `limit` changed.
+pub async fn find_recent<C>(
+    db: &C,
+    lens: Option<&str>,
+    domain: Option<&str>,
+    after: Option<DateTimeUtc>,
+    limit: u64,
+) -> Result<Vec<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut query = match after {
+        Some(after) => Entity::find()
+            .filter(Column::UpdatedAt.gt(after))
+            .order_by_asc(Column::UpdatedAt)
+            .order_by_asc(Column::Id),
+        None => Entity::find()
+            .order_by_desc(Column::UpdatedAt)
+            .order_by_desc(Column::Id),
+    }
+    .limit(limit);
+
+    if let Some(lens) = lens {
+        let in_lens = Query::select()
+            .column((
+                document_tag::Entity,
+                document_tag::Column::IndexedDocumentId,
+            ))
+            .from(document_tag::Entity)
+            .inner_join(
+                tag::Entity,
+                Expr::col((tag::Entity, tag::Column::Id))
+                    .equals((document_tag::Entity, document_tag::Column::TagId)),
+            )
+            .and_where(
+                Expr::col((tag::Entity, tag::Column::Label)).eq(tag::TagType::Lens.to_string()),
+            )
+            .and_where(Expr::col((tag::Entity, tag::Column::Value)).eq(lens))
+            .to_owned();
+        query = query.filter(Column::Id.in_subquery(in_lens));
+    }
+
+    if let Some(domain) = domain {
+        query = query.filter(Column::Domain.eq(domain));
+    }
+
+    query.all(db).await
+}
+
+/// Web documents last indexed or recrawled before `before`, oldest first.
+/// Documents already queued or being crawled again are skipped, as are ones
+/// whose crawl was attempted since `before`, so pages that keep failing to
+/// recrawl aren't queued again until the next interval.
+pub async fn find_stale<C>(db: &C, before: DateTimeUtc, limit: u64) -> Result<Vec<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let pending = Query::select()
+        .column((crawl_queue::Entity, crawl_queue::Column::Url))
+        .from(crawl_queue::Entity)
+        .cond_where(
+            Condition::any()
+                .add(
+                    Expr::col((crawl_queue::Entity, crawl_queue::Column::Status)).is_in([
+                        crawl_queue::CrawlStatus::Queued,
+                        crawl_queue::CrawlStatus::Processing,
+                    ]),
+                )
+                .add(Expr::col((crawl_queue::Entity, crawl_queue::Column::UpdatedAt)).gt(before)),
+        )
+        .to_owned();
+
+    Entity::find()
+        .filter(Column::UpdatedAt.lt(before))
+        .filter(Column::Url.starts_with("http"))
+        .filter(Column::Url.not_in_subquery(pending))
+        .order_by_asc(Column::UpdatedAt)
+        .order_by_asc(Column::Id)
+        .limit(limit)
+        .all(db)
+        .await
+}
+
 // Helper method to copy the table from one database to another
 pub async fn copy_table(
     from: &DatabaseConnection,
@@ -513,9 +715,9 @@ pub async fn copy_table(
 mod test {
     use std::collections::HashMap;
 
-    use crate::models::document_tag;
-    use crate::models::indexed_document::insert_tags_for_docs;
+    use crate::models::indexed_document::{self, insert_tags_for_docs};
     use crate::models::tag::{self, TagType};
+    use crate::models::{crawl_queue, document_content, document_tag};
     use crate::test::setup_test_db;
     use sea_orm::{ActiveModelTrait, DbErr, EntityTrait, ModelTrait, Set};
 
@@ -538,10 +740,93 @@ mod test {
         };
         doc.save(&db).await.unwrap();
 
+        let kept_url = "https://en.wikipedia.org/wiki/Rust_(programming_language)";
+        let removed_url = "https://en.wikipedia.com/wiki/Cheese?id=13314&action=edit";
+        for url in [kept_url, removed_url] {
+            let new_content = document_content::NewContent {
+                title: "title",
+                content: "content",
+                ..Default::default()
+            };
+            document_content::save(&db, url, &new_content)
+                .await
+                .unwrap();
+        }
+
         let removed = super::delete_by_rule(&db, "https://en.wikipedia.com/%action=%")
             .await
             .unwrap();
         assert_eq!(removed.len(), 1);
+
+        // Stored content goes w/ the document
+        assert!(document_content::get(&db, removed_url)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(document_content::get(&db, kept_url)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_touch_by_url() {
+        let db = setup_test_db().await;
+
+        let checked_at = chrono::Utc::now() - chrono::Duration::days(2);
+        let doc = super::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/".into()),
+            doc_id: Set("1".into()),
+            updated_at: Set(checked_at),
+            etag: Set(Some("\"v1\"".into())),
+            ..Default::default()
+        };
+        let doc = doc.insert(&db).await.unwrap();
+
+        let updated = super::touch_by_url(&db, "https://example.com/")
+            .await
+            .unwrap();
+        assert_eq!(updated, 1);
+
+        let doc = super::Entity::find_by_id(doc.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(doc.updated_at > checked_at);
+        assert_eq!(doc.etag, Some("\"v1\"".into()));
+    }
+
+    #[tokio::test]
+    async fn test_find_by_crawl_url() {
+        let db = setup_test_db().await;
+
+        let doc = super::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/page".into()),
+            open_url: Set(Some("https://example.com/page?ref=home".into())),
+            doc_id: Set("1".into()),
+            ..Default::default()
+        };
+        let doc = doc.insert(&db).await.unwrap();
+        crate::models::document_alias::add(&db, "https://example.com/page/", &doc.url)
+            .await
+            .unwrap();
+
+        for url in [
+            "https://example.com/page",
+            "https://example.com/page?ref=home",
+            "https://example.com/page/",
+        ] {
+            let found = super::find_by_crawl_url(&db, url).await.unwrap();
+            assert_eq!(found.map(|found| found.id), Some(doc.id));
+        }
+
+        let found = super::find_by_crawl_url(&db, "https://example.com/other")
+            .await
+            .unwrap();
+        assert!(found.is_none());
     }
 
     #[tokio::test]
@@ -641,4 +926,130 @@ mod test {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_find_recent() {
+        let db = setup_test_db().await;
+
+        let now = chrono::Utc::now();
+        for (idx, (domain, lens)) in [
+            ("example.com", "docs"),
+            ("example.com", "blog"),
+            ("other.com", "docs"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let doc = indexed_document::ActiveModel {
+                domain: Set(domain.into()),
+                url: Set(format!("https://{domain}/{idx}")),
+                doc_id: Set(format!("doc_{idx}")),
+                updated_at: Set(now - chrono::Duration::minutes(10 - idx as i64)),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert doc");
+            doc.insert_tags(&db, &[(TagType::Lens, lens.to_owned())])
+                .await
+                .expect("Unable to insert tags");
+        }
+
+        let recent = indexed_document::find_recent(&db, None, None, None, 2)
+            .await
+            .expect("Unable to find docs");
+        let doc_ids = recent
+            .iter()
+            .map(|doc| doc.doc_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(doc_ids, vec!["doc_2", "doc_1"]);
+
+        let recent = indexed_document::find_recent(&db, Some("docs"), None, None, 10)
+            .await
+            .expect("Unable to find docs");
+        let doc_ids = recent
+            .iter()
+            .map(|doc| doc.doc_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(doc_ids, vec!["doc_2", "doc_0"]);
+
+        let recent =
+            indexed_document::find_recent(&db, Some("docs"), Some("example.com"), None, 10)
+                .await
+                .expect("Unable to find docs");
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].doc_id, "doc_0");
+
+        // Only documents updated since the last check
+        let after = now - chrono::Duration::minutes(9);
+        let recent = indexed_document::find_recent(&db, None, None, Some(after), 10)
+            .await
+            .expect("Unable to find docs");
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].doc_id, "doc_2");
+
+        // Oldest first when polling, so no documents are skipped
+        let after = now - chrono::Duration::minutes(11);
+        let recent = indexed_document::find_recent(&db, None, None, Some(after), 2)
+            .await
+            .expect("Unable to find docs");
+        let doc_ids = recent
+            .iter()
+            .map(|doc| doc.doc_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(doc_ids, vec!["doc_0", "doc_1"]);
+    }
+
+    #[tokio::test]
+    async fn test_find_stale() {
+        let db = setup_test_db().await;
+
+        let now = chrono::Utc::now();
+        for (idx, url, days_old) in [
+            (0, "https://example.com/old", 10),
+            (1, "https://example.com/older", 20),
+            (2, "https://example.com/new", 1),
+            (3, "https://example.com/queued", 30),
+            (4, "file:///tmp/notes.txt", 30),
+        ] {
+            indexed_document::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(url.into()),
+                doc_id: Set(format!("doc_{idx}")),
+                updated_at: Set(now - chrono::Duration::days(days_old)),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert doc");
+        }
+
+        crawl_queue::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/queued".into()),
+            status: Set(crawl_queue::CrawlStatus::Queued),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert task");
+
+        // Failed to recrawl recently, not due again until the next interval
+        crawl_queue::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/old".into()),
+            status: Set(crawl_queue::CrawlStatus::Failed),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert task");
+
+        let stale = indexed_document::find_stale(&db, now - chrono::Duration::days(7), 10)
+            .await
+            .expect("Unable to find docs");
+        let urls = stale.iter().map(|doc| doc.url.as_str()).collect::<Vec<_>>();
+        assert_eq!(urls, vec!["https://example.com/older"]);
+    }
 }
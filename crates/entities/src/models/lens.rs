@@ -6,6 +6,7 @@ use sea_orm::Set;
 use sea_orm::{ConnectionTrait, Statement};
 use serde::Serialize;
 use shared::config::LensConfig;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize)]
 #[sea_orm(rs_type = "String", db_type = "String(Some(1))")]
@@ -42,6 +43,16 @@ pub struct Model {
     pub last_cache_update: Option<DateTimeUtc>,
     // Indicates the url of the remote source of the lens
     pub remote_url: Option<String>,
+    // Crawls for a paused lens are skipped until the lens is resumed.
+    #[sea_orm(default_value = false)]
+    pub is_paused: bool,
+    // Multiplier applied to the search score of documents in this lens, as a
+    // percentage. 100 leaves scores unchanged.
+    #[sea_orm(default_value = 100)]
+    pub ranking_weight: i32,
+    // Pages allowed per domain for this lens' crawls, in place of the user's
+    // domain crawl limit. Comes from the lens config.
+    pub domain_crawl_limit: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter)]
@@ -108,6 +119,13 @@ pub async fn update_cache_time(
     Ok(false)
 }
 
+/// Per-lens domain crawl limit as stored, w/ no limit stored as u32::MAX
+fn domain_crawl_limit(lens: &LensConfig) -> Option<i64> {
+    lens.domain_crawl_limit
+        .as_ref()
+        .map(|limit| i64::from(limit.value()))
+}
+
 /// True if the lens was added, False if it already exists.
 pub async fn add_or_enable(
     db: &DatabaseConnection,
@@ -134,6 +152,7 @@ pub async fn add_or_enable(
         updated.version = Set(lens.version.to_string());
         updated.trigger = Set(Some(trigger_label));
         updated.hash = Set(Some(lens.hash.clone()));
+        updated.domain_crawl_limit = Set(domain_crawl_limit(lens));
 
         match &lens.description {
             Some(desc) => updated.description = Set(Some(desc.clone())),
@@ -169,6 +188,7 @@ pub async fn add_or_enable(
         lens_type: Set(lens_type),
         remote_url: Set(None),
         hash: Set(Some(lens.hash.to_owned())),
+        domain_crawl_limit: Set(domain_crawl_limit(lens)),
         ..Default::default()
     };
     let new_db_entry = new_lens.insert(db).await?;
@@ -198,6 +218,7 @@ pub async fn install_or_update(
         // Update description / etc.
         updated.author = Set(lens.author.to_string());
         updated.version = Set(lens.version.to_string());
+        updated.domain_crawl_limit = Set(domain_crawl_limit(lens));
 
         // If a change occurred such that we are reinstalling we
         // need to clean out the last cache update to allow
@@ -226,6 +247,7 @@ pub async fn install_or_update(
         is_enabled: Set(lens_type == LensType::Simple),
         lens_type: Set(lens_type),
         remote_url: Set(remote_url),
+        domain_crawl_limit: Set(domain_crawl_limit(lens)),
         ..Default::default()
     };
     let new_db_entry = new_lens.insert(db).await?;
@@ -233,6 +255,73 @@ pub async fn install_or_update(
     Ok((true, new_db_entry))
 }
 
+/// Pauses/resumes crawling for the lens. Returns false if there is no lens
+/// with that name.
+pub async fn set_paused(
+    db: &DatabaseConnection,
+    lens_name: &str,
+    is_paused: bool,
+) -> Result<bool, DbErr> {
+    match find_by_name(lens_name, db).await? {
+        Some(existing) => {
+            let mut updated: ActiveModel = existing.into();
+            updated.is_paused = Set(is_paused);
+            updated.update(db).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Default ranking weight, documents are scored as is.
+pub const DEFAULT_RANKING_WEIGHT: i32 = 100;
+/// Bounds for the ranking weight, keeps a lens from burying or swamping
+/// every other result entirely.
+pub const MIN_RANKING_WEIGHT: i32 = 10;
+pub const MAX_RANKING_WEIGHT: i32 = 1000;
+
+/// Sets how much the lens' documents are boosted in search results, as a
+/// percentage clamped to the allowed range. Returns false if there is no lens
+/// with that name.
+pub async fn set_ranking_weight(
+    db: &DatabaseConnection,
+    lens_name: &str,
+    weight: i32,
+) -> Result<bool, DbErr> {
+    match find_by_name(lens_name, db).await? {
+        Some(existing) => {
+            let mut updated: ActiveModel = existing.into();
+            updated.ranking_weight = Set(weight.clamp(MIN_RANKING_WEIGHT, MAX_RANKING_WEIGHT));
+            updated.update(db).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Ranking weights of lenses that don't use the default, keyed by lens name.
+pub async fn ranking_weights(db: &DatabaseConnection) -> Result<HashMap<String, i32>, DbErr> {
+    let weighted = Entity::find()
+        .filter(Column::RankingWeight.ne(DEFAULT_RANKING_WEIGHT))
+        .all(db)
+        .await?;
+
+    Ok(weighted
+        .into_iter()
+        .map(|model| (model.name, model.ranking_weight))
+        .collect())
+}
+
+/// Names of all paused lenses
+pub async fn paused_lenses(db: &DatabaseConnection) -> Result<Vec<String>, DbErr> {
+    let paused = Entity::find()
+        .filter(Column::IsPaused.eq(true))
+        .all(db)
+        .await?;
+
+    Ok(paused.into_iter().map(|model| model.name).collect())
+}
+
 /// Represents the tag id that is associated with a document
 #[derive(Debug, FromQueryResult)]
 pub struct LensName {
@@ -278,7 +367,7 @@ pub async fn copy_table(
 
 #[cfg(test)]
 mod test {
-    use super::{add_or_enable, Entity};
+    use super::{add_or_enable, ranking_weights, set_ranking_weight, Entity};
     use crate::test::setup_test_db;
     use sea_orm::EntityTrait;
     use shared::config::LensConfig;
@@ -317,4 +406,25 @@ mod test {
         assert_eq!(model.trigger, Some("new_trigger".to_owned()));
         assert_eq!(model.description, Some("description".to_owned()));
     }
+
+    #[tokio::test]
+    async fn test_ranking_weight() {
+        let db = setup_test_db().await;
+        let lens = LensConfig {
+            name: "test_lens".to_owned(),
+            ..Default::default()
+        };
+        add_or_enable(&db, &lens, super::LensType::Simple)
+            .await
+            .unwrap();
+
+        // Lenses start out with the default weight
+        assert!(ranking_weights(&db).await.unwrap().is_empty());
+
+        assert!(set_ranking_weight(&db, "test_lens", 5000).await.unwrap());
+        let weights = ranking_weights(&db).await.unwrap();
+        assert_eq!(weights.get("test_lens"), Some(&super::MAX_RANKING_WEIGHT));
+
+        assert!(!set_ranking_weight(&db, "missing", 200).await.unwrap());
+    }
 }
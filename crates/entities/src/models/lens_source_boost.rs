@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+use serde::Serialize;
+
+/// Boost used when a source hasn't been given one, leaves scores unchanged.
+pub const DEFAULT_SOURCE_BOOST: f32 = 1.0;
+pub const MIN_SOURCE_BOOST: f32 = 0.1;
+pub const MAX_SOURCE_BOOST: f32 = 5.0;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "lens_source_boost")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Name of the lens the source belongs to.
+    pub lens: String,
+    /// Domain of the source, documents from it are boosted in lens searches.
+    pub domain: String,
+    /// Multiplier applied to the search score of the source's documents.
+    pub boost: f32,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+        self.updated_at = Set(chrono::Utc::now());
+
+        Ok(self)
+    }
+}
+
+/// Sets how much documents from `domain` are boosted in searches of `lens`,
+/// clamped to the allowed range. Setting the default boost removes it.
+pub async fn set_boost<C>(db: &C, lens: &str, domain: &str, boost: f32) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let boost = boost.clamp(MIN_SOURCE_BOOST, MAX_SOURCE_BOOST);
+    let existing = Entity::find()
+        .filter(Column::Lens.eq(lens))
+        .filter(Column::Domain.eq(domain))
+        .one(db)
+        .await?;
+
+    match existing {
+        Some(existing) if boost == DEFAULT_SOURCE_BOOST => {
+            existing.delete(db).await?;
+        }
+        Some(existing) => {
+            let mut update: ActiveModel = existing.into();
+            update.boost = Set(boost);
+            update.update(db).await?;
+        }
+        None if boost == DEFAULT_SOURCE_BOOST => {}
+        None => {
+            let new = ActiveModel {
+                lens: Set(lens.to_string()),
+                domain: Set(domain.to_string()),
+                boost: Set(boost),
+                ..Default::default()
+            };
+            new.insert(db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Source boosts for each of the lenses, keyed by lens name & then domain.
+pub async fn boosts_for_lenses<C>(
+    db: &C,
+    lenses: &[String],
+) -> Result<HashMap<String, HashMap<String, f32>>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let boosts = Entity::find()
+        .filter(Column::Lens.is_in(lenses.to_vec()))
+        .all(db)
+        .await?;
+
+    let mut by_lens: HashMap<String, HashMap<String, f32>> = HashMap::new();
+    for model in boosts {
+        by_lens
+            .entry(model.lens)
+            .or_default()
+            .insert(model.domain, model.boost);
+    }
+
+    Ok(by_lens)
+}
+
+/// Removes the source boosts of a lens, e.g. when it's uninstalled.
+pub async fn delete_by_lens<C>(db: &C, lens: &str) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let res = Entity::delete_many()
+        .filter(Column::Lens.eq(lens))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::lens_source_boost;
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_set_boost() {
+        let db = setup_test_db().await;
+
+        lens_source_boost::set_boost(&db, "docs", "docs.example.com", 2.0)
+            .await
+            .unwrap();
+        lens_source_boost::set_boost(&db, "docs", "blog.example.com", 10.0)
+            .await
+            .unwrap();
+        lens_source_boost::set_boost(&db, "other", "docs.example.com", 0.5)
+            .await
+            .unwrap();
+
+        let boosts = lens_source_boost::boosts_for_lenses(&db, &["docs".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(boosts.len(), 1);
+        assert_eq!(boosts["docs"]["docs.example.com"], 2.0);
+        // Clamped to the max boost
+        assert_eq!(
+            boosts["docs"]["blog.example.com"],
+            lens_source_boost::MAX_SOURCE_BOOST
+        );
+
+        // Resetting to the default removes the boost
+        lens_source_boost::set_boost(
+            &db,
+            "docs",
+            "docs.example.com",
+            lens_source_boost::DEFAULT_SOURCE_BOOST,
+        )
+        .await
+        .unwrap();
+        let boosts = lens_source_boost::boosts_for_lenses(&db, &["docs".to_string()])
+            .await
+            .unwrap();
+        assert!(!boosts["docs"].contains_key("docs.example.com"));
+    }
+}
@@ -0,0 +1,190 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{Set, Statement};
+use serde::Serialize;
+
+/// Running totals of the crawls done on behalf of a lens, used to show users
+/// how much a lens costs to keep up to date.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "lens_crawl_stats")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Name of the lens these stats are for.
+    #[sea_orm(unique)]
+    pub lens: String,
+    /// Pages crawled & added to/updated in the index.
+    pub pages_indexed: i64,
+    /// Bytes downloaded, including pages that weren't indexed.
+    pub bytes_fetched: i64,
+    /// Time spent fetching & processing pages, in milliseconds.
+    pub crawl_time_ms: i64,
+    /// When a crawl for this lens last finished.
+    pub last_crawl_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+        self.updated_at = Set(chrono::Utc::now());
+
+        Ok(self)
+    }
+}
+
+/// Usage of a single finished crawl.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrawlUsage {
+    pub bytes_fetched: u64,
+    pub crawl_time_ms: u64,
+    pub indexed: bool,
+}
+
+/// Returns the crawl stats for `lens`, if anything was crawled for it yet.
+pub async fn get<C>(db: &C, lens: &str) -> Result<Option<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find().filter(Column::Lens.eq(lens)).one(db).await
+}
+
+/// Adds the usage of a finished crawl to the totals of each of the `lenses`
+/// it was crawled for. Totals are added to in the database, so concurrent
+/// crawls don't overwrite each other's usage.
+pub async fn record_crawl<C>(db: &C, lenses: &[String], usage: CrawlUsage) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let pages = i64::from(usage.indexed);
+    let bytes = usage.bytes_fetched as i64;
+    let time_ms = usage.crawl_time_ms as i64;
+
+    for lens in lenses {
+        let now = chrono::Utc::now();
+        db.execute(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            r#"
+            INSERT INTO lens_crawl_stats
+                (lens, pages_indexed, bytes_fetched, crawl_time_ms, last_crawl_at, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (lens) DO UPDATE SET
+                pages_indexed = lens_crawl_stats.pages_indexed + excluded.pages_indexed,
+                bytes_fetched = lens_crawl_stats.bytes_fetched + excluded.bytes_fetched,
+                crawl_time_ms = lens_crawl_stats.crawl_time_ms + excluded.crawl_time_ms,
+                last_crawl_at = excluded.last_crawl_at,
+                updated_at = excluded.updated_at"#,
+            vec![
+                lens.as_str().into(),
+                pages.into(),
+                bytes.into(),
+                time_ms.into(),
+                now.into(),
+                now.into(),
+                now.into(),
+            ],
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Removes the stats for `lens`, e.g. when the lens is uninstalled.
+pub async fn delete<C>(db: &C, lens: &str) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::delete_many()
+        .filter(Column::Lens.eq(lens))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::lens_stats::{self, CrawlUsage};
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_record_crawl() {
+        let db = setup_test_db().await;
+        let lenses = vec!["lens_a".to_string(), "lens_b".to_string()];
+
+        lens_stats::record_crawl(
+            &db,
+            &lenses,
+            CrawlUsage {
+                bytes_fetched: 1024,
+                crawl_time_ms: 200,
+                indexed: true,
+            },
+        )
+        .await
+        .unwrap();
+        lens_stats::record_crawl(
+            &db,
+            &lenses[..1],
+            CrawlUsage {
+                bytes_fetched: 512,
+                crawl_time_ms: 100,
+                indexed: false,
+            },
+        )
+        .await
+        .unwrap();
+
+        let stats = lens_stats::get(&db, "lens_a").await.unwrap().unwrap();
+        assert_eq!(stats.pages_indexed, 1);
+        assert_eq!(stats.bytes_fetched, 1536);
+        assert_eq!(stats.crawl_time_ms, 300);
+        assert!(stats.last_crawl_at.is_some());
+
+        let stats = lens_stats::get(&db, "lens_b").await.unwrap().unwrap();
+        assert_eq!(stats.bytes_fetched, 1024);
+
+        lens_stats::delete(&db, "lens_b").await.unwrap();
+        assert!(lens_stats::get(&db, "lens_b").await.unwrap().is_none());
+    }
+}
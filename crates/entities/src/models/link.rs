@@ -1,7 +1,12 @@
 use sea_orm::entity::prelude::*;
-use sea_orm::Set;
+use sea_orm::{QueryOrder, Set};
 use url::Url;
 
+/// Max number of links saved per crawled page.
+pub const MAX_ANCHORS_PER_PAGE: usize = 500;
+/// Max number of distinct anchor texts indexed with a document.
+pub const MAX_ANCHORS_PER_DOC: usize = 50;
+
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
 #[sea_orm(table_name = "link")]
 pub struct Model {
@@ -11,6 +16,8 @@ pub struct Model {
     pub src_url: String,
     pub dst_domain: String,
     pub dst_url: String,
+    /// Text of the link, if any.
+    pub anchor_text: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter)]
@@ -51,6 +58,76 @@ pub async fn save_link(
     Ok(())
 }
 
+/// Replaces the links found on `src` with `anchors`, a list of (destination URL,
+/// anchor text) pairs. Links are kept until the destination is crawled so the
+/// text describing it can be indexed along with it.
+pub async fn save_anchors<C>(
+    db: &C,
+    src: &str,
+    anchors: &[(String, String)],
+) -> anyhow::Result<(), sea_orm::DbErr>
+where
+    C: ConnectionTrait,
+{
+    let src_url = match Url::parse(src) {
+        Ok(url) => url,
+        Err(_) => return Ok(()),
+    };
+
+    Entity::delete_many()
+        .filter(Column::SrcUrl.eq(src))
+        .exec(db)
+        .await?;
+
+    let links = anchors
+        .iter()
+        .take(MAX_ANCHORS_PER_PAGE)
+        .filter_map(|(dst, text)| {
+            let dst_url = Url::parse(dst).ok()?;
+            Some(ActiveModel {
+                src_domain: Set(src_url.host_str().unwrap_or_default().to_owned()),
+                src_url: Set(src.to_owned()),
+                dst_domain: Set(dst_url.host_str().unwrap_or_default().to_owned()),
+                dst_url: Set(dst.to_owned()),
+                anchor_text: Set(Some(text.to_owned())),
+                ..Default::default()
+            })
+        })
+        .collect::<Vec<ActiveModel>>();
+
+    if !links.is_empty() {
+        Entity::insert_many(links).exec(db).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the distinct anchor text of links pointing to `dst`.
+pub async fn get_anchor_text<C>(db: &C, dst: &str) -> anyhow::Result<Vec<String>, sea_orm::DbErr>
+where
+    C: ConnectionTrait,
+{
+    let links = Entity::find()
+        .filter(Column::DstUrl.eq(dst))
+        .filter(Column::AnchorText.is_not_null())
+        .order_by_asc(Column::Id)
+        .all(db)
+        .await?;
+
+    let mut anchor_text: Vec<String> = Vec::new();
+    for text in links.into_iter().filter_map(|link| link.anchor_text) {
+        if !anchor_text.contains(&text) {
+            anchor_text.push(text);
+        }
+
+        if anchor_text.len() >= MAX_ANCHORS_PER_DOC {
+            break;
+        }
+    }
+
+    Ok(anchor_text)
+}
+
 // Helper method to copy the table from one database to another
 pub async fn copy_table(
     from: &DatabaseConnection,
@@ -74,3 +151,44 @@ pub async fn copy_table(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use crate::models::link;
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_anchor_text() {
+        let db = setup_test_db().await;
+        let dst = "https://example.com/docs";
+
+        link::save_anchors(
+            &db,
+            "https://example.com/",
+            &[
+                (dst.to_string(), "Documentation".to_string()),
+                (dst.to_string(), "Documentation".to_string()),
+                ("https://example.com/blog".to_string(), "Blog".to_string()),
+            ],
+        )
+        .await
+        .unwrap();
+        link::save_anchors(
+            &db,
+            "https://other.com/",
+            &[(dst.to_string(), "Example docs".to_string())],
+        )
+        .await
+        .unwrap();
+
+        let text = link::get_anchor_text(&db, dst).await.unwrap();
+        assert_eq!(text, vec!["Documentation", "Example docs"]);
+
+        // Recrawling a page replaces its links
+        link::save_anchors(&db, "https://other.com/", &[])
+            .await
+            .unwrap();
+        let text = link::get_anchor_text(&db, dst).await.unwrap();
+        assert_eq!(text, vec!["Documentation"]);
+    }
+}
@@ -1,18 +1,30 @@
-use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend, Statement,
+};
 
 pub mod bootstrap_queue;
 pub mod connection;
 pub mod crawl_queue;
 pub mod crawl_tag;
+pub mod document_alias;
+pub mod document_content;
+pub mod document_note;
 pub mod document_tag;
+pub mod document_version;
 pub mod fetch_history;
 pub mod indexed_document;
 pub mod lens;
+pub mod lens_source_boost;
+pub mod lens_stats;
 pub mod link;
 pub mod processed_files;
 pub mod resource_rule;
+pub mod saved_query;
+pub mod saved_query_seen;
 pub mod schema;
 pub mod tag;
+pub mod url_blocklist;
+pub mod url_pattern;
 
 use shared::config::Config;
 
@@ -50,6 +62,21 @@ pub async fn create_connection_by_uri(db_uri: &str) -> anyhow::Result<DatabaseCo
     Ok(Database::connect(opt).await?)
 }
 
+/// Rebuilds the database file to reclaim the space left behind by deleted rows
+/// & refreshes the query planner stats. Only supported for SQLite, this can
+/// take a while for large databases.
+pub async fn vacuum(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
+    if db.get_database_backend() != DbBackend::Sqlite {
+        return Ok(());
+    }
+
+    for sql in ["VACUUM", "PRAGMA optimize"] {
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+    }
+    Ok(())
+}
+
 // Helper method used to copy all tables from one database to another.
 // Note that the destination database will have all content deleted.
 pub async fn copy_all_tables(
@@ -67,12 +94,22 @@ pub async fn copy_all_tables(
     resource_rule::copy_table(from, to).await?;
     tag::copy_table(from, to).await?;
     document_tag::copy_table(from, to).await?;
+    document_note::copy_table(from, to).await?;
+    document_alias::copy_table(from, to).await?;
+    document_version::copy_table(from, to).await?;
+    document_content::copy_table(from, to).await?;
+    lens_stats::copy_table(from, to).await?;
+    lens_source_boost::copy_table(from, to).await?;
+    saved_query::copy_table(from, to).await?;
+    saved_query_seen::copy_table(from, to).await?;
+    url_pattern::copy_table(from, to).await?;
+    url_blocklist::copy_table(from, to).await?;
     Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::models::create_connection;
+    use crate::models::{create_connection, vacuum};
     use shared::config::Config;
 
     #[tokio::test]
@@ -81,4 +118,10 @@ mod test {
         let res = create_connection(&config, true).await;
         assert!(res.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_vacuum() {
+        let db = crate::test::setup_test_db().await;
+        assert!(vacuum(&db).await.is_ok());
+    }
 }
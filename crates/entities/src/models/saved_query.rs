@@ -0,0 +1,207 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+
+use super::saved_query_seen;
+
+/// Filters applied when running a saved query.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct SavedQueryFilters {
+    /// Only match documents in these lenses, all documents if empty.
+    #[serde(default)]
+    pub lenses: Vec<String>,
+}
+
+/// A search that's run periodically in the background, with any new matches
+/// exported to a file or webhook.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "saved_queries")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub name: String,
+    /// Search terms, same syntax as a regular search.
+    pub query: String,
+    pub filters: SavedQueryFilters,
+    /// How often the query is run, in minutes.
+    pub interval_mins: i64,
+    /// Where new results are delivered, either a webhook URL or the path of a
+    /// NDJSON file results are appended to.
+    pub destination: String,
+    /// When the query was last run, NULL if it hasn't been run yet.
+    pub last_run_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+        self.updated_at = Set(chrono::Utc::now());
+
+        Ok(self)
+    }
+}
+
+impl Model {
+    /// Whether the query is due to be run at `now`.
+    pub fn is_due(&self, now: DateTimeUtc) -> bool {
+        match self.last_run_at {
+            Some(last_run_at) => now - last_run_at >= chrono::Duration::minutes(self.interval_mins),
+            None => true,
+        }
+    }
+}
+
+/// Saves a new query, returning the stored model.
+pub async fn add<C>(
+    db: &C,
+    name: &str,
+    query: &str,
+    filters: SavedQueryFilters,
+    interval_mins: i64,
+    destination: &str,
+) -> Result<Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let model = ActiveModel {
+        name: Set(name.to_string()),
+        query: Set(query.to_string()),
+        filters: Set(filters),
+        interval_mins: Set(interval_mins),
+        destination: Set(destination.to_string()),
+        ..Default::default()
+    };
+
+    model.insert(db).await
+}
+
+/// All saved queries, ordered by name.
+pub async fn list<C>(db: &C) -> Result<Vec<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find().order_by_asc(Column::Name).all(db).await
+}
+
+/// Saved queries that are due to be run.
+pub async fn due<C>(db: &C) -> Result<Vec<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let now = chrono::Utc::now();
+    Ok(list(db)
+        .await?
+        .into_iter()
+        .filter(|query| query.is_due(now))
+        .collect())
+}
+
+/// Records that the query is being run now, unless another run has claimed it
+/// since `query` was loaded. Returns false if it was already claimed.
+pub async fn claim_run<C>(db: &C, query: &Model) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let update = Entity::update_many()
+        .col_expr(Column::LastRunAt, Expr::value(Some(chrono::Utc::now())))
+        .filter(Column::Id.eq(query.id));
+    let update = match query.last_run_at {
+        Some(last_run_at) => update.filter(Column::LastRunAt.eq(last_run_at)),
+        None => update.filter(Column::LastRunAt.is_null()),
+    };
+
+    let res = update.exec(db).await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// Removes a saved query along with the results it has already delivered.
+/// Returns false if there was no query with that id.
+pub async fn delete<C>(db: &C, id: i64) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    saved_query_seen::delete_for_query(db, id).await?;
+    let res = Entity::delete_by_id(id).exec(db).await?;
+    Ok(res.rows_affected > 0)
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::saved_query::{self, SavedQueryFilters};
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_due() {
+        let db = setup_test_db().await;
+        let query = saved_query::add(
+            &db,
+            "cves",
+            "CVE-2024",
+            SavedQueryFilters::default(),
+            60,
+            "cves.ndjson",
+        )
+        .await
+        .unwrap();
+
+        // Never run, so it's due right away
+        let due = saved_query::due(&db).await.unwrap();
+        assert_eq!(due.len(), 1);
+
+        assert!(saved_query::claim_run(&db, &due[0]).await.unwrap());
+        assert!(saved_query::due(&db).await.unwrap().is_empty());
+        // Already claimed by the run above
+        assert!(!saved_query::claim_run(&db, &due[0]).await.unwrap());
+
+        let ran = saved_query::list(&db).await.unwrap().pop().unwrap();
+        let later = ran.last_run_at.unwrap() + chrono::Duration::minutes(60);
+        assert!(ran.is_due(later));
+
+        assert!(saved_query::delete(&db, query.id).await.unwrap());
+        assert!(!saved_query::delete(&db, query.id).await.unwrap());
+    }
+}
@@ -0,0 +1,163 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::Set;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// URLs already delivered for a saved query, so each run only exports new
+/// results.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "saved_query_seen")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub saved_query_id: i64,
+    pub url: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Filters `urls` down to the ones that haven't been delivered for the query
+/// yet, keeping their order.
+pub async fn unseen<C>(db: &C, saved_query_id: i64, urls: &[String]) -> Result<Vec<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if urls.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let seen = Entity::find()
+        .filter(Column::SavedQueryId.eq(saved_query_id))
+        .filter(Column::Url.is_in(urls.to_vec()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| model.url)
+        .collect::<HashSet<_>>();
+
+    Ok(urls
+        .iter()
+        .filter(|url| !seen.contains(*url))
+        .cloned()
+        .collect())
+}
+
+/// Records `urls` as delivered for the query.
+pub async fn mark_seen<C>(db: &C, saved_query_id: i64, urls: &[String]) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    if urls.is_empty() {
+        return Ok(());
+    }
+
+    let models = urls.iter().map(|url| ActiveModel {
+        saved_query_id: Set(saved_query_id),
+        url: Set(url.to_string()),
+        created_at: Set(chrono::Utc::now()),
+        ..Default::default()
+    });
+
+    Entity::insert_many(models)
+        .on_conflict(
+            OnConflict::columns(vec![Column::SavedQueryId, Column::Url])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Forgets everything delivered for the query, e.g. when it's deleted.
+pub async fn delete_for_query<C>(db: &C, saved_query_id: i64) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::delete_many()
+        .filter(Column::SavedQueryId.eq(saved_query_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::saved_query_seen;
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_unseen() {
+        let db = setup_test_db().await;
+        let urls = vec![
+            "https://example.com/a".to_string(),
+            "https://example.com/b".to_string(),
+        ];
+
+        assert_eq!(saved_query_seen::unseen(&db, 1, &urls).await.unwrap(), urls);
+
+        saved_query_seen::mark_seen(&db, 1, &urls[..1])
+            .await
+            .unwrap();
+        // Marking a URL twice is a no-op
+        saved_query_seen::mark_seen(&db, 1, &urls[..1])
+            .await
+            .unwrap();
+        assert_eq!(
+            saved_query_seen::unseen(&db, 1, &urls).await.unwrap(),
+            urls[1..].to_vec()
+        );
+
+        // Seen URLs are tracked per query
+        assert_eq!(saved_query_seen::unseen(&db, 2, &urls).await.unwrap(), urls);
+    }
+}
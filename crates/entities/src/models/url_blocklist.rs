@@ -0,0 +1,218 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{QueryOrder, Set};
+use serde::Serialize;
+use std::collections::HashSet;
+use url::Url;
+
+use crate::BATCH_SIZE;
+
+/// Individual URLs the user never wants crawled or indexed. Unlike the domain
+/// blocklist in the user settings, only the exact page is blocked.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "url_blocklist")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub url: String,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Normalizes a URL so blocking a page matches regardless of fragment.
+pub fn normalize(url: &str) -> String {
+    match Url::parse(url.trim()) {
+        Ok(mut url) => {
+            url.set_fragment(None);
+            url.to_string()
+        }
+        Err(_) => url.trim().to_string(),
+    }
+}
+
+pub async fn is_blocked<C>(db: &C, url: &str) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Ok(Entity::find()
+        .filter(Column::Url.eq(normalize(url)))
+        .one(db)
+        .await?
+        .is_some())
+}
+
+/// Filters `urls` down to the ones that aren't blocked, keeping their order.
+pub async fn remove_blocked<C>(db: &C, urls: Vec<String>) -> Result<Vec<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if urls.is_empty() {
+        return Ok(urls);
+    }
+
+    let normalized = urls.iter().map(|url| normalize(url)).collect::<Vec<_>>();
+    let mut blocked = HashSet::new();
+    for chunk in normalized.chunks(BATCH_SIZE) {
+        blocked.extend(
+            Entity::find()
+                .filter(Column::Url.is_in(chunk.to_vec()))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|model| model.url),
+        );
+    }
+
+    if blocked.is_empty() {
+        return Ok(urls);
+    }
+
+    Ok(urls
+        .into_iter()
+        .zip(normalized)
+        .filter(|(_, normalized)| !blocked.contains(normalized))
+        .map(|(url, _)| url)
+        .collect())
+}
+
+/// Blocks a URL, blocking it again is a no-op.
+pub async fn add<C>(db: &C, url: &str) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let model = ActiveModel {
+        url: Set(normalize(url)),
+        created_at: Set(chrono::Utc::now()),
+        ..Default::default()
+    };
+
+    Entity::insert(model)
+        .on_conflict(OnConflict::column(Column::Url).do_nothing().to_owned())
+        .exec_without_returning(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Unblocks a URL, returns false if it wasn't blocked.
+pub async fn remove<C>(db: &C, url: &str) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let res = Entity::delete_many()
+        .filter(Column::Url.eq(normalize(url)))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected > 0)
+}
+
+/// All blocked URLs, most recently blocked first.
+pub async fn list<C>(db: &C) -> Result<Vec<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Ok(Entity::find()
+        .order_by_desc(Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| model.url)
+        .collect())
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::url_blocklist;
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_blocklist() {
+        let db = setup_test_db().await;
+        url_blocklist::add(&db, "https://example.com/tracking#top")
+            .await
+            .unwrap();
+        // Blocking a URL twice is a no-op
+        url_blocklist::add(&db, "https://example.com/tracking")
+            .await
+            .unwrap();
+
+        assert!(
+            url_blocklist::is_blocked(&db, "https://example.com/tracking")
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            url_blocklist::list(&db).await.unwrap(),
+            vec!["https://example.com/tracking".to_string()]
+        );
+
+        let urls = vec![
+            "https://example.com/".to_string(),
+            "https://example.com/tracking".to_string(),
+        ];
+        assert_eq!(
+            url_blocklist::remove_blocked(&db, urls).await.unwrap(),
+            vec!["https://example.com/".to_string()]
+        );
+
+        assert!(url_blocklist::remove(&db, "https://example.com/tracking")
+            .await
+            .unwrap());
+        assert!(!url_blocklist::remove(&db, "https://example.com/tracking")
+            .await
+            .unwrap());
+        assert!(
+            !url_blocklist::is_blocked(&db, "https://example.com/tracking")
+                .await
+                .unwrap()
+        );
+    }
+}
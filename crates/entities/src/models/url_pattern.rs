@@ -0,0 +1,236 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{Set, Statement};
+use serde::Serialize;
+use url::Url;
+
+/// Number of distinct URLs queued for a path template on a domain. Used to
+/// spot crawl traps, e.g. calendars or faceted search pages that link to an
+/// endless number of variations of themselves.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "url_patterns")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub domain: String,
+    /// Path & sorted query keys, see `signature`.
+    pub signature: String,
+    pub num_urls: i64,
+    /// Set once the pattern hits the limit, no more URLs matching it are
+    /// queued.
+    pub is_trap: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+        }
+        self.updated_at = Set(chrono::Utc::now());
+
+        Ok(self)
+    }
+}
+
+/// The path template of a URL, its path followed by its sorted query keys with
+/// the values dropped, e.g. `/calendar?day&month`. URLs without a query string
+/// have no template, each path is only ever a single page.
+pub fn signature(url: &Url) -> Option<String> {
+    let mut keys = url
+        .query_pairs()
+        .map(|(key, _)| key.to_string())
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        return None;
+    }
+
+    keys.sort();
+    keys.dedup();
+    Some(format!("{}?{}", url.path(), keys.join("&")))
+}
+
+pub async fn find<C>(db: &C, domain: &str, signature: &str) -> Result<Option<Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Entity::find()
+        .filter(Column::Domain.eq(domain))
+        .filter(Column::Signature.eq(signature))
+        .one(db)
+        .await
+}
+
+/// How long a pattern is remembered after its count last changed. Old
+/// patterns are dropped so sites that fixed a trap, or kept growing slowly,
+/// get crawled again.
+pub const PATTERN_TTL_DAYS: i64 = 30;
+
+/// Adds `added` URLs to the count for a pattern. The count is incremented in
+/// the db so concurrent enqueues don't overwrite each other.
+pub async fn add_urls<C>(
+    db: &C,
+    domain: &str,
+    signature: &str,
+    added: i64,
+    is_trap: bool,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let now = chrono::Utc::now();
+    db.execute(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        r#"
+        INSERT INTO url_patterns
+            (domain, signature, num_urls, is_trap, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (domain, signature) DO UPDATE SET
+            num_urls = url_patterns.num_urls + excluded.num_urls,
+            is_trap = url_patterns.is_trap OR excluded.is_trap,
+            updated_at = excluded.updated_at"#,
+        vec![
+            domain.into(),
+            signature.into(),
+            added.into(),
+            is_trap.into(),
+            now.into(),
+            now.into(),
+        ],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Removes patterns that haven't changed in `PATTERN_TTL_DAYS`, resetting
+/// their counts & trap flag.
+pub async fn delete_stale<C>(db: &C) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(PATTERN_TTL_DAYS);
+    let res = Entity::delete_many()
+        .filter(Column::UpdatedAt.lt(cutoff))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
+
+// Helper method to copy the table from one database to another
+pub async fn copy_table(
+    from: &DatabaseConnection,
+    to: &DatabaseConnection,
+) -> anyhow::Result<(), sea_orm::DbErr> {
+    let mut pages = Entity::find().paginate(from, 1000);
+    Entity::delete_many().exec(to).await?;
+    while let Ok(Some(pages)) = pages.fetch_and_next().await {
+        let active_model = pages
+            .into_iter()
+            .map(|model| model.into())
+            .collect::<Vec<ActiveModel>>();
+        Entity::insert_many(active_model)
+            .on_conflict(
+                sea_orm::sea_query::OnConflict::columns(vec![Column::Id])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec(to)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::url_pattern;
+    use crate::test::setup_test_db;
+    use sea_orm::sea_query::Expr;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+    use url::Url;
+
+    #[test]
+    fn test_signature() {
+        let url = Url::parse("https://example.com/calendar?month=12&day=31&month=1").unwrap();
+        assert_eq!(
+            url_pattern::signature(&url),
+            Some("/calendar?day&month".to_string())
+        );
+
+        let url = Url::parse("https://example.com/calendar").unwrap();
+        assert_eq!(url_pattern::signature(&url), None);
+    }
+
+    #[tokio::test]
+    async fn test_add_urls() {
+        let db = setup_test_db().await;
+        url_pattern::add_urls(&db, "example.com", "/calendar?day", 10, false)
+            .await
+            .unwrap();
+        url_pattern::add_urls(&db, "example.com", "/calendar?day", 20, true)
+            .await
+            .unwrap();
+        url_pattern::add_urls(&db, "example.com", "/calendar?day", 0, false)
+            .await
+            .unwrap();
+
+        let pattern = url_pattern::find(&db, "example.com", "/calendar?day")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pattern.num_urls, 30);
+        assert!(pattern.is_trap);
+        assert!(url_pattern::find(&db, "other.com", "/calendar?day")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_stale() {
+        let db = setup_test_db().await;
+        url_pattern::add_urls(&db, "example.com", "/calendar?day", 10, true)
+            .await
+            .unwrap();
+        url_pattern::add_urls(&db, "example.com", "/search?q", 10, false)
+            .await
+            .unwrap();
+
+        url_pattern::Entity::update_many()
+            .col_expr(
+                url_pattern::Column::UpdatedAt,
+                Expr::value(
+                    chrono::Utc::now() - chrono::Duration::days(url_pattern::PATTERN_TTL_DAYS + 1),
+                ),
+            )
+            .filter(url_pattern::Column::Signature.eq("/calendar?day"))
+            .exec(&db)
+            .await
+            .unwrap();
+
+        assert_eq!(url_pattern::delete_stale(&db).await.unwrap(), 1);
+        assert!(url_pattern::find(&db, "example.com", "/calendar?day")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(url_pattern::find(&db, "example.com", "/search?q")
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
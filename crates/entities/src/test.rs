@@ -2,8 +2,10 @@ use sea_orm::{sea_query::Index, ConnectionTrait, DatabaseConnection, Schema};
 use shared::config::Config;
 
 use crate::models::{
-    bootstrap_queue, connection, crawl_queue, crawl_tag, create_connection, document_tag,
-    fetch_history, indexed_document, lens, link, resource_rule, tag,
+    bootstrap_queue, connection, crawl_queue, crawl_tag, create_connection, document_alias,
+    document_content, document_note, document_tag, document_version, fetch_history,
+    indexed_document, lens, lens_source_boost, lens_stats, link, resource_rule, saved_query,
+    saved_query_seen, tag, url_blocklist, url_pattern,
 };
 
 #[allow(dead_code)]
@@ -110,6 +112,96 @@ async fn setup_schema(db: &DatabaseConnection) -> anyhow::Result<(), sea_orm::Db
     )
     .await?;
 
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(document_note::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(document_version::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(document_content::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(lens_stats::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(lens_source_boost::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(saved_query::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(saved_query_seen::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(url_pattern::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(url_blocklist::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(document_alias::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
     db.execute(
         builder.build(
             &Index::create()
@@ -149,5 +241,44 @@ async fn setup_schema(db: &DatabaseConnection) -> anyhow::Result<(), sea_orm::Db
     )
     .await?;
 
+    db.execute(
+        builder.build(
+            &Index::create()
+                .unique()
+                .name("idx-saved-query-seen-query-id-url")
+                .table(saved_query_seen::Entity)
+                .col(saved_query_seen::Column::SavedQueryId)
+                .col(saved_query_seen::Column::Url)
+                .to_owned(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            &Index::create()
+                .unique()
+                .name("idx-lens_source_boost-lens-domain")
+                .table(lens_source_boost::Entity)
+                .col(lens_source_boost::Column::Lens)
+                .col(lens_source_boost::Column::Domain)
+                .to_owned(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            &Index::create()
+                .unique()
+                .name("idx-url-patterns-domain-signature")
+                .table(url_pattern::Entity)
+                .col(url_pattern::Column::Domain)
+                .col(url_pattern::Column::Signature)
+                .to_owned(),
+        ),
+    )
+    .await?;
+
     Ok(())
 }
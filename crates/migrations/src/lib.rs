@@ -30,6 +30,32 @@ mod m20230201_000001_add_tag_index;
 mod m20230203_000001_add_indexed_document_index;
 mod m20230220_000001_remove_legacy_plugins;
 mod m20230315_000001_migrate_search_schema;
+mod m20231120_000001_add_depth_columns;
+mod m20231121_000001_create_document_notes_table;
+mod m20231122_000001_add_link_anchor_text;
+mod m20231122_000002_migrate_search_schema;
+mod m20231123_000001_create_document_versions_table;
+mod m20231123_000002_add_lens_paused_column;
+mod m20231124_000001_migrate_search_schema;
+mod m20231125_000001_create_lens_crawl_stats_table;
+mod m20231126_000001_add_lens_ranking_weight_column;
+mod m20231127_000001_create_saved_queries_table;
+mod m20231128_000001_create_url_patterns_table;
+mod m20231129_000001_create_url_blocklist_table;
+mod m20231130_000001_add_crawl_queue_sitemap_column;
+mod m20231201_000001_add_document_validator_columns;
+mod m20231202_000001_add_crawl_queue_retry_column;
+mod m20231203_000001_add_document_dedupe;
+mod m20231204_000001_migrate_search_schema;
+mod m20231205_000001_add_lens_domain_crawl_limit_column;
+mod m20231206_000001_create_document_content_table;
+mod m20231207_000001_add_crawl_queue_priority_column;
+mod m20231208_000001_create_lens_source_boost_table;
+mod m20231209_000001_add_document_content_code_column;
+mod m20231210_000001_add_crawl_queue_domain_index;
+mod m20231211_000001_backfill_document_content_hash;
+mod m20231212_000001_add_document_content_metadata_columns;
+mod m20231213_000001_add_crawl_queue_upgraded_column;
 mod utils;
 
 pub struct Migrator;
@@ -65,6 +91,32 @@ impl MigratorTrait for Migrator {
             Box::new(m20230203_000001_add_indexed_document_index::Migration),
             Box::new(m20230220_000001_remove_legacy_plugins::Migration),
             Box::new(m20230315_000001_migrate_search_schema::Migration),
+            Box::new(m20231120_000001_add_depth_columns::Migration),
+            Box::new(m20231121_000001_create_document_notes_table::Migration),
+            Box::new(m20231122_000001_add_link_anchor_text::Migration),
+            Box::new(m20231122_000002_migrate_search_schema::Migration),
+            Box::new(m20231123_000001_create_document_versions_table::Migration),
+            Box::new(m20231123_000002_add_lens_paused_column::Migration),
+            Box::new(m20231124_000001_migrate_search_schema::Migration),
+            Box::new(m20231125_000001_create_lens_crawl_stats_table::Migration),
+            Box::new(m20231126_000001_add_lens_ranking_weight_column::Migration),
+            Box::new(m20231127_000001_create_saved_queries_table::Migration),
+            Box::new(m20231128_000001_create_url_patterns_table::Migration),
+            Box::new(m20231129_000001_create_url_blocklist_table::Migration),
+            Box::new(m20231130_000001_add_crawl_queue_sitemap_column::Migration),
+            Box::new(m20231201_000001_add_document_validator_columns::Migration),
+            Box::new(m20231202_000001_add_crawl_queue_retry_column::Migration),
+            Box::new(m20231203_000001_add_document_dedupe::Migration),
+            Box::new(m20231204_000001_migrate_search_schema::Migration),
+            Box::new(m20231205_000001_add_lens_domain_crawl_limit_column::Migration),
+            Box::new(m20231206_000001_create_document_content_table::Migration),
+            Box::new(m20231207_000001_add_crawl_queue_priority_column::Migration),
+            Box::new(m20231208_000001_create_lens_source_boost_table::Migration),
+            Box::new(m20231209_000001_add_document_content_code_column::Migration),
+            Box::new(m20231210_000001_add_crawl_queue_domain_index::Migration),
+            Box::new(m20231211_000001_backfill_document_content_hash::Migration),
+            Box::new(m20231212_000001_add_document_content_metadata_columns::Migration),
+            Box::new(m20231213_000001_add_crawl_queue_upgraded_column::Migration),
         ]
     }
 }
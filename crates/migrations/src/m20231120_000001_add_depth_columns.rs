@@ -0,0 +1,51 @@
+use entities::models::{crawl_queue, indexed_document};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231120_000001_add_depth_columns"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Number of links followed from the seed URL to reach this task.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(crawl_queue::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("depth"))
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Depth at which the document was discovered
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(indexed_document::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("depth"))
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
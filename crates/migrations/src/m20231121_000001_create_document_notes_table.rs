@@ -0,0 +1,56 @@
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231121_000001_create_document_notes_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Notes are keyed on URL (not document id) so they survive recrawls.
+        let document_notes = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "document_notes" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "url" text NOT NULL UNIQUE,
+                    "note" text NOT NULL,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL);"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "document_notes" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "url" text NOT NULL UNIQUE,
+                    "note" text NOT NULL,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "updated_at" TIMESTAMPTZ NOT NULL);"#,
+            )
+        } else {
+            None
+        };
+
+        if let Some(document_notes) = document_notes {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    document_notes.to_owned().to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use entities::models::link;
+use entities::sea_orm::{ConnectionTrait, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231122_000001_add_link_anchor_text"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Text of the link, indexed with the destination document once it's crawled.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(link::Entity)
+                    .add_column_if_not_exists(ColumnDef::new(Alias::new("anchor_text")).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Links are looked up by destination when indexing a document.
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "CREATE INDEX IF NOT EXISTS \"idx-link-dst-url\" ON \"link\" (\"dst_url\");"
+                    .to_string(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231123_000001_create_document_versions_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only the most recent prior version of each document is kept.
+        let document_versions = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "document_versions" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "url" text NOT NULL UNIQUE,
+                    "content" text NOT NULL,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL);"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "document_versions" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "url" text NOT NULL UNIQUE,
+                    "content" text NOT NULL,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "updated_at" TIMESTAMPTZ NOT NULL);"#,
+            )
+        } else {
+            None
+        };
+
+        if let Some(document_versions) = document_versions {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    document_versions.to_owned().to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
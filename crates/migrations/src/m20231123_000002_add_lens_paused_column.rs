@@ -0,0 +1,36 @@
+use entities::models::lens;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231123_000002_add_lens_paused_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Crawls for a paused lens are left in the queue until it's resumed.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(lens::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("is_paused"))
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
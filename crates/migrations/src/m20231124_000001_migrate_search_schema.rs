@@ -0,0 +1,24 @@
+use sea_orm_migration::prelude::*;
+
+use crate::utils::search_schema;
+
+/// Adds the `body` field to the search index, the main article text is added
+/// as pages are recrawled.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231124_000001_migrate_search_schema"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        search_schema::add_field(manager, "body").await
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231125_000001_create_lens_crawl_stats_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Running crawl totals, one row per lens.
+        let lens_crawl_stats = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "lens_crawl_stats" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "lens" text NOT NULL UNIQUE,
+                    "pages_indexed" integer NOT NULL DEFAULT 0,
+                    "bytes_fetched" integer NOT NULL DEFAULT 0,
+                    "crawl_time_ms" integer NOT NULL DEFAULT 0,
+                    "last_crawl_at" text,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL);"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "lens_crawl_stats" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "lens" text NOT NULL UNIQUE,
+                    "pages_indexed" BIGINT NOT NULL DEFAULT 0,
+                    "bytes_fetched" BIGINT NOT NULL DEFAULT 0,
+                    "crawl_time_ms" BIGINT NOT NULL DEFAULT 0,
+                    "last_crawl_at" TIMESTAMPTZ,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "updated_at" TIMESTAMPTZ NOT NULL);"#,
+            )
+        } else {
+            None
+        };
+
+        if let Some(lens_crawl_stats) = lens_crawl_stats {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    lens_crawl_stats.to_owned().to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use entities::models::lens;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231126_000001_add_lens_ranking_weight_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Percentage applied to search scores of the lens' documents.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(lens::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("ranking_weight"))
+                            .integer()
+                            .not_null()
+                            .default(100),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231127_000001_create_saved_queries_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Searches run in the background, new matches are exported to a file
+        // or webhook.
+        let saved_queries = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "saved_queries" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "name" text NOT NULL UNIQUE,
+                    "query" text NOT NULL,
+                    "filters" text NOT NULL,
+                    "interval_mins" integer NOT NULL,
+                    "destination" text NOT NULL,
+                    "last_run_at" text,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL);"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "saved_queries" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "name" text NOT NULL UNIQUE,
+                    "query" text NOT NULL,
+                    "filters" text NOT NULL,
+                    "interval_mins" BIGINT NOT NULL,
+                    "destination" text NOT NULL,
+                    "last_run_at" TIMESTAMPTZ,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "updated_at" TIMESTAMPTZ NOT NULL);"#,
+            )
+        } else {
+            None
+        };
+
+        // URLs already delivered for each saved query.
+        let saved_query_seen = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "saved_query_seen" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "saved_query_id" integer NOT NULL,
+                    "url" text NOT NULL,
+                    "created_at" text NOT NULL,
+                    UNIQUE ("saved_query_id", "url"));"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "saved_query_seen" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "saved_query_id" BIGINT NOT NULL,
+                    "url" text NOT NULL,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    UNIQUE ("saved_query_id", "url"));"#,
+            )
+        } else {
+            None
+        };
+
+        for sql in [saved_queries, saved_query_seen].into_iter().flatten() {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    sql.to_owned().to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
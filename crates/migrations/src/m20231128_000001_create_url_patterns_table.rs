@@ -0,0 +1,62 @@
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231128_000001_create_url_patterns_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Distinct URLs queued per path template, used to detect crawl traps.
+        let sql = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "url_patterns" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "domain" text NOT NULL,
+                    "signature" text NOT NULL,
+                    "num_urls" integer NOT NULL,
+                    "is_trap" bool NOT NULL DEFAULT FALSE,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL,
+                    UNIQUE ("domain", "signature"));"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "url_patterns" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "domain" text NOT NULL,
+                    "signature" text NOT NULL,
+                    "num_urls" BIGINT NOT NULL,
+                    "is_trap" bool NOT NULL DEFAULT FALSE,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "updated_at" TIMESTAMPTZ NOT NULL,
+                    UNIQUE ("domain", "signature"));"#,
+            )
+        } else {
+            None
+        };
+
+        if let Some(sql) = sql {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    sql.to_owned().to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231129_000001_create_url_blocklist_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Individual URLs the user never wants crawled or indexed.
+        let sql = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "url_blocklist" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "url" text NOT NULL UNIQUE,
+                    "created_at" text NOT NULL);"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "url_blocklist" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "url" text NOT NULL UNIQUE,
+                    "created_at" TIMESTAMPTZ NOT NULL);"#,
+            )
+        } else {
+            None
+        };
+
+        if let Some(sql) = sql {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    sql.to_owned().to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
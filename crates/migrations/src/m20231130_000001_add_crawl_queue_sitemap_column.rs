@@ -0,0 +1,36 @@
+use entities::models::crawl_queue;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231130_000001_add_crawl_queue_sitemap_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Whether crawling the task should also pick up the site's sitemap.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(crawl_queue::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("use_sitemap"))
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use entities::models::indexed_document;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231201_000001_add_document_validator_columns"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // ETag & Last-Modified headers from the last time the document was
+        // fetched, used to make the next fetch conditional.
+        for column in ["etag", "last_modified"] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(indexed_document::Entity)
+                        .add_column_if_not_exists(ColumnDef::new(Alias::new(column)).string())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use entities::models::crawl_queue;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231202_000001_add_crawl_queue_retry_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // When a task that failed for a temporary reason can be retried.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(crawl_queue::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("next_retry_at")).date_time(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use entities::models::indexed_document;
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231203_000001_add_document_dedupe"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Hash of the extracted text, used to find pages served under
+        // multiple URLs.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(indexed_document::Entity)
+                    .add_column_if_not_exists(ColumnDef::new(Alias::new("content_hash")).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "CREATE INDEX IF NOT EXISTS \"idx-indexed_document-content_hash\" ON \"indexed_document\" (\"content_hash\");"
+                    .to_string(),
+            ))
+            .await?;
+
+        // URLs that weren't indexed because their content is identical to an
+        // already indexed document.
+        let sql = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "document_alias" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "url" text NOT NULL UNIQUE,
+                    "canonical_url" text NOT NULL,
+                    "created_at" text NOT NULL);"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "document_alias" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "url" text NOT NULL UNIQUE,
+                    "canonical_url" text NOT NULL,
+                    "created_at" TIMESTAMPTZ NOT NULL);"#,
+            )
+        } else {
+            None
+        };
+
+        if let Some(sql) = sql {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    sql.to_owned().to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,24 @@
+use sea_orm_migration::prelude::*;
+
+use crate::utils::search_schema;
+
+/// Adds the `code` field to the search index, code blocks are added as pages
+/// are recrawled.
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231204_000001_migrate_search_schema"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        search_schema::add_field(manager, "code").await
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
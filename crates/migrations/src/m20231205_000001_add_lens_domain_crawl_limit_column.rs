@@ -0,0 +1,34 @@
+use entities::models::lens;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231205_000001_add_lens_domain_crawl_limit_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Pages allowed per domain for the lens' crawls, the user setting is
+        // used when not set.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(lens::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("domain_crawl_limit")).big_integer(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231206_000001_create_document_content_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Text of each indexed document, so the index can be rebuilt w/o crawling.
+        let document_content = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "document_content" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "url" text NOT NULL UNIQUE,
+                    "title" text NOT NULL,
+                    "content" text NOT NULL,
+                    "body" text,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL);"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "document_content" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "url" text NOT NULL UNIQUE,
+                    "title" text NOT NULL,
+                    "content" text NOT NULL,
+                    "body" text,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "updated_at" TIMESTAMPTZ NOT NULL);"#,
+            )
+        } else {
+            None
+        };
+
+        if let Some(document_content) = document_content {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    document_content.to_owned().to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use entities::models::crawl_queue;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231207_000001_add_crawl_queue_priority_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Tasks w/ a higher priority are crawled first.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(crawl_queue::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("priority"))
+                            .integer()
+                            .not_null()
+                            .default(crawl_queue::PRIORITY_DEFAULT),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
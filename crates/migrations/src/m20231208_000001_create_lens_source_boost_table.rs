@@ -0,0 +1,67 @@
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231208_000001_create_lens_source_boost_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // How much each lens source is boosted in searches of that lens.
+        let lens_source_boost = if manager.get_database_backend() == DbBackend::Sqlite {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "lens_source_boost" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "lens" text NOT NULL,
+                    "domain" text NOT NULL,
+                    "boost" real NOT NULL,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL);"#,
+            )
+        } else if manager.get_database_backend() == DbBackend::Postgres {
+            Some(
+                r#"
+                CREATE TABLE IF NOT EXISTS "lens_source_boost" (
+                    "id" BIGSERIAL PRIMARY KEY,
+                    "lens" text NOT NULL,
+                    "domain" text NOT NULL,
+                    "boost" real NOT NULL,
+                    "created_at" TIMESTAMPTZ NOT NULL,
+                    "updated_at" TIMESTAMPTZ NOT NULL);"#,
+            )
+        } else {
+            None
+        };
+
+        if let Some(lens_source_boost) = lens_source_boost {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    lens_source_boost.to_owned().to_string(),
+                ))
+                .await?;
+
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    "CREATE UNIQUE INDEX IF NOT EXISTS \"idx-lens_source_boost-lens-domain\" ON \"lens_source_boost\" (\"lens\", \"domain\");"
+                        .to_string(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
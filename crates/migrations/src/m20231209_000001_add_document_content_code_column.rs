@@ -0,0 +1,36 @@
+use entities::models::document_content;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231209_000001_add_document_content_code_column"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Code blocks, so they're indexed again when a document is restored.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(document_content::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("code"))
+                            .text()
+                            .not_null()
+                            .default("[]"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use entities::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+use sea_orm_migration::sea_orm::ConnectionTrait;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231210_000001_add_crawl_queue_domain_index"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Used to list queued domains w/o scanning the whole queue.
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "CREATE INDEX IF NOT EXISTS \"idx-crawl_queue-domain\" ON \"crawl_queue\" (\"domain\");"
+                    .to_string(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
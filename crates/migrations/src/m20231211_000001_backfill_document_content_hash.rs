@@ -0,0 +1,115 @@
+use entities::models::{document_alias, indexed_document};
+use entities::sea_orm::sea_query::{CaseStatement, Expr, Query};
+use entities::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, TransactionTrait};
+use shared::config::Config;
+use tantivy::{DocAddress, Index};
+
+/// Documents updated per statement. Each one takes 3 bind parameters, this
+/// stays under SQLite's default limit of 999.
+const UPDATE_BATCH_SIZE: usize = 300;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231211_000001_backfill_document_content_hash"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Content hashes used to only cover the page text & were only set for
+        // new crawls. Recompute them from the index for every web page so
+        // pages indexed before can be aliased too.
+        let index = match Index::open_in_dir(Config::new().index_dir()) {
+            Ok(index) => index,
+            Err(err) => {
+                println!("Error opening index: {err}");
+                return Ok(());
+            }
+        };
+
+        let schema = index.schema();
+        let (id_field, url_field, title_field, content_field) = match (
+            schema.get_field("id"),
+            schema.get_field("url"),
+            schema.get_field("title"),
+            schema.get_field("content"),
+        ) {
+            (Some(id), Some(url), Some(title), Some(content)) => (id, url, title, content),
+            _ => return Ok(()),
+        };
+
+        let searcher = index
+            .reader()
+            .map_err(|e| DbErr::Custom(format!("Unable to read index: {e}")))?
+            .searcher();
+
+        let mut hashes = Vec::new();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                let doc = match searcher.doc(DocAddress::new(segment_ord as u32, doc_id)) {
+                    Ok(doc) => doc,
+                    Err(e) => {
+                        log::error!("Unable to read doc {:?}", e);
+                        continue;
+                    }
+                };
+
+                let text = |field| {
+                    doc.get_first(field)
+                        .and_then(|value| value.as_text())
+                        .unwrap_or_default()
+                };
+                // Same rules as new crawls, only web pages w/ some content.
+                let content = text(content_field);
+                if !text(url_field).starts_with("http") || content.trim().is_empty() {
+                    continue;
+                }
+
+                hashes.push((
+                    text(id_field).to_string(),
+                    document_alias::content_hash(text(title_field), content),
+                ));
+            }
+        }
+
+        let txn = manager.get_connection().begin().await?;
+        txn.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "UPDATE indexed_document SET content_hash = NULL".to_string(),
+        ))
+        .await?;
+
+        for chunk in hashes.chunks(UPDATE_BATCH_SIZE) {
+            let mut content_hash = CaseStatement::new();
+            for (doc_id, hash) in chunk {
+                content_hash = content_hash.case(
+                    Expr::col(indexed_document::Column::DocId).eq(doc_id.as_str()),
+                    hash.as_str(),
+                );
+            }
+
+            let update = Query::update()
+                .table(indexed_document::Entity)
+                .value(indexed_document::Column::ContentHash, content_hash)
+                .and_where(
+                    Expr::col(indexed_document::Column::DocId)
+                        .is_in(chunk.iter().map(|(doc_id, _)| doc_id.as_str())),
+                )
+                .to_owned();
+            txn.execute(manager.get_database_backend().build(&update))
+                .await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use entities::models::document_content;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20231212_000001_add_document_content_metadata_columns"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Description & published date, so restored documents keep their
+        // snippets & sort by date as before.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(document_content::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("description"))
+                            .text()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(document_content::Entity)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Alias::new("published_at")).timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -1 +1,2 @@
 pub mod migration_utils;
+pub mod search_schema;
@@ -0,0 +1,132 @@
+use std::time::Instant;
+
+use sea_orm_migration::prelude::*;
+use tantivy::{schema::*, DocAddress, Index};
+
+use entities::sea_orm::{ConnectionTrait, Statement};
+use shared::config::Config;
+use spyglass_searcher::schema::{self, DocFields, SearchDocument};
+
+use super::migration_utils;
+
+/// Copies a document into the new schema, field by field. Fields missing from
+/// the old schema are left empty.
+fn migrate_document(old_doc: &Document, old_schema: &Schema, new_schema: &Schema) -> Document {
+    let mut new_doc = Document::default();
+    for (old_field, entry) in old_schema.fields() {
+        if let Some(new_field) = new_schema.get_field(entry.name()) {
+            for value in old_doc.get_all(old_field) {
+                new_doc.add_field_value(new_field, value.clone());
+            }
+        }
+    }
+
+    new_doc
+}
+
+/// Rebuilds the search index w/ the current schema when it doesn't have
+/// `field` yet. Every other field is copied over as is, so the new field is
+/// only filled in as documents are recrawled.
+pub async fn add_field(manager: &SchemaManager<'_>, field: &str) -> Result<(), DbErr> {
+    let result = manager
+        .get_connection()
+        .query_all(Statement::from_string(
+            manager.get_database_backend(),
+            "SELECT id FROM indexed_document LIMIT 1".to_owned(),
+        ))
+        .await?;
+
+    let config = Config::new();
+    let old_index_path = config.index_dir();
+    // No docs yet, nothing to migrate.
+    if result.is_empty() {
+        // Removing the old index folder will also remove any metadata that lingers
+        // from an empty index.
+        let _ = std::fs::remove_dir_all(old_index_path);
+        return Ok(());
+    }
+
+    let old_index = match Index::open_in_dir(&old_index_path) {
+        Ok(index) => index,
+        Err(err) => {
+            println!("Error opening index: {err}");
+            return Ok(());
+        }
+    };
+
+    let old_schema = old_index.schema();
+    if old_schema.get_field(field).is_some() {
+        // Already migrated
+        return Ok(());
+    }
+
+    let new_index_path = old_index_path
+        .parent()
+        .expect("Expected parent path")
+        .join("migrated_index");
+
+    if !new_index_path.exists() {
+        if let Err(e) = std::fs::create_dir(new_index_path.clone()) {
+            return Err(DbErr::Custom(format!("Can't create new index: {e}")));
+        }
+    }
+
+    println!("Migrating index @ {old_index_path:?} to {new_index_path:?}");
+
+    let new_schema = DocFields::as_schema();
+    let new_index = schema::initialize_index(new_schema.clone(), &new_index_path)
+        .map_err(|e| DbErr::Custom(format!("Unable to open new index: {e}")))?;
+    let mut new_writer = new_index
+        .writer(50_000_000)
+        .map_err(|e| DbErr::Custom(format!("Unable to create writer: {e}")))?;
+
+    let now = Instant::now();
+
+    let searcher = old_index
+        .reader()
+        .map_err(|e| DbErr::Custom(format!("Unable to read old index: {e}")))?
+        .searcher();
+
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+        for doc_id in segment_reader.doc_ids_alive() {
+            let old_doc = match searcher.doc(DocAddress::new(segment_ord as u32, doc_id)) {
+                Ok(doc) => doc,
+                Err(e) => {
+                    log::error!("Unable to read doc {:?}", e);
+                    continue;
+                }
+            };
+
+            if let Err(e) =
+                new_writer.add_document(migrate_document(&old_doc, &old_schema, &new_schema))
+            {
+                log::error!("Error migrating doc {:?}", e);
+            }
+        }
+    }
+
+    // Save change to new index
+    if let Err(e) = new_writer.commit() {
+        return Err(DbErr::Custom(format!("Unable to commit changes: {e}")));
+    }
+    // Release the index files before moving them around
+    drop(new_writer);
+    drop(searcher);
+    drop(old_index);
+
+    if let Err(e) = migration_utils::backup_dir(&old_index_path) {
+        return Err(DbErr::Custom(format!("Unable to backup old index: {e}")));
+    }
+
+    // Move new index into place.
+    if let Err(e) = migration_utils::replace_dir(&new_index_path, &old_index_path) {
+        return Err(DbErr::Custom(format!(
+            "Unable to move new index into place: {e}"
+        )));
+    }
+
+    let elapsed_time = now.elapsed();
+    println!("Migration took {} seconds.", elapsed_time.as_secs());
+
+    Ok(())
+}
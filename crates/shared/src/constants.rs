@@ -13,6 +13,12 @@ pub const FIREFOX_EXT_LINK: &str = "https://addons.mozilla.org/en-US/firefox/add
 // pub const OPERA_EXT_LINK: &str = "";
 // pub const EDGE_EXT_LINK: &str = "";
 
+/// Max number of characters allowed in a lens' display name.
+pub const LENS_DISPLAY_NAME_MAX_LEN: usize = 64;
+/// Default for the number of sources a single lens can hold, see
+/// `UserSettings::max_sources_per_lens`.
+pub const DEFAULT_MAX_SOURCES_PER_LENS: u32 = 10_000;
+
 pub const FEEDBACK_FORM: &str = "https://forms.gle/7UWP8gvhnwBbwF3KA";
 
 // Model download links
@@ -73,10 +73,22 @@ pub enum ClientInvoke {
     CopyToClipboard,
     #[strum(serialize = "open_settings_folder")]
     OpenSettingsFolder,
+    #[strum(serialize = "plugin:lens-updater|pause_lens")]
+    PauseLens,
+    #[strum(serialize = "plugin:lens-updater|add_lens_folder")]
+    AddLensFolder,
+    #[strum(serialize = "plugin:lens-updater|import_bookmarks")]
+    ImportBookmarks,
+    #[strum(serialize = "plugin:lens-updater|reset_lens")]
+    ResetLens,
+    #[strum(serialize = "plugin:lens-updater|set_lens_weight")]
+    SetLensWeight,
     #[strum(serialize = "plugin:lens-updater|uninstall_lens")]
     UninstallLens,
     #[strum(serialize = "update_and_restart")]
     UpdateAndRestart,
+    #[strum(serialize = "vacuum_database")]
+    VacuumDatabase,
     #[strum(serialize = "wizard_finished")]
     WizardFinished,
     #[strum(serialize = "navigate")]
@@ -116,6 +128,42 @@ pub struct TogglePluginParams {
     pub enabled: bool,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct PauseLensParams {
+    pub name: String,
+    #[serde(rename(serialize = "isPaused"))]
+    pub is_paused: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SetLensWeightParams {
+    pub name: String,
+    pub weight: u32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AddLensFolderParams {
+    pub name: String,
+    pub path: String,
+    /// Only index files with these extensions, all supported files when empty
+    pub extensions: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ImportBookmarksParams {
+    pub name: String,
+    /// Bookmarks file exported from a browser
+    pub path: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ResetLensParams {
+    pub name: String,
+    /// Also remove the lens' documents, rebuilding it from scratch
+    #[serde(rename(serialize = "removeDocuments"))]
+    pub remove_documents: bool,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct UninstallLensParams {
     pub name: String,
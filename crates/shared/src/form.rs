@@ -2,9 +2,28 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use strum_macros::{Display, EnumString};
 
+use crate::constants::LENS_DISPLAY_NAME_MAX_LEN;
 use crate::keyboard::KeyCode;
 use crate::{accelerator, MAC_OS};
 
+/// Checks a lens display name is non-empty & fits within the max length,
+/// returning the trimmed name.
+pub fn validate_lens_display_name(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("Name can't be empty".to_string());
+    }
+
+    let len = value.chars().count();
+    if len > LENS_DISPLAY_NAME_MAX_LEN {
+        return Err(format!(
+            "Name must be {LENS_DISPLAY_NAME_MAX_LEN} characters or less ({len}/{LENS_DISPLAY_NAME_MAX_LEN})"
+        ));
+    }
+
+    Ok(value.to_string())
+}
+
 #[derive(Clone, Debug, Display, EnumString, PartialEq, Serialize, Deserialize, Eq)]
 pub enum FormType {
     Bool,
@@ -115,3 +134,22 @@ pub struct SettingOpts {
     #[serde(default)]
     pub restart_required: bool,
 }
+
+#[cfg(test)]
+mod test {
+    use super::validate_lens_display_name;
+    use crate::constants::LENS_DISPLAY_NAME_MAX_LEN;
+
+    #[test]
+    fn test_validate_lens_display_name() {
+        assert_eq!(
+            validate_lens_display_name("  My Lens "),
+            Ok("My Lens".to_string())
+        );
+        assert!(validate_lens_display_name("   ").is_err());
+
+        let max = "a".repeat(LENS_DISPLAY_NAME_MAX_LEN);
+        assert!(validate_lens_display_name(&max).is_ok());
+        assert!(validate_lens_display_name(&format!("{max}a")).is_err());
+    }
+}
@@ -41,3 +41,74 @@ pub fn url_to_file_path(path: &str, is_windows: bool) -> String {
 
     path
 }
+
+/// Index files that servers usually serve for a directory URL.
+const INDEX_FILES: [&str; 2] = ["index.html", "index.htm"];
+
+/// Collapses URLs that servers almost always treat as the same page, e.g.
+/// `/docs/`, `/docs/index.html` & `/docs/index.htm` all become `/docs`. Only
+/// applies to http(s) URLs.
+pub fn collapse_equivalent_url(url: &mut url::Url) {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return;
+    }
+
+    let mut path = url.path().to_string();
+    for index in INDEX_FILES {
+        if path.ends_with(&format!("/{index}")) {
+            path.truncate(path.len() - index.len());
+            break;
+        }
+    }
+
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        url.set_path("/");
+    } else {
+        url.set_path(trimmed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::collapse_equivalent_url;
+    use url::Url;
+
+    fn collapse(url: &str) -> String {
+        let mut url = Url::parse(url).expect("Invalid url");
+        collapse_equivalent_url(&mut url);
+        url.to_string()
+    }
+
+    #[test]
+    fn test_collapse_equivalent_url() {
+        let expected = "https://example.com/docs";
+        assert_eq!(collapse("https://example.com/docs"), expected);
+        assert_eq!(collapse("https://example.com/docs/"), expected);
+        assert_eq!(collapse("https://example.com/docs/index.html"), expected);
+        assert_eq!(collapse("https://example.com/docs/index.htm"), expected);
+
+        // Root of a site stays as is
+        assert_eq!(collapse("https://example.com/"), "https://example.com/");
+        assert_eq!(
+            collapse("https://example.com/index.html"),
+            "https://example.com/"
+        );
+
+        // Query strings are kept & only whole index file names are collapsed
+        assert_eq!(
+            collapse("https://example.com/docs/index.html?page=2"),
+            "https://example.com/docs?page=2"
+        );
+        assert_eq!(
+            collapse("https://example.com/docs/myindex.html"),
+            "https://example.com/docs/myindex.html"
+        );
+
+        // Non-web URLs are untouched
+        assert_eq!(
+            collapse("file:///home/user/docs/index.html"),
+            "file:///home/user/docs/index.html"
+        );
+    }
+}
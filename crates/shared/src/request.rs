@@ -2,10 +2,164 @@ use crate::response::DocMetadata;
 use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct SearchParam {
     pub lenses: Vec<String>,
     pub query: String,
+    /// Fields to highlight query matches in, either `title` and/or `content`.
+    /// Defaults to only highlighting the content preview.
+    #[serde(default)]
+    pub highlight_fields: Option<Vec<String>>,
+    /// HTML tag used to wrap matches, defaults to `mark`.
+    #[serde(default)]
+    pub highlight_tag: Option<String>,
+    /// Include user notes attached to each result.
+    #[serde(default)]
+    pub include_notes: bool,
+    /// Only return documents with all of these (label, value) tags.
+    #[serde(default)]
+    pub tags: Vec<(String, String)>,
+    /// Opaque cursor from a previous response's `next_cursor`, used to fetch
+    /// the next page of results.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// How results are ordered, defaults to relevance.
+    #[serde(default)]
+    pub sort: Option<SortMode>,
+    /// Only keep the top few results from each domain, noting how many more
+    /// were collapsed in `remaining_count`.
+    #[serde(default)]
+    pub collapse_by_domain: bool,
+    /// Collapse consecutive results with the same title (e.g. templated
+    /// titles) into the highest scoring one, noting how many were collapsed
+    /// in `duplicate_title_count`. When using `page`, only results on the
+    /// same page are collapsed.
+    #[serde(default)]
+    pub dedupe_titles: bool,
+    /// How the content preview for each result is picked.
+    #[serde(default)]
+    pub snippet_strategy: SnippetStrategy,
+    /// Max number of results to return, e.g. for autocomplete. Capped by the
+    /// server, defaults to a single page of results (20 when using `page`).
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Zero based page of results to return, each `limit` results long. An
+    /// alternative to `cursor` when jumping to a specific page, the response
+    /// notes the total number of matches & pages.
+    #[serde(default)]
+    pub page: Option<usize>,
+    /// Suggest related queries in `related_queries`, built from terms that
+    /// show up across the top results.
+    #[serde(default)]
+    pub related_queries: bool,
+    /// Rank documents w/ the query terms close to each other higher, even
+    /// when they aren't an exact phrase. Makes searches a bit slower.
+    #[serde(default)]
+    pub proximity_boost: bool,
+}
+
+/// Ways to pick the content preview shown for a search result.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SnippetStrategy {
+    /// The part of the content matching the most query terms.
+    #[default]
+    BestMatch,
+    /// The part of the content around the first match.
+    FirstMatch,
+    /// The document's stored description, without highlighting.
+    Description,
+}
+
+/// File formats search results can be exported as.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// A JSON array of results.
+    #[default]
+    Json,
+    /// One row per result, with a header row.
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Body of a `POST /search/export` request.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchExportParam {
+    pub query: String,
+    #[serde(default)]
+    pub lenses: Vec<String>,
+    /// Only export documents with all of these (label, value) tags.
+    #[serde(default)]
+    pub tags: Vec<(String, String)>,
+    /// Only export results from these domains, all domains if empty.
+    #[serde(default)]
+    pub domains: Vec<String>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Result orderings supported by search.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SortMode {
+    #[default]
+    Relevance,
+    /// Newest documents first, by publish date or when they were last crawled.
+    DateDesc,
+    /// Oldest documents first, by publish date or when they were last crawled.
+    DateAsc,
+    TitleAsc,
+}
+
+/// Most recently indexed documents, e.g. to poll for what was just crawled.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RecentDocumentsParam {
+    /// Max number of documents returned, defaults to 25.
+    #[serde(default)]
+    pub limit: Option<u64>,
+    /// Only documents indexed or recrawled after this Unix timestamp (in
+    /// seconds), i.e. since the last time the feed was checked. These are
+    /// returned oldest first, poll again w/ the last `updated_at` for more.
+    #[serde(default)]
+    pub after: Option<i64>,
+    #[serde(default)]
+    pub lens: Option<String>,
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+/// Counts matching documents without returning them.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchCountParam {
+    pub query: String,
+    #[serde(default)]
+    pub lenses: Vec<String>,
+    /// Domains to break down the count by.
+    #[serde(default)]
+    pub domains: Vec<String>,
+}
+
+/// Cheaply estimates the number of matching documents, e.g. as a query is
+/// typed.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchEstimateParam {
+    pub query: String,
+    #[serde(default)]
+    pub lenses: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -56,6 +210,35 @@ pub struct BatchDocumentRequest {
     pub urls: Vec<String>,
     pub source: RawDocSource,
     pub tags: Vec<(String, String)>,
+    /// Only crawl URLs that haven't been indexed yet.
+    #[serde(default)]
+    pub new_only: bool,
+}
+
+/// Tags every document matching `query` with `tag`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TagDocumentsRequest {
+    pub query: String,
+    pub tag: (String, String),
+    /// Must be set to actually apply the tag, guards against accidental
+    /// mass-tagging.
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// A search to run periodically in the background, exporting new matches.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SavedQueryParam {
+    pub name: String,
+    pub query: String,
+    /// Only match documents in these lenses, all documents if empty.
+    #[serde(default)]
+    pub lenses: Vec<String>,
+    /// How often to run the query, in minutes.
+    pub interval_mins: u32,
+    /// Webhook URL new results are POSTed to, or the name of a NDJSON file in
+    /// the exports folder (under the data directory) they're appended to.
+    pub destination: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -7,6 +7,68 @@ use url::Url;
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AppStatus {
     pub num_docs: u64,
+    /// Age in seconds of the oldest queued crawl.
+    #[serde(default)]
+    pub oldest_queued_secs: Option<i64>,
+    /// Age in seconds of the longest running crawl.
+    #[serde(default)]
+    pub oldest_processing_secs: Option<i64>,
+    /// True when a crawl has been processing for longer than the configured
+    /// threshold, i.e. a worker is likely stuck or dead.
+    #[serde(default)]
+    pub crawl_stalled: bool,
+    /// True when web crawling is paused because most fetches are failing,
+    /// e.g. the network is down. Resumes on its own once sites can be reached.
+    #[serde(default)]
+    pub crawl_paused_for_failures: bool,
+    /// Lenses whose crawls are currently paused.
+    #[serde(default)]
+    pub paused_lenses: Vec<String>,
+    /// Number of distinct domains in the crawl queue outside of those crawled
+    /// by lenses.
+    #[serde(default)]
+    pub external_domain_count: u64,
+    /// Number of crawled pages that weren't indexed because their content was
+    /// identical to an already indexed document.
+    #[serde(default)]
+    pub num_deduplicated: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum CrawlUrlStatus {
+    /// Crawled & added to the index
+    Indexed,
+    /// Skipped by the crawler, e.g. blocked by robots.txt
+    Skipped,
+    Failed(String),
+    /// Didn't finish in time, the crawl continues in the background.
+    TimedOut,
+}
+
+/// Whether a requested database vacuum started right away or was deferred
+/// until the maintenance window opens.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum VacuumStatus {
+    Started,
+    Deferred,
+}
+
+/// Result of manually crawling a single URL
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CrawlUrlResult {
+    pub url: String,
+    pub status: CrawlUrlStatus,
+    /// Title extracted from the page, if it was indexed
+    pub title: Option<String>,
+}
+
+/// Result of importing a bookmarks file
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ImportBookmarksResult {
+    /// Number of unique links queued for crawling
+    pub num_imported: usize,
+    /// Entries w/o a link that can be crawled
+    pub num_skipped: usize,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -112,6 +174,13 @@ pub struct LensResult {
     pub download_url: Option<String>,
     pub progress: InstallStatus,
     pub lens_type: LensType,
+    /// Crawls for this lens are paused.
+    #[serde(default)]
+    pub is_paused: bool,
+    /// Search ranking multiplier as a percentage, 100 leaves scores unchanged.
+    /// Only set for installed lenses.
+    #[serde(default)]
+    pub ranking_weight: Option<u32>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
@@ -126,7 +195,14 @@ pub struct PluginResult {
 pub struct SearchMeta {
     pub query: String,
     pub num_docs: u32,
-    pub wall_time_ms: u32,
+    /// Time taken by the search, in milliseconds.
+    pub wall_time_ms: u64,
+    /// Cursor used to request the next page of results, if there may be more.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+    /// Suggested follow-up queries, when requested.
+    #[serde(default)]
+    pub related_queries: Vec<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -141,6 +217,43 @@ pub struct SearchResult {
     pub url: String,
     pub tags: Vec<(String, String)>,
     pub score: f32,
+    /// User note attached to this document, only set when requested.
+    #[serde(default)]
+    pub note: Option<String>,
+    /// When collapsing results by domain, the number of other results from
+    /// this domain that were left out. Only set on the last shown result for
+    /// the domain.
+    #[serde(default)]
+    pub remaining_count: usize,
+    /// When deduping titles, the number of results right after this one with
+    /// the same title that were left out.
+    #[serde(default)]
+    pub duplicate_title_count: usize,
+}
+
+/// An indexed document looked up by its URL.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IndexedDocumentResult {
+    pub doc_id: String,
+    pub url: String,
+    pub domain: String,
+    pub title: String,
+    pub description: String,
+    /// Unix timestamp (in seconds) of when the document was first indexed.
+    pub created_at: i64,
+    /// Unix timestamp (in seconds) of when the document was last crawled.
+    pub updated_at: i64,
+}
+
+/// What changed in a document between its two most recent crawls.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DocumentChanges {
+    pub url: String,
+    /// Unix timestamp (in seconds) of when the previous version was replaced.
+    pub changed_at: i64,
+    /// Lines removed since the previous crawl are prefixed with "-", lines added
+    /// with "+".
+    pub diff: String,
 }
 
 // The search result template is used to provide extra
@@ -226,10 +339,40 @@ impl From<SearchResult> for SearchResultTemplate {
     }
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchCountResult {
+    pub query: String,
+    /// Total number of matching documents
+    pub count: usize,
+    /// Number of matching documents for each requested domain
+    pub domains: Vec<(String, usize)>,
+    pub wall_time_ms: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SearchEstimateResult {
+    pub query: String,
+    /// Number of matching documents, up to a cap
+    pub count: usize,
+    /// Set when counting stopped at the cap, i.e. there are `count`+ matches
+    pub is_capped: bool,
+    pub wall_time_ms: u32,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SearchResults {
     pub results: Vec<SearchResult>,
     pub meta: SearchMeta,
+    /// Total number of matching documents, only set when requesting a `page`.
+    #[serde(default)]
+    pub total_hits: Option<usize>,
+    /// Page of results returned, only set when requesting a `page`.
+    #[serde(default)]
+    pub page: Option<usize>,
+    /// Number of pages needed to go through every match, only set when
+    /// requesting a `page`.
+    #[serde(default)]
+    pub num_pages: Option<usize>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -237,6 +380,77 @@ pub struct SearchLensesResp {
     pub results: Vec<LensResult>,
 }
 
+/// Resources used crawling a lens, totalled over every crawl.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LensCrawlStats {
+    pub lens: String,
+    pub pages_indexed: u64,
+    pub bytes_fetched: u64,
+    pub crawl_time_ms: u64,
+    /// Unix timestamp (in seconds) of when the last crawl finished.
+    pub last_crawl_at: Option<i64>,
+}
+
+/// What was cleared when resetting a lens' crawl state
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LensResetResult {
+    /// Queued, failed & finished crawls removed
+    pub num_tasks_removed: usize,
+    /// Documents removed from the index, only when asked to
+    pub num_documents_removed: usize,
+}
+
+/// Progress of a full recrawl of a lens.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct LensRecrawlProgress {
+    pub job_id: String,
+    pub lens: String,
+    pub total: u64,
+    pub crawled: u64,
+    pub failed: u64,
+    pub remaining: u64,
+    pub percent_complete: f32,
+    /// Estimated seconds left, unknown until the first task is done.
+    pub eta_secs: Option<u64>,
+    pub is_finished: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SavedQueryResult {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+    pub lenses: Vec<String>,
+    pub interval_mins: u32,
+    pub destination: String,
+    /// Unix timestamp (in seconds) of when the query was last run.
+    pub last_run_at: Option<i64>,
+}
+
+/// A node in the tree of queued & indexed URLs, grouped by domain and then by
+/// path segment. Counts include everything below the node.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct QueueTreeNode {
+    pub name: String,
+    pub queued: u64,
+    pub indexed: u64,
+    pub children: Vec<QueueTreeNode>,
+}
+
+/// A crawl that failed & won't be retried.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FailedCrawl {
+    /// Id of the queue entry
+    pub id: i64,
+    pub url: String,
+    pub domain: String,
+    /// Why the crawl failed, if known
+    pub error: Option<String>,
+    pub num_retries: u8,
+    /// Unix timestamp (in seconds) of when the crawl failed.
+    pub failed_at: i64,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct LibraryStats {
     pub lens_name: String,
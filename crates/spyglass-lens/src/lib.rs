@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use blake2::{Blake2s256, Digest};
@@ -6,7 +7,7 @@ use serde::{Deserialize, Serialize};
 pub mod pipeline;
 pub mod types;
 mod utils;
-use types::{LensFilters, LensRule, LensSource};
+use types::{LensFeed, LensFilters, LensRule, LensSource, Limit};
 
 pub use crate::pipeline::PipelineConfiguration;
 use utils::{regex_for_domain, regex_for_prefix};
@@ -45,6 +46,18 @@ pub struct LensConfig {
     /// Tags to automatically apply to any URLs indexed by this lens
     #[serde(default)]
     pub tags: Vec<(String, String)>,
+    /// Number of pages allowed per domain for this lens' crawls, overriding
+    /// the user's `domain_crawl_limit`.
+    #[serde(default)]
+    pub domain_crawl_limit: Option<Limit>,
+    /// RSS/Atom feeds polled for new entries to crawl, e.g. the feed sources
+    /// added w/ the lens editor.
+    #[serde(default)]
+    pub feeds: Vec<LensFeed>,
+    /// Search ranking multipliers for the lens' sources, keyed by domain, e.g.
+    /// set w/ the lens editor. Boosts set locally take precedence.
+    #[serde(default)]
+    pub source_boosts: HashMap<String, f32>,
     // Fields that are used internally & should not be serialized/deserialized
     #[serde(skip)]
     pub file_path: PathBuf,
@@ -166,6 +179,27 @@ mod test {
         assert_eq!(config.name, "extra_fields");
     }
 
+    #[test]
+    fn test_load_feeds() {
+        let config = LensConfig::from_string(
+            r#"(
+                version: "1",
+                name: "feeds",
+                domains: ["example.com"],
+                urls: [],
+                feeds: [
+                    (url: "https://example.com/feed.xml"),
+                    (url: "https://example.com/atom.xml", poll_interval_minutes: 15),
+                ],
+            )"#,
+        )
+        .expect("Unable to parse lens");
+
+        assert_eq!(config.feeds.len(), 2);
+        assert_eq!(config.feeds[0].poll_interval_minutes, 60);
+        assert_eq!(config.feeds[1].poll_interval_minutes, 15);
+    }
+
     #[test]
     fn test_all_tags() {
         let config = LensConfig {
@@ -1,3 +1,4 @@
+use diff::Diff;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -8,6 +9,27 @@ pub struct LensFilters {
     pub skipped: Vec<String>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, Diff)]
+pub enum Limit {
+    Infinite,
+    Finite(u32),
+}
+
+impl Default for Limit {
+    fn default() -> Self {
+        Self::Finite(100)
+    }
+}
+
+impl Limit {
+    pub fn value(&self) -> u32 {
+        match self {
+            Limit::Infinite => u32::MAX,
+            Limit::Finite(val) => *val,
+        }
+    }
+}
+
 /// Different rules that filter out the URLs that would be crawled for a lens
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum LensRule {
@@ -23,6 +45,29 @@ pub enum LensRule {
     SanitizeUrls(String, UrlSanitizeConfig),
 }
 
+/// RSS/Atom feed that's fetched again every `poll_interval_minutes`, so new
+/// entries are crawled as they're published.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LensFeed {
+    pub url: String,
+    #[serde(default = "LensFeed::default_poll_interval_minutes")]
+    pub poll_interval_minutes: u32,
+    /// Index the feed's own entry titles & summaries as a document, on top of
+    /// the entries it links to.
+    #[serde(default = "LensFeed::default_include_summary")]
+    pub include_summary: bool,
+}
+
+impl LensFeed {
+    pub fn default_poll_interval_minutes() -> u32 {
+        60
+    }
+
+    pub fn default_include_summary() -> bool {
+        true
+    }
+}
+
 /// Defines Url Sanitization Configuration. This configuration allows urls to be modified to
 /// produce the correct url for crawling.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -113,6 +113,11 @@ pub enum HttpMethod {
     PATCH,
 }
 
+/// Crawl queue priority for URLs discovered by a plugin.
+pub const PRIORITY_DEFAULT: i32 = 0;
+/// Crawl queue priority for URLs the user asked for, these are crawled first.
+pub const PRIORITY_USER: i32 = 100;
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum PluginCommandRequest {
     DeleteDoc {
@@ -121,6 +126,9 @@ pub enum PluginCommandRequest {
     // Enqueue a list of URLs into the crawl queue
     Enqueue {
         urls: Vec<String>,
+        /// See `PRIORITY_DEFAULT` & `PRIORITY_USER`.
+        #[serde(default)]
+        priority: i32,
     },
     // Requests a set of documents that match the
     // provided query. If subscribe is set to false
@@ -1,6 +1,6 @@
 use crate::{
     Authentication, DocumentQuery, DocumentUpdate, HttpMethod, PluginCommandRequest, Tag,
-    TagModification,
+    TagModification, PRIORITY_DEFAULT,
 };
 use serde::{de::DeserializeOwned, Serialize};
 use std::io;
@@ -185,7 +185,18 @@ pub fn delete_doc(url: &str) {
 
 /// Add an item to the Spyglass crawl queue
 pub fn enqueue_all(urls: &[String]) {
-    if object_to_stdout(&PluginCommandRequest::Enqueue { urls: urls.into() }).is_ok() {
+    enqueue_all_with_priority(urls, PRIORITY_DEFAULT);
+}
+
+/// Add items to the Spyglass crawl queue at `priority`, URLs the user asked
+/// for should use `PRIORITY_USER` so they're crawled first.
+pub fn enqueue_all_with_priority(urls: &[String], priority: i32) {
+    if object_to_stdout(&PluginCommandRequest::Enqueue {
+        urls: urls.into(),
+        priority,
+    })
+    .is_ok()
+    {
         unsafe {
             plugin_cmd();
         }
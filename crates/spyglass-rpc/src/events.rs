@@ -3,9 +3,11 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub enum RpcEventType {
     ConnectionSyncFinished,
+    DatabaseVacuumed,
     LensUninstalled,
     LensInstalled,
     ModelDownloadStatus,
+    ReindexProgress,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -22,3 +24,21 @@ pub enum ModelDownloadStatusPayload {
     Error { model_name: String, msg: String },
     InProgress { model_name: String, percent: u8 },
 }
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ReindexProgressPayload {
+    /// Documents are either `restored`, i.e. rebuilt in the index from their
+    /// stored content, or `requeued` to be crawled again when missing from it.
+    Finished {
+        checked: u64,
+        restored: u64,
+        requeued: u64,
+    },
+    Error {
+        msg: String,
+    },
+    InProgress {
+        checked: u64,
+        total: u64,
+    },
+}
@@ -1,16 +1,28 @@
 use jsonrpsee::core::{Error, JsonValue};
 use jsonrpsee::proc_macros::rpc;
-use shared::config::UserSettings;
-use shared::request::{BatchDocumentRequest, RawDocumentRequest, SearchLensesParam, SearchParam};
+use shared::config::{LensConfig, UserSettings};
+use shared::request::{
+    BatchDocumentRequest, RawDocumentRequest, RecentDocumentsParam, SavedQueryParam,
+    SearchCountParam, SearchEstimateParam, SearchLensesParam, SearchParam, TagDocumentsRequest,
+};
 use shared::response::{
-    AppStatus, DefaultIndices, LensResult, LibraryStats, ListConnectionResult, PluginResult,
-    SearchLensesResp, SearchResults,
+    AppStatus, CrawlUrlResult, DefaultIndices, DocumentChanges, FailedCrawl, ImportBookmarksResult,
+    IndexedDocumentResult, LensCrawlStats, LensRecrawlProgress, LensResetResult, LensResult,
+    LibraryStats, ListConnectionResult, PluginResult, QueueTreeNode, SavedQueryResult,
+    SearchCountResult, SearchEstimateResult, SearchLensesResp, SearchResults, VacuumStatus,
 };
 use std::collections::HashMap;
 
 mod events;
 pub use events::*;
 
+/// Error code for requests on something that doesn't exist, e.g. an unknown
+/// queue entry.
+pub const ERROR_CODE_NOT_FOUND: i32 = -32004;
+/// Error code for requests that conflict w/ the current state of what they
+/// act on, e.g. deleting a queue entry that's being crawled.
+pub const ERROR_CODE_CONFLICT: i32 = -32009;
+
 /// Rpc trait
 #[rpc(server, client, namespace = "spyglass")]
 pub trait Rpc {
@@ -33,6 +45,18 @@ pub trait Rpc {
     #[method(name = "index.is_document_indexed")]
     async fn is_document_indexed(&self, url: String) -> Result<bool, Error>;
 
+    /// Looks up the indexed document w/ exactly this URL, w/o running a search.
+    #[method(name = "index.get_indexed_document")]
+    async fn get_indexed_document(&self, url: String) -> Result<IndexedDocumentResult, Error>;
+
+    /// Most recently indexed or recrawled documents, newest first. Oldest first
+    /// when polling w/ `after`.
+    #[method(name = "index.recent_documents")]
+    async fn recent_documents(
+        &self,
+        params: RecentDocumentsParam,
+    ) -> Result<Vec<IndexedDocumentResult>, Error>;
+
     /// Permanently deletes a document from the spyglass index and any associated
     /// data.
     #[method(name = "index.delete_document")]
@@ -41,6 +65,51 @@ pub trait Rpc {
     #[method(name = "index.delete_document_by_url")]
     async fn delete_document_by_url(&self, url: String) -> Result<(), Error>;
 
+    /// Removes the document at this URL from the index & queues it to be
+    /// crawled again.
+    #[method(name = "index.remove_indexed_document")]
+    async fn remove_indexed_document(&self, url: String) -> Result<(), Error>;
+
+    /// Permanently deletes a document & blocks its URL so it's never indexed
+    /// again.
+    #[method(name = "index.block_document")]
+    async fn block_document(&self, id: String) -> Result<(), Error>;
+
+    /// Blocks a single URL from ever being crawled or indexed, removing it
+    /// from the index if it's already there.
+    #[method(name = "url_blocklist.add")]
+    async fn block_url(&self, url: String) -> Result<(), Error>;
+
+    #[method(name = "url_blocklist.remove")]
+    async fn unblock_url(&self, url: String) -> Result<(), Error>;
+
+    #[method(name = "url_blocklist.list")]
+    async fn blocked_urls(&self) -> Result<Vec<String>, Error>;
+
+    /// Returns the user note attached to the document at `url`, if any.
+    #[method(name = "index.get_document_note")]
+    async fn get_document_note(&self, url: String) -> Result<Option<String>, Error>;
+
+    /// Attaches a note to the document at `url`, replacing any existing note.
+    /// Notes persist across recrawls, an empty note removes it.
+    #[method(name = "index.set_document_note")]
+    async fn set_document_note(&self, url: String, note: String) -> Result<(), Error>;
+
+    /// Returns what changed in the document at `url` the last time it was
+    /// recrawled, if it has changed since first being indexed.
+    #[method(name = "index.document_changes")]
+    async fn document_changes(&self, url: String) -> Result<Option<DocumentChanges>, Error>;
+
+    /// Tags all documents matching a search query, up to a safety cap. Returns
+    /// the number of documents tagged.
+    #[method(name = "index.tag_documents")]
+    async fn tag_documents(&self, req: TagDocumentsRequest) -> Result<usize, Error>;
+
+    /// Crawls a single URL immediately & waits (up to a timeout) for it to be
+    /// indexed.
+    #[method(name = "crawl_url_now")]
+    async fn crawl_url_now(&self, url: String) -> Result<CrawlUrlResult, Error>;
+
     #[method(name = "authorize_connection")]
     async fn authorize_connection(&self, id: String) -> Result<(), Error>;
 
@@ -50,12 +119,62 @@ pub trait Rpc {
     #[method(name = "default_indices")]
     async fn default_indices(&self) -> Result<DefaultIndices, Error>;
 
+    /// Favicon for a domain as a data URL, or a placeholder icon if the
+    /// domain has no favicon.
+    #[method(name = "favicon")]
+    async fn favicon(&self, domain: String) -> Result<String, Error>;
+
     #[method(name = "get_library_stats")]
     async fn get_library_stats(&self) -> Result<HashMap<String, LibraryStats>, Error>;
 
     #[method(name = "install_lens")]
     async fn install_lens(&self, lens_name: String) -> Result<(), Error>;
 
+    /// Returns the full definition of an installed lens.
+    #[method(name = "lens_definition")]
+    async fn lens_definition(&self, name: String) -> Result<LensConfig, Error>;
+
+    /// Pages, bytes & time spent crawling an installed lens.
+    #[method(name = "lens_stats")]
+    async fn lens_stats(&self, name: String) -> Result<LensCrawlStats, Error>;
+
+    /// Queues every source of an installed lens to be crawled again. Returns
+    /// a job id to check on the recrawl's progress with.
+    #[method(name = "recrawl_lens")]
+    async fn recrawl_lens(&self, name: String) -> Result<String, Error>;
+
+    /// Progress of a lens recrawl. Finished jobs are only kept for a while.
+    #[method(name = "lens_recrawl_progress")]
+    async fn lens_recrawl_progress(
+        &self,
+        name: String,
+        job_id: String,
+    ) -> Result<LensRecrawlProgress, Error>;
+
+    /// Clears every crawl of a lens & bootstraps its sources again, w/o
+    /// changing the lens definition. Documents only in the lens are removed
+    /// too w/ `remove_documents`.
+    #[method(name = "reset_lens")]
+    async fn reset_lens(
+        &self,
+        name: String,
+        remove_documents: bool,
+    ) -> Result<LensResetResult, Error>;
+
+    /// Rebuilds a lens' separate index from the shared index, returning the
+    /// number of documents in it. Only available w/ lens indexes enabled.
+    #[method(name = "rebuild_lens_index")]
+    async fn rebuild_lens_index(&self, name: String) -> Result<usize, Error>;
+
+    /// Replaces the definition of an installed lens. Queued crawls for sources
+    /// no longer in the lens are removed & new sources are crawled.
+    #[method(name = "update_lens_definition")]
+    async fn update_lens_definition(
+        &self,
+        name: String,
+        definition: LensConfig,
+    ) -> Result<(), Error>;
+
     #[method(name = "list_connections")]
     async fn list_connections(&self) -> Result<ListConnectionResult, Error>;
 
@@ -77,9 +196,62 @@ pub trait Rpc {
     #[method(name = "search_docs")]
     async fn search_docs(&self, query: SearchParam) -> Result<SearchResults, Error>;
 
+    /// Counts documents matching a query without retrieving them, a cheaper
+    /// alternative to `search_docs` when only counts are needed.
+    #[method(name = "search_count")]
+    async fn search_count(&self, query: SearchCountParam) -> Result<SearchCountResult, Error>;
+
+    /// Approximate number of documents matching a query, counting stops once
+    /// the estimate is large. Cheap enough to call as a query is typed.
+    #[method(name = "search_estimate")]
+    async fn search_estimate(
+        &self,
+        query: SearchEstimateParam,
+    ) -> Result<SearchEstimateResult, Error>;
+
+    /// Finds documents similar to the indexed document at `url`, excluding
+    /// the document itself.
+    #[method(name = "similar_documents")]
+    async fn similar_documents(&self, url: String) -> Result<SearchResults, Error>;
+
+    /// Queued & indexed URLs as a tree of domains & paths, optionally limited
+    /// to a single domain.
+    #[method(name = "queue_tree")]
+    async fn queue_tree(&self, domain: Option<String>) -> Result<QueueTreeNode, Error>;
+
+    /// Most recently failed crawls & why they failed, optionally limited to a
+    /// single domain.
+    #[method(name = "failed_crawls")]
+    async fn failed_crawls(&self, domain: Option<String>) -> Result<Vec<FailedCrawl>, Error>;
+
+    /// Queues a failed crawl to be crawled again, w/ its retries reset.
+    #[method(name = "retry_failed_crawl")]
+    async fn retry_failed_crawl(&self, id: i64) -> Result<(), Error>;
+
+    /// Removes an entry from the crawl queue, unless it's being crawled.
+    #[method(name = "delete_queue_entry")]
+    async fn delete_queue_entry(&self, id: i64) -> Result<(), Error>;
+
+    /// Looks up indexed documents by their URL, either matching `pattern`
+    /// exactly or as a substring of the URL. Unlike `search_docs`, the URL is
+    /// not tokenized.
+    #[method(name = "search_by_url")]
+    async fn search_by_url(&self, pattern: String, exact: bool) -> Result<SearchResults, Error>;
+
     #[method(name = "search_lenses")]
     async fn search_lenses(&self, query: SearchLensesParam) -> Result<SearchLensesResp, Error>;
 
+    /// Saves a query that's run in the background on a schedule, with new
+    /// matches exported to a NDJSON file or webhook.
+    #[method(name = "saved_queries.add")]
+    async fn add_saved_query(&self, query: SavedQueryParam) -> Result<SavedQueryResult, Error>;
+
+    #[method(name = "saved_queries.list")]
+    async fn list_saved_queries(&self) -> Result<Vec<SavedQueryResult>, Error>;
+
+    #[method(name = "saved_queries.delete")]
+    async fn delete_saved_query(&self, id: i64) -> Result<(), Error>;
+
     #[method(name = "update_user_settings")]
     async fn update_user_settings(
         &self,
@@ -89,14 +261,66 @@ pub trait Rpc {
     #[method(name = "user_settings")]
     async fn user_settings(&self) -> Result<UserSettings, Error>;
 
+    /// Compacts the database & refreshes query planner stats in the
+    /// background. A `DatabaseVacuumed` event is sent when it's done. Outside
+    /// of the maintenance window it's deferred until the window opens.
+    #[method(name = "vacuum_database")]
+    async fn vacuum_database(&self) -> Result<VacuumStatus, Error>;
+
+    /// Rebuilds the search index from the database in the background.
+    /// Documents are re-added from their stored content, or queued to be
+    /// crawled again if there is none & they're missing from the index.
+    /// Progress is sent as `ReindexProgress` events.
+    #[method(name = "reindex")]
+    async fn reindex(&self) -> Result<(), Error>;
+
     #[method(name = "toggle_pause")]
     async fn toggle_pause(&self, is_paused: bool) -> Result<(), Error>;
 
+    /// Pauses/resumes crawling for a single lens, leaving other lenses running.
+    #[method(name = "pause_lens")]
+    async fn pause_lens(&self, name: String, is_paused: bool) -> Result<(), Error>;
+
+    /// Boosts/buries a lens' documents in search results. The weight is a
+    /// percentage, 100 leaves scores unchanged.
+    #[method(name = "set_lens_weight")]
+    async fn set_lens_weight(&self, name: String, weight: u32) -> Result<(), Error>;
+
+    /// Boosts/buries documents from a lens source (domain) when searching that
+    /// lens. The boost is a multiplier from 0.1 to 5.0, 1.0 leaves scores unchanged.
+    #[method(name = "set_lens_source_boost")]
+    async fn set_lens_source_boost(
+        &self,
+        name: String,
+        domain: String,
+        boost: f32,
+    ) -> Result<(), Error>;
+
+    /// Indexes the files in a local folder as part of a lens, optionally only
+    /// files with the given extensions.
+    #[method(name = "add_lens_folder")]
+    async fn add_lens_folder(
+        &self,
+        name: String,
+        path: String,
+        extensions: Vec<String>,
+    ) -> Result<(), Error>;
+
+    /// Queues the links in a bookmarks file (Netscape HTML format) for
+    /// crawling as part of a lens. Folder names are added as tags.
+    #[method(name = "import_bookmarks")]
+    async fn import_bookmarks(
+        &self,
+        name: String,
+        path: String,
+    ) -> Result<ImportBookmarksResult, Error>;
+
     #[method(name = "toggle_plugin")]
     async fn toggle_plugin(&self, name: String, enabled: bool) -> Result<(), Error>;
 
+    /// Returns the number of documents removed with the lens.
     #[method(name = "uninstall_lens")]
-    async fn uninstall_lens(&self, name: String) -> Result<(), Error>;
+    async fn uninstall_lens(&self, name: String) -> Result<usize, Error>;
 
     #[subscription(name = "subscribe_events", item = RpcEvent)]
     fn subscribe_events(&self, events: Vec<RpcEventType>);
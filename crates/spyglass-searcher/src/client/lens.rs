@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use tantivy::collector::DocSetCollector;
+use tantivy::query::TermQuery;
+use tantivy::schema::{Document, IndexRecordOption, Term};
+use tantivy::{Index, IndexReader, ReloadPolicy};
+
+use super::Searcher;
+use crate::schema::{self, DocFields, SearchDocument};
+use crate::{field_to_u64vec, SearchError, SearcherResult};
+
+/// Memory budget for a lens index writer. Documents trickle in as they're
+/// crawled, so a single small writer is plenty.
+const LENS_WRITER_HEAP: usize = 15_000_000;
+
+struct LensIndex {
+    name: String,
+    tag_id: u64,
+    /// Where the index is stored, `None` when it's in memory.
+    path: Option<PathBuf>,
+    searcher: Searcher,
+    /// Whether there are changes waiting to be committed.
+    dirty: AtomicBool,
+}
+
+/// Separate indexes holding a copy of each lens' documents, keyed by the lens'
+/// tag id. The shared index stays the source of truth, a lens index can be
+/// thrown away & rebuilt from it without touching any other lens.
+#[derive(Clone, Default)]
+pub struct LensIndexes {
+    /// Directory lens indexes are stored in, kept in memory when not set.
+    root: Option<PathBuf>,
+    indexes: Arc<RwLock<HashMap<u64, Arc<LensIndex>>>>,
+}
+
+impl LensIndexes {
+    pub fn new(root: Option<PathBuf>) -> Self {
+        Self {
+            root,
+            indexes: Default::default(),
+        }
+    }
+
+    fn find(&self, lens: &str) -> Option<Arc<LensIndex>> {
+        self.indexes
+            .read()
+            .ok()?
+            .values()
+            .find(|index| index.name == lens)
+            .cloned()
+    }
+
+    /// Searcher for the index of `lens`, if it has one.
+    pub fn get(&self, lens: &str) -> Option<Searcher> {
+        self.find(lens).map(|index| index.searcher.clone())
+    }
+
+    /// Opens (or creates) the index for `lens`. Returns true when the index
+    /// was just created & still needs to be filled.
+    pub(crate) fn open(&self, lens: &str, tag_id: u64, source: &Searcher) -> SearcherResult<bool> {
+        let mut indexes = self
+            .indexes
+            .write()
+            .map_err(|_| SearchError::WriterLocked)?;
+        if indexes.contains_key(&tag_id) {
+            return Ok(false);
+        }
+
+        let schema = source.index.schema();
+        let (index, path, is_new) = match &self.root {
+            Some(root) => {
+                let path = root.join(tag_id.to_string());
+                // Indexes created before a schema change are rebuilt
+                let is_outdated = path.join("meta.json").exists()
+                    && Index::open_in_dir(&path)
+                        .map(|index| index.schema() != schema)
+                        .unwrap_or(true);
+                if is_outdated {
+                    std::fs::remove_dir_all(&path).map_err(|err| SearchError::Other(err.into()))?;
+                }
+                let is_new = !path.join("meta.json").exists();
+                std::fs::create_dir_all(&path).map_err(|err| SearchError::Other(err.into()))?;
+                (schema::initialize_index(schema, &path)?, Some(path), is_new)
+            }
+            None => (schema::initialize_in_memory_index(schema), None, true),
+        };
+
+        let writer = index.writer_with_num_threads(1, LENS_WRITER_HEAP)?;
+        let reader: IndexReader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+
+        indexes.insert(
+            tag_id,
+            Arc::new(LensIndex {
+                name: lens.to_string(),
+                tag_id,
+                path,
+                searcher: Searcher {
+                    index,
+                    reader,
+                    writer: Some(Arc::new(Mutex::new(writer))),
+                    lens_indexes: None,
+                },
+                dirty: AtomicBool::new(false),
+            }),
+        );
+
+        Ok(is_new)
+    }
+
+    /// Closes the index for `lens` & deletes it from disk.
+    pub(crate) fn remove(&self, lens: &str) -> SearcherResult<()> {
+        let removed = {
+            let mut indexes = self
+                .indexes
+                .write()
+                .map_err(|_| SearchError::WriterLocked)?;
+            let tag_id = indexes
+                .values()
+                .find(|index| index.name == lens)
+                .map(|index| index.tag_id);
+            tag_id.and_then(|tag_id| indexes.remove(&tag_id))
+        };
+
+        if let Some(path) = removed.and_then(|index| index.path.clone()) {
+            std::fs::remove_dir_all(path).map_err(|err| SearchError::Other(err.into()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the contents of the index for `lens` w/ the lens' documents in
+    /// `source`, returning the number of documents copied. Only committed
+    /// documents are copied.
+    pub(crate) fn rebuild(&self, lens: &str, source: &Searcher) -> SearcherResult<usize> {
+        let index = self
+            .find(lens)
+            .ok_or_else(|| SearchError::Other(anyhow::anyhow!("No index for lens {lens}")))?;
+        let fields = DocFields::as_fields();
+        let query = TermQuery::new(
+            Term::from_field_u64(fields.tags, index.tag_id),
+            IndexRecordOption::Basic,
+        );
+        let searcher = source.reader.searcher();
+        let addresses = searcher.search(&query, &DocSetCollector)?;
+
+        let mut writer = index.searcher.lock_writer()?;
+        writer.delete_all_documents()?;
+        for address in &addresses {
+            writer.add_document(searcher.doc(*address)?)?;
+        }
+        writer.commit()?;
+        index.dirty.store(false, Ordering::SeqCst);
+        drop(writer);
+
+        index.searcher.refresh()?;
+        Ok(addresses.len())
+    }
+
+    /// Adds `doc` to the index of each lens it's tagged w/.
+    pub(crate) fn add(&self, doc: &Document) -> SearcherResult<()> {
+        let indexes = self.indexes.read().map_err(|_| SearchError::WriterLocked)?;
+        for tag_id in field_to_u64vec(doc, DocFields::as_fields().tags) {
+            if let Some(index) = indexes.get(&tag_id) {
+                index.searcher.lock_writer()?.add_document(doc.clone())?;
+                index.dirty.store(true, Ordering::SeqCst);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes documents from every lens index that has them.
+    pub(crate) fn delete(&self, doc_ids: &[String]) -> SearcherResult<()> {
+        let fields = DocFields::as_fields();
+        let indexes = self.indexes.read().map_err(|_| SearchError::WriterLocked)?;
+        for index in indexes.values() {
+            let searcher = index.searcher.reader.searcher();
+            let writer = index.searcher.lock_writer()?;
+            for doc_id in doc_ids {
+                let term = Term::from_field_text(fields.id, doc_id);
+                // Uncommitted documents aren't visible to the reader yet
+                if index.dirty.load(Ordering::SeqCst) || searcher.doc_freq(&term)? > 0 {
+                    writer.delete_term(term);
+                    index.dirty.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Commits the lens indexes w/ pending changes. Blocks until done.
+    pub(crate) fn commit(&self) -> SearcherResult<()> {
+        let indexes = self.indexes.read().map_err(|_| SearchError::WriterLocked)?;
+        for index in indexes.values() {
+            if index.dirty.swap(false, Ordering::SeqCst) {
+                index.searcher.lock_writer()?.commit()?;
+                index.searcher.refresh()?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -2,28 +2,147 @@ use std::fmt::{Debug, Error, Formatter};
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Instant;
 
-use tantivy::collector::TopDocs;
+use tantivy::collector::{Collector, Count, SegmentCollector, TopDocs};
 use tantivy::directory::error::LockError;
-use tantivy::query::TermQuery;
+use tantivy::query::{BooleanQuery, MoreLikeThisQuery, Occur, QueryClone, TermQuery, Weight};
 use tantivy::{schema::*, TantivyError};
-use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy};
+use tantivy::{
+    DocAddress, DocId, DocSet, Index, IndexReader, IndexWriter, ReloadPolicy, SegmentOrdinal,
+    SegmentReader, TERMINATED,
+};
 use uuid::Uuid;
 
+use shared::request::SortMode;
+
+use super::LensIndexes;
 use crate::query::{build_document_query, build_query, terms_for_field, QueryOptions};
 use crate::schema::{self, DocFields, SearchDocument};
 use crate::{
     document_to_struct, field_to_string, Boost, IndexBackend, QueryBoost, RetrievedDocument, Score,
-    SearchError, SearchQueryResult, SearchTrait, SearcherResult, WriteTrait,
+    SearchCursor, SearchError, SearchQueryResult, SearchTrait, SearcherResult, WriteTrait,
 };
 
 pub const SPYGLASS_NS: Uuid = uuid::uuid!("5fdfe40a-de2c-11ed-bfa7-00155deae876");
+/// Number of top matches considered when sorting by a stored field.
+const MAX_SORT_CANDIDATES: usize = 1000;
+
+/// Counts matching documents, giving up once `cap` matches are found. Much
+/// cheaper than `Count` for broad queries when an estimate is good enough.
+struct CappedCount {
+    cap: usize,
+}
+
+struct CappedSegmentCount {
+    cap: usize,
+    count: usize,
+}
+
+impl SegmentCollector for CappedSegmentCount {
+    type Fruit = usize;
+
+    fn collect(&mut self, _doc: DocId, _score: Score) {
+        if self.count < self.cap {
+            self.count += 1;
+        }
+    }
+
+    fn harvest(self) -> usize {
+        self.count
+    }
+}
+
+impl Collector for CappedCount {
+    type Fruit = usize;
+    type Child = CappedSegmentCount;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        _segment: &SegmentReader,
+    ) -> tantivy::Result<CappedSegmentCount> {
+        Ok(CappedSegmentCount {
+            cap: self.cap,
+            count: 0,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_counts: Vec<usize>) -> tantivy::Result<usize> {
+        Ok(segment_counts.into_iter().sum::<usize>().min(self.cap))
+    }
+
+    // Walk the matches directly so a segment stops as soon as the cap is hit
+    // instead of visiting every match.
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        _segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<usize> {
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let alive_bitset = reader.alive_bitset();
+
+        let mut count = 0;
+        let mut doc = scorer.doc();
+        while doc != TERMINATED && count < self.cap {
+            if alive_bitset.map_or(true, |alive| alive.is_alive(doc)) {
+                count += 1;
+            }
+            doc = scorer.advance();
+        }
+
+        Ok(count)
+    }
+}
+
+/// Top matches of a search, w/ documents only loaded a page at a time. Keeps
+/// the searcher the matches came from, so pages stay consistent while the
+/// index changes.
+pub struct TopMatches {
+    searcher: tantivy::Searcher,
+    matches: Vec<(Score, DocAddress)>,
+}
+
+impl TopMatches {
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// Loads up to `limit` documents, starting at `offset` in the matches.
+    pub fn page(&self, offset: usize, limit: usize) -> Vec<(Score, RetrievedDocument)> {
+        self.matches
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .flat_map(|(score, addr)| {
+                if let Ok(Some(doc)) = self.searcher.doc(*addr).map(|x| document_to_struct(&x)) {
+                    Some((*score, doc))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
 
 /// Tantivy searcher client
 #[derive(Clone)]
 pub struct Searcher {
     pub index: Index,
+    /// Reader searches are served from. It's separate from the writer so a
+    /// search never waits on indexing, the tradeoff being that results only
+    /// reflect the index as of the last reader refresh, i.e. the last commit.
     pub reader: IndexReader,
     pub writer: Option<Arc<Mutex<IndexWriter>>>,
+    /// Per-lens copies of the index, when enabled.
+    pub lens_indexes: Option<LensIndexes>,
 }
 
 impl Debug for Searcher {
@@ -45,6 +164,10 @@ impl WriteTrait for Searcher {
             }
         }
 
+        if let Some(lens_indexes) = &self.lens_indexes {
+            lens_indexes.delete(doc_ids)?;
+        }
+
         self.save().await?;
         Ok(doc_ids.len())
     }
@@ -54,8 +177,14 @@ impl WriteTrait for Searcher {
         let fields = DocFields::as_fields();
 
         for doc_update in updates {
-            let writer = self.lock_writer()?;
-            writer.add_document(doc_update.clone())?;
+            {
+                let writer = self.lock_writer()?;
+                writer.add_document(doc_update.clone())?;
+            }
+
+            if let Some(lens_indexes) = &self.lens_indexes {
+                lens_indexes.add(doc_update)?;
+            }
 
             let doc_id = field_to_string(doc_update, fields.id);
             upserted.push(doc_id.clone());
@@ -169,9 +298,36 @@ impl Searcher {
         }
     }
 
+    /// Commits pending changes & refreshes the reader so searches pick them
+    /// up. The commit runs on a blocking thread, it can take a while during a
+    /// large crawl & shouldn't hold up searches on the async runtime.
     pub async fn save(&self) -> SearcherResult<()> {
-        let mut writer = self.lock_writer()?;
-        writer.commit()?;
+        let writer = match &self.writer {
+            Some(writer) => writer.clone(),
+            None => return Err(SearchError::ReadOnly),
+        };
+
+        let lens_indexes = self.lens_indexes.clone();
+        tokio::task::spawn_blocking(move || -> SearcherResult<()> {
+            writer
+                .lock()
+                .map_err(|_| SearchError::WriterLocked)?
+                .commit()?;
+            if let Some(lens_indexes) = lens_indexes {
+                lens_indexes.commit()?;
+            }
+            Ok(())
+        })
+        .await
+        .map_err(|err| SearchError::Other(err.into()))??;
+
+        self.refresh()
+    }
+
+    /// Reloads the reader to the latest commit. Searches already in progress
+    /// keep using the previous snapshot.
+    pub fn refresh(&self) -> SearcherResult<()> {
+        self.reader.reload()?;
         Ok(())
     }
 
@@ -202,10 +358,17 @@ impl Searcher {
         };
 
         // For a search server you will typically create on reader for the entire
-        // lifetime of your program.
+        // lifetime of your program. When we own the writer the reader is refreshed
+        // after each commit in `save`, otherwise watch for commits made by whoever
+        // does.
+        let reload_policy = if readonly {
+            ReloadPolicy::OnCommit
+        } else {
+            ReloadPolicy::Manual
+        };
         let reader = index
             .reader_builder()
-            .reload_policy(ReloadPolicy::OnCommit)
+            .reload_policy(reload_policy)
             .try_into()
             .expect("Unable to create reader");
 
@@ -213,9 +376,80 @@ impl Searcher {
             index,
             reader,
             writer,
+            lens_indexes: None,
         })
     }
 
+    /// Keeps a separate index for each lens opened w/ `open_lens_index`,
+    /// stored under `index_path`. Remote indexes aren't supported.
+    pub fn with_lens_indexes(mut self, index_path: &IndexBackend) -> SearcherResult<Self> {
+        let root = match index_path {
+            IndexBackend::LocalPath(path) => Some(path.clone()),
+            IndexBackend::Memory => None,
+            IndexBackend::Http(_) => {
+                return Err(SearchError::Other(anyhow::anyhow!(
+                    "Lens indexes aren't supported w/ a remote index"
+                )))
+            }
+        };
+
+        self.lens_indexes = Some(LensIndexes::new(root));
+        Ok(self)
+    }
+
+    /// Searcher for the separate index of `lens`, if it has one.
+    pub fn lens_index(&self, lens: &str) -> Option<Searcher> {
+        self.lens_indexes
+            .as_ref()
+            .and_then(|lens_indexes| lens_indexes.get(lens))
+    }
+
+    /// Opens the separate index for `lens`, documents tagged w/ `tag_id` are
+    /// copied into it from then on. A newly created index is filled w/ the
+    /// lens' documents already in this index. Does nothing when lens indexes
+    /// aren't enabled.
+    pub async fn open_lens_index(&self, lens: &str, tag_id: u64) -> SearcherResult<()> {
+        let lens_indexes = match &self.lens_indexes {
+            Some(lens_indexes) => lens_indexes,
+            None => return Ok(()),
+        };
+
+        if lens_indexes.open(lens, tag_id, self)? {
+            let num_docs = self.rebuild_lens_index(lens).await?;
+            log::info!("created index for lens {} w/ {} docs", lens, num_docs);
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds the separate index for `lens` from this index, returning the
+    /// number of documents in it. Pending changes are committed first so
+    /// they're included.
+    pub async fn rebuild_lens_index(&self, lens: &str) -> SearcherResult<usize> {
+        let lens_indexes = match &self.lens_indexes {
+            Some(lens_indexes) => lens_indexes.clone(),
+            None => return Err(SearchError::Other(anyhow::anyhow!("Lens indexes disabled"))),
+        };
+
+        if !self.is_readonly() {
+            self.save().await?;
+        }
+
+        let source = self.clone();
+        let lens = lens.to_string();
+        tokio::task::spawn_blocking(move || lens_indexes.rebuild(&lens, &source))
+            .await
+            .map_err(|err| SearchError::Other(err.into()))?
+    }
+
+    /// Closes the separate index for `lens` & deletes it.
+    pub fn remove_lens_index(&self, lens: &str) -> SearcherResult<()> {
+        match &self.lens_indexes {
+            Some(lens_indexes) => lens_indexes.remove(lens),
+            None => Ok(()),
+        }
+    }
+
     /// Helper method to execute a search based on the provided document query
     pub async fn search_by_query(
         &self,
@@ -251,6 +485,375 @@ impl Searcher {
             .collect()
     }
 
+    /// Finds documents similar to the one identified by `doc_id` by building a
+    /// "more like this" query from its title & content. The source document is
+    /// excluded from the results. Returns `None` if the document is not in the index.
+    pub async fn more_like_this(
+        &self,
+        doc_id: &str,
+        num_results: usize,
+    ) -> Option<Vec<(Score, RetrievedDocument)>> {
+        let source = self.get(doc_id).await?;
+        let fields = DocFields::as_fields();
+
+        let query = MoreLikeThisQuery::builder()
+            .with_min_doc_frequency(1)
+            .with_min_term_frequency(1)
+            .with_max_query_terms(25)
+            .with_document_fields(vec![
+                (fields.title, vec![Value::Str(source.title.clone())]),
+                (fields.content, vec![Value::Str(source.content.clone())]),
+            ]);
+
+        let searcher = self.reader.searcher();
+        // Grab one extra since the source document will most likely match itself.
+        let collector = TopDocs::with_limit(num_results + 1);
+        let top_docs = searcher
+            .search(&query, &collector)
+            .expect("Unable to execute query");
+
+        let docs = top_docs
+            .into_iter()
+            .filter(|(score, _)| *score > 0.0)
+            .flat_map(|(score, addr)| {
+                if let Ok(Some(doc)) = searcher.doc(addr).map(|x| document_to_struct(&x)) {
+                    Some((score, doc))
+                } else {
+                    None
+                }
+            })
+            .filter(|(_, doc)| doc.doc_id != source.doc_id)
+            .take(num_results)
+            .collect();
+
+        Some(docs)
+    }
+
+    /// Counts the documents matching the query without retrieving them. Also
+    /// returns the number of matches for each of the requested `domains`.
+    pub fn count(
+        &self,
+        query_string: &str,
+        filters: &[QueryBoost],
+        domains: &[String],
+    ) -> (usize, Vec<(String, usize)>) {
+        let searcher = self.reader.searcher();
+        let fields = DocFields::as_fields();
+
+        let (_, query) = build_query(
+            &self.index,
+            query_string,
+            filters,
+            &[],
+            QueryOptions::default(),
+        );
+
+        let total = searcher
+            .search(&query, &Count)
+            .expect("Unable to execute query");
+
+        let mut by_domain = Vec::new();
+        for domain in domains {
+            let domain_query = BooleanQuery::new(vec![
+                (Occur::Must, query.box_clone()),
+                (
+                    Occur::Must,
+                    Box::new(TermQuery::new(
+                        Term::from_field_text(fields.domain, domain),
+                        IndexRecordOption::Basic,
+                    )),
+                ),
+            ]);
+
+            let count = searcher
+                .search(&domain_query, &Count)
+                .expect("Unable to execute query");
+            by_domain.push((domain.clone(), count));
+        }
+
+        (total, by_domain)
+    }
+
+    /// Rough number of documents matching a query, counting at most `cap`
+    /// matches. Returns the count & whether the cap was hit, i.e. whether
+    /// there are likely more matches.
+    pub fn estimate_count(
+        &self,
+        query_string: &str,
+        filters: &[QueryBoost],
+        cap: usize,
+    ) -> (usize, bool) {
+        let searcher = self.reader.searcher();
+        let (_, query) = build_query(
+            &self.index,
+            query_string,
+            filters,
+            &[],
+            QueryOptions::default(),
+        );
+
+        let count = searcher
+            .search(&query, &CappedCount { cap })
+            .expect("Unable to execute query");
+        (count, count >= cap)
+    }
+
+    /// Cursor based version of `search`. Returns up to `num_results` documents
+    /// ordered by score & doc_id, starting right after `after` if set.
+    pub async fn search_after(
+        &self,
+        query_string: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        num_results: usize,
+        after: Option<&SearchCursor>,
+    ) -> SearchQueryResult {
+        let start_timer = Instant::now();
+        let searcher = self.reader.searcher();
+
+        let (term_counts, query) = build_query(
+            &self.index,
+            query_string,
+            filters,
+            boosts,
+            QueryOptions::default(),
+        );
+
+        // Tantivy can't start collecting from a given key, so grab more & more
+        // results until the page after the cursor is complete.
+        let mut limit = num_results + 1;
+        let documents = loop {
+            let top_docs = searcher
+                .search(&query, &TopDocs::with_limit(limit))
+                .expect("Unable to execute query");
+
+            let exhausted = top_docs.len() < limit;
+            let lowest_score = top_docs.last().map(|(score, _)| *score);
+
+            let mut docs = top_docs
+                .into_iter()
+                .filter(|(score, _)| *score > 0.0)
+                // No need to load docs that come before the cursor
+                .filter(|(score, _)| after.map_or(true, |cursor| *score <= cursor.score))
+                .flat_map(|(score, addr)| {
+                    if let Ok(Some(doc)) = searcher.doc(addr).map(|x| document_to_struct(&x)) {
+                        Some((score, doc))
+                    } else {
+                        None
+                    }
+                })
+                .filter(|(score, doc)| {
+                    after.map_or(true, |cursor| cursor.precedes(*score, &doc.doc_id))
+                })
+                .collect::<Vec<(Score, RetrievedDocument)>>();
+
+            docs.sort_by(|(a_score, a), (b_score, b)| {
+                b_score
+                    .total_cmp(a_score)
+                    .then_with(|| a.doc_id.cmp(&b.doc_id))
+            });
+            docs.truncate(num_results);
+
+            // Anything not collected scores at most `lowest_score`, so the page is
+            // complete once its last result scores higher than that.
+            let is_complete = docs.len() == num_results
+                && docs.last().map_or(true, |(score, _)| {
+                    lowest_score.map_or(true, |lowest| *score > lowest)
+                });
+
+            if exhausted || is_complete {
+                break docs;
+            }
+
+            limit *= 2;
+        };
+
+        SearchQueryResult {
+            wall_time_ms: Instant::now().duration_since(start_timer).as_millis(),
+            num_docs: searcher.num_docs(),
+            term_counts,
+            documents,
+        }
+    }
+
+    /// Collects the top `limit` matches in a single pass w/o loading any
+    /// documents, for reading through a large number of results.
+    pub async fn search_top(
+        &self,
+        query_string: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        limit: usize,
+    ) -> TopMatches {
+        let searcher = self.reader.searcher();
+        let (_, query) = build_query(
+            &self.index,
+            query_string,
+            filters,
+            boosts,
+            QueryOptions::default(),
+        );
+
+        let matches = searcher
+            .search(&query, &TopDocs::with_limit(limit.max(1)))
+            .expect("Unable to execute query")
+            .into_iter()
+            // Filter out negative scores
+            .filter(|(score, _)| *score > 0.0)
+            .collect();
+
+        TopMatches { searcher, matches }
+    }
+
+    /// Offset based version of `search`. Returns up to `limit` documents
+    /// starting at `offset` in the results, along w/ the total number of
+    /// matching documents. Only the top `offset + limit` documents are
+    /// collected & only the requested ones are loaded.
+    pub async fn search_page(
+        &self,
+        query_string: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        offset: usize,
+        limit: usize,
+    ) -> (SearchQueryResult, usize) {
+        let start_timer = Instant::now();
+        let searcher = self.reader.searcher();
+
+        let (term_counts, query) = build_query(
+            &self.index,
+            query_string,
+            filters,
+            boosts,
+            QueryOptions::default(),
+        );
+
+        let collector = (TopDocs::with_limit(limit.max(1)).and_offset(offset), Count);
+        let (top_docs, total_hits) = searcher
+            .search(&query, &collector)
+            .expect("Unable to execute query");
+
+        let documents = top_docs
+            .into_iter()
+            // Filter out negative scores
+            .filter(|(score, _)| *score > 0.0)
+            .flat_map(|(score, addr)| {
+                if let Ok(Some(doc)) = searcher.doc(addr).map(|x| document_to_struct(&x)) {
+                    Some((score, doc))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let result = SearchQueryResult {
+            wall_time_ms: Instant::now().duration_since(start_timer).as_millis(),
+            num_docs: searcher.num_docs(),
+            term_counts,
+            documents,
+        };
+
+        (result, total_hits)
+    }
+
+    /// Same as `search` but orders the results using `sort`. Documents without a
+    /// date are returned after all dated documents when sorting by date.
+    pub async fn search_sorted(
+        &self,
+        query_string: &str,
+        filters: &[QueryBoost],
+        boosts: &[QueryBoost],
+        num_results: usize,
+        sort: SortMode,
+    ) -> SearchQueryResult {
+        if sort == SortMode::Relevance {
+            return self
+                .search(query_string, filters, boosts, num_results)
+                .await;
+        }
+
+        let start_timer = Instant::now();
+        let searcher = self.reader.searcher();
+
+        let (term_counts, query) = build_query(
+            &self.index,
+            query_string,
+            filters,
+            boosts,
+            QueryOptions::default(),
+        );
+
+        let top_docs = match sort {
+            SortMode::DateDesc | SortMode::DateAsc => {
+                let fields = DocFields::as_fields();
+                let newest_first = sort == SortMode::DateDesc;
+                // Sort on the publish date, falling back to when the document
+                // was last crawled. Ties are broken by relevance.
+                let collector = TopDocs::with_limit(num_results).tweak_score(
+                    move |segment_reader: &SegmentReader| {
+                        let fast_fields = segment_reader.fast_fields();
+                        let published = fast_fields.date(fields.published).ok();
+                        let modified = fast_fields.date(fields.lastmodified).ok();
+
+                        move |doc: DocId, score: Score| {
+                            // Missing dates are stored as the epoch
+                            let timestamp = [published.as_ref(), modified.as_ref()]
+                                .into_iter()
+                                .flatten()
+                                .map(|reader| reader.get_val(doc).into_timestamp_micros())
+                                .find(|timestamp| *timestamp != 0);
+
+                            let key = match timestamp {
+                                Some(timestamp) if newest_first => timestamp,
+                                Some(timestamp) => -timestamp,
+                                None => i64::MIN,
+                            };
+
+                            (key, score)
+                        }
+                    },
+                );
+
+                searcher
+                    .search(&query, &collector)
+                    .expect("Unable to execute query")
+                    .into_iter()
+                    .map(|((_, score), addr)| (score, addr))
+                    .collect::<Vec<_>>()
+            }
+            // Titles are tokenized so there's no fast field to sort on, instead
+            // sort the best matches by their stored title.
+            _ => searcher
+                .search(&query, &TopDocs::with_limit(MAX_SORT_CANDIDATES))
+                .expect("Unable to execute query"),
+        };
+
+        let mut documents = top_docs
+            .into_iter()
+            // Filter out negative scores
+            .filter(|(score, _)| *score > 0.0)
+            .flat_map(|(score, addr)| {
+                if let Ok(Some(doc)) = searcher.doc(addr).map(|x| document_to_struct(&x)) {
+                    Some((score, doc))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<(Score, RetrievedDocument)>>();
+
+        if sort == SortMode::TitleAsc {
+            documents.sort_by_cached_key(|(_, doc)| (doc.title.to_lowercase(), doc.doc_id.clone()));
+            documents.truncate(num_results);
+        }
+
+        SearchQueryResult {
+            wall_time_ms: Instant::now().duration_since(start_timer).as_millis(),
+            num_docs: searcher.num_docs(),
+            term_counts,
+            documents,
+        }
+    }
+
     pub async fn explain_search_with_lens(
         &self,
         doc_id: String,
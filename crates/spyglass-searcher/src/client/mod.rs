@@ -1,2 +1,4 @@
+mod lens;
 mod local;
+pub use self::lens::*;
 pub use self::local::*;
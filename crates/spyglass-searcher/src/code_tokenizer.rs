@@ -0,0 +1,117 @@
+use std::str::CharIndices;
+use tantivy::tokenizer::{BoxTokenStream, Token, TokenStream, Tokenizer};
+
+/// Tokenizer for code snippets. Identifiers (runs of alphanumerics & `_`) are
+/// kept whole & every other non-whitespace character is a token of its own.
+/// Nothing is lowercased or stemmed, so a phrase query like `foo.bar(` only
+/// matches those exact characters in that order.
+#[derive(Clone)]
+pub struct CodeTokenizer;
+
+pub struct CodeTokenStream<'a> {
+    text: &'a str,
+    chars: CharIndices<'a>,
+    token: Token,
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Tokenizer for CodeTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(CodeTokenStream {
+            text,
+            chars: text.char_indices(),
+            token: Token::default(),
+        })
+    }
+}
+
+impl<'a> CodeTokenStream<'a> {
+    /// Consumes the rest of the current identifier, returning where it ends.
+    fn identifier_end(&mut self) -> usize {
+        let text_len = self.text.len();
+        loop {
+            match self.chars.clone().next() {
+                Some((offset, c)) if !is_identifier(c) => return offset,
+                Some(_) => {
+                    self.chars.next();
+                }
+                None => return text_len,
+            }
+        }
+    }
+}
+
+impl<'a> TokenStream for CodeTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        self.token.text.clear();
+        self.token.position = self.token.position.wrapping_add(1);
+
+        while let Some((offset_from, c)) = self.chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+
+            let offset_to = if is_identifier(c) {
+                self.identifier_end()
+            } else {
+                offset_from + c.len_utf8()
+            };
+
+            self.token.offset_from = offset_from;
+            self.token.offset_to = offset_to;
+            self.token.text.push_str(&self.text[offset_from..offset_to]);
+            return true;
+        }
+
+        false
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CodeTokenizer;
+    use tantivy::tokenizer::{TokenStream, Tokenizer};
+
+    fn tokens(text: &str) -> Vec<(usize, String)> {
+        let mut stream = CodeTokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            let token = stream.token();
+            tokens.push((token.position, token.text.clone()));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_code_tokens() {
+        let texts = tokens("let x = foo.bar_baz(1);\n  Ünicode->run()")
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            vec![
+                "let", "x", "=", "foo", ".", "bar_baz", "(", "1", ")", ";", "Ünicode", "-", ">",
+                "run", "(", ")"
+            ]
+        );
+
+        let positions = tokens("a.b")
+            .into_iter()
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![0, 1, 2]);
+        assert!(tokens("   ").is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::path::PathBuf;
@@ -6,17 +7,19 @@ use thiserror::Error;
 use url::Url;
 
 pub mod client;
+pub mod code_tokenizer;
 pub mod schema;
 pub mod stop_word_filter;
 use schema::{DocFields, SearchDocument};
 
 mod query;
+pub mod ranking;
 pub mod similarity;
 pub mod utils;
 
 type Score = f32;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum IndexBackend {
     // Elasticsearch compatible REST API (such as Quickwit for example)
     Http(Url),
@@ -40,8 +43,10 @@ impl QueryBoost {
             Boost::DocId(_) => 3.0,
             Boost::Favorite { .. } => 3.0,
             Boost::Tag(_) => 1.5,
+            Boost::Domain(_) => 1.0,
             Boost::Url(_) => 3.0,
             Boost::CustomField { .. } => 0.0,
+            Boost::Proximity => 1.0,
         };
 
         QueryBoost {
@@ -58,7 +63,11 @@ pub enum Boost {
     Url(String),
     DocId(String),
     Tag(u64),
+    // As a filter, matches documents in any of the filtered domains.
+    Domain(String),
     CustomField { field_name: String, value: u64 },
+    // Query terms found close to each other in the content.
+    Proximity,
 }
 
 /// Contains stats & results for a search request
@@ -70,6 +79,40 @@ pub struct SearchQueryResult {
     pub documents: Vec<(Score, RetrievedDocument)>,
 }
 
+/// Position in a result set, used to continue a search right after the last
+/// result seen. Results are ordered by score (highest first) with ties broken by
+/// doc_id, so pages stay stable when documents are added or removed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchCursor {
+    pub score: Score,
+    pub doc_id: String,
+}
+
+impl SearchCursor {
+    pub fn new(score: Score, doc_id: &str) -> Self {
+        SearchCursor {
+            score,
+            doc_id: doc_id.to_string(),
+        }
+    }
+
+    /// Encodes the cursor into an opaque string to hand out to clients.
+    pub fn encode(&self) -> String {
+        format!("{:08x}:{}", self.score.to_bits(), self.doc_id)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let (score, doc_id) = cursor.split_once(':')?;
+        let score = u32::from_str_radix(score, 16).ok().map(f32::from_bits)?;
+        Some(SearchCursor::new(score, doc_id))
+    }
+
+    /// Whether a result with `score` & `doc_id` comes after this cursor.
+    pub fn precedes(&self, score: Score, doc_id: &str) -> bool {
+        score < self.score || (score == self.score && doc_id > self.doc_id.as_str())
+    }
+}
+
 #[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum SearchError {
@@ -126,8 +169,17 @@ pub struct RetrievedDocument {
     pub title: String,
     pub description: String,
     pub content: String,
+    /// Main article text, empty when it couldn't be extracted.
+    pub body: String,
     pub url: String,
     pub tags: Vec<u64>,
+    /// Code blocks, kept when the document is re-indexed.
+    #[serde(skip)]
+    pub code: Vec<String>,
+    #[serde(skip)]
+    pub published_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub last_modified: Option<DateTime<Utc>>,
 }
 
 // Helper method used to get the string value from a field
@@ -138,6 +190,21 @@ fn field_to_string(doc: &Document, field: Field) -> String {
         .unwrap_or_default()
 }
 
+// Helper method used to get every string value of a field.
+fn field_to_stringvec(doc: &Document, field: Field) -> Vec<String> {
+    doc.get_all(field)
+        .filter_map(|val| val.as_text())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+// Helper method used to get the date value from a field.
+fn field_to_date(doc: &Document, field: Field) -> Option<DateTime<Utc>> {
+    doc.get_first(field)
+        .and_then(|x| x.as_date())
+        .and_then(|x| Utc.timestamp_millis_opt(x.into_timestamp_millis()).single())
+}
+
 // Helper method used to get the u64 vector from a field.
 fn field_to_u64vec(doc: &Document, field: Field) -> Vec<u64> {
     doc.get_all(field).filter_map(|val| val.as_u64()).collect()
@@ -156,7 +223,11 @@ pub fn document_to_struct(doc: &Document) -> Option<RetrievedDocument> {
     let description = field_to_string(doc, fields.description);
     let url = field_to_string(doc, fields.url);
     let content = field_to_string(doc, fields.content);
+    let body = field_to_string(doc, fields.body);
     let tags = field_to_u64vec(doc, fields.tags);
+    let code = field_to_stringvec(doc, fields.code);
+    let published_at = field_to_date(doc, fields.published);
+    let last_modified = field_to_date(doc, fields.lastmodified);
 
     Some(RetrievedDocument {
         doc_id,
@@ -164,22 +235,30 @@ pub fn document_to_struct(doc: &Document) -> Option<RetrievedDocument> {
         title,
         description,
         content,
+        body,
         url,
         tags,
+        code,
+        published_at,
+        last_modified,
     })
 }
 
 #[cfg(test)]
 mod test {
     use crate::client::Searcher;
+    use crate::query::is_code_like;
     use crate::schema::{DocFields, DocumentUpdate, SearchDocument, ToDocument};
-    use crate::{Boost, IndexBackend, QueryBoost, SearchTrait, WriteTrait};
+    use crate::{Boost, IndexBackend, QueryBoost, SearchCursor, SearchTrait, WriteTrait};
+    use chrono::{TimeZone, Utc};
+    use shared::request::SortMode;
 
     async fn _build_test_index(searcher: &mut Searcher) {
         searcher
             .upsert(&DocumentUpdate {
                 doc_id: None,
                 title: "Of Mice and Men",
+                description: "",
                 domain: "example.com",
                 url: "https://example.com/mice_and_men",
                 content:
@@ -192,6 +271,9 @@ mod test {
             debris of the winter’s flooding; and sycamores with mottled, white, recumbent
             limbs and branches that arch over the pool",
                 tags: &vec![1_i64],
+                anchor_text: &[],
+                code: &[],
+                body: None,
                 published_at: None,
                 last_modified: None,
             }.to_document())
@@ -202,6 +284,7 @@ mod test {
             .upsert(&DocumentUpdate {
                 doc_id: None,
                 title: "Of Mice and Men",
+                description: "",
                 domain: "en.wikipedia.org",
                 url: "https://en.wikipedia.org/mice_and_men",
                 content:
@@ -214,6 +297,9 @@ mod test {
             debris of the winter’s flooding; and sycamores with mottled, white, recumbent
             limbs and branches that arch over the pool",
                 tags: &vec![2_i64],
+                anchor_text: &[],
+                code: &[],
+                body: None,
                 published_at: None,
                 last_modified: None,
             }.to_document())
@@ -225,6 +311,7 @@ mod test {
                 &DocumentUpdate {
                     doc_id: None,
                     title: "Of Cheese and Crackers",
+                    description: "",
                     domain: "en.wikipedia.org",
                     url: "https://en.wikipedia.org/cheese_and_crackers",
                     content: "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nulla
@@ -235,6 +322,9 @@ mod test {
             ac volutpat massa. Vivamus sed imperdiet est, id pretium ex. Praesent suscipit
             mattis ipsum, a lacinia nunc semper vitae.",
                     tags: &vec![2_i64],
+                    anchor_text: &[],
+                    code: &[],
+                    body: None,
                     published_at: None,
                     last_modified: None,
                 }
@@ -247,6 +337,7 @@ mod test {
             &DocumentUpdate {
             doc_id: None,
             title:"Frankenstein: The Modern Prometheus",
+            description:"",
             domain:"monster.com",
             url:"https://example.com/frankenstein",
             content:"You will rejoice to hear that no disaster has accompanied the commencement of an
@@ -254,6 +345,9 @@ mod test {
              yesterday, and my first task is to assure my dear sister of my welfare and
              increasing confidence in the success of my undertaking.",
              tags: &vec![1_i64],
+             anchor_text: &[],
+             code: &[],
+             body: None,
              published_at: None,
              last_modified: None
         }.to_document()).await
@@ -306,4 +400,514 @@ mod test {
         let results = searcher.search(query, &filters, &[], 5).await;
         assert_eq!(results.documents.len(), 0);
     }
+
+    #[tokio::test]
+    pub async fn test_more_like_this() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let source = searcher
+            .search_by_query(
+                Some(vec!["https://example.com/mice_and_men".into()]),
+                None,
+                &[],
+                &[],
+            )
+            .await;
+        let (_, source) = source.first().expect("Unable to find source doc");
+
+        let results = searcher
+            .more_like_this(&source.doc_id, 5)
+            .await
+            .expect("Source doc should exist");
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|(_, doc)| doc.doc_id != source.doc_id));
+        assert_eq!(results[0].1.url, "https://en.wikipedia.org/mice_and_men");
+
+        assert!(searcher.more_like_this("not-a-doc", 5).await.is_none());
+    }
+
+    #[tokio::test]
+    pub async fn test_count() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let (total, domains) = searcher.count(
+            "salinas",
+            &[],
+            &["en.wikipedia.org".into(), "example.org".into()],
+        );
+        assert_eq!(total, 2);
+        assert_eq!(
+            domains,
+            vec![
+                ("en.wikipedia.org".to_string(), 1),
+                ("example.org".to_string(), 0)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_estimate_count() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        assert_eq!(searcher.estimate_count("salinas", &[], 10), (2, false));
+        // Stops counting once the cap is hit
+        assert_eq!(searcher.estimate_count("salinas", &[], 1), (1, true));
+
+        let filters = vec![QueryBoost::new(Boost::Tag(2_u64))];
+        assert_eq!(searcher.estimate_count("salinas", &filters, 10), (1, false));
+    }
+
+    #[tokio::test]
+    pub async fn test_search_after() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let query = "salinas";
+        let all = searcher.search(query, &[], &[], 10).await.documents;
+        assert_eq!(all.len(), 2);
+
+        // Page through the results one at a time
+        let mut seen = Vec::new();
+        let mut cursor: Option<SearchCursor> = None;
+        loop {
+            let page = searcher
+                .search_after(query, &[], &[], 1, cursor.as_ref())
+                .await
+                .documents;
+            match page.last() {
+                Some((score, doc)) => {
+                    seen.push(doc.doc_id.clone());
+                    assert!(seen.len() <= all.len());
+                    let next = SearchCursor::new(*score, &doc.doc_id);
+                    assert_eq!(SearchCursor::decode(&next.encode()), Some(next.clone()));
+                    cursor = Some(next);
+                }
+                None => break,
+            }
+        }
+
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), all.len());
+    }
+
+    #[tokio::test]
+    pub async fn test_search_page() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let query = "salinas";
+        let all = searcher.search(query, &[], &[], 10).await.documents;
+        assert_eq!(all.len(), 2);
+
+        // Single result pages, in the same order as a regular search
+        for (idx, (_, expected)) in all.iter().enumerate() {
+            let (page, total_hits) = searcher.search_page(query, &[], &[], idx, 1).await;
+            assert_eq!(total_hits, 2);
+            assert_eq!(page.documents.len(), 1);
+            assert_eq!(page.documents[0].1.doc_id, expected.doc_id);
+        }
+
+        // Past the last result
+        let (page, total_hits) = searcher.search_page(query, &[], &[], 2, 1).await;
+        assert_eq!(total_hits, 2);
+        assert!(page.documents.is_empty());
+
+        // Last page is only partially filled
+        let (page, _) = searcher.search_page(query, &[], &[], 1, 5).await;
+        assert_eq!(page.documents.len(), 1);
+        assert_eq!(page.documents[0].1.doc_id, all[1].1.doc_id);
+    }
+
+    #[tokio::test]
+    pub async fn test_search_top() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let query = "salinas";
+        let all = searcher.search(query, &[], &[], 10).await.documents;
+        let top = searcher.search_top(query, &[], &[], 10).await;
+        assert_eq!(top.len(), 2);
+
+        // Same order as a regular search, loaded a page at a time
+        for (idx, (_, expected)) in all.iter().enumerate() {
+            let page = top.page(idx, 1);
+            assert_eq!(page.len(), 1);
+            assert_eq!(page[0].1.doc_id, expected.doc_id);
+        }
+        assert!(top.page(2, 1).is_empty());
+
+        // Domain filters match any of the domains
+        let filters = vec![QueryBoost::new(Boost::Domain("example.com".into()))];
+        let top = searcher.search_top(query, &filters, &[], 10).await;
+        let page = top.page(0, 10);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].1.domain, "example.com");
+
+        let filters = vec![
+            QueryBoost::new(Boost::Domain("example.com".into())),
+            QueryBoost::new(Boost::Domain("en.wikipedia.org".into())),
+        ];
+        let top = searcher.search_top(query, &filters, &[], 10).await;
+        assert_eq!(top.len(), 2);
+    }
+
+    #[tokio::test]
+    pub async fn test_domain_boost() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        let query = "salinas";
+        let docs = searcher.search(query, &[], &[], 10).await.documents;
+        assert_eq!(docs.len(), 2);
+
+        // Boosting the last result's domain moves it to the top
+        let last = docs[1].1.domain.clone();
+        let boosts = vec![QueryBoost {
+            field: Boost::Domain(last.clone()),
+            value: 5.0,
+        }];
+        let boosted = searcher.search(query, &[], &boosts, 10).await.documents;
+        assert_eq!(boosted[0].1.domain, last);
+
+        // & burying it keeps it at the bottom
+        let boosts = vec![QueryBoost {
+            field: Boost::Domain(last.clone()),
+            value: -0.9,
+        }];
+        let buried = searcher.search(query, &[], &boosts, 10).await.documents;
+        assert_eq!(buried[1].1.domain, last);
+    }
+
+    #[tokio::test]
+    pub async fn test_search_limit() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index");
+        _build_test_index(&mut searcher).await;
+
+        // Both "Of Mice and Men" docs & the cheese one match
+        let query = "mice cheese";
+        let all = searcher.search(query, &[], &[], 10).await.documents;
+        assert_eq!(all.len(), 3);
+
+        for limit in 1..all.len() {
+            let results = searcher.search(query, &[], &[], limit).await.documents;
+            assert_eq!(results.len(), limit);
+            assert_eq!(results[0].1.doc_id, all[0].1.doc_id);
+        }
+    }
+
+    #[tokio::test]
+    pub async fn test_proximity_boost() {
+        let searcher = Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .expect("Unable to open index");
+
+        let filler = "water sand trees hills ".repeat(5);
+        let docs = [
+            (
+                "https://example.com/far",
+                format!("river in the {filler} valley"),
+            ),
+            (
+                "https://example.com/near",
+                format!("{filler} river in the valley"),
+            ),
+        ];
+        for (url, content) in &docs {
+            searcher
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title: "Notes",
+                        description: "",
+                        domain: "example.com",
+                        url,
+                        content,
+                        tags: &[],
+                        anchor_text: &[],
+                        code: &[],
+                        body: None,
+                        published_at: None,
+                        last_modified: None,
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        let query = "river valley";
+        let results = searcher.search(query, &[], &[], 10).await.documents;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, results[1].0);
+
+        let boosts = vec![QueryBoost::new(Boost::Proximity)];
+        let results = searcher.search(query, &[], &boosts, 10).await.documents;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1.url, "https://example.com/near");
+        assert!(results[0].0 > results[1].0);
+    }
+
+    #[tokio::test]
+    pub async fn test_search_sorted() {
+        let searcher = Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .expect("Unable to open index");
+
+        let docs = [
+            ("Beta", "https://example.com/beta", Some(2020)),
+            ("alpha", "https://example.com/alpha", None),
+            ("Gamma", "https://example.com/gamma", Some(2022)),
+        ];
+        for (title, url, year) in docs {
+            searcher
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title,
+                        description: "",
+                        domain: "example.com",
+                        url,
+                        content: "the salinas river",
+                        tags: &[],
+                        anchor_text: &[],
+                        code: &[],
+                        body: None,
+                        published_at: year
+                            .map(|year| Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).unwrap()),
+                        last_modified: None,
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        let titles = |sort: SortMode| {
+            let searcher = &searcher;
+            async move {
+                searcher
+                    .search_sorted("salinas", &[], &[], 5, sort)
+                    .await
+                    .documents
+                    .into_iter()
+                    .map(|(_, doc)| doc.title)
+                    .collect::<Vec<String>>()
+            }
+        };
+
+        assert_eq!(
+            titles(SortMode::DateDesc).await,
+            vec!["Gamma", "Beta", "alpha"]
+        );
+        assert_eq!(
+            titles(SortMode::DateAsc).await,
+            vec!["Beta", "Gamma", "alpha"]
+        );
+        assert_eq!(
+            titles(SortMode::TitleAsc).await,
+            vec!["alpha", "Beta", "Gamma"]
+        );
+        assert_eq!(titles(SortMode::Relevance).await.len(), 3);
+
+        // Dates are read back w/ the document, so re-indexing keeps them
+        let results = searcher
+            .search_sorted("salinas", &[], &[], 1, SortMode::DateDesc)
+            .await;
+        assert_eq!(
+            results.documents[0].1.published_at,
+            Some(Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_anchor_text_search() {
+        let searcher = Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .expect("Unable to open index");
+
+        searcher
+            .upsert(
+                &DocumentUpdate {
+                    doc_id: None,
+                    title: "Home",
+                    description: "",
+                    domain: "example.com",
+                    url: "https://example.com/",
+                    content: "Welcome!",
+                    tags: &[],
+                    anchor_text: &["Steinbeck fan club".to_string()],
+                    code: &[],
+                    body: None,
+                    published_at: None,
+                    last_modified: None,
+                }
+                .to_document(),
+            )
+            .await
+            .expect("Unable to add doc");
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        let results = searcher.search("steinbeck", &[], &[], 5).await;
+        assert_eq!(results.documents.len(), 1);
+    }
+
+    #[tokio::test]
+    pub async fn test_search_reflects_last_commit() {
+        let searcher = Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .expect("Unable to open index");
+
+        searcher
+            .upsert(
+                &DocumentUpdate {
+                    doc_id: None,
+                    title: "Cannery Row",
+                    description: "",
+                    domain: "example.com",
+                    url: "https://example.com/cannery_row",
+                    content: "Cannery Row in Monterey in California is a poem",
+                    tags: &[],
+                    anchor_text: &[],
+                    code: &[],
+                    body: None,
+                    published_at: None,
+                    last_modified: None,
+                }
+                .to_document(),
+            )
+            .await
+            .expect("Unable to add doc");
+
+        // Uncommitted docs aren't visible to searches yet
+        let results = searcher.search("monterey", &[], &[], 5).await;
+        assert_eq!(results.documents.len(), 0);
+
+        searcher.save().await.expect("Unable to save index");
+        let results = searcher.search("monterey", &[], &[], 5).await;
+        assert_eq!(results.documents.len(), 1);
+    }
+
+    #[tokio::test]
+    pub async fn test_lens_index() {
+        let mut searcher =
+            Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .expect("Unable to open index")
+                .with_lens_indexes(&IndexBackend::Memory)
+                .expect("Unable to open lens indexes");
+        _build_test_index(&mut searcher).await;
+
+        // Filled w/ the lens' documents already indexed
+        searcher
+            .open_lens_index("books", 1)
+            .await
+            .expect("Unable to open lens index");
+        let lens_index = searcher.lens_index("books").expect("No lens index");
+        assert_eq!(lens_index.reader.searcher().num_docs(), 2);
+        assert!(searcher.lens_index("wiki").is_none());
+
+        // Kept in sync w/ the shared index
+        let doc_id = searcher
+            .upsert(
+                &DocumentUpdate {
+                    doc_id: None,
+                    title: "Cannery Row",
+                    description: "",
+                    domain: "example.com",
+                    url: "https://example.com/cannery_row",
+                    content: "Cannery Row in Monterey in California is a poem",
+                    tags: &[1_i64],
+                    anchor_text: &[],
+                    code: &[],
+                    body: None,
+                    published_at: None,
+                    last_modified: None,
+                }
+                .to_document(),
+            )
+            .await
+            .expect("Unable to add doc");
+        searcher.save().await.expect("Unable to save index");
+        let results = lens_index.search("monterey", &[], &[], 5).await;
+        assert_eq!(results.documents.len(), 1);
+
+        searcher
+            .delete(&doc_id)
+            .await
+            .expect("Unable to delete doc");
+        assert_eq!(lens_index.reader.searcher().num_docs(), 2);
+        let results = lens_index.search("salinas", &[], &[], 5).await;
+        assert_eq!(results.documents.len(), 1);
+
+        assert_eq!(searcher.rebuild_lens_index("books").await.ok(), Some(2));
+        searcher
+            .remove_lens_index("books")
+            .expect("Unable to remove lens index");
+        assert!(searcher.lens_index("books").is_none());
+    }
+
+    #[tokio::test]
+    pub async fn test_code_search() {
+        assert!(is_code_like("foo.bar("));
+        assert!(is_code_like("Vec<u8>"));
+        assert!(is_code_like("std::fs"));
+        assert!(!is_code_like("version 1.2 released. Next"));
+
+        let searcher = Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .expect("Unable to open index");
+
+        for (url, code) in [
+            ("https://example.com/literal", "let x = foo.bar(1);"),
+            ("https://example.com/spread", "foo = bar; baz(foo)"),
+        ] {
+            searcher
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title: "Docs",
+                        description: "",
+                        domain: "example.com",
+                        url,
+                        content: "Some docs",
+                        tags: &[],
+                        anchor_text: &[],
+                        code: &[code.to_string()],
+                        body: None,
+                        published_at: None,
+                        last_modified: None,
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+        }
+        searcher.save().await.expect("Unable to save index");
+        std::thread::sleep(std::time::Duration::from_millis(1000));
+
+        // Only the literal match, stemming & tokenizing prose-style would
+        // match both
+        let results = searcher.search("foo.bar(", &[], &[], 5).await;
+        assert_eq!(results.documents.len(), 1);
+        assert_eq!(results.documents[0].1.url, "https://example.com/literal");
+    }
 }
@@ -10,6 +10,10 @@ use super::DocFields;
 
 type QueryVec = Vec<(Occur, Box<dyn Query>)>;
 
+/// Max number of positions between two query terms for them to count as
+/// close to each other.
+const PROXIMITY_SLOP: u32 = 8;
+
 fn _boosted_term(term: Term, boost: Score) -> Box<BoostQuery> {
     Box::new(BoostQuery::new(
         Box::new(TermQuery::new(
@@ -21,6 +25,26 @@ fn _boosted_term(term: Term, boost: Score) -> Box<BoostQuery> {
     ))
 }
 
+/// Matches documents where each pair of consecutive terms is within
+/// `PROXIMITY_SLOP` positions of each other.
+fn _proximity_queries(terms: &[(usize, Term)], boost: Score) -> QueryVec {
+    terms
+        .windows(2)
+        .map(|pair| {
+            let (first, second) = (&pair[0], &pair[1]);
+            let phrase = PhraseQuery::new_with_offset_and_slop(
+                vec![
+                    (0, first.1.clone()),
+                    (second.0.saturating_sub(first.0), second.1.clone()),
+                ],
+                PROXIMITY_SLOP,
+            );
+            let query: Box<dyn Query> = Box::new(BoostQuery::new(Box::new(phrase), boost));
+            (Occur::Should, query)
+        })
+        .collect()
+}
+
 fn _boosted_phrase(terms: Vec<(usize, Term)>, boost: Score) -> Box<BoostQuery> {
     let slop = terms
         .last()
@@ -41,6 +65,13 @@ pub struct QueryOptions {
     title_boost: f32,
     /// full phrase matches in the title
     title_phrase_boost: f32,
+    /// single term matches in the text of links to the document
+    anchor_boost: f32,
+    /// single term matches in the main article text, on top of the content
+    /// match, so pages about the terms beat pages that mention them in passing
+    body_boost: f32,
+    /// literal matches in code blocks, only for queries that look like code
+    code_boost: f32,
 }
 
 impl Default for QueryOptions {
@@ -51,10 +82,32 @@ impl Default for QueryOptions {
             // weight title matches a little more
             title_boost: 2.0,
             title_phrase_boost: 2.5,
+            // links describe a page about as well as its title
+            anchor_boost: 1.5,
+            body_boost: 0.5,
+            // the exact snippet someone's looking for beats any prose match
+            code_boost: 3.0,
         }
     }
 }
 
+/// Whether a query is likely a code snippet, e.g. `foo.bar(` or `Vec<u8>`,
+/// rather than prose.
+pub fn is_code_like(query: &str) -> bool {
+    if query.contains("::") || query.contains("->") {
+        return true;
+    }
+
+    if query.chars().any(|c| "()[]{}<>=;_$|&\\".contains(c)) {
+        return true;
+    }
+
+    // Member access, but not the end of a sentence or a decimal number
+    query.as_bytes().windows(3).any(|chars| {
+        chars[0].is_ascii_alphanumeric() && chars[1] == b'.' && chars[2].is_ascii_alphabetic()
+    })
+}
+
 pub fn build_query(
     index: &Index,
     query_string: &str,
@@ -71,6 +124,8 @@ pub fn build_query(
 
     let content_terms = terms_for_field(&schema, tokenizers, query_string, fields.content);
     let title_terms = terms_for_field(&schema, tokenizers, query_string, fields.title);
+    let anchor_terms = terms_for_field(&schema, tokenizers, query_string, fields.anchors);
+    let body_terms = terms_for_field(&schema, tokenizers, query_string, fields.body);
 
     let term_count = content_terms.len();
 
@@ -93,6 +148,15 @@ pub fn build_query(
         term_query.push((Occur::Should, _boosted_phrase(title_terms.clone(), boost)));
     }
 
+    // Boost documents w/ the query terms near each other, even when they
+    // aren't an exact phrase
+    if let Some(boost) = boosts
+        .iter()
+        .find(|boost| matches!(boost.field, Boost::Proximity))
+    {
+        term_query.extend(_proximity_queries(&content_terms, boost.value));
+    }
+
     for (_position, term) in content_terms {
         term_query.push((Occur::Should, _boosted_term(term, opts.content_boost)));
     }
@@ -101,9 +165,34 @@ pub fn build_query(
         term_query.push((Occur::Should, _boosted_term(term, opts.title_boost)));
     }
 
+    for (_position, term) in anchor_terms {
+        term_query.push((Occur::Should, _boosted_term(term, opts.anchor_boost)));
+    }
+
+    for (_position, term) in body_terms {
+        term_query.push((Occur::Should, _boosted_term(term, opts.body_boost)));
+    }
+
+    // Code-like queries also match code blocks literally, punctuation &
+    // all, which the prose fields can't do.
+    if is_code_like(query_string) {
+        let mut code_terms = terms_for_field(&schema, tokenizers, query_string, fields.code);
+        if code_terms.len() > 1 {
+            let boost = opts.code_boost * code_terms.len() as f32;
+            let phrase = PhraseQuery::new_with_offset_and_slop(code_terms, 0);
+            term_query.push((
+                Occur::Should,
+                Box::new(BoostQuery::new(Box::new(phrase), boost)),
+            ));
+        } else if let Some((_position, term)) = code_terms.pop() {
+            term_query.push((Occur::Should, _boosted_term(term, opts.code_boost)));
+        }
+    }
+
     // Boost fields that happen to have a value, such as
     // - Tags that might be represented by search terms (e.g. "repository" or "file")
     // - Certain URLs or documents we want to focus on
+    let mut domain_boosts: QueryVec = Vec::new();
     for boost in boosts {
         let term = match &boost.field {
             Boost::DocId(doc_id) => {
@@ -112,10 +201,21 @@ pub fn build_query(
             }
             // Only considered in filters
             Boost::Favorite { .. } => continue,
+            // Applied to the query terms above
+            Boost::Proximity => continue,
             Boost::Tag(tag_id) => {
                 // Defaults to 1.5
                 _boosted_term(Term::from_field_u64(fields.tags, *tag_id), boost.value)
             }
+            // Kept out of the terms, documents still need to match the query.
+            // Negative values bury the domain.
+            Boost::Domain(domain) => {
+                domain_boosts.push((
+                    Occur::Should,
+                    _boosted_term(Term::from_field_text(fields.domain, domain), boost.value),
+                ));
+                continue;
+            }
             // todo: handle regex/prefixes?
             Boost::Url(url) => {
                 // Originally boosted to 3.0
@@ -135,6 +235,8 @@ pub fn build_query(
 
     // Must hit at least one of the terms
     let mut combined: QueryVec = vec![(Occur::Must, Box::new(BooleanQuery::new(term_query)))];
+    combined.extend(domain_boosts);
+    let mut domain_query: QueryVec = Vec::new();
     // Must have one of these, will filter out stuff that doesn't
     for filter in filters {
         let term = match &filter.field {
@@ -160,6 +262,14 @@ pub fn build_query(
                 // Defaults to 1.5
                 _boosted_term(Term::from_field_u64(fields.tags, *tag_id), 0.0)
             }
+            // Any of the domains
+            Boost::Domain(domain) => {
+                domain_query.push((
+                    Occur::Should,
+                    _boosted_term(Term::from_field_text(fields.domain, domain), 0.0),
+                ));
+                continue;
+            }
             // todo: handle regex/prefixes?
             Boost::Url(url) => {
                 // Originally boosted to 3.0
@@ -172,11 +282,17 @@ pub fn build_query(
                     continue;
                 }
             }
+            // Not a filter
+            Boost::Proximity => continue,
         };
 
         combined.push((Occur::Must, term));
     }
 
+    if !domain_query.is_empty() {
+        combined.push((Occur::Must, Box::new(BooleanQuery::new(domain_query))));
+    }
+
     (term_count, BooleanQuery::new(combined))
 }
 
@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::{RetrievedDocument, Score};
+
+/// Number of days it takes for the recency signal to drop by half.
+const RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+
+/// Document metadata that is available to a scoring hook after retrieval.
+pub struct RankingSignals<'a> {
+    pub doc: &'a RetrievedDocument,
+    /// When the document was last crawled/updated.
+    pub last_updated: Option<DateTime<Utc>>,
+    pub is_favorite: bool,
+}
+
+/// Post-retrieval hook used to adjust the score returned by the index.
+pub trait ScoringHook: Send + Sync {
+    fn adjust(&self, score: Score, signals: &RankingSignals) -> Score;
+}
+
+/// Default scoring hook which boosts scores based on a set of weights. With
+/// all weights set to zero the original score is returned unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct WeightedScoring {
+    /// Boost applied to recently updated documents.
+    pub recency_boost: f32,
+    /// Boost applied to favorited documents.
+    pub favorite_boost: f32,
+    /// Per-domain boosts, e.g. to promote sites the user trusts.
+    pub domain_boosts: HashMap<String, f32>,
+    /// Per-tag score multipliers, e.g. to boost or bury a lens. Documents with
+    /// several weighted tags use the largest weight.
+    pub tag_weights: HashMap<u64, f32>,
+}
+
+impl WeightedScoring {
+    fn recency(last_updated: Option<DateTime<Utc>>) -> f32 {
+        match last_updated {
+            Some(updated) => {
+                let age_days = (Utc::now() - updated).num_days().max(0) as f32;
+                0.5_f32.powf(age_days / RECENCY_HALF_LIFE_DAYS)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+impl ScoringHook for WeightedScoring {
+    fn adjust(&self, score: Score, signals: &RankingSignals) -> Score {
+        let mut multiplier = 1.0;
+        multiplier += self.recency_boost * Self::recency(signals.last_updated);
+
+        if signals.is_favorite {
+            multiplier += self.favorite_boost;
+        }
+
+        if let Some(boost) = self.domain_boosts.get(&signals.doc.domain) {
+            multiplier += boost;
+        }
+
+        let tag_weight = signals
+            .doc
+            .tags
+            .iter()
+            .filter_map(|tag_id| self.tag_weights.get(tag_id))
+            .copied()
+            .reduce(f32::max)
+            .unwrap_or(1.0);
+
+        score * multiplier.max(0.0) * tag_weight.max(0.0)
+    }
+}
+
+/// Sorts results by score, highest first.
+pub fn sort_by_score<T>(results: &mut [(Score, T)]) {
+    results.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RankingSignals, ScoringHook, WeightedScoring};
+    use crate::RetrievedDocument;
+    use chrono::{Duration, Utc};
+
+    fn doc(domain: &str) -> RetrievedDocument {
+        RetrievedDocument {
+            doc_id: "id".into(),
+            domain: domain.into(),
+            title: String::new(),
+            description: String::new(),
+            content: String::new(),
+            body: String::new(),
+            url: format!("https://{domain}"),
+            tags: Vec::new(),
+            code: Vec::new(),
+            published_at: None,
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn test_default_weights_unchanged() {
+        let doc = doc("example.com");
+        let signals = RankingSignals {
+            doc: &doc,
+            last_updated: Some(Utc::now()),
+            is_favorite: true,
+        };
+
+        assert_eq!(WeightedScoring::default().adjust(2.0, &signals), 2.0);
+    }
+
+    #[test]
+    fn test_weighted_scoring() {
+        let scoring = WeightedScoring {
+            recency_boost: 1.0,
+            favorite_boost: 1.0,
+            ..Default::default()
+        };
+
+        let doc = doc("example.com");
+        let fresh = RankingSignals {
+            doc: &doc,
+            last_updated: Some(Utc::now()),
+            is_favorite: false,
+        };
+        let stale = RankingSignals {
+            doc: &doc,
+            last_updated: Some(Utc::now() - Duration::days(365)),
+            is_favorite: false,
+        };
+        let favorite = RankingSignals {
+            doc: &doc,
+            last_updated: None,
+            is_favorite: true,
+        };
+
+        assert!(scoring.adjust(1.0, &fresh) > scoring.adjust(1.0, &stale));
+        assert_eq!(scoring.adjust(1.0, &favorite), 2.0);
+    }
+
+    #[test]
+    fn test_tag_weights() {
+        let scoring = WeightedScoring {
+            tag_weights: [(1, 2.0), (2, 0.5)].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let mut doc = doc("example.com");
+        doc.tags = vec![2, 3];
+        let signals = RankingSignals {
+            doc: &doc,
+            last_updated: None,
+            is_favorite: false,
+        };
+        assert_eq!(scoring.adjust(1.0, &signals), 0.5);
+
+        doc.tags = vec![1, 2];
+        let signals = RankingSignals {
+            doc: &doc,
+            last_updated: None,
+            is_favorite: false,
+        };
+        assert_eq!(scoring.adjust(1.0, &signals), 2.0);
+    }
+}
@@ -1,3 +1,4 @@
+use super::code_tokenizer::CodeTokenizer;
 use super::stop_word_filter::StopWordFilter;
 use chrono::Utc;
 use std::path::PathBuf;
@@ -17,9 +18,11 @@ use crate::client::SPYGLASS_NS;
 pub type FieldName = String;
 
 pub const TOKENIZER_NAME: &str = "spyglass_tokenizer_en";
+/// Tokenizer for code blocks, see `CodeTokenizer`.
+pub const CODE_TOKENIZER_NAME: &str = "spyglass_tokenizer_code";
 
 /// The current schema version
-pub const SCHEMA_VERSION: &str = "4";
+pub const SCHEMA_VERSION: &str = "7";
 pub struct SchemaMapping {
     pub text_fields: Option<Vec<(FieldName, TextOptions)>>,
     pub date_fields: Option<Vec<(FieldName, DateOptions)>>,
@@ -86,6 +89,11 @@ pub fn register_tokenizer(index: &Index) {
     index
         .tokenizers()
         .register(TOKENIZER_NAME, full_content_tokenizer_en);
+
+    let code_tokenizer = TextAnalyzer::from(CodeTokenizer).filter(RemoveLongFilter::limit(40));
+    index
+        .tokenizers()
+        .register(CODE_TOKENIZER_NAME, code_tokenizer);
 }
 
 pub trait ToDocument {
@@ -97,10 +105,18 @@ pub trait ToDocument {
 pub struct DocumentUpdate<'a> {
     pub doc_id: Option<String>,
     pub title: &'a str,
+    /// Short description of the page, e.g. from its `<meta>` description.
+    pub description: &'a str,
     pub domain: &'a str,
     pub url: &'a str,
     pub content: &'a str,
+    /// Main article text of the page, without navigation & other boilerplate.
+    pub body: Option<&'a str>,
     pub tags: &'a [i64],
+    /// Text of links pointing to this document from other pages.
+    pub anchor_text: &'a [String],
+    /// Code blocks found in the document, indexed verbatim.
+    pub code: &'a [String],
     pub published_at: Option<chrono::DateTime<Utc>>,
     pub last_modified: Option<chrono::DateTime<Utc>>,
 }
@@ -120,13 +136,35 @@ impl<'a> ToDocument for DocumentUpdate<'a> {
 
         let mut doc = Document::default();
         doc.add_text(fields.content, self.content);
+        if let Some(body) = self.body.filter(|body| !body.is_empty()) {
+            doc.add_text(fields.body, body);
+        }
         doc.add_text(fields.domain, self.domain);
         doc.add_text(fields.id, &doc_id);
         doc.add_text(fields.title, self.title);
+        doc.add_text(fields.description, self.description);
         doc.add_text(fields.url, self.url);
         for t in self.tags {
             doc.add_u64(fields.tags, *t as u64);
         }
+        for anchor in self.anchor_text {
+            doc.add_text(fields.anchors, anchor);
+        }
+        for code in self.code {
+            doc.add_text(fields.code, code);
+        }
+        if let Some(published) = self.published_at {
+            doc.add_date(
+                fields.published,
+                tantivy::DateTime::from_timestamp_millis(published.timestamp_millis()),
+            );
+        }
+        if let Some(modified) = self.last_modified {
+            doc.add_date(
+                fields.lastmodified,
+                tantivy::DateTime::from_timestamp_millis(modified.timestamp_millis()),
+            );
+        }
 
         doc
     }
@@ -137,12 +175,15 @@ pub struct DocFields {
     pub id: Field,
     pub domain: Field,
     pub content: Field,
+    pub body: Field,
     pub description: Field,
     pub title: Field,
     pub url: Field,
     pub tags: Field,
     pub published: Field,
     pub lastmodified: Field,
+    pub anchors: Field,
+    pub code: Field,
 }
 
 impl SearchDocument for DocFields {
@@ -153,6 +194,14 @@ impl SearchDocument for DocFields {
         let text_options = TextOptions::default()
             .set_indexing_options(text_field_indexing)
             .set_stored();
+        // Code is matched as is, w/o any stemming or stop words
+        let code_options = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(CODE_TOKENIZER_NAME)
+                    .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+            )
+            .set_stored();
 
         // FAST:    Fast fields can be random-accessed rapidly. Use this for fields useful
         //          for scoring, filtering, or collection.
@@ -174,7 +223,13 @@ impl SearchDocument for DocFields {
                 ("description".into(), text_options.clone()),
                 ("url".into(), STRING | STORED | FAST),
                 // Indexed
-                ("content".into(), text_options),
+                ("content".into(), text_options.clone()),
+                // Main article text, preferred for snippets when available
+                ("body".into(), text_options.clone()),
+                // Text of inbound links, used to boost relevance
+                ("anchors".into(), text_options),
+                // Contents of <pre>/<code> blocks, for literal code searches
+                ("code".into(), code_options),
             ]),
             date_fields: Some(vec![
                 (
@@ -210,6 +265,7 @@ impl SearchDocument for DocFields {
             id: schema.get_field("id").expect("No id in schema"),
             domain: schema.get_field("domain").expect("No domain in schema"),
             content: schema.get_field("content").expect("No content in schema"),
+            body: schema.get_field("body").expect("No body in schema"),
             description: schema
                 .get_field("description")
                 .expect("No description in schema"),
@@ -222,6 +278,8 @@ impl SearchDocument for DocFields {
             lastmodified: schema
                 .get_field("lastmodified")
                 .expect("No last modified date in schema"),
+            anchors: schema.get_field("anchors").expect("No anchors in schema"),
+            code: schema.get_field("code").expect("No code in schema"),
         }
     }
 }
@@ -8,6 +8,14 @@ use tantivy::{
 const MAX_HIGHLIGHT_SCAN: usize = 10_000;
 /// Max number of matches we need to generate a decent preview.
 const MAX_HIGHLIGHT_MATCHES: usize = 5;
+/// Max number of words kept when highlighting short fields such as the title.
+const MAX_HIGHLIGHT_WORDS: usize = 64;
+/// Number of words in a single-window snippet.
+const SNIPPET_WINDOW_WORDS: usize = 30;
+/// Words of context kept before the match a snippet starts at.
+const SNIPPET_LEAD_WORDS: usize = 5;
+/// Default HTML tag used to wrap matched terms.
+pub const DEFAULT_HIGHLIGHT_TAG: &str = "mark";
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct WordRange {
@@ -58,17 +66,74 @@ pub fn ff_to_string(
     None
 }
 
-/// Creates a short preview from content based on the search query terms by
-/// finding matches for words and creating a window around each match, joining
-/// together overlaps & returning the final string.
-pub fn generate_highlight_preview(tokenizer: &TextAnalyzer, query: &str, content: &str) -> String {
-    // tokenize search query
+fn query_terms(tokenizer: &TextAnalyzer, query: &str) -> HashSet<String> {
     let mut terms = HashSet::new();
     let mut tokens = tokenizer.token_stream(query);
     while let Some(t) = tokens.next() {
         terms.insert(t.text.clone());
     }
 
+    terms
+}
+
+fn is_match(tokenizer: &TextAnalyzer, terms: &HashSet<String>, word: &str) -> bool {
+    let normalized = tokenizer
+        .token_stream(word)
+        .next()
+        .map(|t| t.text.clone())
+        .unwrap_or_else(|| word.to_string());
+    terms.contains(&normalized)
+}
+
+/// Highlights every term in a short piece of text (such as a title) that matches
+/// the search query, wrapping matches in `<tag>`. Text is capped at
+/// `MAX_HIGHLIGHT_WORDS` words.
+pub fn generate_highlight_text(
+    tokenizer: &TextAnalyzer,
+    query: &str,
+    text: &str,
+    tag: &str,
+) -> String {
+    let terms = query_terms(tokenizer, query);
+    let mut words = text.split_whitespace().collect::<Vec<_>>();
+    let truncated = words.len() > MAX_HIGHLIGHT_WORDS;
+    words.truncate(MAX_HIGHLIGHT_WORDS);
+
+    let mut highlighted = words
+        .into_iter()
+        .map(|w| {
+            if is_match(tokenizer, &terms, w) {
+                format!("<{tag}>{w}</{tag}>")
+            } else {
+                w.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if truncated {
+        highlighted.push("...".to_string());
+    }
+
+    highlighted.join(" ")
+}
+
+/// Creates a short preview from content based on the search query terms by
+/// finding matches for words and creating a window around each match, joining
+/// together overlaps & returning the final string.
+pub fn generate_highlight_preview(tokenizer: &TextAnalyzer, query: &str, content: &str) -> String {
+    generate_highlight_preview_with_tag(tokenizer, query, content, DEFAULT_HIGHLIGHT_TAG)
+}
+
+/// Same as `generate_highlight_preview` but wraps matches in `<tag>`.
+pub fn generate_highlight_preview_with_tag(
+    tokenizer: &TextAnalyzer,
+    query: &str,
+    content: &str,
+    tag: &str,
+) -> String {
+    // tokenize search query
+    let terms = query_terms(tokenizer, query);
+
     let tokens = content
         .split_whitespace()
         .map(|s| s.to_string())
@@ -79,12 +144,7 @@ pub fn generate_highlight_preview(tokenizer: &TextAnalyzer, query: &str, content
     for (idx, w) in content.split_whitespace().enumerate() {
         num_tokens_scanned += 1;
 
-        let normalized = tokenizer
-            .token_stream(w)
-            .next()
-            .map(|t| t.text.clone())
-            .unwrap_or_else(|| w.to_string());
-        if terms.contains(&normalized) {
+        if is_match(tokenizer, &terms, w) {
             matched_indices.push(idx);
         }
 
@@ -122,7 +182,7 @@ pub fn generate_highlight_preview(tokenizer: &TextAnalyzer, query: &str, content
         if !slice.is_empty() {
             for idx in range.matches {
                 let slice_idx = idx - range.start;
-                slice[slice_idx] = format!("<mark>{}</mark>", &slice[slice_idx]);
+                slice[slice_idx] = format!("<{tag}>{}</{tag}>", &slice[slice_idx]);
             }
             desc.extend(slice);
             desc.push("...".to_string());
@@ -137,6 +197,99 @@ pub fn generate_highlight_preview(tokenizer: &TextAnalyzer, query: &str, content
     format!("<span>{}</span>", desc.join(" "))
 }
 
+/// Creates a snippet from the window of content around the first word that
+/// matches the search query. Returns None if nothing matches.
+pub fn generate_first_match_snippet(
+    tokenizer: &TextAnalyzer,
+    query: &str,
+    content: &str,
+    tag: &str,
+) -> Option<String> {
+    generate_snippet(tokenizer, query, content, tag, false)
+}
+
+/// Creates a snippet from the window of content that matches the most distinct
+/// query terms, preferring earlier windows on ties. Returns None if nothing
+/// matches.
+pub fn generate_best_match_snippet(
+    tokenizer: &TextAnalyzer,
+    query: &str,
+    content: &str,
+    tag: &str,
+) -> Option<String> {
+    generate_snippet(tokenizer, query, content, tag, true)
+}
+
+fn generate_snippet(
+    tokenizer: &TextAnalyzer,
+    query: &str,
+    content: &str,
+    tag: &str,
+    best_match: bool,
+) -> Option<String> {
+    let terms = query_terms(tokenizer, query);
+    let tokens = content
+        .split_whitespace()
+        .take(MAX_HIGHLIGHT_SCAN)
+        .collect::<Vec<_>>();
+
+    // The matching query term for each word, if any
+    let matches = tokens
+        .iter()
+        .map(|word| {
+            let normalized = tokenizer
+                .token_stream(word)
+                .next()
+                .map(|t| t.text.clone())
+                .unwrap_or_else(|| word.to_string());
+            Some(normalized).filter(|term| terms.contains(term))
+        })
+        .collect::<Vec<_>>();
+
+    let mut match_indices = matches
+        .iter()
+        .enumerate()
+        .filter(|(_, term)| term.is_some())
+        .map(|(idx, _)| idx);
+
+    let start = if best_match {
+        // Score windows by distinct terms matched, then total matches.
+        let mut best: Option<((usize, usize), usize)> = None;
+        for idx in match_indices {
+            let start = idx.saturating_sub(SNIPPET_LEAD_WORDS);
+            let end = (start + SNIPPET_WINDOW_WORDS).min(tokens.len());
+            let window = matches[start..end].iter().flatten().collect::<Vec<_>>();
+            let distinct = window.iter().collect::<HashSet<_>>().len();
+            let score = (distinct, window.len());
+
+            if best.map_or(true, |(best_score, _)| score > best_score) {
+                best = Some((score, start));
+            }
+        }
+        best?.1
+    } else {
+        match_indices.next()?.saturating_sub(SNIPPET_LEAD_WORDS)
+    };
+
+    let end = (start + SNIPPET_WINDOW_WORDS).min(tokens.len());
+    let mut snippet = Vec::new();
+    if start > 0 {
+        snippet.push("...".to_string());
+    }
+    for (word, term) in tokens[start..end].iter().zip(&matches[start..end]) {
+        if term.is_some() {
+            snippet.push(format!("<{tag}>{word}</{tag}>"));
+        } else {
+            snippet.push(word.to_string());
+        }
+    }
+    if end < tokens.len() {
+        snippet.push("...".to_string());
+    }
+
+    Some(format!("<span>{}</span>", snippet.join(" ")))
+}
+
 pub fn group_urls_by_scheme(urls: Vec<&str>) -> HashMap<&str, Vec<&str>> {
     let mut grouping: HashMap<&str, Vec<&str>> = HashMap::new();
     urls.iter().for_each(|url| {
@@ -155,7 +308,10 @@ pub fn group_urls_by_scheme(urls: Vec<&str>) -> HashMap<&str, Vec<&str>> {
 mod test {
     use crate::client::Searcher;
     use crate::schema::{DocFields, SearchDocument};
-    use crate::utils::generate_highlight_preview;
+    use crate::utils::{
+        generate_best_match_snippet, generate_first_match_snippet, generate_highlight_preview,
+        generate_highlight_text,
+    };
     use crate::IndexBackend;
 
     #[test]
@@ -172,4 +328,45 @@ mod test {
         let desc = generate_highlight_preview(&tokenizer, "rust programming", &blurb);
         assert_eq!(desc, "<span><mark>Rust</mark> <mark>rust</mark> is a multi-paradigm, high-level, general-purpose <mark>programming</mark> ...</span>");
     }
+
+    #[test]
+    fn test_highlight_text() {
+        let searcher = Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .expect("Unable to open index");
+
+        let fields = DocFields::as_fields();
+        let tokenizer = searcher
+            .index
+            .tokenizer_for_field(fields.content)
+            .expect("Unable to get tokenizer for content field");
+        let title = generate_highlight_text(&tokenizer, "rust", "The Rust Book", "b");
+        assert_eq!(title, "The <b>Rust</b> Book");
+    }
+
+    #[test]
+    fn test_snippet_strategies() {
+        let searcher = Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .expect("Unable to open index");
+
+        let fields = DocFields::as_fields();
+        let tokenizer = searcher
+            .index
+            .tokenizer_for_field(fields.content)
+            .expect("Unable to get tokenizer for content field");
+
+        let filler = ["filler"; 40].join(" ");
+        let content = format!("rust once {filler} rust programming language");
+
+        let first = generate_first_match_snippet(&tokenizer, "rust programming", &content, "b")
+            .expect("should match");
+        assert!(first.starts_with("<span><b>rust</b> once filler"));
+
+        let best = generate_best_match_snippet(&tokenizer, "rust programming", &content, "b")
+            .expect("should match");
+        assert!(best.starts_with("<span>... filler"));
+        assert!(best.ends_with("<b>rust</b> <b>programming</b> language</span>"));
+
+        assert!(generate_best_match_snippet(&tokenizer, "python", &content, "b").is_none());
+        assert!(generate_first_match_snippet(&tokenizer, "rust", "", "b").is_none());
+    }
 }
@@ -1,6 +1,8 @@
 use anyhow::anyhow;
 use clap::{Parser, Subcommand};
 use entities::models::{self, indexed_document::DocumentIdentifier, tag::check_query_for_tags};
+use libspyglass::crawler::{session, CrawlResult, Crawler};
+use libspyglass::documents::process_crawl_results;
 use libspyglass::state::AppState;
 use ron::ser::PrettyConfig;
 use shared::config::Config;
@@ -49,6 +51,23 @@ enum Command {
         name: String,
         archive_path: PathBuf,
     },
+    /// Re-scrapes & indexes the responses of a recorded crawl session (see the
+    /// `record_crawls` setting) into a throwaway index, comparing the results
+    /// w/ what was recorded. Nothing is fetched.
+    ReplayCrawlSession {
+        session_path: PathBuf,
+    },
+}
+
+/// Short summary of a crawl result used to compare recorded & replayed crawls.
+fn describe_result(result: &CrawlResult) -> String {
+    format!(
+        "url: {}, title: {:?}, content hash: {:?}, links: {}",
+        result.url,
+        result.title,
+        result.content_hash,
+        result.links.len()
+    )
 }
 
 #[tokio::main]
@@ -216,6 +235,63 @@ async fn main() -> anyhow::Result<ExitCode> {
             process_update(state.clone(), &lens, archive_path, true).await;
             let _ = state.index.save().await;
         }
+        Command::ReplayCrawlSession { session_path } => {
+            let entries = session::read_session(&session_path)?;
+            let state = AppState::builder()
+                .with_db(entities::test::setup_test_db().await)
+                .with_user_settings(&config.user_settings)
+                .with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+                .build();
+            let crawler = Crawler::default();
+
+            let mut num_changed = 0;
+            for entry in &entries {
+                println!("## Task {} ({}) ##", entry.task_id, entry.outcome);
+                if let Some(err) = &entry.error {
+                    println!("Recorded error: {}", err);
+                }
+
+                let mut replayed = Vec::new();
+                for response in &entry.responses {
+                    println!("Response: {} {}", response.status, response.final_url);
+                    match crawler.replay(&state, response).await {
+                        Some(result) => replayed.push(result),
+                        None => println!("Unable to scrape {}", response.url),
+                    }
+                }
+
+                let recorded = entry.result.as_ref().map(describe_result);
+                // The first response is the page itself, series pages aren't merged
+                // on replay
+                let replayed_desc = replayed.first().map(describe_result);
+                if let Some(recorded) = &recorded {
+                    println!("Recorded: {}", recorded);
+                }
+                if let Some(replayed) = &replayed_desc {
+                    println!("Replayed: {}", replayed);
+                }
+                if !entry.responses.is_empty() && recorded != replayed_desc {
+                    println!("Results differ");
+                    num_changed += 1;
+                }
+
+                if let Some(result) = replayed.first() {
+                    match process_crawl_results(&state, &[result.clone()], &[]).await {
+                        Ok(res) => println!(
+                            "Indexed: {} added, {} updated, {} unchanged",
+                            res.num_added, res.num_updated, res.num_unchanged
+                        ),
+                        Err(err) => println!("Unable to index: {}", err),
+                    }
+                }
+            }
+
+            println!(
+                "Replayed {} crawls, {} with different results",
+                entries.len(),
+                num_changed
+            );
+        }
     }
 
     Ok(ExitCode::SUCCESS)
@@ -0,0 +1,276 @@
+use bytes::Bytes;
+use hyper::body::Sender;
+use hyper::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use libspyglass::state::AppState;
+use serde::Serialize;
+use shared::request::{ExportFormat, SearchExportParam};
+use spyglass_searcher::{Boost, QueryBoost};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use super::handler::search::search_filters;
+use super::rate_limit::{read_body, MAX_BODY_BYTES};
+
+/// Path of the search results export endpoint.
+pub const EXPORT_PATH: &str = "/search/export";
+/// Max number of results in a single export.
+const MAX_EXPORT_RESULTS: usize = 10_000;
+/// Results loaded from the index & sent to the client at a time.
+const EXPORT_PAGE_SIZE: usize = 100;
+
+const CSV_HEADER: &str = "title,url,description,domain,score\r\n";
+
+/// A single exported search result.
+#[derive(Debug, Serialize)]
+struct ExportRow<'a> {
+    title: &'a str,
+    url: &'a str,
+    description: &'a str,
+    domain: &'a str,
+    score: f32,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Serializes a row, `is_first` is used to separate JSON array items.
+fn format_row(format: ExportFormat, row: &ExportRow, is_first: bool) -> String {
+    match format {
+        ExportFormat::Csv => format!(
+            "{},{},{},{},{}\r\n",
+            csv_field(row.title),
+            csv_field(row.url),
+            csv_field(row.description),
+            csv_field(row.domain),
+            row.score
+        ),
+        ExportFormat::Json => {
+            let json = serde_json::to_string(row).unwrap_or_default();
+            if is_first {
+                json
+            } else {
+                format!(",{json}")
+            }
+        }
+    }
+}
+
+/// Serves `POST /search/export` alongside the JSON-RPC API. Every other
+/// request is passed on to the API server.
+#[derive(Clone)]
+pub struct SearchExportLayer {
+    state: AppState,
+}
+
+impl SearchExportLayer {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+impl<S> Layer<S> for SearchExportLayer {
+    type Service = SearchExport<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SearchExport {
+            inner,
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SearchExport<S> {
+    inner: S,
+    state: AppState,
+}
+
+impl<S> Service<Request<Body>> for SearchExport<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if req.uri().path() != EXPORT_PATH {
+            return Box::pin(self.inner.call(req));
+        }
+
+        let state = self.state.clone();
+        Box::pin(async move {
+            if req.method() != Method::POST {
+                return Ok(error_response(
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "Exports must be requested with POST",
+                ));
+            }
+
+            let body = match read_body(req.into_body(), MAX_BODY_BYTES).await {
+                Ok(body) => body,
+                Err(err) => {
+                    log::warn!("Unable to read request body: {err}");
+                    return Ok(error_response(
+                        StatusCode::BAD_REQUEST,
+                        "Unable to read request body",
+                    ));
+                }
+            };
+
+            match serde_json::from_slice::<SearchExportParam>(&body) {
+                Ok(param) => Ok(export_results(state, param).await),
+                Err(err) => Ok(error_response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Invalid export request: {err}"),
+                )),
+            }
+        })
+    }
+}
+
+fn error_response(status: StatusCode, msg: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(msg.to_string()))
+        .expect("Unable to build response")
+}
+
+/// Runs the export query, streaming results to the client as they're read from
+/// the index instead of buffering the whole export.
+async fn export_results(state: AppState, param: SearchExportParam) -> Response<Body> {
+    let filters = match search_filters(&state, &param.lenses, &param.tags).await {
+        Ok(filters) => filters,
+        Err(err) => {
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string());
+        }
+    };
+
+    let format = param.format;
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        match stream_results(&state, &param, filters, &mut sender).await {
+            Ok(num_exported) => {
+                log::debug!("exported {} results for \"{}\"", num_exported, param.query)
+            }
+            Err(err) => {
+                log::warn!("Search export ended early: {err}");
+                sender.abort();
+            }
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, format.content_type())
+        .header(
+            CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"search-results.{}\"",
+                format.extension()
+            ),
+        )
+        .body(body)
+        .expect("Unable to build response")
+}
+
+/// Collects the matches in one pass, then loads & sends them a page at a time.
+/// `filters` is None when nothing can match, which sends an empty export.
+async fn stream_results(
+    state: &AppState,
+    param: &SearchExportParam,
+    filters: Option<Vec<QueryBoost>>,
+    sender: &mut Sender,
+) -> Result<usize, hyper::Error> {
+    let format = param.format;
+    let header = match format {
+        ExportFormat::Csv => CSV_HEADER,
+        ExportFormat::Json => "[",
+    };
+    sender.send_data(Bytes::from(header)).await?;
+
+    let mut num_exported = 0;
+    if let Some(mut filters) = filters {
+        for domain in &param.domains {
+            filters.push(QueryBoost::new(Boost::Domain(domain.to_string())));
+        }
+
+        let matches = state
+            .index
+            .search_top(&param.query, &filters, &[], MAX_EXPORT_RESULTS)
+            .await;
+        for offset in (0..matches.len()).step_by(EXPORT_PAGE_SIZE) {
+            let mut chunk = String::new();
+            for (score, doc) in matches.page(offset, EXPORT_PAGE_SIZE) {
+                let row = ExportRow {
+                    title: &doc.title,
+                    url: &doc.url,
+                    description: &doc.description,
+                    domain: &doc.domain,
+                    score,
+                };
+                chunk.push_str(&format_row(format, &row, num_exported == 0));
+                num_exported += 1;
+            }
+
+            if !chunk.is_empty() {
+                sender.send_data(Bytes::from(chunk)).await?;
+            }
+        }
+    }
+
+    if format == ExportFormat::Json {
+        sender.send_data(Bytes::from("]")).await?;
+    }
+
+    Ok(num_exported)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{csv_field, format_row, ExportRow};
+    use shared::request::ExportFormat;
+
+    #[test]
+    fn test_format_row() {
+        let row = ExportRow {
+            title: "Hello, \"world\"",
+            url: "https://example.com/",
+            description: "line one\nline two",
+            domain: "example.com",
+            score: 1.5,
+        };
+
+        assert_eq!(
+            format_row(ExportFormat::Csv, &row, true),
+            "\"Hello, \"\"world\"\"\",https://example.com/,\"line one\nline two\",example.com,1.5\r\n"
+        );
+
+        let json = format_row(ExportFormat::Json, &row, true);
+        assert!(json.starts_with('{'));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["domain"], "example.com");
+        assert!(format_row(ExportFormat::Json, &row, false).starts_with(','));
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+    }
+}
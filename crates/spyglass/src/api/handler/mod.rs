@@ -1,40 +1,51 @@
 use super::response;
 use anyhow::anyhow;
+use chrono::TimeZone;
 use directories::UserDirs;
-use entities::get_library_stats;
 use entities::models::crawl_queue::{CrawlStatus, EnqueueSettings};
 use entities::models::lens::LensType;
 use entities::models::tag::TagType;
 use entities::models::{
-    bootstrap_queue, connection::get_all_connections, crawl_queue, fetch_history, indexed_document,
-    lens,
+    bootstrap_queue, connection::get_all_connections, crawl_queue, document_alias, document_note,
+    document_version, fetch_history, indexed_document, lens, lens_source_boost, lens_stats,
+    url_blocklist,
 };
-use entities::sea_orm::{prelude::*, sea_query, Set};
+use entities::sea_orm::{prelude::*, sea_query, Set, TransactionTrait};
+use entities::{get_library_stats, BATCH_SIZE};
 use jsonrpsee::core::Error;
 use libnetrunner::parser::html::html_to_text;
 use libspyglass::connection::{self, credentials, handle_authorize_connection};
-use libspyglass::crawler::CrawlResult;
-use libspyglass::documents::process_crawl_results;
+use libspyglass::crawler::{self, transform, CrawlResult};
+use libspyglass::documents::{self, process_crawl_results, text_diff};
 use libspyglass::filesystem;
 use libspyglass::plugin::PluginCommand;
 use libspyglass::state::AppState;
-use libspyglass::task::{AppPause, UserSettingsChange};
+use libspyglass::task::maintenance;
+use libspyglass::task::worker::{self, FetchResult};
+use libspyglass::task::{AppPause, CollectTask, CrawlTask, ManagerCommand, UserSettingsChange};
 use num_format::{Locale, ToFormattedString};
-use shared::config::{self, Config, UserSettings};
+use regex::{Regex, RegexSet};
+use shared::config::{self, Config, LensConfig, UserSettings};
 use shared::metrics::Event;
-use shared::request::{BatchDocumentRequest, RawDocType, RawDocumentRequest};
+use shared::regex::{regex_for_domain, regex_for_prefix};
+use shared::request::{BatchDocumentRequest, RawDocType, RawDocumentRequest, RecentDocumentsParam};
 use shared::response::{
-    AppStatus, DefaultIndices, InstallStatus, LensResult, LibraryStats, ListConnectionResult,
-    PluginResult, SupportedConnection, UserConnection,
+    AppStatus, CrawlUrlResult, CrawlUrlStatus, DefaultIndices, DocumentChanges,
+    ImportBookmarksResult, IndexedDocumentResult, InstallStatus, LensCrawlStats,
+    LensRecrawlProgress, LensResetResult, LensResult, LibraryStats, ListConnectionResult,
+    PluginResult, SupportedConnection, UserConnection, VacuumStatus,
 };
 use spyglass_rpc::{RpcEvent, RpcEventType};
-use spyglass_searcher::WriteTrait;
-use std::collections::HashMap;
+use spyglass_searcher::{SearchTrait, WriteTrait};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 use tracing::instrument;
 use url::Url;
 
+pub mod queue;
+pub mod saved_query;
 pub mod search;
 
 pub async fn add_document_batch(state: &AppState, req: &BatchDocumentRequest) -> Result<(), Error> {
@@ -62,7 +73,9 @@ pub async fn add_document_batch(state: &AppState, req: &BatchDocumentRequest) ->
     let overrides = EnqueueSettings {
         force_allow: true,
         is_recrawl: true,
+        new_only: req.new_only,
         tags,
+        priority: crawl_queue::PRIORITY_USER,
         ..Default::default()
     };
 
@@ -116,6 +129,14 @@ pub async fn add_raw_document(state: &AppState, req: &RawDocumentRequest) -> Res
                 }
             };
 
+            if url_blocklist::is_blocked(&state.db, url.as_str())
+                .await
+                .unwrap_or_default()
+            {
+                log::debug!("skipping blocked url {}", url);
+                return Ok(());
+            }
+
             let mut crawl = CrawlResult::new(
                 &url,
                 Some(url.to_string()),
@@ -144,6 +165,7 @@ pub async fn add_raw_document(state: &AppState, req: &RawDocumentRequest) -> Res
                 force_allow: true,
                 is_recrawl: true,
                 tags,
+                priority: crawl_queue::PRIORITY_USER,
                 ..Default::default()
             };
 
@@ -184,15 +206,153 @@ pub async fn authorize_connection(state: AppState, api_id: String) -> Result<(),
     }
 }
 
+/// How long to wait for a manual crawl to finish.
+const CRAWL_NOW_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Crawls a single URL right away, skipping the queue, & waits for it to be
+/// indexed.
+#[instrument(skip(state))]
+pub async fn crawl_url_now(state: AppState, url: String) -> Result<CrawlUrlResult, Error> {
+    let settings = state.user_settings.load_full();
+    let mut parsed = Url::parse(&url).map_err(|_| Error::Custom(format!("Invalid URL: {url}")))?;
+    // Match the normalization done when enqueueing
+    if settings.collapse_equivalent_urls {
+        shared::collapse_equivalent_url(&mut parsed);
+    }
+
+    let overrides = EnqueueSettings {
+        force_allow: true,
+        is_recrawl: true,
+        priority: crawl_queue::PRIORITY_USER,
+        ..Default::default()
+    };
+    if let Err(err) =
+        crawl_queue::enqueue_all(&state.db, &[url], &[], &settings, &overrides, None).await
+    {
+        return Err(Error::Custom(format!("Unable to queue URL: {err}")));
+    }
+
+    let task = crawl_queue::Entity::find()
+        .filter(crawl_queue::Column::Url.eq(parsed.as_str()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?
+        .ok_or_else(|| Error::Custom(format!("Unable to queue URL: {parsed}")))?;
+
+    // Mark as processing right away so the manager doesn't also pick it up.
+    let task_id = task.id;
+    let mut update: crawl_queue::ActiveModel = task.into();
+    update.status = Set(CrawlStatus::Processing);
+    if let Err(err) = update.update(&state.db).await {
+        return Err(Error::Custom(format!("Unable to update task: {err}")));
+    }
+
+    // Spawned so the crawl still finishes if we stop waiting on it.
+    let handle = tokio::spawn(worker::handle_fetch(
+        state.clone(),
+        CrawlTask { id: task_id },
+    ));
+    let result = match tokio::time::timeout(CRAWL_NOW_TIMEOUT, handle).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(err)) => return Err(Error::Custom(format!("Unable to crawl URL: {err}"))),
+        Err(_) => {
+            return Ok(CrawlUrlResult {
+                url: parsed.to_string(),
+                status: CrawlUrlStatus::TimedOut,
+                title: None,
+            })
+        }
+    };
+
+    // The task URL is updated to the canonical URL when crawled.
+    let url = crawl_queue::Entity::find_by_id(task_id)
+        .one(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .map(|task| task.url)
+        .unwrap_or_else(|| parsed.to_string());
+
+    let doc = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.eq(url.clone()))
+        .one(&state.db)
+        .await
+        .ok()
+        .flatten();
+
+    let status = match result {
+        FetchResult::New | FetchResult::Updated => {
+            // Make it searchable right away
+            let _ = state.index.save().await;
+            CrawlUrlStatus::Indexed
+        }
+        // Recently crawled & already in the index
+        FetchResult::Ignore if doc.is_some() => CrawlUrlStatus::Indexed,
+        FetchResult::Ignore => CrawlUrlStatus::Skipped,
+        FetchResult::NotFound => CrawlUrlStatus::Failed("Page not found".into()),
+        FetchResult::Error(err) => CrawlUrlStatus::Failed(err),
+    };
+
+    let title = match (&status, doc) {
+        (CrawlUrlStatus::Indexed, Some(doc)) => {
+            state.index.get(&doc.doc_id).await.map(|doc| doc.title)
+        }
+        _ => None,
+    };
+
+    Ok(CrawlUrlResult { url, status, title })
+}
+
 /// Fun stats about index size, etc.
 #[instrument(skip(state))]
 pub async fn app_status(state: AppState) -> Result<AppStatus, Error> {
     // Grab details about index
-    let index = state.index;
+    let index = &state.index;
     let reader = index.reader.searcher();
 
+    // Surface stalled workers, e.g. a worker that died mid-crawl leaves its
+    // task processing forever & the queue stops draining.
+    let now = chrono::Utc::now();
+    let oldest_queued = crawl_queue::oldest_queued(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?
+        .map(|created_at| (now - created_at).num_seconds());
+    let oldest_processing = crawl_queue::oldest_processing(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?
+        .map(|updated_at| (now - updated_at).num_seconds());
+
+    let threshold_secs = state.user_settings.load().crawl_stall_threshold_mins as i64 * 60;
+    let crawl_stalled = oldest_processing
+        .map(|age| age > threshold_secs)
+        .unwrap_or_default();
+
+    let paused_lenses = lens::paused_lenses(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    let lenses: Vec<LensConfig> = state
+        .lenses
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+    let external_domains = crawl_queue::external_domains(&state.db, &lenses)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    let num_deduplicated = document_alias::count(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
     Ok(AppStatus {
         num_docs: reader.num_docs(),
+        oldest_queued_secs: oldest_queued,
+        oldest_processing_secs: oldest_processing,
+        crawl_stalled,
+        crawl_paused_for_failures: state.crawl_breaker.is_tripped(),
+        paused_lenses,
+        external_domain_count: external_domains.len() as u64,
+        num_deduplicated,
     })
 }
 
@@ -207,6 +367,264 @@ pub async fn delete_document(state: AppState, id: String) -> Result<(), Error> {
     Ok(())
 }
 
+/// Blocks a URL from ever being crawled or indexed, removing it from the
+/// index & crawl queue if it's already there.
+#[instrument(skip(state))]
+pub async fn block_url(state: AppState, url: String) -> Result<(), Error> {
+    if url.trim().is_empty() {
+        return Err(Error::Custom("URL can't be empty".to_string()));
+    }
+
+    if let Err(err) = url_blocklist::add(&state.db, &url).await {
+        return Err(Error::Custom(format!("Unable to block url: {err}")));
+    }
+
+    let normalized = url_blocklist::normalize(&url);
+    documents::delete_documents_by_uri(&state, vec![url, normalized]).await;
+    Ok(())
+}
+
+/// Allows a previously blocked URL to be crawled again
+#[instrument(skip(state))]
+pub async fn unblock_url(state: AppState, url: String) -> Result<(), Error> {
+    match url_blocklist::remove(&state.db, &url).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::Custom(format!("URL is not blocked: {url}"))),
+        Err(err) => Err(Error::Custom(format!("Unable to unblock url: {err}"))),
+    }
+}
+
+#[instrument(skip(state))]
+pub async fn blocked_urls(state: AppState) -> Result<Vec<String>, Error> {
+    url_blocklist::list(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))
+}
+
+/// Deletes a document & blocks its URL so a recrawl doesn't bring it back
+#[instrument(skip(state))]
+pub async fn block_document(state: AppState, id: String) -> Result<(), Error> {
+    let doc = indexed_document::Entity::find()
+        .filter(indexed_document::Column::DocId.eq(id.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    match doc {
+        Some(doc) => block_url(state, doc.url).await,
+        None => Err(Error::Custom(format!("Document not found: {id}"))),
+    }
+}
+
+/// Normalizes a document URL so notes match regardless of fragment.
+fn note_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut url) => {
+            url.set_fragment(None);
+            url.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Get the user note attached to the document at `url`
+#[instrument(skip(state))]
+pub async fn get_document_note(state: AppState, url: String) -> Result<Option<String>, Error> {
+    match document_note::get_note(&state.db, &note_url(&url)).await {
+        Ok(note) => Ok(note.map(|note| note.note)),
+        Err(err) => Err(Error::Custom(format!("Unable to query db: {err}"))),
+    }
+}
+
+/// Attach a user note to the document at `url`. An empty note removes it.
+#[instrument(skip(state, note))]
+pub async fn set_document_note(state: AppState, url: String, note: String) -> Result<(), Error> {
+    document_note::set_note(&state.db, &note_url(&url), &note)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to save note: {err}")))
+}
+
+/// Show what changed in the document at `url` since its previous crawl
+#[instrument(skip(state))]
+pub async fn document_changes(
+    state: AppState,
+    url: String,
+) -> Result<Option<DocumentChanges>, Error> {
+    let url = note_url(&url);
+    let previous = match document_version::get_previous(&state.db, &url).await {
+        Ok(Some(previous)) => previous,
+        Ok(None) => return Ok(None),
+        Err(err) => return Err(Error::Custom(format!("Unable to query db: {err}"))),
+    };
+
+    let indexed = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.eq(url.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    let current = match indexed {
+        Some(indexed) => state.index.get(&indexed.doc_id).await,
+        None => None,
+    };
+
+    match current {
+        Some(current) => Ok(Some(DocumentChanges {
+            url,
+            changed_at: previous.updated_at.timestamp(),
+            diff: text_diff(&previous.content, &current.content),
+        })),
+        None => Err(Error::Custom(format!("Document not indexed: {url}"))),
+    }
+}
+
+/// Look up the indexed document at exactly `url`
+#[instrument(skip(state))]
+pub async fn get_indexed_document(
+    state: AppState,
+    url: String,
+) -> Result<IndexedDocumentResult, Error> {
+    let url = note_url(&url);
+    let indexed = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.eq(url.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    let indexed = match indexed {
+        Some(indexed) => indexed,
+        None => return Err(Error::Custom(format!("Document not indexed: {url}"))),
+    };
+
+    match indexed_document_result(&state, indexed).await {
+        Some(result) => Ok(result),
+        None => Err(Error::Custom(format!(
+            "Document missing from the index: {url}"
+        ))),
+    }
+}
+
+/// Removes the document at `url` from the index & queues the URL to be crawled
+/// again. Nothing is removed from the db unless it's removed from the index.
+#[instrument(skip(state))]
+pub async fn remove_indexed_document(state: AppState, url: String) -> Result<(), Error> {
+    let url = note_url(&url);
+    let tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    let indexed = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.eq(url.clone()))
+        .one(&tx)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+    let indexed = match indexed {
+        Some(indexed) => indexed,
+        None => return Err(Error::Custom(format!("Document not indexed: {url}"))),
+    };
+
+    if let Err(err) = indexed_document::delete_many_by_id(&tx, &[indexed.id]).await {
+        return Err(Error::Custom(format!("Unable to delete document: {err}")));
+    }
+
+    let settings = state.user_settings.load_full();
+    let overrides = EnqueueSettings {
+        force_allow: true,
+        is_recrawl: true,
+        priority: crawl_queue::PRIORITY_USER,
+        ..Default::default()
+    };
+    if let Err(err) =
+        crawl_queue::enqueue_all(&tx, &[url.clone()], &[], &settings, &overrides, None).await
+    {
+        return Err(Error::Custom(format!("Unable to queue URL: {err}")));
+    }
+
+    // Removing the document commits the index, so it's put back if the db
+    // changes can't be committed afterwards.
+    let stored = state.index.get(&indexed.doc_id).await;
+    if let Err(err) = state.index.delete(&indexed.doc_id).await {
+        let _ = tx.rollback().await;
+        return Err(Error::Custom(format!(
+            "Unable to remove document from index: {err}"
+        )));
+    }
+
+    if let Err(err) = tx.commit().await {
+        if let Some(stored) = stored {
+            if let Err(err) = documents::restore_document(&state, &stored).await {
+                log::error!("Unable to restore {} to the index: {}", url, err);
+            }
+        }
+        return Err(Error::Custom(format!("Unable to query db: {err}")));
+    }
+    log::info!("removed {} from the index & queued it for recrawl", url);
+    Ok(())
+}
+
+/// Default & max number of documents returned by `recent_documents`.
+const DEFAULT_RECENT_DOCS: u64 = 25;
+const MAX_RECENT_DOCS: u64 = 100;
+
+/// Most recently indexed or recrawled documents, newest first. Oldest first
+/// when polling w/ `after`, see `indexed_document::find_recent`.
+#[instrument(skip(state))]
+pub async fn recent_documents(
+    state: AppState,
+    params: RecentDocumentsParam,
+) -> Result<Vec<IndexedDocumentResult>, Error> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_RECENT_DOCS)
+        .clamp(1, MAX_RECENT_DOCS);
+    let after = match params.after {
+        Some(after) => match chrono::Utc.timestamp_opt(after, 0).single() {
+            Some(after) => Some(after),
+            None => return Err(Error::Custom(format!("Invalid timestamp: {after}"))),
+        },
+        None => None,
+    };
+
+    let recent = indexed_document::find_recent(
+        &state.db,
+        params.lens.as_deref(),
+        params.domain.as_deref(),
+        after,
+        limit,
+    )
+    .await
+    .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    let mut results = Vec::new();
+    for indexed in recent {
+        if let Some(result) = indexed_document_result(&state, indexed).await {
+            results.push(result);
+        }
+    }
+
+    Ok(results)
+}
+
+/// Combines an indexed document w/ its details from the index, None if it's
+/// missing from the index.
+async fn indexed_document_result(
+    state: &AppState,
+    indexed: indexed_document::Model,
+) -> Option<IndexedDocumentResult> {
+    let doc = state.index.get(&indexed.doc_id).await?;
+    Some(IndexedDocumentResult {
+        doc_id: indexed.doc_id,
+        url: indexed.url,
+        domain: doc.domain,
+        title: doc.title,
+        description: doc.description,
+        created_at: indexed.created_at.timestamp(),
+        updated_at: indexed.updated_at.timestamp(),
+    })
+}
+
 /// Remove a domain from crawl queue & index
 #[instrument(skip(state))]
 pub async fn delete_domain(state: AppState, domain: String) -> Result<(), Error> {
@@ -284,6 +702,8 @@ pub async fn list_connections(state: AppState) -> Result<ListConnectionResult, E
 #[instrument(skip(state))]
 pub async fn list_installed_lenses(state: AppState) -> Result<Vec<LensResult>, Error> {
     let stats = get_library_stats(&state.db).await.unwrap_or_default();
+    let paused = lens::paused_lenses(&state.db).await.unwrap_or_default();
+    let weights = lens::ranking_weights(&state.db).await.unwrap_or_default();
     let mut lenses: Vec<LensResult> = state
         .lenses
         .iter()
@@ -321,6 +741,13 @@ pub async fn list_installed_lenses(state: AppState) -> Result<Vec<LensResult>, E
                 file_path: Some(lens.file_path.clone()),
                 progress,
                 lens_type: shared::response::LensType::Lens,
+                is_paused: paused.contains(&lens.name),
+                ranking_weight: Some(
+                    weights
+                        .get(&lens.name)
+                        .copied()
+                        .unwrap_or(lens::DEFAULT_RANKING_WEIGHT) as u32,
+                ),
                 ..Default::default()
             }
         })
@@ -501,6 +928,43 @@ pub async fn recrawl_domain(state: AppState, domain: String) -> Result<(), Error
     Ok(())
 }
 
+/// Kicks off a database vacuum, which runs in the background. Outside of the
+/// maintenance window it's deferred until the window opens.
+#[instrument(skip(state))]
+pub async fn vacuum_database(state: AppState) -> Result<VacuumStatus, Error> {
+    if state.readonly_mode {
+        return Err(Error::Custom(
+            "Unable to vacuum in read only mode".to_string(),
+        ));
+    }
+
+    match maintenance::vacuum_or_defer(&state) {
+        Some(status) => Ok(status),
+        None => Err(Error::Custom(
+            "Database is already being vacuumed".to_string(),
+        )),
+    }
+}
+
+/// Kicks off a rebuild of the search index from the database, which runs in
+/// the background.
+#[instrument(skip(state))]
+pub async fn reindex(state: AppState) -> Result<(), Error> {
+    if state.readonly_mode {
+        return Err(Error::Custom(
+            "Unable to reindex in read only mode".to_string(),
+        ));
+    }
+
+    if maintenance::start_reindex(&state) {
+        Ok(())
+    } else {
+        Err(Error::Custom(
+            "Documents are already being reindexed".to_string(),
+        ))
+    }
+}
+
 #[instrument(skip(state))]
 pub async fn toggle_pause(state: AppState, is_paused: bool) -> Result<(), Error> {
     // Scope so that the app_state mutex is correctly released.
@@ -515,6 +979,163 @@ pub async fn toggle_pause(state: AppState, is_paused: bool) -> Result<(), Error>
     Ok(())
 }
 
+/// Pause/resume crawling for a single lens
+#[instrument(skip(state))]
+pub async fn pause_lens(state: AppState, name: String, is_paused: bool) -> Result<(), Error> {
+    match lens::set_paused(&state.db, &name, is_paused).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::Custom(format!("Unable to find lens: {name}"))),
+        Err(err) => Err(Error::Custom(format!("Unable to update lens: {err}"))),
+    }
+}
+
+/// Boost or bury a lens' documents in search results. The weight is a
+/// percentage, 100 leaves scores unchanged.
+#[instrument(skip(state))]
+pub async fn set_lens_weight(state: AppState, name: String, weight: u32) -> Result<(), Error> {
+    let weight = weight.min(lens::MAX_RANKING_WEIGHT as u32) as i32;
+    match lens::set_ranking_weight(&state.db, &name, weight).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::Custom(format!("Unable to find lens: {name}"))),
+        Err(err) => Err(Error::Custom(format!("Unable to update lens: {err}"))),
+    }
+}
+
+/// Boost or bury documents from one of a lens' sources in searches of that
+/// lens. The boost is a score multiplier, 1.0 leaves scores unchanged.
+#[instrument(skip(state))]
+pub async fn set_lens_source_boost(
+    state: AppState,
+    name: String,
+    domain: String,
+    boost: f32,
+) -> Result<(), Error> {
+    if !state.lenses.contains_key(&name) {
+        return Err(Error::Custom(format!("Unable to find lens: {name}")));
+    }
+
+    if !boost.is_finite() {
+        return Err(Error::Custom(format!("Invalid boost: {boost}")));
+    }
+
+    lens_source_boost::set_boost(&state.db, &name, &domain, boost)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to update lens: {err}")))
+}
+
+/// Indexes the files in a local folder as part of a lens. The folder is walked
+/// in the background, adding it again picks up any changed or new files.
+#[instrument(skip(state))]
+pub async fn add_lens_folder(
+    state: AppState,
+    name: String,
+    path: String,
+    extensions: Vec<String>,
+) -> Result<(), Error> {
+    if state.readonly_mode {
+        return Err(Error::Custom(
+            "Unable to add folders in read only mode".to_string(),
+        ));
+    }
+
+    if !state.lenses.contains_key(&name) {
+        return Err(Error::Custom(format!("Lens not found: {name}")));
+    }
+
+    let root = PathBuf::from(&path);
+    if !root.is_absolute() || !root.is_dir() {
+        return Err(Error::Custom(format!("Not a folder: {path}")));
+    }
+
+    let extensions = extensions.into_iter().collect();
+    tokio::spawn(async move {
+        match filesystem::index_folder(&state, &name, &root, &extensions).await {
+            Ok(num_queued) => log::info!("queued {} files from {} for {}", num_queued, path, name),
+            Err(err) => log::error!("Unable to index {} for {}: {}", path, name, err),
+        }
+    });
+
+    Ok(())
+}
+
+/// Queues the links in a bookmarks file for crawling into a lens, tagging each
+/// w/ the folders it was saved in.
+pub async fn import_bookmarks(
+    state: AppState,
+    name: String,
+    path: String,
+) -> Result<ImportBookmarksResult, Error> {
+    if state.readonly_mode {
+        return Err(Error::Custom(
+            "Unable to import bookmarks in read only mode".to_string(),
+        ));
+    }
+
+    if !state.lenses.contains_key(&name) {
+        return Err(Error::Custom(format!("Lens not found: {name}")));
+    }
+
+    let html = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to read {path}: {err}")))?;
+    let parsed = tokio::task::spawn_blocking(move || crawler::bookmarks::parse_bookmarks(&html))
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to parse bookmarks: {err}")))?;
+
+    // Group links by folder so each group can be queued in bulk w/ its tags.
+    // Links saved more than once keep the first folder they were found in.
+    let mut seen = HashSet::new();
+    let mut by_folder: HashMap<Vec<String>, Vec<String>> = HashMap::new();
+    for bookmark in parsed.bookmarks {
+        let url = match Url::parse(&bookmark.url) {
+            Ok(mut url) => {
+                url.set_fragment(None);
+                url.to_string()
+            }
+            Err(_) => continue,
+        };
+
+        if seen.insert(url.clone()) {
+            by_folder.entry(bookmark.folders).or_default().push(url);
+        }
+    }
+
+    let settings = state.user_settings.load_full();
+    for (folders, urls) in by_folder {
+        let mut tags = vec![(TagType::Lens, name.clone())];
+        tags.extend(
+            folders
+                .into_iter()
+                .map(|folder| (TagType::Category, folder)),
+        );
+
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            tags,
+            ..Default::default()
+        };
+
+        if let Err(err) =
+            crawl_queue::enqueue_all(&state.db, &urls, &[], &settings, &overrides, None).await
+        {
+            return Err(Error::Custom(format!("Unable to queue bookmarks: {err}")));
+        }
+    }
+
+    log::info!(
+        "imported {} bookmarks from {} into {}, skipped {}",
+        seen.len(),
+        path,
+        name,
+        parsed.num_skipped
+    );
+
+    Ok(ImportBookmarksResult {
+        num_imported: seen.len(),
+        num_skipped: parsed.num_skipped,
+    })
+}
+
 #[instrument(skip(state))]
 pub async fn toggle_plugin(state: AppState, name: String, enabled: bool) -> Result<(), Error> {
     // Find the plugin
@@ -553,6 +1174,38 @@ pub async fn update_user_settings(
     _config: &Config,
     user_settings: &UserSettings,
 ) -> Result<UserSettings, Error> {
+    // Validate any custom crawler headers. Values may be secrets so they're
+    // never included in the error.
+    for (domain, headers) in user_settings.domain_headers.iter() {
+        for (name, value) in headers.iter() {
+            if http::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(Error::Custom(format!(
+                    "Invalid header name `{name}` for domain `{domain}`"
+                )));
+            }
+
+            if http::header::HeaderValue::from_str(value).is_err() {
+                return Err(Error::Custom(format!(
+                    "Invalid value for header `{name}` for domain `{domain}`"
+                )));
+            }
+        }
+    }
+
+    let proxies = user_settings
+        .crawl_proxy
+        .iter()
+        .chain(user_settings.domain_proxies.values());
+    for proxy in proxies {
+        if let Err(err) = crawler::validate_proxy(proxy) {
+            return Err(Error::Custom(err));
+        }
+    }
+
+    if let Err(err) = transform::validate(&user_settings.content_transforms) {
+        return Err(Error::Custom(err));
+    }
+
     if let Err(error) = app
         .config_cmd_tx
         .lock()
@@ -570,7 +1223,8 @@ pub async fn user_settings(app: &AppState) -> Result<UserSettings, Error> {
 }
 
 #[instrument(skip(state))]
-pub async fn uninstall_lens(state: AppState, config: &Config, name: &str) -> Result<(), Error> {
+/// Uninstalls a lens, returning the number of documents removed along with it.
+pub async fn uninstall_lens(state: AppState, config: &Config, name: &str) -> Result<usize, Error> {
     // Remove from filesystem
     let lens_path = config.lenses_dir().join(format!("{name}.ron"));
     let config = state.lenses.remove(name);
@@ -583,24 +1237,28 @@ pub async fn uninstall_lens(state: AppState, config: &Config, name: &str) -> Res
         .exec(&state.db)
         .await;
 
-    // - find relevant doc ids to remove
-    if let Ok(ids) = indexed_document::find_by_lens(state.db.clone(), name).await {
-        // - remove from db & index
-        let doc_ids: Vec<String> = ids.iter().map(|x| x.doc_id.to_owned()).collect();
-        let dbids: Vec<i64> = ids.iter().map(|x| x.id).collect();
+    // - remove crawl stats
+    let _ = lens_stats::delete(&state.db, name).await;
 
-        // Remove from index
-        if let Err(err) = state.index.delete_many_by_id(&doc_ids).await {
-            return Err(Error::Custom(err.to_string()));
-        }
-        // Remove from db
-        let _ = indexed_document::delete_many_by_id(&state.db, &dbids).await;
+    // - remove source boosts
+    let _ = lens_source_boost::delete_by_lens(&state.db, name).await;
+
+    // - remove documents that no other lens uses from db & index
+    let num_removed = if state.user_settings.load().remove_lens_documents {
+        remove_lens_documents(&state, name).await?
+    } else {
+        0
+    };
+
+    if let Err(err) = state.index.remove_lens_index(name) {
+        log::warn!("Unable to remove index for lens {}: {}", name, err);
     }
 
     // -- remove from crawl queue
     if let Err(err) = crawl_queue::delete_by_lens(state.db.clone(), name).await {
         return Err(Error::Custom(err.to_string()));
     }
+    state.recrawl_jobs.abandon(name);
 
     // - remove seed urls from bootstrap queue table
     if let Some((_, config)) = config {
@@ -610,33 +1268,383 @@ pub async fn uninstall_lens(state: AppState, config: &Config, name: &str) -> Res
     state
         .publish_event(&RpcEvent {
             event_type: RpcEventType::LensUninstalled,
-            payload: format!("{} lens uninstalled", name),
+            payload: format!(
+                "{} lens uninstalled, {} documents removed",
+                name, num_removed
+            ),
         })
         .await;
 
-    Ok(())
+    Ok(num_removed)
 }
 
-pub async fn default_indices() -> DefaultIndices {
-    let mut file_paths: Vec<PathBuf> = Vec::new();
+/// Removes the documents that only belong to the lens `name` from the database
+/// & index. Documents shared with another lens are kept, only losing the lens
+/// tag. Database changes are only committed once the documents are out of the
+/// index.
+async fn remove_lens_documents(state: &AppState, name: &str) -> Result<usize, Error> {
+    let txn = state
+        .db
+        .begin()
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
 
-    if let Some(user_dirs) = UserDirs::new() {
-        if let Some(path) = user_dirs.desktop_dir() {
-            file_paths.push(path.to_path_buf());
-        }
+    let docs = indexed_document::find_only_in_lens(&txn, name)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    let doc_ids: Vec<String> = docs.iter().map(|doc| doc.doc_id.clone()).collect();
+    let dbids: Vec<i64> = docs.iter().map(|doc| doc.id).collect();
 
-        if let Some(path) = user_dirs.document_dir() {
-            file_paths.push(path.to_path_buf());
-        }
+    indexed_document::delete_many_by_id(&txn, &dbids)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    indexed_document::remove_lens_tag(&txn, name)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    if let Err(err) = state.index.delete_many_by_id(&doc_ids).await {
+        let _ = txn.rollback().await;
+        return Err(Error::Custom(err.to_string()));
     }
 
-    // Application path is os dependent
-    // NOTE: Uncomment when we add in app searching ability
-    // if cfg!(target_os = "macos") {
-    //     file_paths.push("/Applications".into());
-    // } else if cfg!(target_os = "windows") {
-    //     file_paths.push("C:\\Program Files (x86)".into());
-    // }
+    txn.commit()
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    log::info!("removed {} documents with lens {}", doc_ids.len(), name);
+
+    Ok(doc_ids.len())
+}
+
+/// Full definition of an installed lens
+#[instrument(skip(state))]
+pub async fn lens_definition(state: AppState, name: String) -> Result<LensConfig, Error> {
+    state
+        .lenses
+        .get(&name)
+        .map(|lens| lens.value().clone())
+        .ok_or_else(|| Error::Custom(format!("Lens not found: {name}")))
+}
+
+/// Crawl usage totals for an installed lens
+#[instrument(skip(state))]
+pub async fn lens_stats(state: AppState, name: String) -> Result<LensCrawlStats, Error> {
+    if !state.lenses.contains_key(&name) {
+        return Err(Error::Custom(format!("Lens not found: {name}")));
+    }
+
+    let stats = lens_stats::get(&state.db, &name)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to get lens stats: {err}")))?;
+
+    Ok(match stats {
+        Some(stats) => LensCrawlStats {
+            lens: stats.lens,
+            pages_indexed: stats.pages_indexed as u64,
+            bytes_fetched: stats.bytes_fetched as u64,
+            crawl_time_ms: stats.crawl_time_ms as u64,
+            last_crawl_at: stats.last_crawl_at.map(|at| at.timestamp()),
+        },
+        None => LensCrawlStats {
+            lens: name,
+            ..Default::default()
+        },
+    })
+}
+
+/// Queues every crawl task for a lens again, returning the id of the job
+/// tracking the recrawl's progress.
+#[instrument(skip(state))]
+pub async fn recrawl_lens(state: AppState, name: String) -> Result<String, Error> {
+    if !state.lenses.contains_key(&name) {
+        return Err(Error::Custom(format!("Lens not found: {name}")));
+    }
+
+    // Tasks being crawled right now are left to finish
+    let task_ids = crawl_queue::find_by_lens(state.db.clone(), &name)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to find lens tasks: {err}")))?
+        .into_iter()
+        .filter(|task| task.status != CrawlStatus::Processing)
+        .map(|task| task.id)
+        .collect::<Vec<_>>();
+
+    for chunk in task_ids.chunks(BATCH_SIZE) {
+        crawl_queue::Entity::update_many()
+            .col_expr(
+                crawl_queue::Column::Status,
+                sea_query::Expr::value(CrawlStatus::Queued),
+            )
+            .col_expr(crawl_queue::Column::NumRetries, sea_query::Expr::value(0))
+            .col_expr(
+                crawl_queue::Column::NextRetryAt,
+                sea_query::Expr::value(Option::<chrono::DateTime<chrono::Utc>>::None),
+            )
+            .filter(crawl_queue::Column::Id.is_in(chunk.to_vec()))
+            .filter(crawl_queue::Column::Status.ne(CrawlStatus::Processing))
+            .exec(&state.db)
+            .await
+            .map_err(|err| Error::Custom(format!("Unable to queue lens tasks: {err}")))?;
+    }
+
+    Ok(state.recrawl_jobs.start(&name, &task_ids))
+}
+
+/// Progress of a lens recrawl started w/ `recrawl_lens`
+#[instrument(skip(state))]
+pub async fn lens_recrawl_progress(
+    state: AppState,
+    name: String,
+    job_id: String,
+) -> Result<LensRecrawlProgress, Error> {
+    state
+        .recrawl_jobs
+        .progress(&job_id)
+        .filter(|progress| progress.lens == name)
+        .ok_or_else(|| Error::Custom(format!("Recrawl job not found: {job_id}")))
+}
+
+/// Rebuilds the separate index of a lens from the shared index, returning the
+/// number of documents in it.
+#[instrument(skip(state))]
+pub async fn rebuild_lens_index(state: AppState, name: String) -> Result<usize, Error> {
+    if state.index.lens_index(&name).is_none() {
+        return Err(Error::Custom(format!("No index for lens: {name}")));
+    }
+
+    state
+        .index
+        .rebuild_lens_index(&name)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to rebuild lens index: {err}")))
+}
+
+/// Clears the crawl state of a lens & crawls its sources again from scratch.
+/// Queued, failed & finished crawls for the lens are removed and its sources
+/// are bootstrapped again, crawls in progress are left to finish. Any recrawl
+/// of the lens is abandoned, the lens definition itself is left as is. With
+/// `remove_documents`, documents only in this lens are removed as well so the
+/// lens is fully rebuilt, otherwise already indexed pages are kept.
+#[instrument(skip(state))]
+pub async fn reset_lens(
+    state: AppState,
+    name: String,
+    remove_documents: bool,
+) -> Result<LensResetResult, Error> {
+    if state.readonly_mode {
+        return Err(Error::Custom(
+            "Unable to reset lens in read only mode".to_string(),
+        ));
+    }
+
+    if !state.lenses.contains_key(&name) {
+        return Err(Error::Custom(format!("Lens not found: {name}")));
+    }
+
+    // Crawls in progress are left for the workers to finish
+    let task_ids = crawl_queue::find_by_lens(state.db.clone(), &name)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to find lens tasks: {err}")))?
+        .into_iter()
+        .filter(|task| task.status != CrawlStatus::Processing)
+        .map(|task| task.id)
+        .collect::<Vec<_>>();
+    let num_tasks_removed = crawl_queue::delete_many_by_id(&state.db, &task_ids)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to remove lens tasks: {err}")))?;
+    state.recrawl_jobs.abandon(&name);
+
+    let num_documents_removed = if remove_documents {
+        let num_removed = remove_lens_documents(&state, &name).await?;
+        if state.index.lens_index(&name).is_some() {
+            if let Err(err) = state.index.rebuild_lens_index(&name).await {
+                log::warn!("Unable to rebuild index for lens {}: {}", name, err);
+            }
+        }
+        num_removed
+    } else {
+        0
+    };
+
+    // Mark the sources as not crawled yet & bootstrap them again
+    bootstrap_queue::dequeue(&state.db, &name)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to reset lens sources: {err}")))?;
+    let _ = state
+        .schedule_work(ManagerCommand::Collect(CollectTask::BootstrapLens {
+            lens: name.clone(),
+        }))
+        .await;
+
+    log::info!(
+        "reset lens {}, removed {} crawls & {} documents",
+        name,
+        num_tasks_removed,
+        num_documents_removed
+    );
+
+    Ok(LensResetResult {
+        num_tasks_removed: num_tasks_removed as usize,
+        num_documents_removed,
+    })
+}
+
+/// Checks a lens definition, returning a message for each invalid field.
+fn validate_lens_definition(name: &str, lens: &LensConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    if lens.name != name {
+        errors.push(format!("name: must be \"{name}\", lenses can't be renamed"));
+    }
+
+    for (idx, domain) in lens.domains.iter().enumerate() {
+        let host = domain.replace('*', "x");
+        if domain.trim().is_empty() || Url::parse(&format!("https://{host}/")).is_err() {
+            errors.push(format!("domains[{idx}]: invalid domain \"{domain}\""));
+        }
+    }
+
+    for (idx, prefix) in lens.urls.iter().enumerate() {
+        match Url::parse(prefix.trim_end_matches('$')) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+            _ => errors.push(format!("urls[{idx}]: invalid URL \"{prefix}\"")),
+        }
+    }
+
+    for (idx, rule) in lens.rules.iter().enumerate() {
+        if let Err(err) = Regex::new(&rule.to_regex()) {
+            errors.push(format!("rules[{idx}]: {err}"));
+        }
+    }
+
+    errors
+}
+
+/// Number of sources (domains, URLs & feeds) a lens has, see
+/// `UserSettings::max_sources_per_lens`.
+fn num_lens_sources(lens: &LensConfig) -> usize {
+    lens.domains.len() + lens.urls.len() + lens.feeds.len()
+}
+
+/// Replaces the definition of an installed lens, removing queued crawls for
+/// sources that are no longer part of the lens & bootstrapping any new ones.
+#[instrument(skip(state, config, definition))]
+pub async fn update_lens_definition(
+    state: AppState,
+    config: &Config,
+    name: String,
+    mut definition: LensConfig,
+) -> Result<(), Error> {
+    let errors = validate_lens_definition(&name, &definition);
+    if !errors.is_empty() {
+        return Err(Error::Custom(format!(
+            "Invalid lens definition: {}",
+            errors.join("; ")
+        )));
+    }
+
+    let existing = lens_definition(state.clone(), name.clone()).await?;
+    // Lenses already past the limit, e.g. after it was lowered, can still
+    // have sources removed.
+    let limit = state.user_settings.load().max_sources_per_lens;
+    let num_sources = num_lens_sources(&definition);
+    if num_sources > limit.value() as usize && num_sources > num_lens_sources(&existing) {
+        return Err(Error::Custom(format!(
+            "Lens {name} has reached the limit of {} sources. Remove some sources to add more.",
+            limit.value()
+        )));
+    }
+    definition.lens_source = existing.lens_source.clone();
+    definition.file_path = if existing.file_path.as_os_str().is_empty() {
+        config.lenses_dir().join(format!("{name}.ron"))
+    } else {
+        existing.file_path.clone()
+    };
+
+    let sources = RegexSet::new(
+        definition
+            .domains
+            .iter()
+            .map(|domain| regex_for_domain(domain))
+            .chain(
+                definition
+                    .urls
+                    .iter()
+                    .map(|prefix| regex_for_prefix(prefix)),
+            ),
+    )
+    .map_err(|err| Error::Custom(format!("Invalid lens definition: {err}")))?;
+
+    let tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    // Drop anything queued from sources that were removed
+    let removed = crawl_queue::queued_by_lens(&tx, &name)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?
+        .into_iter()
+        .filter(|task| !sources.is_match(&task.url))
+        .map(|task| task.id)
+        .collect::<Vec<i64>>();
+    crawl_queue::delete_many_by_id(&tx, &removed)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to remove queued crawls: {err}")))?;
+
+    // Write to a temp file first so a failed write doesn't leave a broken lens
+    // behind. The transaction is rolled back if this fails.
+    let serialized = ron::ser::to_string_pretty(&definition, Default::default())
+        .map_err(|err| Error::Custom(format!("Unable to serialize lens: {err}")))?;
+    let tmp_path = definition.file_path.with_extension("ron.tmp");
+    if let Err(err) = std::fs::write(&tmp_path, serialized)
+        .and_then(|_| std::fs::rename(&tmp_path, &definition.file_path))
+    {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(Error::Custom(format!("Unable to save lens: {err}")));
+    }
+
+    tx.commit()
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+    state.recrawl_jobs.remove(&removed);
+    log::info!(
+        "updated lens {}, removed {} queued crawls",
+        name,
+        removed.len()
+    );
+
+    // Recheck the lens sources so new ones get crawled.
+    state.lenses.insert(name.clone(), definition);
+    let _ = bootstrap_queue::dequeue(&state.db, &name).await;
+    let _ = state
+        .schedule_work(ManagerCommand::Collect(CollectTask::BootstrapLens {
+            lens: name,
+        }))
+        .await;
+
+    Ok(())
+}
+
+pub async fn default_indices() -> DefaultIndices {
+    let mut file_paths: Vec<PathBuf> = Vec::new();
+
+    if let Some(user_dirs) = UserDirs::new() {
+        if let Some(path) = user_dirs.desktop_dir() {
+            file_paths.push(path.to_path_buf());
+        }
+
+        if let Some(path) = user_dirs.document_dir() {
+            file_paths.push(path.to_path_buf());
+        }
+    }
+
+    // Application path is os dependent
+    // NOTE: Uncomment when we add in app searching ability
+    // if cfg!(target_os = "macos") {
+    //     file_paths.push("/Applications".into());
+    // } else if cfg!(target_os = "windows") {
+    //     file_paths.push("C:\\Program Files (x86)".into());
+    // }
 
     file_paths.retain(|f| f.exists());
     DefaultIndices {
@@ -650,17 +1658,23 @@ pub async fn default_indices() -> DefaultIndices {
 
 #[cfg(test)]
 mod test {
-    use super::uninstall_lens;
+    use super::{
+        get_indexed_document, import_bookmarks, lens_recrawl_progress, recrawl_lens,
+        remove_indexed_document, reset_lens, uninstall_lens, update_lens_definition,
+        validate_lens_definition,
+    };
+    use entities::models::crawl_queue::CrawlStatus;
     use entities::models::tag::TagType;
-    use entities::sea_orm::{ActiveModelTrait, EntityTrait, Set};
+    use entities::sea_orm::{ActiveModelTrait, EntityTrait, ModelTrait, QueryOrder, Set};
     use entities::{
-        models::{crawl_queue, indexed_document},
+        models::{bootstrap_queue, crawl_queue, indexed_document, tag},
         test::setup_test_db,
     };
     use libspyglass::state::AppState;
-    use shared::config::{Config, LensConfig};
+    use libspyglass::task::{CollectTask, ManagerCommand};
+    use shared::config::{Config, LensConfig, Limit, UserSettings};
     use spyglass_searcher::schema::{DocumentUpdate, ToDocument};
-    use spyglass_searcher::WriteTrait;
+    use spyglass_searcher::{SearchTrait, WriteTrait};
 
     #[tokio::test]
     async fn test_uninstall_lens() {
@@ -680,10 +1694,14 @@ mod test {
                 &DocumentUpdate {
                     doc_id: Some("test_id".into()),
                     title: "test title",
+                    description: "",
                     domain: "example.com",
                     url: "https://example.com/test",
                     content: "test content",
                     tags: &[],
+                    anchor_text: &[],
+                    code: &[],
+                    body: None,
                     published_at: None,
                     last_modified: None,
                 }
@@ -726,4 +1744,476 @@ mod test {
         std::thread::sleep(std::time::Duration::from_millis(500));
         assert_eq!(state.index.reader.searcher().num_docs(), 0);
     }
+
+    #[tokio::test]
+    async fn test_uninstall_lens_keeps_shared_documents() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        let mut config = Config::new();
+        let lens = LensConfig {
+            name: "test".to_string(),
+            urls: vec!["https://example.com".into()],
+            ..Default::default()
+        };
+
+        for (doc_id, lenses) in [
+            ("test_id", vec!["test"]),
+            ("shared_id", vec!["test", "other"]),
+        ] {
+            let model = indexed_document::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(format!("https://example.com/{doc_id}")),
+                doc_id: Set(doc_id.into()),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert doc");
+
+            let tags = lenses
+                .iter()
+                .map(|name| (TagType::Lens, name.to_string()))
+                .collect::<Vec<_>>();
+            model
+                .insert_tags(&db, &tags)
+                .await
+                .expect("Unable to insert tags");
+        }
+
+        config.lenses.insert(lens.name.clone(), lens.clone());
+        let num_removed = uninstall_lens(state.clone(), &config, &lens.name)
+            .await
+            .expect("Unable to uninstall");
+        assert_eq!(num_removed, 1);
+
+        let indexed = indexed_document::Entity::find()
+            .all(&state.db)
+            .await
+            .expect("Unable to find indexed docs");
+        assert_eq!(indexed.len(), 1);
+        assert_eq!(indexed[0].doc_id, "shared_id");
+
+        let tags = indexed[0]
+            .find_related(tag::Entity)
+            .all(&state.db)
+            .await
+            .expect("Unable to find tags");
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].value, "other");
+    }
+
+    #[tokio::test]
+    async fn test_get_indexed_document() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        state
+            .index
+            .upsert(
+                &DocumentUpdate {
+                    doc_id: Some("test_id".into()),
+                    title: "test title",
+                    description: "",
+                    domain: "example.com",
+                    url: "https://example.com/test",
+                    content: "test content",
+                    tags: &[],
+                    anchor_text: &[],
+                    code: &[],
+                    body: None,
+                    published_at: None,
+                    last_modified: None,
+                }
+                .to_document(),
+            )
+            .await
+            .expect("Unable to add doc");
+        let _ = state.index.save().await;
+
+        indexed_document::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/test".into()),
+            doc_id: Set("test_id".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert doc");
+
+        // Fragments are ignored
+        let doc = get_indexed_document(state.clone(), "https://example.com/test#intro".into())
+            .await
+            .expect("Unable to find doc");
+        assert_eq!(doc.doc_id, "test_id");
+        assert_eq!(doc.url, "https://example.com/test");
+        assert_eq!(doc.title, "test title");
+        assert_eq!(doc.domain, "example.com");
+
+        assert!(
+            get_indexed_document(state, "https://example.com/other".into())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_indexed_document() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        state
+            .index
+            .upsert(
+                &DocumentUpdate {
+                    doc_id: Some("test_id".into()),
+                    title: "test title",
+                    description: "",
+                    domain: "example.com",
+                    url: "https://example.com/test",
+                    content: "test content",
+                    tags: &[],
+                    anchor_text: &[],
+                    code: &[],
+                    body: None,
+                    published_at: None,
+                    last_modified: None,
+                }
+                .to_document(),
+            )
+            .await
+            .expect("Unable to add doc");
+        let _ = state.index.save().await;
+
+        indexed_document::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/test".into()),
+            doc_id: Set("test_id".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert doc");
+        crawl_queue::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/test".into()),
+            status: Set(CrawlStatus::Completed),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert task");
+
+        remove_indexed_document(state.clone(), "https://example.com/test".into())
+            .await
+            .expect("Unable to remove doc");
+        let _ = state.index.save().await;
+
+        assert!(state.index.get("test_id").await.is_none());
+        assert_eq!(
+            indexed_document::Entity::find()
+                .all(&db)
+                .await
+                .unwrap()
+                .len(),
+            0
+        );
+        let task = crawl_queue::Entity::find()
+            .one(&db)
+            .await
+            .unwrap()
+            .expect("Task should still be queued");
+        assert_eq!(task.status, CrawlStatus::Queued);
+
+        // Nothing left to remove
+        assert!(
+            remove_indexed_document(state, "https://example.com/test".into())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recrawl_lens() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+        state.lenses.insert(
+            "test".to_string(),
+            LensConfig {
+                name: "test".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let task = crawl_queue::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/".into()),
+            status: Set(CrawlStatus::Completed),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert task");
+        task.insert_tags(&db, &[(TagType::Lens, "test".to_string())])
+            .await
+            .expect("Unable to insert tags");
+        let processing = crawl_queue::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/processing".into()),
+            status: Set(CrawlStatus::Processing),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert task");
+        processing
+            .insert_tags(&db, &[(TagType::Lens, "test".to_string())])
+            .await
+            .expect("Unable to insert tags");
+
+        assert!(recrawl_lens(state.clone(), "missing".into()).await.is_err());
+        let job_id = recrawl_lens(state.clone(), "test".into())
+            .await
+            .expect("Unable to recrawl lens");
+
+        let task = crawl_queue::Entity::find_by_id(task.id)
+            .one(&db)
+            .await
+            .expect("Unable to find task")
+            .expect("Task should exist");
+        assert_eq!(task.status, CrawlStatus::Queued);
+        // Tasks being crawled are left alone
+        let processing = crawl_queue::Entity::find_by_id(processing.id)
+            .one(&db)
+            .await
+            .expect("Unable to find task")
+            .expect("Task should exist");
+        assert_eq!(processing.status, CrawlStatus::Processing);
+
+        state.recrawl_jobs.record(task.id, false);
+        let progress = lens_recrawl_progress(state.clone(), "test".into(), job_id.clone())
+            .await
+            .expect("Unable to get progress");
+        assert_eq!(progress.total, 1);
+        assert_eq!(progress.crawled, 1);
+        assert!(progress.is_finished);
+
+        // Jobs are looked up under the lens they belong to
+        assert!(lens_recrawl_progress(state, "other".into(), job_id)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reset_lens() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_lenses(&vec![LensConfig {
+                name: "test".to_string(),
+                urls: vec!["https://example.com".into()],
+                ..Default::default()
+            }])
+            .build();
+        let (cmd_tx, mut cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+        *state.manager_cmd_tx.lock().await = Some(cmd_tx);
+
+        for (url, status, lens) in [
+            ("https://example.com/", CrawlStatus::Completed, "test"),
+            ("https://example.com/stuck", CrawlStatus::Processing, "test"),
+            ("https://example.org/", CrawlStatus::Queued, "other"),
+        ] {
+            let task = crawl_queue::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(url.into()),
+                status: Set(status),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert task");
+            task.insert_tags(&db, &[(TagType::Lens, lens.to_string())])
+                .await
+                .expect("Unable to insert tags");
+        }
+
+        let doc = indexed_document::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/".into()),
+            doc_id: Set("test_id".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert doc");
+        doc.insert_tags(&db, &[(TagType::Lens, "test".to_string())])
+            .await
+            .expect("Unable to insert tags");
+        bootstrap_queue::enqueue(&db, "test", 2)
+            .await
+            .expect("Unable to mark as bootstrapped");
+
+        let job_id = state.recrawl_jobs.start("test", &[100]);
+
+        assert!(reset_lens(state.clone(), "missing".into(), false)
+            .await
+            .is_err());
+        let result = reset_lens(state.clone(), "test".into(), false)
+            .await
+            .expect("Unable to reset lens");
+        assert_eq!(result.num_tasks_removed, 1);
+        assert_eq!(result.num_documents_removed, 0);
+
+        // The crawl in progress & the other lens' crawl are left
+        let mut urls = crawl_queue::Entity::find()
+            .all(&db)
+            .await
+            .expect("Unable to find tasks")
+            .into_iter()
+            .map(|task| task.url)
+            .collect::<Vec<_>>();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec!["https://example.com/stuck", "https://example.org/"]
+        );
+
+        let progress = state
+            .recrawl_jobs
+            .progress(&job_id)
+            .expect("job should exist");
+        assert!(progress.is_finished);
+
+        // Sources are bootstrapped again
+        assert!(!bootstrap_queue::is_bootstrapped(&db, "test")
+            .await
+            .expect("Unable to check bootstrap"));
+        match cmd_rx.try_recv() {
+            Ok(ManagerCommand::Collect(CollectTask::BootstrapLens { lens })) => {
+                assert_eq!(lens, "test")
+            }
+            other => panic!("Unexpected command: {other:?}"),
+        }
+
+        let result = reset_lens(state.clone(), "test".into(), true)
+            .await
+            .expect("Unable to reset lens");
+        assert_eq!(result.num_tasks_removed, 0);
+        assert_eq!(result.num_documents_removed, 1);
+        let indexed = indexed_document::Entity::find()
+            .all(&db)
+            .await
+            .expect("Unable to find indexed docs");
+        assert!(indexed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_bookmarks() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_lenses(&vec![LensConfig {
+                name: "test".to_string(),
+                ..Default::default()
+            }])
+            .build();
+
+        let path = std::env::temp_dir().join(format!("bookmarks-{}.html", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><A HREF="https://example.com/">Example</A>
+    <DT><H3>Reading</H3>
+    <DL><p>
+        <DT><A HREF="https://example.org/#intro">Example Org</A>
+        <DT><A HREF="https://example.com/">Example again</A>
+        <DT><A HREF="about:blank">Blank</A>
+    </DL><p>
+</DL><p>"#,
+        )
+        .expect("Unable to write bookmarks");
+        let path = path.display().to_string();
+
+        assert!(
+            import_bookmarks(state.clone(), "missing".into(), path.clone())
+                .await
+                .is_err()
+        );
+        let result = import_bookmarks(state, "test".into(), path.clone())
+            .await
+            .expect("Unable to import bookmarks");
+        let _ = std::fs::remove_file(path);
+        assert_eq!(result.num_imported, 2);
+        assert_eq!(result.num_skipped, 1);
+
+        let queued = crawl_queue::Entity::find()
+            .order_by_asc(crawl_queue::Column::Url)
+            .find_with_related(tag::Entity)
+            .all(&db)
+            .await
+            .expect("Unable to find tasks");
+        assert_eq!(queued.len(), 2);
+
+        let (task, tags) = &queued[0];
+        assert_eq!(task.url, "https://example.com/");
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].tag_pair(), (TagType::Lens, "test".to_string()));
+
+        let (task, tags) = &queued[1];
+        assert_eq!(task.url, "https://example.org/");
+        assert!(tags
+            .iter()
+            .any(|tag| tag.tag_pair() == (TagType::Category, "Reading".to_string())));
+    }
+
+    #[test]
+    fn test_validate_lens_definition() {
+        let lens = LensConfig {
+            name: "test".to_string(),
+            domains: vec!["*.example.com".into()],
+            urls: vec!["https://example.com/docs".into()],
+            ..Default::default()
+        };
+        assert!(validate_lens_definition("test", &lens).is_empty());
+
+        let lens = LensConfig {
+            name: "renamed".to_string(),
+            domains: vec!["".into()],
+            urls: vec!["not a url".into(), "https://example.com".into()],
+            ..Default::default()
+        };
+        let errors = validate_lens_definition("test", &lens);
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("name:"));
+        assert!(errors[1].starts_with("domains[0]:"));
+        assert!(errors[2].starts_with("urls[0]:"));
+    }
+
+    #[tokio::test]
+    async fn test_update_lens_definition_source_limit() {
+        let db = setup_test_db().await;
+        let settings = UserSettings {
+            max_sources_per_lens: Limit::Finite(2),
+            ..Default::default()
+        };
+        let lens = LensConfig {
+            name: "test".to_string(),
+            domains: vec!["example.com".into()],
+            urls: vec!["https://example.org/docs".into()],
+            ..Default::default()
+        };
+        let state = AppState::builder()
+            .with_db(db)
+            .with_user_settings(&settings)
+            .with_lenses(&vec![lens.clone()])
+            .build();
+
+        let mut definition = lens.clone();
+        definition.urls.push("https://example.net/".into());
+        let err = update_lens_definition(state, &Config::new(), "test".into(), definition)
+            .await
+            .expect_err("Past the source limit");
+        assert!(err.to_string().contains("limit of 2 sources"));
+    }
 }
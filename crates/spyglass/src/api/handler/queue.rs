@@ -0,0 +1,502 @@
+use entities::models::crawl_queue::{self, CrawlStatus};
+use entities::sea_orm::{
+    ActiveEnum, ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult,
+    QueryFilter, QueryOrder, QuerySelect, Set, Statement, Value,
+};
+use jsonrpsee::core::Error;
+use jsonrpsee::types::error::{CallError, ErrorObject};
+use libspyglass::state::AppState;
+use shared::response::{FailedCrawl, QueueTreeNode};
+use spyglass_rpc::{ERROR_CODE_CONFLICT, ERROR_CODE_NOT_FOUND};
+use std::collections::HashMap;
+use tracing::instrument;
+use url::Url;
+
+/// Max number of path segments below the domain shown in the tree. Deeper URLs
+/// are counted towards their ancestor at this depth.
+const MAX_TREE_DEPTH: usize = 3;
+/// Max number of children kept per node, the rest are grouped together.
+const MAX_TREE_CHILDREN: usize = 25;
+/// Name of the node grouping children past `MAX_TREE_CHILDREN`.
+const OTHER_NODE: &str = "(other)";
+/// Max number of failed crawls listed.
+const MAX_FAILED_CRAWLS: u64 = 100;
+
+/// Number of rows read at a time while building the queue tree.
+const TREE_PAGE_SIZE: u64 = 1000;
+
+#[derive(Debug, FromQueryResult)]
+struct TreeUrl {
+    id: i64,
+    url: String,
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    queued: u64,
+    indexed: u64,
+    children: HashMap<String, TreeBuilder>,
+}
+
+impl TreeBuilder {
+    fn add(&mut self, url: &str, is_indexed: bool) {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+
+        let host = url.host_str().unwrap_or("localhost").to_string();
+        let segments = url
+            .path_segments()
+            .map(|segments| {
+                segments
+                    .filter(|segment| !segment.is_empty())
+                    .take(MAX_TREE_DEPTH)
+                    .map(|segment| segment.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let mut node = self;
+        node.count(is_indexed);
+        for segment in std::iter::once(host).chain(segments) {
+            node = node.children.entry(segment).or_default();
+            node.count(is_indexed);
+        }
+    }
+
+    fn count(&mut self, is_indexed: bool) {
+        if is_indexed {
+            self.indexed += 1;
+        } else {
+            self.queued += 1;
+        }
+    }
+
+    fn build(self, name: String) -> QueueTreeNode {
+        let mut children = self.children.into_iter().collect::<Vec<_>>();
+        children.sort_by(|(a_name, a), (b_name, b)| {
+            (b.queued + b.indexed)
+                .cmp(&(a.queued + a.indexed))
+                .then_with(|| a_name.cmp(b_name))
+        });
+
+        let mut other = QueueTreeNode {
+            name: OTHER_NODE.to_string(),
+            ..Default::default()
+        };
+        let mut nodes = Vec::new();
+        for (idx, (name, child)) in children.into_iter().enumerate() {
+            if idx < MAX_TREE_CHILDREN {
+                nodes.push(child.build(name));
+            } else {
+                other.queued += child.queued;
+                other.indexed += child.indexed;
+            }
+        }
+
+        if other.queued + other.indexed > 0 {
+            nodes.push(other);
+        }
+
+        QueueTreeNode {
+            name,
+            queued: self.queued,
+            indexed: self.indexed,
+            children: nodes,
+        }
+    }
+}
+
+/// Adds the URLs from `table` matching `filter` to the tree, a page at a time
+/// so the whole table is never held in memory.
+async fn add_tree_urls(
+    state: &AppState,
+    tree: &mut TreeBuilder,
+    table: &str,
+    filter: &str,
+    values: &[Value],
+    is_indexed: bool,
+) -> Result<(), Error> {
+    let backend = state.db.get_database_backend();
+    let mut last_id = 0;
+    loop {
+        let mut page_values = vec![last_id.into()];
+        page_values.extend(values.iter().cloned());
+        let page = TreeUrl::find_by_statement(Statement::from_sql_and_values(
+            backend,
+            &format!(
+                "SELECT id, url FROM {table} WHERE id > $1 {filter} ORDER BY id LIMIT {TREE_PAGE_SIZE}"
+            ),
+            page_values,
+        ))
+        .all(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+        let last = match page.last() {
+            Some(row) => row.id,
+            None => return Ok(()),
+        };
+
+        for row in &page {
+            tree.add(&row.url, is_indexed);
+        }
+
+        if (page.len() as u64) < TREE_PAGE_SIZE {
+            return Ok(());
+        }
+        last_id = last;
+    }
+}
+
+/// Queued & indexed URLs grouped by domain & path, optionally limited to a
+/// single domain.
+#[instrument(skip(state))]
+pub async fn queue_tree(state: AppState, domain: Option<String>) -> Result<QueueTreeNode, Error> {
+    let (filter, values): (&str, Vec<Value>) = match &domain {
+        Some(domain) => ("AND domain = $2", vec![domain.into()]),
+        None => ("", Vec::new()),
+    };
+
+    let queued_filter = format!(
+        "AND status IN ('{}', '{}') {filter}",
+        CrawlStatus::Queued.to_value(),
+        CrawlStatus::Processing.to_value(),
+    );
+
+    let mut tree = TreeBuilder::default();
+    add_tree_urls(
+        &state,
+        &mut tree,
+        "crawl_queue",
+        &queued_filter,
+        &values,
+        false,
+    )
+    .await?;
+    add_tree_urls(&state, &mut tree, "indexed_document", filter, &values, true).await?;
+
+    Ok(tree.build(domain.unwrap_or_default()))
+}
+
+/// Most recently failed crawls, optionally limited to a single domain.
+#[instrument(skip(state))]
+pub async fn failed_crawls(
+    state: AppState,
+    domain: Option<String>,
+) -> Result<Vec<FailedCrawl>, Error> {
+    let mut query = crawl_queue::Entity::find()
+        .filter(crawl_queue::Column::Status.eq(CrawlStatus::Failed))
+        .order_by_desc(crawl_queue::Column::UpdatedAt)
+        .limit(MAX_FAILED_CRAWLS);
+    if let Some(domain) = domain {
+        query = query.filter(crawl_queue::Column::Domain.eq(domain));
+    }
+
+    let failed = query
+        .all(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    Ok(failed
+        .into_iter()
+        .map(|task| FailedCrawl {
+            id: task.id,
+            url: task.url,
+            domain: task.domain,
+            error: task.error.map(|error| error.msg().to_string()),
+            num_retries: task.num_retries,
+            failed_at: task.updated_at.timestamp(),
+        })
+        .collect())
+}
+
+/// Moves a failed crawl back into the queue, clearing the error & retries so
+/// it gets the full number of retries again.
+#[instrument(skip(state))]
+pub async fn retry_failed_crawl(state: AppState, id: i64) -> Result<(), Error> {
+    let task = crawl_queue::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    match task {
+        Some(task) if task.status == CrawlStatus::Failed => {
+            let mut update: crawl_queue::ActiveModel = task.into();
+            update.status = Set(CrawlStatus::Queued);
+            update.error = Set(None);
+            update.num_retries = Set(0);
+            update.next_retry_at = Set(None);
+            update
+                .update(&state.db)
+                .await
+                .map_err(|err| Error::Custom(format!("Unable to requeue crawl: {err}")))?;
+            Ok(())
+        }
+        Some(_) => Err(Error::Custom(format!("Queue entry {id} hasn't failed"))),
+        None => Err(Error::Custom(format!("Queue entry not found: {id}"))),
+    }
+}
+
+/// Removes a single entry from the crawl queue. Entries being crawled are
+/// left alone until the worker is done w/ them. Fails w/ `ERROR_CODE_NOT_FOUND`
+/// for unknown entries & `ERROR_CODE_CONFLICT` for ones being crawled.
+#[instrument(skip(state))]
+pub async fn delete_queue_entry(state: AppState, id: i64) -> Result<(), Error> {
+    let removed = crawl_queue::delete_unless_processing(&state.db, id)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to delete queue entry: {err}")))?;
+    if removed > 0 {
+        state.recrawl_jobs.remove(&[id]);
+        return Ok(());
+    }
+
+    // Nothing deleted, find out why
+    let task = crawl_queue::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+    let (code, msg) = match task {
+        Some(_) => (
+            ERROR_CODE_CONFLICT,
+            format!("Queue entry {id} is being crawled"),
+        ),
+        None => (ERROR_CODE_NOT_FOUND, format!("Queue entry not found: {id}")),
+    };
+    Err(Error::Call(CallError::Custom(ErrorObject::owned(
+        code, msg, None::<()>,
+    ))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        delete_queue_entry, failed_crawls, queue_tree, retry_failed_crawl, TreeBuilder,
+        MAX_TREE_CHILDREN, OTHER_NODE, TREE_PAGE_SIZE,
+    };
+    use entities::models::crawl_queue::{self, CrawlStatus, TaskError, TaskErrorType};
+    use entities::models::indexed_document;
+    use entities::sea_orm::{ActiveModelTrait, EntityTrait, Set};
+    use entities::test::setup_test_db;
+    use jsonrpsee::core::Error;
+    use jsonrpsee::types::error::CallError;
+    use libspyglass::state::AppState;
+    use spyglass_rpc::{ERROR_CODE_CONFLICT, ERROR_CODE_NOT_FOUND};
+
+    #[test]
+    fn test_build_tree() {
+        let mut tree = TreeBuilder::default();
+        tree.add("https://example.com/docs/intro", true);
+        tree.add("https://example.com/docs/setup", true);
+        tree.add("https://example.com/docs/a/b/c/d", false);
+        tree.add("https://example.com/api", true);
+        tree.add("https://other.com/", false);
+
+        let root = tree.build(String::new());
+        assert_eq!((root.queued, root.indexed), (2, 3));
+        assert_eq!(root.children[0].name, "example.com");
+
+        let docs = &root.children[0].children[0];
+        assert_eq!(docs.name, "docs");
+        assert_eq!((docs.queued, docs.indexed), (1, 2));
+        // Deep URLs stop at the max depth
+        let a = docs.children.iter().find(|c| c.name == "a").unwrap();
+        assert!(a.children[0].children[0].children.is_empty());
+    }
+
+    #[test]
+    fn test_tree_children_capped() {
+        let mut tree = TreeBuilder::default();
+        for idx in 0..(MAX_TREE_CHILDREN + 5) {
+            tree.add(&format!("https://site{idx}.com/"), true);
+        }
+
+        let root = tree.build(String::new());
+        assert_eq!(root.children.len(), MAX_TREE_CHILDREN + 1);
+        let other = root.children.last().unwrap();
+        assert_eq!(other.name, OTHER_NODE);
+        assert_eq!(other.indexed, 5);
+    }
+
+    #[tokio::test]
+    async fn test_queue_tree() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        // Enough rows to span more than one page
+        let num_queued = TREE_PAGE_SIZE as usize + 5;
+        for idx in 0..num_queued {
+            crawl_queue::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(format!("https://example.com/{idx}")),
+                status: Set(CrawlStatus::Queued),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert task");
+        }
+
+        for (domain, status) in [
+            ("example.com", CrawlStatus::Completed),
+            ("other.com", CrawlStatus::Processing),
+        ] {
+            crawl_queue::ActiveModel {
+                domain: Set(domain.into()),
+                url: Set(format!("https://{domain}/done")),
+                status: Set(status),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert task");
+        }
+
+        indexed_document::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/done".into()),
+            doc_id: Set("done".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert doc");
+
+        let root = queue_tree(state.clone(), None)
+            .await
+            .expect("Unable to build tree");
+        assert_eq!(root.queued, num_queued as u64 + 1);
+        assert_eq!(root.indexed, 1);
+
+        let root = queue_tree(state, Some("example.com".into()))
+            .await
+            .expect("Unable to build tree");
+        assert_eq!(root.name, "example.com");
+        assert_eq!((root.queued, root.indexed), (num_queued as u64, 1));
+        assert_eq!(root.children.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_crawls() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        let mut ids = Vec::new();
+        for domain in ["example.com", "example.com", "other.com"] {
+            let task = crawl_queue::ActiveModel {
+                domain: Set(domain.into()),
+                url: Set(format!("https://{domain}/{}", ids.len())),
+                status: Set(CrawlStatus::Queued),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert task");
+            ids.push(task.id);
+        }
+
+        let error = TaskError::new(TaskErrorType::Fetch, "connection refused");
+        // Retried tasks aren't failed yet
+        assert!(crawl_queue::mark_failed(&db, ids[0], 1, error.clone()).await);
+        assert!(!crawl_queue::mark_failed(&db, ids[1], 0, error.clone()).await);
+        assert!(!crawl_queue::mark_failed(&db, ids[2], 0, error).await);
+
+        let failed = failed_crawls(state.clone(), None)
+            .await
+            .expect("Unable to list failed crawls");
+        assert_eq!(failed.len(), 2);
+
+        let failed = failed_crawls(state, Some("example.com".into()))
+            .await
+            .expect("Unable to list failed crawls");
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].url, "https://example.com/1");
+        assert_eq!(failed[0].domain, "example.com");
+        assert_eq!(failed[0].error.as_deref(), Some("connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed_crawl() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        let task = crawl_queue::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/".into()),
+            status: Set(CrawlStatus::Queued),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert task");
+
+        // Only failed crawls can be retried
+        assert!(retry_failed_crawl(state.clone(), task.id).await.is_err());
+
+        let error = TaskError::new(TaskErrorType::Fetch, "connection refused");
+        assert!(crawl_queue::mark_failed(&db, task.id, 1, error.clone()).await);
+        assert!(!crawl_queue::mark_failed(&db, task.id, 1, error).await);
+
+        retry_failed_crawl(state.clone(), task.id)
+            .await
+            .expect("Unable to retry crawl");
+        let task = crawl_queue::Entity::find_by_id(task.id)
+            .one(&db)
+            .await
+            .expect("Unable to find task")
+            .expect("Task missing");
+        assert_eq!(task.status, CrawlStatus::Queued);
+        assert_eq!(task.num_retries, 0);
+        assert!(task.error.is_none());
+        assert!(retry_failed_crawl(state, task.id + 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_queue_entry() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        let mut ids = Vec::new();
+        for (idx, status) in [CrawlStatus::Queued, CrawlStatus::Processing]
+            .into_iter()
+            .enumerate()
+        {
+            let task = crawl_queue::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(format!("https://example.com/{idx}")),
+                status: Set(status),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert task");
+            ids.push(task.id);
+        }
+
+        let error_code = |err: Error| match err {
+            Error::Call(CallError::Custom(err)) => Some(err.code()),
+            _ => None,
+        };
+
+        delete_queue_entry(state.clone(), ids[0])
+            .await
+            .expect("Unable to delete queue entry");
+        let err = delete_queue_entry(state.clone(), ids[0])
+            .await
+            .expect_err("Already deleted");
+        assert_eq!(error_code(err), Some(ERROR_CODE_NOT_FOUND));
+        // Left alone while being crawled
+        let err = delete_queue_entry(state, ids[1])
+            .await
+            .expect_err("Being crawled");
+        assert_eq!(error_code(err), Some(ERROR_CODE_CONFLICT));
+
+        let remaining = crawl_queue::Entity::find()
+            .all(&db)
+            .await
+            .expect("Unable to find tasks");
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, ids[1]);
+    }
+}
@@ -0,0 +1,83 @@
+use entities::models::saved_query::{self, SavedQueryFilters};
+use jsonrpsee::core::Error;
+use libspyglass::state::AppState;
+use libspyglass::task::saved_query::Destination;
+use shared::request::SavedQueryParam;
+use shared::response::SavedQueryResult;
+use tracing::instrument;
+
+/// Saved queries can't run more often than this, in minutes.
+const MIN_INTERVAL_MINS: u32 = 5;
+
+fn to_result(model: saved_query::Model) -> SavedQueryResult {
+    SavedQueryResult {
+        id: model.id,
+        name: model.name,
+        query: model.query,
+        lenses: model.filters.lenses,
+        interval_mins: model.interval_mins as u32,
+        destination: model.destination,
+        last_run_at: model.last_run_at.map(|at| at.timestamp()),
+    }
+}
+
+/// Saves a query to be run in the background on a schedule
+#[instrument(skip(state))]
+pub async fn add_saved_query(
+    state: AppState,
+    param: SavedQueryParam,
+) -> Result<SavedQueryResult, Error> {
+    let name = param.name.trim();
+    if name.is_empty() || param.query.trim().is_empty() {
+        return Err(Error::Custom("Name & query are required".to_string()));
+    }
+
+    if param.interval_mins < MIN_INTERVAL_MINS {
+        return Err(Error::Custom(format!(
+            "Saved queries can run at most every {MIN_INTERVAL_MINS} minutes"
+        )));
+    }
+
+    Destination::parse(&param.destination).map_err(Error::Custom)?;
+
+    for lens in &param.lenses {
+        if !state.lenses.contains_key(lens) {
+            return Err(Error::Custom(format!("Lens not found: {lens}")));
+        }
+    }
+
+    saved_query::add(
+        &state.db,
+        name,
+        param.query.trim(),
+        SavedQueryFilters {
+            lenses: param.lenses,
+        },
+        param.interval_mins as i64,
+        param.destination.trim(),
+    )
+    .await
+    .map(to_result)
+    .map_err(|err| Error::Custom(format!("Unable to save query: {err}")))
+}
+
+/// Lists the user's saved queries
+#[instrument(skip(state))]
+pub async fn list_saved_queries(state: AppState) -> Result<Vec<SavedQueryResult>, Error> {
+    saved_query::list(&state.db)
+        .await
+        .map(|queries| queries.into_iter().map(to_result).collect())
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))
+}
+
+/// Deletes a saved query & forgets the results it has delivered
+#[instrument(skip(state))]
+pub async fn delete_saved_query(state: AppState, id: i64) -> Result<(), Error> {
+    match saved_query::delete(&state.db, id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::Custom(format!("Unable to find saved query: {id}"))),
+        Err(err) => Err(Error::Custom(format!(
+            "Unable to delete saved query: {err}"
+        ))),
+    }
+}
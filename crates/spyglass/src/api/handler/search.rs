@@ -1,20 +1,177 @@
 use entities::models::tag::{check_query_for_tags, get_favorite_tag, TagType};
-use entities::models::{indexed_document, lens, tag};
+use entities::models::{document_note, indexed_document, lens, lens_source_boost, tag};
 use entities::sea_orm::{
     self, prelude::*, sea_query::Expr, FromQueryResult, JoinType, QueryOrder, QuerySelect,
 };
 use jsonrpsee::core::Error;
+use libspyglass::documents;
 use libspyglass::state::AppState;
 use libspyglass::task::{CleanupTask, ManagerCommand};
+use shared::config::MAX_SEARCH_RESULTS;
 use shared::metrics;
-use shared::request;
-use shared::response::{LensResult, SearchLensesResp, SearchMeta, SearchResult, SearchResults};
+use shared::request::{self, SnippetStrategy, SortMode};
+use shared::response::{
+    LensResult, SearchCountResult, SearchEstimateResult, SearchLensesResp, SearchMeta,
+    SearchResult, SearchResults,
+};
+use spyglass_plugin::TagModification;
+use spyglass_searcher::ranking::{self, RankingSignals, ScoringHook, WeightedScoring};
 use spyglass_searcher::schema::{DocFields, SearchDocument};
-use spyglass_searcher::{Boost, QueryBoost, SearchTrait};
-use std::collections::HashSet;
-use std::time::SystemTime;
+use spyglass_searcher::utils::{self, DEFAULT_HIGHLIGHT_TAG};
+use spyglass_searcher::{Boost, QueryBoost, SearchCursor, SearchTrait};
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use tantivy::tokenizer::{TextAnalyzer, TokenStream};
 use tracing::instrument;
 
+/// Number of results returned per page of search results
+const SEARCH_PAGE_SIZE: usize = 5;
+/// Number of results per page when requesting a specific page & no limit.
+const DEFAULT_PAGE_LIMIT: usize = 20;
+/// Results past this are never paged to, since deep pages make the searcher
+/// collect & sort every result before the offset.
+const MAX_PAGED_RESULTS: usize = 10_000;
+/// Number of results fetched per page when collapsing results by domain or
+/// title, so there are enough results left after collapsing.
+const COLLAPSED_PAGE_SIZE: usize = 50;
+/// Max number of results shown per domain when collapsing results by domain.
+const COLLAPSED_RESULTS_PER_DOMAIN: usize = 3;
+
+/// Number of top results mined for related queries.
+const RELATED_QUERY_DOCS: usize = 10;
+/// Characters of each result's text looked at for related queries.
+const RELATED_QUERY_TEXT_LEN: usize = 5_000;
+/// Max number of related queries suggested.
+const MAX_RELATED_QUERIES: usize = 5;
+
+/// Fields that can be highlighted in search results
+const HIGHLIGHT_TITLE: &str = "title";
+const HIGHLIGHT_CONTENT: &str = "content";
+
+/// Validates the requested highlight options, returning the fields to highlight
+/// and the tag used to wrap matches.
+fn highlight_options(search_req: &request::SearchParam) -> Result<(Vec<String>, String), Error> {
+    let fields = search_req
+        .highlight_fields
+        .clone()
+        .unwrap_or_else(|| vec![HIGHLIGHT_CONTENT.to_string()]);
+
+    for field in fields.iter() {
+        if field != HIGHLIGHT_TITLE && field != HIGHLIGHT_CONTENT {
+            return Err(Error::Custom(format!("Invalid highlight field: {field}")));
+        }
+    }
+
+    let tag = search_req
+        .highlight_tag
+        .clone()
+        .unwrap_or_else(|| DEFAULT_HIGHLIGHT_TAG.to_string());
+    if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(Error::Custom(format!("Invalid highlight tag: {tag}")));
+    }
+
+    Ok((fields, tag))
+}
+
+/// Builds the search filters used to limit results to the given lenses
+async fn lens_filters(state: &AppState, lenses: &[String]) -> Vec<QueryBoost> {
+    tag::Entity::find()
+        .filter(tag::Column::Label.eq(tag::TagType::Lens.to_string()))
+        .filter(tag::Column::Value.is_in(lenses.to_vec()))
+        .all(&state.db)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .map(|model| QueryBoost::new(Boost::Tag(model.id as u64)))
+        .collect()
+}
+
+/// Builds the filters used to limit results to the given lenses and
+/// (label, value) tags. Returns None if one of the tags doesn't exist, since no
+/// document can match.
+pub async fn search_filters(
+    state: &AppState,
+    lenses: &[String],
+    tags: &[(String, String)],
+) -> Result<Option<Vec<QueryBoost>>, Error> {
+    let mut filters = lens_filters(state, lenses).await;
+
+    for (label, value) in tags.iter() {
+        let tag_model = tag::Entity::find()
+            .filter(tag::Column::Label.eq(label.as_str()))
+            .filter(tag::Column::Value.eq(value.as_str()))
+            .one(&state.db)
+            .await
+            .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+        match tag_model {
+            Some(tag_model) => filters.push(QueryBoost::new(Boost::Tag(tag_model.id as u64))),
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(filters))
+}
+
+/// Score multipliers for lenses the user has boosted or buried, keyed by the
+/// lens' tag id.
+async fn lens_tag_weights(state: &AppState) -> HashMap<u64, f32> {
+    let weights = lens::ranking_weights(&state.db).await.unwrap_or_default();
+    if weights.is_empty() {
+        return HashMap::new();
+    }
+
+    tag::Entity::find()
+        .filter(tag::Column::Label.eq(tag::TagType::Lens.to_string()))
+        .filter(tag::Column::Value.is_in(weights.keys().cloned().collect::<Vec<_>>()))
+        .all(&state.db)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|model| {
+            weights
+                .get(&model.value)
+                .map(|pct| (model.id as u64, *pct as f32 / 100.0))
+        })
+        .collect()
+}
+
+/// Boosts for the sources of the searched lenses, keyed by domain. Sources
+/// boosted in several of the lenses use the largest boost.
+async fn lens_source_boosts(state: &AppState, lenses: &[String]) -> HashMap<String, f32> {
+    if lenses.is_empty() {
+        return HashMap::new();
+    }
+
+    let mut by_lens = lens_source_boost::boosts_for_lenses(&state.db, lenses)
+        .await
+        .unwrap_or_default();
+    // Boosts from the lens definitions, unless they were changed locally
+    for lens in lenses {
+        if let Some(config) = state.lenses.get(lens) {
+            let local = by_lens.entry(lens.clone()).or_default();
+            for (domain, boost) in &config.source_boosts {
+                local.entry(domain.clone()).or_insert(boost.clamp(
+                    lens_source_boost::MIN_SOURCE_BOOST,
+                    lens_source_boost::MAX_SOURCE_BOOST,
+                ));
+            }
+        }
+    }
+
+    let mut boosts: HashMap<String, f32> = HashMap::new();
+    for (domain, boost) in by_lens.into_values().flatten() {
+        let entry = boosts.entry(domain).or_insert(boost);
+        *entry = entry.max(boost);
+    }
+
+    // Added to the score of matching documents, buried sources go negative
+    boosts
+        .into_iter()
+        .map(|(domain, boost)| (domain, boost - lens_source_boost::DEFAULT_SOURCE_BOOST))
+        .collect()
+}
+
 /// Search the user's indexed documents
 #[instrument(skip(state))]
 pub async fn search_docs(
@@ -28,39 +185,164 @@ pub async fn search_docs(
         })
         .await;
 
-    let start = SystemTime::now();
-    let index = &state.index;
-    let searcher = index.reader.searcher();
-    let query = search_req.query.clone();
+    let (highlight_fields, highlight_tag) = highlight_options(&search_req)?;
+    let sort = search_req.sort.unwrap_or_default();
+    // Cursors are based on the relevance score, so only work for relevance sorting
+    if sort != SortMode::Relevance && search_req.cursor.is_some() {
+        return Err(Error::Custom(
+            "Cursors are only supported when sorting by relevance".to_string(),
+        ));
+    }
+    let cursor = match &search_req.cursor {
+        Some(cursor) => match SearchCursor::decode(cursor) {
+            Some(cursor) => Some(cursor),
+            None => return Err(Error::Custom(format!("Invalid cursor: {cursor}"))),
+        },
+        None => None,
+    };
+    if search_req.page.is_some() {
+        if cursor.is_some() {
+            return Err(Error::Custom(
+                "Use either a cursor or a page, not both".to_string(),
+            ));
+        }
+        if sort != SortMode::Relevance {
+            return Err(Error::Custom(
+                "Pages are only supported when sorting by relevance".to_string(),
+            ));
+        }
+    }
 
-    let lens_ids = tag::Entity::find()
-        .filter(tag::Column::Label.eq(tag::TagType::Lens.to_string()))
-        .filter(tag::Column::Value.is_in(search_req.lenses))
-        .all(&state.db)
-        .await
-        .unwrap_or_default()
+    let start = Instant::now();
+    // Every result has to be in all of the requested lenses, so the smallest
+    // of their separate indexes has everything the search needs.
+    let lens_index = search_req
+        .lenses
         .iter()
-        .map(|model| model.id as u64)
-        .collect::<Vec<u64>>();
+        .filter_map(|lens| state.index.lens_index(lens))
+        .min_by_key(|index| index.reader.searcher().num_docs());
+    let index = lens_index.as_ref().unwrap_or(&state.index);
+    // Counted in the main index, whichever index serves the search.
+    let num_docs = state.index.reader.searcher().num_docs();
+    let query = search_req.query.clone();
 
     let mut boosts = Vec::new();
     for tag in check_query_for_tags(&state.db, &query).await {
         boosts.push(QueryBoost::new(Boost::Tag(tag)))
     }
-
-    let mut filters = Vec::new();
-    for lens in lens_ids {
-        filters.push(QueryBoost::new(Boost::Tag(lens)));
+    if search_req.proximity_boost {
+        boosts.push(QueryBoost::new(Boost::Proximity));
     }
+    // Applied while collecting results, so boosted sources can bring in
+    // documents that would otherwise miss the top results.
+    for (domain, value) in lens_source_boosts(&state, &search_req.lenses).await {
+        boosts.push(QueryBoost {
+            field: Boost::Domain(domain),
+            value,
+        });
+    }
+
+    let mut filters = match search_filters(&state, &search_req.lenses, &search_req.tags).await? {
+        Some(filters) => filters,
+        // No document can have a tag that doesn't exist
+        None => {
+            return Ok(SearchResults {
+                results: Vec::new(),
+                meta: SearchMeta {
+                    query,
+                    num_docs: num_docs as u32,
+                    wall_time_ms: 0,
+                    next_cursor: None,
+                    related_queries: Vec::new(),
+                },
+                total_hits: search_req.page.map(|_| 0),
+                page: search_req.page,
+                num_pages: search_req.page.map(|_| 0),
+            })
+        }
+    };
 
-    if let Some(tag_id) = get_favorite_tag(&state.db).await {
+    let favorite_tag = get_favorite_tag(&state.db).await;
+    if let Some(tag_id) = favorite_tag {
         filters.push(QueryBoost::new(Boost::Favorite {
             id: tag_id,
             required: false,
         }));
     }
 
-    let search_result = state.index.search(&query, &filters, &boosts, 5).await;
+    let tag_weights = lens_tag_weights(&state).await;
+    let scoring = {
+        let settings = state.user_settings.load();
+        WeightedScoring {
+            recency_boost: settings.recency_boost,
+            favorite_boost: settings.favorite_boost,
+            tag_weights,
+            ..Default::default()
+        }
+    };
+
+    let max_limit = state
+        .user_settings
+        .load()
+        .max_search_results
+        .clamp(1, MAX_SEARCH_RESULTS);
+    let limit = search_req.limit.map(|limit| limit.clamp(1, max_limit));
+    let page_size = if search_req.page.is_some() {
+        limit.unwrap_or(DEFAULT_PAGE_LIMIT)
+    } else if search_req.collapse_by_domain || search_req.dedupe_titles {
+        COLLAPSED_PAGE_SIZE.max(limit.unwrap_or_default())
+    } else {
+        limit.unwrap_or(SEARCH_PAGE_SIZE)
+    };
+
+    let (search_result, total_hits) = match search_req.page {
+        Some(page) => {
+            let offset = page.saturating_mul(page_size);
+            if offset < MAX_PAGED_RESULTS {
+                let (result, total_hits) = index
+                    .search_page(
+                        &query,
+                        &filters,
+                        &boosts,
+                        offset,
+                        page_size.min(MAX_PAGED_RESULTS - offset),
+                    )
+                    .await;
+                (result, Some(total_hits))
+            } else {
+                // Past the cap, only count the hits
+                let (mut result, total_hits) =
+                    index.search_page(&query, &filters, &boosts, 0, 1).await;
+                result.documents.clear();
+                (result, Some(total_hits))
+            }
+        }
+        None if sort == SortMode::Relevance => (
+            index
+                .search_after(&query, &filters, &boosts, page_size, cursor.as_ref())
+                .await,
+            None,
+        ),
+        None => (
+            index
+                .search_sorted(&query, &filters, &boosts, page_size, sort)
+                .await,
+            None,
+        ),
+    };
+
+    // Cursors are based on the index score, before any re-ranking.
+    let next_cursor = if sort == SortMode::Relevance
+        && search_req.page.is_none()
+        && search_result.documents.len() == page_size
+    {
+        search_result
+            .documents
+            .last()
+            .map(|(score, doc)| SearchCursor::new(*score, &doc.doc_id).encode())
+    } else {
+        None
+    };
     log::debug!(
         "query {}: {} results from {} docs in {}ms",
         query,
@@ -69,8 +351,10 @@ pub async fn search_docs(
         search_result.wall_time_ms
     );
 
-    let mut results: Vec<SearchResult> = Vec::new();
+    let mut results: Vec<(f32, SearchResult)> = Vec::new();
     let mut missing: Vec<(String, String)> = Vec::new();
+    // Text of the top results, used for related queries
+    let mut related_texts: Vec<String> = Vec::new();
     for (score, doc) in search_result.documents {
         log::debug!("Got id with url {} {}", doc.doc_id, doc.url);
         let indexed = indexed_document::Entity::find()
@@ -78,7 +362,7 @@ pub async fn search_docs(
             .one(&state.db)
             .await;
 
-        let crawl_uri = doc.url;
+        let crawl_uri = doc.url.clone();
         match indexed {
             Ok(Some(indexed)) => {
                 let tags = indexed
@@ -96,24 +380,76 @@ pub async fn search_docs(
                     .tokenizer_for_field(fields.content)
                     .expect("Unable to get tokenizer for content field");
 
-                let description = spyglass_searcher::utils::generate_highlight_preview(
-                    &tokenizer,
-                    &query,
-                    &doc.content,
+                // Prefer the main article text over the full page content,
+                // falling back to the description when there's no content or
+                // nothing in it matches.
+                let snippet_source = if doc.body.is_empty() {
+                    &doc.content
+                } else {
+                    &doc.body
+                };
+                if search_req.related_queries && related_texts.len() < RELATED_QUERY_DOCS {
+                    related_texts.push(
+                        snippet_source
+                            .chars()
+                            .take(RELATED_QUERY_TEXT_LEN)
+                            .collect(),
+                    );
+                }
+                let description = if highlight_fields.iter().any(|f| f == HIGHLIGHT_CONTENT) {
+                    match search_req.snippet_strategy {
+                        SnippetStrategy::BestMatch => utils::generate_best_match_snippet(
+                            &tokenizer,
+                            &query,
+                            snippet_source,
+                            &highlight_tag,
+                        ),
+                        SnippetStrategy::FirstMatch => utils::generate_first_match_snippet(
+                            &tokenizer,
+                            &query,
+                            snippet_source,
+                            &highlight_tag,
+                        ),
+                        SnippetStrategy::Description => None,
+                    }
+                    .unwrap_or_else(|| doc.description.clone())
+                } else {
+                    doc.description.clone()
+                };
+
+                let title = if highlight_fields.iter().any(|f| f == HIGHLIGHT_TITLE) {
+                    utils::generate_highlight_text(&tokenizer, &query, &doc.title, &highlight_tag)
+                } else {
+                    doc.title.clone()
+                };
+
+                // Re-rank using document metadata
+                let score = scoring.adjust(
+                    score,
+                    &RankingSignals {
+                        doc: &doc,
+                        last_updated: Some(indexed.updated_at),
+                        is_favorite: favorite_tag
+                            .map(|tag_id| doc.tags.contains(&tag_id))
+                            .unwrap_or_default(),
+                    },
                 );
 
                 let result = SearchResult {
                     doc_id: doc.doc_id.clone(),
                     domain: doc.domain,
-                    title: doc.title,
+                    title,
                     crawl_uri: crawl_uri.clone(),
                     description,
                     url: indexed.open_url.unwrap_or(crawl_uri),
                     tags,
                     score,
+                    note: None,
+                    remaining_count: 0,
+                    duplicate_title_count: 0,
                 };
 
-                results.push(result);
+                results.push((score, result));
             }
             _ => {
                 missing.push((doc.doc_id.to_owned(), crawl_uri.to_owned()));
@@ -121,15 +457,62 @@ pub async fn search_docs(
         }
     }
 
-    let wall_time_ms = SystemTime::now()
-        .duration_since(start)
-        .map_or_else(|_| 0, |duration| duration.as_millis() as u64);
+    // Results are already in order when using any other sort mode
+    if sort == SortMode::Relevance {
+        ranking::sort_by_score(&mut results);
+    }
+    let mut results: Vec<SearchResult> = results.into_iter().map(|(_, result)| result).collect();
+    if search_req.dedupe_titles {
+        results = dedupe_titles(results);
+    }
+    if search_req.collapse_by_domain {
+        results = collapse_by_domain(results, COLLAPSED_RESULTS_PER_DOMAIN);
+    }
+    // Extra results were fetched to make up for the collapsed ones
+    if search_req.page.is_none() && (search_req.collapse_by_domain || search_req.dedupe_titles) {
+        if let Some(limit) = limit {
+            results.truncate(limit);
+        } else if !search_req.collapse_by_domain {
+            results.truncate(SEARCH_PAGE_SIZE);
+        }
+    }
+
+    if search_req.include_notes {
+        let urls = results
+            .iter()
+            .map(|result| result.crawl_uri.clone())
+            .collect::<Vec<String>>();
+        match document_note::get_notes(&state.db, &urls).await {
+            Ok(notes) => {
+                for result in results.iter_mut() {
+                    result.note = notes.get(&result.crawl_uri).cloned();
+                }
+            }
+            Err(err) => log::warn!("Unable to fetch document notes: {err}"),
+        }
+    }
+
+    let wall_time_ms = start.elapsed().as_millis() as u64;
+
+    let related_queries = if search_req.related_queries {
+        let fields = DocFields::as_fields();
+        match index.index.tokenizer_for_field(fields.content) {
+            Ok(tokenizer) => suggest_related_queries(&tokenizer, &query, &related_texts),
+            Err(err) => {
+                log::warn!("Unable to suggest related queries: {err}");
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
 
-    let num_docs = searcher.num_docs();
     let meta = SearchMeta {
         query: search_req.query.clone(),
         num_docs: num_docs as u32,
-        wall_time_ms: wall_time_ms as u32,
+        wall_time_ms,
+        next_cursor,
+        related_queries,
     };
 
     let domains: HashSet<String> = HashSet::from_iter(results.iter().map(|r| r.domain.clone()));
@@ -157,7 +540,373 @@ pub async fn search_docs(
         }
     }
 
-    Ok(SearchResults { results, meta })
+    Ok(SearchResults {
+        results,
+        meta,
+        total_hits,
+        page: search_req.page,
+        num_pages: total_hits
+            .map(|total_hits| total_hits.min(MAX_PAGED_RESULTS).div_ceil(page_size)),
+    })
+}
+
+/// Suggests queries related to `query`: the query plus a term that shows up in
+/// several of the top results' `texts`. Terms are compared after tokenizing,
+/// so stop words, the query's own terms & variations of them are skipped.
+fn suggest_related_queries(tokenizer: &TextAnalyzer, query: &str, texts: &[String]) -> Vec<String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut query_terms = HashSet::new();
+    let mut stream = tokenizer.token_stream(query);
+    while stream.advance() {
+        query_terms.insert(stream.token().text.clone());
+    }
+
+    // Term -> (number of results it's in, count of each spelling of it)
+    let mut terms: HashMap<String, (usize, HashMap<String, usize>)> = HashMap::new();
+    for text in texts {
+        let mut seen = HashSet::new();
+        let mut stream = tokenizer.token_stream(text);
+        while stream.advance() {
+            let token = stream.token();
+            let word = text[token.offset_from..token.offset_to].to_lowercase();
+            if word.chars().count() < 3
+                || word.chars().all(|c| c.is_numeric())
+                || query_terms.contains(&token.text)
+            {
+                continue;
+            }
+
+            let (num_docs, spellings) = terms.entry(token.text.clone()).or_default();
+            if seen.insert(token.text.clone()) {
+                *num_docs += 1;
+            }
+            *spellings.entry(word).or_default() += 1;
+        }
+    }
+
+    // Rank terms found in more than one result by how many results they're
+    // in, then by how often they're used.
+    let mut ranked = terms
+        .into_values()
+        .filter(|(num_docs, _)| *num_docs > 1)
+        .filter_map(|(num_docs, spellings)| {
+            let count: usize = spellings.values().sum();
+            let word = spellings
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .map(|(word, _)| word)?;
+            Some((num_docs, count, word))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+
+    ranked
+        .into_iter()
+        .take(MAX_RELATED_QUERIES)
+        .map(|(_, _, word)| format!("{query} {word}"))
+        .collect()
+}
+
+/// Keeps the first `per_domain` results from each domain, preserving the
+/// overall ordering. The last kept result for a domain notes how many results
+/// from that domain were dropped.
+fn collapse_by_domain(results: Vec<SearchResult>, per_domain: usize) -> Vec<SearchResult> {
+    let mut kept: Vec<SearchResult> = Vec::new();
+    // Domain -> (number of results shown, index of the last shown result)
+    let mut shown: HashMap<String, (usize, usize)> = HashMap::new();
+    for result in results {
+        match shown.get_mut(&result.domain) {
+            Some((count, last_idx)) if *count >= per_domain => {
+                kept[*last_idx].remaining_count += 1;
+            }
+            Some((count, last_idx)) => {
+                *count += 1;
+                *last_idx = kept.len();
+                kept.push(result);
+            }
+            None => {
+                shown.insert(result.domain.clone(), (1, kept.len()));
+                kept.push(result);
+            }
+        }
+    }
+
+    kept
+}
+
+/// Collapses runs of consecutive results with the same title into the first
+/// (highest scoring) one, which notes how many results were dropped.
+fn dedupe_titles(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut kept: Vec<SearchResult> = Vec::new();
+    for result in results {
+        match kept.last_mut() {
+            Some(last) if last.title == result.title => last.duplicate_title_count += 1,
+            _ => kept.push(result),
+        }
+    }
+
+    kept
+}
+
+/// Count the documents matching a query, optionally broken down by domain,
+/// without retrieving any of them.
+#[instrument(skip(state))]
+pub async fn search_count(
+    state: AppState,
+    search_req: request::SearchCountParam,
+) -> Result<SearchCountResult, Error> {
+    let start = Instant::now();
+    let filters = lens_filters(&state, &search_req.lenses).await;
+    let (count, domains) = state
+        .index
+        .count(&search_req.query, &filters, &search_req.domains);
+
+    let wall_time_ms = start.elapsed().as_millis() as u64;
+
+    Ok(SearchCountResult {
+        query: search_req.query,
+        count,
+        domains,
+        wall_time_ms: wall_time_ms as u32,
+    })
+}
+
+/// Matches counted before an estimate gives up, shown as e.g. "1000+".
+const ESTIMATE_COUNT_CAP: usize = 1_000;
+
+/// Estimate how many documents match a query. Counting stops at a cap, so it's
+/// cheap enough to run as the user types.
+#[instrument(skip(state))]
+pub async fn search_estimate(
+    state: AppState,
+    search_req: request::SearchEstimateParam,
+) -> Result<SearchEstimateResult, Error> {
+    let start = Instant::now();
+    let filters = lens_filters(&state, &search_req.lenses).await;
+    let (count, is_capped) =
+        state
+            .index
+            .estimate_count(&search_req.query, &filters, ESTIMATE_COUNT_CAP);
+
+    let wall_time_ms = start.elapsed().as_millis() as u64;
+
+    Ok(SearchEstimateResult {
+        query: search_req.query,
+        count,
+        is_capped,
+        wall_time_ms: wall_time_ms as u32,
+    })
+}
+
+/// Max number of documents that can be tagged in a single request.
+const MAX_BULK_TAG_DOCS: usize = 1_000;
+
+/// Tag all documents matching the query. Requires `confirm` to be set, otherwise
+/// returns an error with the number of documents that would be tagged.
+#[instrument(skip(state))]
+pub async fn tag_documents(
+    state: AppState,
+    req: request::TagDocumentsRequest,
+) -> Result<usize, Error> {
+    let (label, value) = &req.tag;
+    if label.trim().is_empty() || value.trim().is_empty() {
+        return Err(Error::Custom("Tag label and value are required".into()));
+    }
+
+    let docs = state
+        .index
+        .search(&req.query, &[], &[], MAX_BULK_TAG_DOCS)
+        .await
+        .documents
+        .into_iter()
+        .map(|(_, doc)| doc)
+        .collect::<Vec<_>>();
+
+    if !req.confirm {
+        return Err(Error::Custom(format!(
+            "Tagging {} documents requires confirmation",
+            docs.len()
+        )));
+    }
+
+    if docs.is_empty() {
+        return Ok(0);
+    }
+
+    let modification = TagModification {
+        add: Some(vec![req.tag.clone()]),
+        remove: None,
+    };
+    documents::update_tags(&state, &docs, &modification)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to tag documents: {err}")))?;
+
+    Ok(docs.len())
+}
+
+/// Max number of similar documents returned for a single document.
+const MAX_SIMILAR_RESULTS: usize = 10;
+
+/// Find indexed documents similar to the document at `url`
+#[instrument(skip(state))]
+pub async fn similar_documents(state: AppState, url: String) -> Result<SearchResults, Error> {
+    let start = Instant::now();
+
+    let source = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.eq(url.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    let source = match source {
+        Some(source) => source,
+        None => return Err(Error::Custom(format!("Document not found: {url}"))),
+    };
+
+    let similar = match state
+        .index
+        .more_like_this(&source.doc_id, MAX_SIMILAR_RESULTS)
+        .await
+    {
+        Some(similar) => similar,
+        None => return Err(Error::Custom(format!("Document not found: {url}"))),
+    };
+
+    let mut results: Vec<SearchResult> = Vec::new();
+    for (score, doc) in similar {
+        let indexed = indexed_document::Entity::find()
+            .filter(indexed_document::Column::DocId.eq(doc.doc_id.clone()))
+            .one(&state.db)
+            .await;
+
+        if let Ok(Some(indexed)) = indexed {
+            let tags = indexed
+                .find_related(tag::Entity)
+                .all(&state.db)
+                .await
+                .unwrap_or_default()
+                .iter()
+                .map(|tag| (tag.label.to_string(), tag.value.clone()))
+                .collect::<Vec<(String, String)>>();
+
+            results.push(SearchResult {
+                doc_id: doc.doc_id,
+                domain: doc.domain,
+                title: doc.title,
+                crawl_uri: doc.url.clone(),
+                description: doc.description,
+                url: indexed.open_url.unwrap_or(doc.url),
+                tags,
+                score,
+                note: None,
+                remaining_count: 0,
+                duplicate_title_count: 0,
+            });
+        }
+    }
+
+    let wall_time_ms = start.elapsed().as_millis() as u64;
+
+    let meta = SearchMeta {
+        query: url,
+        num_docs: state.index.reader.searcher().num_docs() as u32,
+        wall_time_ms,
+        next_cursor: None,
+        related_queries: Vec::new(),
+    };
+
+    Ok(SearchResults {
+        results,
+        meta,
+        total_hits: None,
+        page: None,
+        num_pages: None,
+    })
+}
+
+/// Max number of documents returned when looking up documents by URL.
+const MAX_URL_RESULTS: u64 = 100;
+
+/// Find indexed documents by URL, matching `pattern` exactly or as a substring.
+#[instrument(skip(state))]
+pub async fn search_by_url(
+    state: AppState,
+    pattern: String,
+    exact: bool,
+) -> Result<SearchResults, Error> {
+    let start = Instant::now();
+
+    let pattern = pattern.trim().to_string();
+    if pattern.is_empty() {
+        return Err(Error::Custom("URL pattern cannot be empty".to_string()));
+    }
+
+    let filter = if exact {
+        indexed_document::Column::Url.eq(pattern.clone())
+    } else {
+        indexed_document::Column::Url.contains(&pattern)
+    };
+
+    let matches = indexed_document::Entity::find()
+        .filter(filter)
+        .order_by_asc(indexed_document::Column::Url)
+        .limit(MAX_URL_RESULTS)
+        .all(&state.db)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to query db: {err}")))?;
+
+    let mut results: Vec<SearchResult> = Vec::new();
+    for indexed in matches {
+        let doc = match state.index.get(&indexed.doc_id).await {
+            Some(doc) => doc,
+            None => continue,
+        };
+
+        let tags = indexed
+            .find_related(tag::Entity)
+            .all(&state.db)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|tag| (tag.label.to_string(), tag.value.clone()))
+            .collect::<Vec<(String, String)>>();
+
+        results.push(SearchResult {
+            doc_id: doc.doc_id,
+            domain: doc.domain,
+            title: doc.title,
+            crawl_uri: doc.url.clone(),
+            description: doc.description,
+            url: indexed.open_url.unwrap_or(doc.url),
+            tags,
+            score: 1.0,
+            note: None,
+            remaining_count: 0,
+            duplicate_title_count: 0,
+        });
+    }
+
+    let wall_time_ms = start.elapsed().as_millis() as u64;
+
+    let meta = SearchMeta {
+        query: pattern,
+        num_docs: state.index.reader.searcher().num_docs() as u32,
+        wall_time_ms,
+        next_cursor: None,
+        related_queries: Vec::new(),
+    };
+
+    Ok(SearchResults {
+        results,
+        meta,
+        total_hits: None,
+        page: None,
+        num_pages: None,
+    })
 }
 
 #[derive(FromQueryResult)]
@@ -208,3 +957,310 @@ pub async fn search_lenses(
 
     Ok(SearchLensesResp { results })
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        collapse_by_domain, dedupe_titles, lens_source_boosts, search_docs, suggest_related_queries,
+    };
+    use entities::models::{indexed_document, lens_source_boost};
+    use entities::sea_orm::{ActiveModelTrait, Set};
+    use entities::test::setup_test_db;
+    use libspyglass::state::AppState;
+    use shared::config::{LensConfig, UserSettings};
+    use shared::request::SearchParam;
+    use shared::response::SearchResult;
+    use spyglass_searcher::schema::{DocFields, DocumentUpdate, SearchDocument, ToDocument};
+    use spyglass_searcher::{IndexBackend, SearchCursor, WriteTrait};
+    use std::collections::HashMap;
+    use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+
+    fn result(domain: &str, title: &str) -> SearchResult {
+        SearchResult {
+            doc_id: title.to_string(),
+            crawl_uri: format!("https://{domain}/{title}"),
+            domain: domain.to_string(),
+            title: title.to_string(),
+            description: String::new(),
+            url: format!("https://{domain}/{title}"),
+            tags: Vec::new(),
+            score: 1.0,
+            note: None,
+            remaining_count: 0,
+            duplicate_title_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_collapse_by_domain() {
+        let results = vec![
+            result("example.com", "a"),
+            result("example.com", "b"),
+            result("other.com", "c"),
+            result("example.com", "d"),
+            result("example.com", "e"),
+            result("example.com", "f"),
+        ];
+
+        let collapsed = collapse_by_domain(results, 2);
+        let titles = collapsed
+            .iter()
+            .map(|r| r.title.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(titles, vec!["a", "b", "c"]);
+        assert_eq!(collapsed[0].remaining_count, 0);
+        assert_eq!(collapsed[1].remaining_count, 3);
+        assert_eq!(collapsed[2].remaining_count, 0);
+    }
+
+    #[test]
+    fn test_dedupe_titles() {
+        let results = vec![
+            result("example.com", "Home"),
+            result("example.com", "Home"),
+            result("example.com", "Home"),
+            result("example.com", "About"),
+            result("example.com", "Home"),
+        ];
+
+        let deduped = dedupe_titles(results);
+        let titles = deduped
+            .iter()
+            .map(|r| r.title.as_str())
+            .collect::<Vec<&str>>();
+        // Only consecutive duplicates are collapsed
+        assert_eq!(titles, vec!["Home", "About", "Home"]);
+        assert_eq!(deduped[0].duplicate_title_count, 2);
+        assert_eq!(deduped[1].duplicate_title_count, 0);
+        assert_eq!(deduped[2].duplicate_title_count, 0);
+    }
+
+    #[test]
+    fn test_suggest_related_queries() {
+        let tokenizer = TextAnalyzer::from(SimpleTokenizer).filter(LowerCaser);
+        let texts = vec![
+            "Rust ownership and borrowing explained. Borrowing rules 2023".to_string(),
+            "Ownership in Rust: moves, borrowing & lifetimes".to_string(),
+            "Lifetimes are part of the borrowing story in rust".to_string(),
+        ];
+
+        assert_eq!(
+            suggest_related_queries(&tokenizer, "rust", &texts),
+            vec![
+                "rust borrowing".to_string(),
+                "rust lifetimes".to_string(),
+                "rust ownership".to_string(),
+            ]
+        );
+        assert!(suggest_related_queries(&tokenizer, " ", &texts).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_docs_wall_time() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&UserSettings {
+                disable_telemetry: true,
+                ..Default::default()
+            })
+            .with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .build();
+
+        let doc_id = state
+            .index
+            .upsert(
+                &DocumentUpdate {
+                    doc_id: None,
+                    title: "Cannery Row",
+                    description: "",
+                    domain: "example.com",
+                    url: "https://example.com/cannery_row",
+                    content: "Cannery Row in Monterey in California is a poem",
+                    tags: &[],
+                    anchor_text: &[],
+                    code: &[],
+                    body: None,
+                    published_at: None,
+                    last_modified: None,
+                }
+                .to_document(),
+            )
+            .await
+            .expect("Unable to add doc");
+        state.index.save().await.expect("Unable to save index");
+        indexed_document::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/cannery_row".into()),
+            doc_id: Set(doc_id),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert doc");
+
+        let results = search_docs(
+            state,
+            SearchParam {
+                query: "monterey".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Unable to search");
+        assert_eq!(results.results.len(), 1);
+        assert!(results.meta.wall_time_ms < 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_lens_source_boosts() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        lens_source_boost::set_boost(&db, "docs", "docs.example.com", 3.0)
+            .await
+            .expect("Unable to set boost");
+        lens_source_boost::set_boost(&db, "docs", "blog.example.com", 0.5)
+            .await
+            .expect("Unable to set boost");
+        lens_source_boost::set_boost(&db, "other", "docs.example.com", 4.0)
+            .await
+            .expect("Unable to set boost");
+
+        // Only lens searches are boosted
+        assert!(lens_source_boosts(&state, &[]).await.is_empty());
+
+        let boosts = lens_source_boosts(&state, &["docs".into()]).await;
+        assert_eq!(boosts.get("docs.example.com"), Some(&2.0));
+        assert_eq!(boosts.get("blog.example.com"), Some(&-0.5));
+
+        // The largest boost wins across lenses
+        let boosts = lens_source_boosts(&state, &["docs".into(), "other".into()]).await;
+        assert_eq!(boosts.get("docs.example.com"), Some(&3.0));
+        // Boosts from the lens definition apply unless set locally
+        state.lenses.insert(
+            "docs".into(),
+            LensConfig {
+                name: "docs".into(),
+                source_boosts: HashMap::from([
+                    ("docs.example.com".to_string(), 1.5),
+                    ("api.example.com".to_string(), 10.0),
+                ]),
+                ..Default::default()
+            },
+        );
+        let boosts = lens_source_boosts(&state, &["docs".into()]).await;
+        assert_eq!(boosts.get("docs.example.com"), Some(&2.0));
+        assert_eq!(boosts.get("api.example.com"), Some(&4.0));
+    }
+
+    #[tokio::test]
+    async fn test_search_docs_pages() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&UserSettings {
+                disable_telemetry: true,
+                max_search_results: 2,
+                ..Default::default()
+            })
+            .with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .build();
+
+        for idx in 0..3 {
+            let url = format!("https://example.com/monterey_{idx}");
+            let doc_id = state
+                .index
+                .upsert(
+                    &DocumentUpdate {
+                        doc_id: None,
+                        title: "Cannery Row",
+                        description: "",
+                        domain: "example.com",
+                        url: &url,
+                        content: "Cannery Row in Monterey in California is a poem",
+                        tags: &[],
+                        anchor_text: &[],
+                        code: &[],
+                        body: None,
+                        published_at: None,
+                        last_modified: None,
+                    }
+                    .to_document(),
+                )
+                .await
+                .expect("Unable to add doc");
+            indexed_document::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(url),
+                doc_id: Set(doc_id),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert doc");
+        }
+        state.index.save().await.expect("Unable to save index");
+
+        let search_page = |page: usize, limit: usize| {
+            search_docs(
+                state.clone(),
+                SearchParam {
+                    query: "monterey".into(),
+                    page: Some(page),
+                    limit: Some(limit),
+                    ..Default::default()
+                },
+            )
+        };
+
+        let first = search_page(0, 2).await.expect("Unable to search");
+        assert_eq!(first.results.len(), 2);
+        assert_eq!(first.total_hits, Some(3));
+        assert_eq!(first.page, Some(0));
+        assert_eq!(first.num_pages, Some(2));
+        assert_eq!(first.meta.next_cursor, None);
+
+        // Partially filled last page
+        let last = search_page(1, 2).await.expect("Unable to search");
+        assert_eq!(last.results.len(), 1);
+        assert!(first
+            .results
+            .iter()
+            .all(|result| result.doc_id != last.results[0].doc_id));
+
+        // Single result pages
+        let single = search_page(2, 1).await.expect("Unable to search");
+        assert_eq!(single.results.len(), 1);
+        assert_eq!(single.num_pages, Some(3));
+
+        // Limits past max_search_results are capped
+        let capped = search_page(0, 10).await.expect("Unable to search");
+        assert_eq!(capped.results.len(), 2);
+        assert_eq!(capped.num_pages, Some(2));
+
+        // Past the last page
+        let empty = search_page(2, 2).await.expect("Unable to search");
+        assert!(empty.results.is_empty());
+        assert_eq!(empty.total_hits, Some(3));
+
+        // Past the paging cap
+        let deep = search_page(usize::MAX, 2).await.expect("Unable to search");
+        assert!(deep.results.is_empty());
+        assert_eq!(deep.total_hits, Some(3));
+        assert_eq!(deep.num_pages, Some(2));
+
+        // Pages can't be combined w/ cursors
+        let res = search_docs(
+            state.clone(),
+            SearchParam {
+                query: "monterey".into(),
+                page: Some(0),
+                cursor: Some(SearchCursor::new(1.0, "doc").encode()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(res.is_err());
+    }
+}
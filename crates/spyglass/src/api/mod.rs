@@ -2,22 +2,30 @@ use crate::task::lens::install_lens;
 use entities::get_library_stats;
 use entities::models::indexed_document;
 use entities::sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter};
+use export::SearchExportLayer;
 use jsonrpsee::core::{async_trait, Error, JsonValue};
 use jsonrpsee::server::middleware::proxy_get_request::ProxyGetRequestLayer;
 use jsonrpsee::server::{ServerBuilder, ServerHandle};
 use jsonrpsee::types::{SubscriptionEmptyError, SubscriptionResult};
 use jsonrpsee::SubscriptionSink;
+use libspyglass::crawler::favicon;
 use libspyglass::state::AppState;
 use libspyglass::task::{CollectTask, ManagerCommand};
-use shared::config::{Config, UserSettings};
-use shared::request::{BatchDocumentRequest, RawDocumentRequest, SearchLensesParam, SearchParam};
-use shared::response::{self as resp, DefaultIndices, LibraryStats};
+use rate_limit::{PeerAddrs, PeerLogger, RateLimitLayer};
+use shared::config::{Config, LensConfig, UserSettings};
+use shared::request::{
+    BatchDocumentRequest, RawDocumentRequest, RecentDocumentsParam, SavedQueryParam,
+    SearchCountParam, SearchEstimateParam, SearchLensesParam, SearchParam, TagDocumentsRequest,
+};
+use shared::response::{self as resp, DefaultIndices, DocumentChanges, LibraryStats};
 use spyglass_rpc::{RpcEventType, RpcServer};
 use spyglass_searcher::WriteTrait;
 use std::collections::{HashMap, HashSet};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
+mod export;
 mod handler;
+mod rate_limit;
 mod response;
 
 pub struct SpyglassRpc {
@@ -56,6 +64,11 @@ impl RpcServer for SpyglassRpc {
         Ok(handler::default_indices().await)
     }
 
+    /// Crawl a single URL right away & wait for the result
+    async fn crawl_url_now(&self, url: String) -> Result<resp::CrawlUrlResult, Error> {
+        handler::crawl_url_now(self.state.clone(), url).await
+    }
+
     /// Delete a single doc
     async fn delete_document(&self, id: String) -> Result<(), Error> {
         handler::delete_document(self.state.clone(), id).await
@@ -73,6 +86,60 @@ impl RpcServer for SpyglassRpc {
         }
     }
 
+    async fn remove_indexed_document(&self, url: String) -> Result<(), Error> {
+        handler::remove_indexed_document(self.state.clone(), url).await
+    }
+
+    async fn block_document(&self, id: String) -> Result<(), Error> {
+        handler::block_document(self.state.clone(), id).await
+    }
+
+    async fn block_url(&self, url: String) -> Result<(), Error> {
+        handler::block_url(self.state.clone(), url).await
+    }
+
+    async fn unblock_url(&self, url: String) -> Result<(), Error> {
+        handler::unblock_url(self.state.clone(), url).await
+    }
+
+    async fn blocked_urls(&self) -> Result<Vec<String>, Error> {
+        handler::blocked_urls(self.state.clone()).await
+    }
+
+    async fn get_document_note(&self, url: String) -> Result<Option<String>, Error> {
+        handler::get_document_note(self.state.clone(), url).await
+    }
+
+    async fn set_document_note(&self, url: String, note: String) -> Result<(), Error> {
+        handler::set_document_note(self.state.clone(), url, note).await
+    }
+
+    async fn document_changes(&self, url: String) -> Result<Option<DocumentChanges>, Error> {
+        handler::document_changes(self.state.clone(), url).await
+    }
+
+    async fn get_indexed_document(
+        &self,
+        url: String,
+    ) -> Result<resp::IndexedDocumentResult, Error> {
+        handler::get_indexed_document(self.state.clone(), url).await
+    }
+
+    async fn recent_documents(
+        &self,
+        params: RecentDocumentsParam,
+    ) -> Result<Vec<resp::IndexedDocumentResult>, Error> {
+        handler::recent_documents(self.state.clone(), params).await
+    }
+
+    async fn tag_documents(&self, req: TagDocumentsRequest) -> Result<usize, Error> {
+        handler::search::tag_documents(self.state.clone(), req).await
+    }
+
+    async fn favicon(&self, domain: String) -> Result<String, Error> {
+        Ok(favicon::favicon_data_url(&self.state.config, &domain))
+    }
+
     async fn get_library_stats(&self) -> Result<HashMap<String, LibraryStats>, Error> {
         match get_library_stats(&self.state.db).await {
             Ok(stats) => Ok(stats),
@@ -110,6 +177,46 @@ impl RpcServer for SpyglassRpc {
         }
     }
 
+    async fn lens_definition(&self, name: String) -> Result<LensConfig, Error> {
+        handler::lens_definition(self.state.clone(), name).await
+    }
+
+    async fn lens_stats(&self, name: String) -> Result<resp::LensCrawlStats, Error> {
+        handler::lens_stats(self.state.clone(), name).await
+    }
+
+    async fn recrawl_lens(&self, name: String) -> Result<String, Error> {
+        handler::recrawl_lens(self.state.clone(), name).await
+    }
+
+    async fn lens_recrawl_progress(
+        &self,
+        name: String,
+        job_id: String,
+    ) -> Result<resp::LensRecrawlProgress, Error> {
+        handler::lens_recrawl_progress(self.state.clone(), name, job_id).await
+    }
+
+    async fn reset_lens(
+        &self,
+        name: String,
+        remove_documents: bool,
+    ) -> Result<resp::LensResetResult, Error> {
+        handler::reset_lens(self.state.clone(), name, remove_documents).await
+    }
+
+    async fn rebuild_lens_index(&self, name: String) -> Result<usize, Error> {
+        handler::rebuild_lens_index(self.state.clone(), name).await
+    }
+
+    async fn update_lens_definition(
+        &self,
+        name: String,
+        definition: LensConfig,
+    ) -> Result<(), Error> {
+        handler::update_lens_definition(self.state.clone(), &self.config, name, definition).await
+    }
+
     async fn list_connections(&self) -> Result<resp::ListConnectionResult, Error> {
         handler::list_connections(self.state.clone()).await
     }
@@ -175,6 +282,48 @@ impl RpcServer for SpyglassRpc {
         handler::search::search_docs(self.state.clone(), query).await
     }
 
+    async fn search_count(
+        &self,
+        query: SearchCountParam,
+    ) -> Result<resp::SearchCountResult, Error> {
+        handler::search::search_count(self.state.clone(), query).await
+    }
+
+    async fn search_estimate(
+        &self,
+        query: SearchEstimateParam,
+    ) -> Result<resp::SearchEstimateResult, Error> {
+        handler::search::search_estimate(self.state.clone(), query).await
+    }
+
+    async fn similar_documents(&self, url: String) -> Result<resp::SearchResults, Error> {
+        handler::search::similar_documents(self.state.clone(), url).await
+    }
+
+    async fn queue_tree(&self, domain: Option<String>) -> Result<resp::QueueTreeNode, Error> {
+        handler::queue::queue_tree(self.state.clone(), domain).await
+    }
+
+    async fn failed_crawls(&self, domain: Option<String>) -> Result<Vec<resp::FailedCrawl>, Error> {
+        handler::queue::failed_crawls(self.state.clone(), domain).await
+    }
+
+    async fn retry_failed_crawl(&self, id: i64) -> Result<(), Error> {
+        handler::queue::retry_failed_crawl(self.state.clone(), id).await
+    }
+
+    async fn delete_queue_entry(&self, id: i64) -> Result<(), Error> {
+        handler::queue::delete_queue_entry(self.state.clone(), id).await
+    }
+
+    async fn search_by_url(
+        &self,
+        pattern: String,
+        exact: bool,
+    ) -> Result<resp::SearchResults, Error> {
+        handler::search::search_by_url(self.state.clone(), pattern, exact).await
+    }
+
     async fn search_lenses(
         &self,
         query: SearchLensesParam,
@@ -182,15 +331,72 @@ impl RpcServer for SpyglassRpc {
         handler::search::search_lenses(self.state.clone(), query).await
     }
 
+    async fn add_saved_query(
+        &self,
+        query: SavedQueryParam,
+    ) -> Result<resp::SavedQueryResult, Error> {
+        handler::saved_query::add_saved_query(self.state.clone(), query).await
+    }
+
+    async fn list_saved_queries(&self) -> Result<Vec<resp::SavedQueryResult>, Error> {
+        handler::saved_query::list_saved_queries(self.state.clone()).await
+    }
+
+    async fn delete_saved_query(&self, id: i64) -> Result<(), Error> {
+        handler::saved_query::delete_saved_query(self.state.clone(), id).await
+    }
+
+    async fn vacuum_database(&self) -> Result<resp::VacuumStatus, Error> {
+        handler::vacuum_database(self.state.clone()).await
+    }
+
+    async fn reindex(&self) -> Result<(), Error> {
+        handler::reindex(self.state.clone()).await
+    }
+
     async fn toggle_pause(&self, is_paused: bool) -> Result<(), Error> {
         handler::toggle_pause(self.state.clone(), is_paused).await
     }
 
+    async fn pause_lens(&self, name: String, is_paused: bool) -> Result<(), Error> {
+        handler::pause_lens(self.state.clone(), name, is_paused).await
+    }
+
+    async fn set_lens_weight(&self, name: String, weight: u32) -> Result<(), Error> {
+        handler::set_lens_weight(self.state.clone(), name, weight).await
+    }
+
+    async fn set_lens_source_boost(
+        &self,
+        name: String,
+        domain: String,
+        boost: f32,
+    ) -> Result<(), Error> {
+        handler::set_lens_source_boost(self.state.clone(), name, domain, boost).await
+    }
+
+    async fn add_lens_folder(
+        &self,
+        name: String,
+        path: String,
+        extensions: Vec<String>,
+    ) -> Result<(), Error> {
+        handler::add_lens_folder(self.state.clone(), name, path, extensions).await
+    }
+
+    async fn import_bookmarks(
+        &self,
+        name: String,
+        path: String,
+    ) -> Result<resp::ImportBookmarksResult, Error> {
+        handler::import_bookmarks(self.state.clone(), name, path).await
+    }
+
     async fn toggle_plugin(&self, name: String, enabled: bool) -> Result<(), Error> {
         handler::toggle_plugin(self.state.clone(), name, enabled).await
     }
 
-    async fn uninstall_lens(&self, name: String) -> Result<(), Error> {
+    async fn uninstall_lens(&self, name: String) -> Result<usize, Error> {
         handler::uninstall_lens(self.state.clone(), &self.config, &name).await
     }
 
@@ -257,15 +463,25 @@ pub async fn start_api_server(
     state: AppState,
     config: Config,
 ) -> anyhow::Result<(SocketAddr, ServerHandle)> {
-    let middleware = tower::ServiceBuilder::new().layer(
-        ProxyGetRequestLayer::new("/health", "spyglass_system_health")
-            .expect("Unable to create middleware"),
-    );
+    let settings = state.user_settings.load_full();
+    let peers = PeerAddrs::default();
+    let middleware = tower::ServiceBuilder::new()
+        .layer(RateLimitLayer::new(
+            settings.api_rate_limit_per_minute,
+            &settings.api_rate_limited_methods,
+            peers.clone(),
+        ))
+        .layer(SearchExportLayer::new(state.clone()))
+        .layer(
+            ProxyGetRequestLayer::new("/health", "spyglass_system_health")
+                .expect("Unable to create middleware"),
+        );
 
     let ip = addr.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
-    let server_addr = SocketAddr::new(ip, state.user_settings.load_full().port);
+    let server_addr = SocketAddr::new(ip, settings.port);
 
     let server = ServerBuilder::default()
+        .set_logger(PeerLogger::new(peers))
         .set_middleware(middleware)
         .build(server_addr)
         .await?;
@@ -0,0 +1,289 @@
+use bytes::Bytes;
+use dashmap::DashMap;
+use governor::clock::{Clock, QuantaClock};
+use governor::state::keyed::DashMapStateStore;
+use governor::{Quota, RateLimiter};
+use hyper::body::HttpBody;
+use hyper::header::{HeaderValue, RETRY_AFTER};
+use hyper::{Body, Request, Response, StatusCode};
+use jsonrpsee::server::logger::{HttpRequest, Logger, MethodKind, Params, TransportProtocol};
+use std::collections::HashSet;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::num::NonZeroU32;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use super::export::EXPORT_PATH;
+
+/// Largest request body read, the same limit the RPC server applies.
+pub const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+/// Number of clients tracked before clients that are no longer limited are
+/// dropped, so a flood of new clients can't grow the limiter state forever.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+/// Key used for requests whose peer address isn't known, i.e. exports, which
+/// are answered before they reach the RPC server.
+const UNKNOWN_CLIENT: &str = "unknown";
+/// Matches a request up w/ the peer address the RPC server logs for it, see
+/// `PeerLogger`. Always overwritten, so it can't be set by clients.
+const REQUEST_ID_HEADER: &str = "x-spyglass-request-id";
+
+type ClientLimiter = RateLimiter<String, DashMapStateStore<String>, QuantaClock>;
+
+/// Peer addresses of requests being checked by the rate limiter, by request
+/// id. jsonrpsee only hands the peer address to its logger.
+#[derive(Clone, Default)]
+pub struct PeerAddrs {
+    next_id: Arc<AtomicU64>,
+    addrs: Arc<DashMap<u64, SocketAddr>>,
+}
+
+/// Records the peer address of requests tagged by the rate limiter. Called by
+/// the RPC server as soon as a request is handed to it, before it's run.
+#[derive(Clone)]
+pub struct PeerLogger {
+    peers: PeerAddrs,
+}
+
+impl PeerLogger {
+    pub fn new(peers: PeerAddrs) -> Self {
+        Self { peers }
+    }
+}
+
+impl Logger for PeerLogger {
+    type Instant = ();
+
+    fn on_connect(&self, remote_addr: SocketAddr, request: &HttpRequest, _t: TransportProtocol) {
+        let id = request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if let Some(id) = id {
+            self.peers.addrs.insert(id, remote_addr);
+        }
+    }
+
+    fn on_request(&self, _t: TransportProtocol) -> Self::Instant {}
+
+    fn on_call(&self, _method: &str, _params: Params, _kind: MethodKind, _t: TransportProtocol) {}
+
+    fn on_result(
+        &self,
+        _method: &str,
+        _success: bool,
+        _started: Self::Instant,
+        _t: TransportProtocol,
+    ) {
+    }
+
+    fn on_response(&self, _result: &str, _started: Self::Instant, _t: TransportProtocol) {}
+
+    fn on_disconnect(&self, _remote_addr: SocketAddr, _t: TransportProtocol) {}
+}
+
+/// Limits how often a single client can call the expensive API methods.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Option<Arc<ClientLimiter>>,
+    methods: Arc<HashSet<String>>,
+    peers: PeerAddrs,
+}
+
+impl RateLimitLayer {
+    /// No limit is applied when `per_minute` is unset or zero.
+    pub fn new(per_minute: Option<u32>, methods: &[String], peers: PeerAddrs) -> Self {
+        let limiter = per_minute
+            .and_then(NonZeroU32::new)
+            .map(|limit| Arc::new(RateLimiter::keyed(Quota::per_minute(limit))));
+
+        Self {
+            limiter,
+            methods: Arc::new(methods.iter().cloned().collect()),
+            peers,
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+            methods: self.methods.clone(),
+            peers: self.peers.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Option<Arc<ClientLimiter>>,
+    methods: Arc<HashSet<String>>,
+    peers: PeerAddrs,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        req.headers_mut().remove(REQUEST_ID_HEADER);
+        let limiter = match &self.limiter {
+            Some(limiter) => limiter.clone(),
+            None => return Box::pin(self.inner.call(req)),
+        };
+
+        // Keep the service that was polled ready, leaving a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let methods = self.methods.clone();
+        let peers = self.peers.clone();
+
+        Box::pin(async move {
+            // The method is only known once the body is read, so buffer it
+            // before handing the request on to the server.
+            let (mut parts, body) = req.into_parts();
+            let body = match read_body(body, MAX_BODY_BYTES).await {
+                Ok(body) => body,
+                Err(err) => {
+                    log::warn!("Unable to read request body: {err}");
+                    return Ok(error_response(StatusCode::BAD_REQUEST, &err));
+                }
+            };
+
+            let is_export = parts.uri.path() == EXPORT_PATH;
+            if !is_export && !is_limited(&methods, &body) {
+                return inner
+                    .call(Request::from_parts(parts, Body::from(body)))
+                    .await;
+            }
+
+            // The request isn't run until the returned future is polled, so
+            // it can still be dropped once the peer is known.
+            let id = peers.next_id.fetch_add(1, Ordering::Relaxed);
+            parts
+                .headers
+                .insert(REQUEST_ID_HEADER, HeaderValue::from(id));
+            let resp = inner.call(Request::from_parts(parts, Body::from(body)));
+            let client = match peers.addrs.remove(&id) {
+                Some((_, addr)) => addr.ip().to_string(),
+                None => UNKNOWN_CLIENT.to_string(),
+            };
+
+            if let Err(not_until) = limiter.check_key(&client) {
+                let wait = not_until.wait_time_from(QuantaClock::default().now());
+                log::debug!("rate limiting {client} for {}s", wait.as_secs());
+                return Ok(too_many_requests(wait.as_secs()));
+            }
+
+            if limiter.len() > MAX_TRACKED_CLIENTS {
+                limiter.retain_recent();
+            }
+
+            resp.await
+        })
+    }
+}
+
+/// Reads a request body, failing once it's larger than `max_bytes`.
+pub async fn read_body(mut body: Body, max_bytes: usize) -> Result<Bytes, String> {
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| err.to_string())?;
+        if buf.len() + chunk.len() > max_bytes {
+            return Err(format!("Request body is larger than {max_bytes} bytes"));
+        }
+        buf.extend_from_slice(&chunk);
+    }
+
+    Ok(Bytes::from(buf))
+}
+
+/// Whether the JSON-RPC request (or any call in a batch) uses a limited method.
+fn is_limited(methods: &HashSet<String>, body: &[u8]) -> bool {
+    let calls = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Array(calls)) => calls,
+        Ok(call) => vec![call],
+        Err(_) => return false,
+    };
+
+    calls.iter().any(|call| {
+        call.get("method")
+            .and_then(|method| method.as_str())
+            .map_or(false, |method| methods.contains(method))
+    })
+}
+
+fn error_response(status: StatusCode, msg: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(msg.to_string()))
+        .expect("Unable to build response")
+}
+
+fn too_many_requests(retry_after_secs: u64) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(RETRY_AFTER, retry_after_secs.max(1).to_string())
+        .body(Body::from("Too many requests"))
+        .expect("Unable to build response")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_limited, read_body};
+    use hyper::Body;
+    use shared::config::UserSettings;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_is_limited() {
+        let methods = UserSettings::default_api_rate_limited_methods()
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        assert!(is_limited(
+            &methods,
+            br#"{"jsonrpc":"2.0","id":1,"method":"spyglass_search_docs","params":[]}"#
+        ));
+        assert!(is_limited(
+            &methods,
+            br#"[{"jsonrpc":"2.0","id":1,"method":"spyglass_app_status"},{"jsonrpc":"2.0","id":2,"method":"spyglass_search_count"}]"#
+        ));
+        assert!(!is_limited(
+            &methods,
+            br#"{"jsonrpc":"2.0","id":1,"method":"spyglass_app_status"}"#
+        ));
+        assert!(!is_limited(&methods, b"not json"));
+        assert!(!is_limited(
+            &HashSet::new(),
+            br#"{"jsonrpc":"2.0","id":1,"method":"spyglass_search_docs","params":[]}"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_body() {
+        let body = read_body(Body::from("0123456789"), 10).await.unwrap();
+        assert_eq!(&body[..], b"0123456789");
+        assert!(read_body(Body::from("0123456789"), 9).await.is_err());
+    }
+}
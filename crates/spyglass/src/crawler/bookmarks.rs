@@ -0,0 +1,136 @@
+use regex::Regex;
+use url::Url;
+
+use super::html_fragment_to_text;
+
+/// A link saved in a bookmarks file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bookmark {
+    pub url: String,
+    pub title: Option<String>,
+    /// Names of the folders the bookmark is in, outermost first.
+    pub folders: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedBookmarks {
+    pub bookmarks: Vec<Bookmark>,
+    /// Entries w/o a link or w/ a link that can't be crawled, e.g. `javascript:`
+    /// bookmarklets.
+    pub num_skipped: usize,
+}
+
+/// Parses a bookmarks file in the Netscape format browsers export to. Folders
+/// are `<H3>` headings, each followed by a `<DL>` list of its contents, so the
+/// folders a bookmark is in are tracked while going through the file once.
+/// The browser toolbar folder isn't kept as a folder name, every bookmark in
+/// a toolbar export would have it.
+pub fn parse_bookmarks(html: &str) -> ParsedBookmarks {
+    let tag_re = Regex::new(r"(?s)<[^>]*>").expect("Invalid tag regex");
+    let entry_re =
+        Regex::new(r"(?is)<h3\b([^>]*)>(.*?)</h3\s*>|<a\b([^>]*)>(.*?)</a\s*>|<(/?)dl\b[^>]*>")
+            .expect("Invalid bookmark regex");
+    let href_re = Regex::new(r#"(?is)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#)
+        .expect("Invalid href regex");
+    let toolbar_re =
+        Regex::new(r"(?i)\bPERSONAL_TOOLBAR_FOLDER\s*=").expect("Invalid toolbar regex");
+
+    let mut parsed = ParsedBookmarks::default();
+    // Folder of each open list, `None` for lists that aren't a kept folder
+    let mut lists: Vec<Option<String>> = Vec::new();
+    // Folder heading waiting for its list to start
+    let mut heading: Option<Option<String>> = None;
+    for cap in entry_re.captures_iter(html) {
+        if let Some(attrs) = cap.get(1) {
+            let name = cap
+                .get(2)
+                .map(|name| html_fragment_to_text(&tag_re, name.as_str()))
+                .filter(|name| !name.is_empty() && !toolbar_re.is_match(attrs.as_str()));
+            heading = Some(name);
+        } else if let Some(attrs) = cap.get(3) {
+            let href = href_re.captures(attrs.as_str()).and_then(|href| {
+                href.get(1)
+                    .or_else(|| href.get(2))
+                    .or_else(|| href.get(3))
+                    .map(|href| html_fragment_to_text(&tag_re, href.as_str()))
+            });
+            let url = href
+                .and_then(|href| Url::parse(&href).ok())
+                .filter(|url| matches!(url.scheme(), "http" | "https"));
+
+            match url {
+                Some(url) => {
+                    let title = cap
+                        .get(4)
+                        .map(|title| html_fragment_to_text(&tag_re, title.as_str()))
+                        .filter(|title| !title.is_empty());
+                    parsed.bookmarks.push(Bookmark {
+                        url: url.to_string(),
+                        title,
+                        folders: lists.iter().flatten().cloned().collect(),
+                    });
+                }
+                None => parsed.num_skipped += 1,
+            }
+        } else if cap.get(5).map(|close| close.as_str()) == Some("/") {
+            lists.pop();
+        } else {
+            lists.push(heading.take().flatten());
+        }
+    }
+
+    parsed
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_bookmarks, Bookmark};
+
+    #[test]
+    fn test_parse_bookmarks() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://example.com/" ADD_DATE="1">Example</A>
+        <DT><H3 ADD_DATE="1">Rust &amp; Tools</H3>
+        <DL><p>
+            <DT><A HREF="https://docs.rs/?search=a&amp;b=c">docs.rs</A>
+            <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+            <DT><H3>Empty</H3>
+            <DL><p>
+            </DL><p>
+        </DL><p>
+        <DT><A HREF="https://example.org">  </A>
+    </DL><p>
+    <DT><A>No link</A>
+</DL><p>
+"#;
+
+        let parsed = parse_bookmarks(html);
+        assert_eq!(parsed.num_skipped, 2);
+        assert_eq!(
+            parsed.bookmarks,
+            vec![
+                Bookmark {
+                    url: "https://example.com/".into(),
+                    title: Some("Example".into()),
+                    folders: vec![],
+                },
+                Bookmark {
+                    url: "https://docs.rs/?search=a&b=c".into(),
+                    title: Some("docs.rs".into()),
+                    folders: vec!["Rust & Tools".into()],
+                },
+                Bookmark {
+                    url: "https://example.org/".into(),
+                    title: None,
+                    folders: vec![],
+                },
+            ]
+        );
+    }
+}
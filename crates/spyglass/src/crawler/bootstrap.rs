@@ -88,6 +88,8 @@ pub async fn bootstrap(
     let overrides = crawl_queue::EnqueueSettings {
         crawl_type: crawl_queue::CrawlType::Normal,
         tags,
+        // Whole sites are added, pick up pages that aren't linked anywhere
+        use_sitemap: !lens.domains.is_empty(),
         ..Default::default()
     };
 
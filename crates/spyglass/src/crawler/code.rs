@@ -0,0 +1,84 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+
+/// Code blocks kept per page, past this the rest are dropped.
+const MAX_CODE_BLOCKS: usize = 100;
+
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").expect("Invalid tag regex"));
+static PRE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<pre\b[^>]*>(.*?)</pre\s*>").expect("Invalid pre regex"));
+static CODE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<code\b[^>]*>(.*?)</code\s*>").expect("Invalid code regex"));
+
+/// Drops the markup in a code block (e.g. syntax highlighting spans) w/o
+/// adding any whitespace, so identifiers split across spans stay whole.
+fn code_to_text(html: &str) -> String {
+    TAG_RE
+        .replace_all(html, "")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+/// Extracts the contents of `<pre>` blocks & any `<code>` elements outside of
+/// them, verbatim. Duplicate blocks are only kept once.
+pub fn extract_code_blocks(html: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add_block = |html: &str| {
+        let block = code_to_text(html);
+        if !block.is_empty() && seen.insert(block.clone()) {
+            blocks.push(block);
+        }
+    };
+
+    let mut outside_pre = String::new();
+    let mut last_end = 0;
+    for cap in PRE_RE.captures_iter(html) {
+        if let (Some(pre), Some(inner)) = (cap.get(0), cap.get(1)) {
+            outside_pre.push_str(&html[last_end..pre.start()]);
+            last_end = pre.end();
+            add_block(inner.as_str());
+        }
+    }
+    outside_pre.push_str(&html[last_end..]);
+
+    for cap in CODE_RE.captures_iter(&outside_pre) {
+        if let Some(inner) = cap.get(1) {
+            add_block(inner.as_str());
+        }
+    }
+
+    blocks.truncate(MAX_CODE_BLOCKS);
+    blocks
+}
+
+#[cfg(test)]
+mod test {
+    use super::extract_code_blocks;
+
+    #[test]
+    fn test_extract_code_blocks() {
+        let html = r#"<body>
+            <p>Call <code>foo.bar()</code> to start &amp; <code>foo.bar()</code> again.</p>
+            <pre class="lang-rust"><code><span class="kw">let</span> x = <span>foo</span>.<span>bar</span>(&amp;y);
+if x &lt; 1 { }</code></pre>
+            <pre>   </pre>
+        </body>"#;
+
+        assert_eq!(
+            extract_code_blocks(html),
+            vec![
+                "let x = foo.bar(&y);\nif x < 1 { }".to_string(),
+                "foo.bar()".to_string(),
+            ]
+        );
+        assert!(extract_code_blocks("<p>No code here</p>").is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use base64::{engine::general_purpose, Engine as _};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Client;
+use shared::config::Config;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use url::Url;
+
+use super::{read_body, MAX_PAGE_BYTES};
+
+/// How long to wait before trying again for a domain that had no favicon.
+const MISSING_RETRY: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+/// Ignore anything bigger, it's not a favicon.
+const MAX_FAVICON_BYTES: usize = 256 * 1024;
+
+/// Shown for domains without a favicon.
+const PLACEHOLDER_FAVICON: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="none" stroke="#a3a3a3" stroke-width="1.5"><circle cx="12" cy="12" r="9"/><path d="M3 12h18M12 3c2.5 2.5 3.75 5.5 3.75 9s-1.25 6.5-3.75 9c-2.5-2.5-3.75-5.5-3.75-9S9.5 5.5 12 3z"/></svg>"##;
+
+/// Favicons are cached on disk, one file per domain. An empty file means the
+/// domain has no favicon (or one is being fetched).
+fn favicon_path(config: &Config, domain: &str) -> PathBuf {
+    let file_name = domain
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    config.cache_dir().join("favicons").join(file_name)
+}
+
+/// Whether we should try to fetch the favicon for this domain, i.e. we haven't
+/// seen it before or it had no favicon the last time we checked a while ago.
+pub fn needs_fetch(config: &Config, domain: &str) -> bool {
+    match fs::metadata(favicon_path(config, domain)) {
+        Ok(meta) => {
+            meta.len() == 0
+                && meta
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .map_or(false, |elapsed| elapsed > MISSING_RETRY)
+        }
+        Err(_) => true,
+    }
+}
+
+/// Fetches & caches the favicon for the site at `page_url`, trying
+/// `/favicon.ico` before looking for an icon link on the home page.
+pub async fn fetch_favicon(client: &Client, config: &Config, page_url: &Url) {
+    let domain = match page_url.host_str() {
+        Some(domain) => domain.to_string(),
+        None => return,
+    };
+
+    // Mark the domain as seen so other crawls don't also fetch the favicon and
+    // failures aren't retried until MISSING_RETRY has passed.
+    let path = favicon_path(config, &domain);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Err(err) = fs::write(&path, b"") {
+        log::warn!("Unable to write favicon cache for {}: {}", domain, err);
+        return;
+    }
+
+    let mut root = page_url.clone();
+    root.set_path("/");
+    root.set_query(None);
+    root.set_fragment(None);
+
+    let mut icon = match root.join("/favicon.ico") {
+        Ok(url) => fetch_icon(client, &url).await,
+        Err(_) => None,
+    };
+
+    if icon.is_none() {
+        if let Some(icon_url) = find_icon_link(client, &root).await {
+            icon = fetch_icon(client, &icon_url).await;
+        }
+    }
+
+    match icon {
+        Some(bytes) => {
+            if let Err(err) = fs::write(&path, bytes) {
+                log::warn!("Unable to save favicon for {}: {}", domain, err);
+            }
+        }
+        None => log::debug!("no favicon found for {}", domain),
+    }
+}
+
+async fn fetch_icon(client: &Client, url: &Url) -> Option<Vec<u8>> {
+    let resp = client.get(url.as_str()).send().await.ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+
+    // Sites often serve their 404 page with a 200 status
+    let is_html = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.contains("text/html"));
+    if is_html {
+        return None;
+    }
+
+    let bytes = read_body(resp, MAX_FAVICON_BYTES).await.ok()?;
+    if bytes.is_empty() {
+        return None;
+    }
+
+    Some(bytes.to_vec())
+}
+
+/// Looks for a `<link rel="icon">` on the page.
+async fn find_icon_link(client: &Client, page: &Url) -> Option<Url> {
+    let resp = client.get(page.as_str()).send().await.ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+
+    let body = read_body(resp, MAX_PAGE_BYTES).await.ok()?;
+    parse_icon_link(page, &String::from_utf8_lossy(&body))
+}
+
+static LINK_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<link\s[^>]*>").expect("Invalid link regex"));
+static REL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\brel\s*=\s*["']([^"']*)["']"#).expect("Invalid rel regex"));
+static HREF_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\bhref\s*=\s*["']([^"']*)["']"#).expect("Invalid href regex"));
+
+fn parse_icon_link(page: &Url, html: &str) -> Option<Url> {
+    LINK_RE.find_iter(html).find_map(|tag| {
+        let tag = tag.as_str();
+        let rel = REL_RE.captures(tag)?.get(1)?.as_str().to_lowercase();
+        if !rel.split_whitespace().any(|rel| rel == "icon") {
+            return None;
+        }
+
+        let href = HREF_RE.captures(tag)?.get(1)?.as_str();
+        page.join(href.trim()).ok()
+    })
+}
+
+fn mime_type(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if bytes.starts_with(b"GIF8") {
+        "image/gif"
+    } else if bytes.starts_with(b"\xFF\xD8") {
+        "image/jpeg"
+    } else if bytes.starts_with(b"<svg") || bytes.starts_with(b"<?xml") {
+        "image/svg+xml"
+    } else {
+        "image/x-icon"
+    }
+}
+
+/// The cached favicon for a domain as a data URL, falling back to a
+/// placeholder icon if there isn't one.
+pub fn favicon_data_url(config: &Config, domain: &str) -> String {
+    match fs::read(favicon_path(config, domain)) {
+        Ok(bytes) if !bytes.is_empty() => format!(
+            "data:{};base64,{}",
+            mime_type(&bytes),
+            general_purpose::STANDARD.encode(bytes)
+        ),
+        _ => format!(
+            "data:image/svg+xml;base64,{}",
+            general_purpose::STANDARD.encode(PLACEHOLDER_FAVICON)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mime_type, parse_icon_link};
+    use url::Url;
+
+    #[test]
+    fn test_parse_icon_link() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="shortcut icon" href="/static/icon.png">
+        </head></html>"#;
+
+        assert_eq!(
+            parse_icon_link(&page, html).map(|url| url.to_string()),
+            Some("https://example.com/static/icon.png".to_string())
+        );
+        assert!(parse_icon_link(&page, "<html></html>").is_none());
+    }
+
+    #[test]
+    fn test_mime_type() {
+        assert_eq!(mime_type(b"\x89PNG\r\n"), "image/png");
+        assert_eq!(mime_type(b"\x00\x00\x01\x00"), "image/x-icon");
+    }
+}
@@ -0,0 +1,213 @@
+use std::io::Cursor;
+use url::Url;
+
+use super::{html_fragment_to_text, TAG_RE};
+
+/// Entries parsed out of an RSS or Atom feed.
+#[derive(Debug, Default)]
+pub struct ParsedFeed {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct FeedEntry {
+    /// Absolute URL of the entry, resolved against the feed URL.
+    pub link: String,
+    pub title: Option<String>,
+    /// Summary provided by the feed, with any markup removed.
+    pub summary: Option<String>,
+}
+
+impl ParsedFeed {
+    /// Text content for the feed itself, made up of the entry titles &
+    /// summaries.
+    pub fn summary_content(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                [entry.title.as_deref(), entry.summary.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<&str>>()
+                    .join("\n")
+            })
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
+}
+
+/// Whether the content type is one used to serve RSS/Atom feeds.
+pub fn is_feed_content(content_type: &str) -> bool {
+    content_type.contains("application/rss+xml")
+        || content_type.contains("application/atom+xml")
+        || content_type.contains("application/xml")
+        || content_type.contains("text/xml")
+}
+
+/// Attempts to parse the body as an RSS feed, falling back to Atom. Returns
+/// None if the body is neither.
+pub fn parse_feed(url: &Url, body: &str) -> Option<ParsedFeed> {
+    if let Ok(channel) = rss::Channel::read_from(Cursor::new(body.as_bytes())) {
+        let entries = channel
+            .items()
+            .iter()
+            .filter_map(|item| {
+                let link = item.link().or_else(|| {
+                    item.guid()
+                        .filter(|guid| guid.is_permalink())
+                        .map(|guid| guid.value())
+                })?;
+
+                Some(FeedEntry {
+                    link: resolve_link(url, link)?,
+                    title: item.title().map(strip_markup).filter(|t| !t.is_empty()),
+                    summary: item
+                        .description()
+                        .or_else(|| item.content())
+                        .map(strip_markup)
+                        .filter(|s| !s.is_empty()),
+                })
+            })
+            .collect();
+
+        return Some(ParsedFeed {
+            title: Some(channel.title().to_string()).filter(|t| !t.is_empty()),
+            description: Some(strip_markup(channel.description())).filter(|d| !d.is_empty()),
+            entries,
+        });
+    }
+
+    if let Ok(feed) = atom_syndication::Feed::read_from(Cursor::new(body.as_bytes())) {
+        let entries = feed
+            .entries()
+            .iter()
+            .filter_map(|entry| {
+                // Prefer the "alternate" link, which points to the entry itself.
+                let link = entry
+                    .links()
+                    .iter()
+                    .find(|link| link.rel() == "alternate")
+                    .or_else(|| entry.links().first())?;
+
+                Some(FeedEntry {
+                    link: resolve_link(url, link.href())?,
+                    title: Some(strip_markup(&entry.title().value)).filter(|t| !t.is_empty()),
+                    summary: entry
+                        .summary()
+                        .map(|summary| summary.value.as_str())
+                        .or_else(|| entry.content().and_then(|content| content.value()))
+                        .map(strip_markup)
+                        .filter(|s| !s.is_empty()),
+                })
+            })
+            .collect();
+
+        return Some(ParsedFeed {
+            title: Some(feed.title().value.clone()).filter(|t| !t.is_empty()),
+            description: feed
+                .subtitle()
+                .map(|subtitle| strip_markup(&subtitle.value))
+                .filter(|d| !d.is_empty()),
+            entries,
+        });
+    }
+
+    None
+}
+
+fn resolve_link(feed_url: &Url, link: &str) -> Option<String> {
+    let link = feed_url.join(link.trim()).ok()?;
+    match link.scheme() {
+        "http" | "https" => Some(link.to_string()),
+        _ => None,
+    }
+}
+
+/// Summaries are usually HTML, drop the tags & collapse whitespace.
+fn strip_markup(text: &str) -> String {
+    html_fragment_to_text(&TAG_RE, text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_feed_content, parse_feed};
+    use url::Url;
+
+    #[test]
+    fn test_is_feed_content() {
+        assert!(is_feed_content("application/rss+xml; charset=utf-8"));
+        assert!(is_feed_content("application/atom+xml"));
+        assert!(is_feed_content("text/xml"));
+        assert!(!is_feed_content("text/html"));
+    }
+
+    #[test]
+    fn test_parse_rss() {
+        let url = Url::parse("https://example.com/feed.xml").unwrap();
+        let body = r#"<?xml version="1.0"?>
+            <rss version="2.0">
+                <channel>
+                    <title>Example</title>
+                    <link>https://example.com</link>
+                    <description>An example feed</description>
+                    <item>
+                        <title>First post</title>
+                        <link>https://example.com/first</link>
+                        <description>&lt;p&gt;Hello &amp;amp; welcome&lt;/p&gt;</description>
+                    </item>
+                    <item>
+                        <title>Second post</title>
+                        <link>/second</link>
+                    </item>
+                    <item>
+                        <title>No link</title>
+                    </item>
+                </channel>
+            </rss>"#;
+
+        let feed = parse_feed(&url, body).expect("should parse rss");
+        assert_eq!(feed.title, Some("Example".to_string()));
+        assert_eq!(feed.entries.len(), 2);
+        assert_eq!(feed.entries[0].link, "https://example.com/first");
+        assert_eq!(feed.entries[0].summary, Some("Hello & welcome".to_string()));
+        assert_eq!(feed.entries[1].link, "https://example.com/second");
+        assert_eq!(
+            feed.summary_content(),
+            "First post\nHello & welcome\n\nSecond post"
+        );
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let url = Url::parse("https://example.com/atom.xml").unwrap();
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+            <feed xmlns="http://www.w3.org/2005/Atom">
+                <title>Example</title>
+                <id>urn:example</id>
+                <updated>2023-11-01T00:00:00Z</updated>
+                <entry>
+                    <title>First post</title>
+                    <id>urn:example:first</id>
+                    <updated>2023-11-01T00:00:00Z</updated>
+                    <link rel="edit" href="https://example.com/edit/first"/>
+                    <link rel="alternate" href="https://example.com/first"/>
+                    <summary>A summary</summary>
+                </entry>
+            </feed>"#;
+
+        let feed = parse_feed(&url, body).expect("should parse atom");
+        assert_eq!(feed.title, Some("Example".to_string()));
+        assert_eq!(feed.entries.len(), 1);
+        assert_eq!(feed.entries[0].link, "https://example.com/first");
+        assert_eq!(feed.entries[0].summary, Some("A summary".to_string()));
+    }
+
+    #[test]
+    fn test_parse_not_a_feed() {
+        let url = Url::parse("https://example.com/").unwrap();
+        assert!(parse_feed(&url, "<html><body>hi</body></html>").is_none());
+    }
+}
@@ -0,0 +1,250 @@
+use dashmap::DashMap;
+use reqwest::header::HeaderMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::CrawlResult;
+
+/// How long a crawl result can be reused for.
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 10);
+/// Max number of pages kept around.
+const DEFAULT_CAPACITY: usize = 1_000;
+
+/// The headers a server uses to signal whether a page has changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_pairs<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut validators = Validators::default();
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("etag") {
+                validators.etag = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("last-modified") {
+                validators.last_modified = Some(value.to_string());
+            }
+        }
+
+        validators
+    }
+
+    pub fn from_header_map(headers: &HeaderMap) -> Self {
+        Self::from_pairs(
+            headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+        )
+    }
+
+    /// Headers that make a request conditional, so the server can reply w/ a
+    /// `304 Not Modified` instead of the page when it hasn't changed.
+    pub fn conditional_headers(&self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = &self.etag {
+            headers.push(("If-None-Match".to_string(), etag.clone()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.push(("If-Modified-Since".to_string(), last_modified.clone()));
+        }
+
+        headers
+    }
+
+    /// Without either header there's no way to tell if the page changed.
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CachedCrawl {
+    validators: Validators,
+    fetched_at: Instant,
+    result: CrawlResult,
+}
+
+/// Short-lived, in-memory cache of recent crawl results. Protects against the
+/// same page being fetched & extracted over and over (e.g. from a
+/// misconfigured recrawl schedule) when the server says it hasn't changed.
+#[derive(Clone, Debug)]
+pub struct RecrawlCache {
+    entries: Arc<DashMap<String, CachedCrawl>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl Default for RecrawlCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_CAPACITY)
+    }
+}
+
+impl RecrawlCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(DashMap::new()),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Validators for a page crawled within the TTL, used to check whether it
+    /// has changed since.
+    pub fn validators(&self, url: &str) -> Option<Validators> {
+        self.entries
+            .get(url)
+            .filter(|entry| entry.fetched_at.elapsed() < self.ttl)
+            .map(|entry| entry.validators.clone())
+    }
+
+    /// The cached result for a page, if it was crawled within the TTL & the
+    /// server still reports the same validators.
+    pub fn get(&self, url: &str, validators: &Validators) -> Option<CrawlResult> {
+        if validators.is_empty() {
+            return None;
+        }
+
+        self.entries
+            .get(url)
+            .filter(|entry| entry.fetched_at.elapsed() < self.ttl)
+            .filter(|entry| &entry.validators == validators)
+            .map(|entry| entry.result.clone())
+    }
+
+    pub fn insert(&self, url: &str, validators: Validators, result: CrawlResult) {
+        if validators.is_empty() || self.capacity == 0 {
+            return;
+        }
+
+        if !self.entries.contains_key(url) && self.entries.len() >= self.capacity {
+            self.entries
+                .retain(|_, entry| entry.fetched_at.elapsed() < self.ttl);
+
+            // Still full, make room by dropping the oldest page.
+            if self.entries.len() >= self.capacity {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|entry| entry.fetched_at)
+                    .map(|entry| entry.key().clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+
+        self.entries.insert(
+            url.to_string(),
+            CachedCrawl {
+                validators,
+                fetched_at: Instant::now(),
+                result,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RecrawlCache, Validators};
+    use crate::crawler::CrawlResult;
+    use std::time::Duration;
+
+    fn result(url: &str) -> CrawlResult {
+        CrawlResult {
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validators() {
+        let validators =
+            Validators::from_pairs(vec![("ETag", "\"abc\""), ("content-type", "text/html")]);
+        assert_eq!(validators.etag, Some("\"abc\"".to_string()));
+        assert_eq!(validators.last_modified, None);
+        assert!(!validators.is_empty());
+        assert!(Validators::from_pairs(vec![("content-type", "text/html")]).is_empty());
+    }
+
+    #[test]
+    fn test_conditional_headers() {
+        let validators = Validators::from_pairs(vec![
+            ("etag", "W/\"abc\""),
+            ("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+        ]);
+        assert_eq!(
+            validators.conditional_headers(),
+            vec![
+                ("If-None-Match".to_string(), "W/\"abc\"".to_string()),
+                (
+                    "If-Modified-Since".to_string(),
+                    "Wed, 21 Oct 2015 07:28:00 GMT".to_string()
+                ),
+            ]
+        );
+        assert!(Validators::default().conditional_headers().is_empty());
+    }
+
+    #[test]
+    fn test_get() {
+        let cache = RecrawlCache::new(Duration::from_secs(60), 10);
+        let url = "https://example.com/";
+        let validators = Validators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+        };
+
+        cache.insert(url, validators.clone(), result(url));
+        assert_eq!(cache.validators(url), Some(validators.clone()));
+        assert!(cache.get(url, &validators).is_some());
+
+        // Page changed
+        let changed = Validators {
+            etag: Some("\"v2\"".to_string()),
+            last_modified: None,
+        };
+        assert!(cache.get(url, &changed).is_none());
+
+        // Nothing to compare against, never cached
+        let other = "https://example.com/other";
+        cache.insert(other, Validators::default(), result(other));
+        assert!(cache.validators(other).is_none());
+    }
+
+    #[test]
+    fn test_expired() {
+        let cache = RecrawlCache::new(Duration::ZERO, 10);
+        let url = "https://example.com/";
+        let validators = Validators {
+            etag: None,
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        };
+
+        cache.insert(url, validators.clone(), result(url));
+        assert!(cache.validators(url).is_none());
+        assert!(cache.get(url, &validators).is_none());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let cache = RecrawlCache::new(Duration::from_secs(60), 2);
+        let validators = Validators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+        };
+
+        for url in ["https://a.com/", "https://b.com/", "https://c.com/"] {
+            cache.insert(url, validators.clone(), result(url));
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        // Oldest page was dropped to make room
+        assert!(cache.get("https://a.com/", &validators).is_none());
+        assert!(cache.get("https://b.com/", &validators).is_some());
+        assert!(cache.get("https://c.com/", &validators).is_some());
+    }
+}
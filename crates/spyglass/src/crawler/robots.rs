@@ -303,7 +303,7 @@ mod test {
             .await
             .expect("Unable to insert allow rule");
 
-        let res = check_resource_rules(&db, &crawler.client, &url).await;
+        let res = check_resource_rules(&db, &crawler.clients.client(None).unwrap(), &url).await;
 
         assert_eq!(res, true);
     }
@@ -0,0 +1,198 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+
+use super::CrawlResult;
+
+/// A response as it was fetched, before anything was extracted from it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecordedResponse {
+    /// URL that was requested.
+    pub url: String,
+    /// URL the response came from, after any redirects.
+    pub final_url: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Everything fetched for a single crawl task & what came of it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SessionEntry {
+    pub task_id: i64,
+    pub recorded_at: DateTime<Utc>,
+    /// Responses fetched for the task, in order. Empty when nothing was
+    /// fetched over the network, e.g. the page was unchanged or a local file.
+    pub responses: Vec<RecordedResponse>,
+    /// What was extracted from the responses, unless crawling failed.
+    pub result: Option<CrawlResult>,
+    pub error: Option<String>,
+    /// How the worker handled the crawl, e.g. whether it was indexed.
+    pub outcome: String,
+}
+
+/// Responses fetched by a crawler, kept until they're recorded.
+pub type ResponseLog = Arc<Mutex<Vec<RecordedResponse>>>;
+
+#[derive(Debug)]
+struct SessionFile {
+    path: PathBuf,
+    file: tokio::fs::File,
+    bytes_written: u64,
+    is_full: bool,
+}
+
+/// Records crawls to a session file, one JSON entry per line, so they can be
+/// replayed later without hitting the network. A new session file is started
+/// for each run of the app.
+#[derive(Clone, Debug, Default)]
+pub struct CrawlRecorder {
+    session: Arc<tokio::sync::Mutex<Option<SessionFile>>>,
+}
+
+impl CrawlRecorder {
+    /// Appends `entry` to the session file in `dir`. Recording stops once the
+    /// file would grow past `max_bytes`.
+    pub async fn record(&self, dir: &Path, max_bytes: u64, entry: &SessionEntry) {
+        let mut session = self.session.lock().await;
+        if session.is_none() {
+            match Self::create_session(dir).await {
+                Ok(file) => {
+                    log::info!("recording crawls to {}", file.path.display());
+                    *session = Some(file);
+                }
+                Err(err) => {
+                    log::warn!("Unable to create crawl session file: {}", err);
+                    return;
+                }
+            }
+        }
+
+        let session = match session.as_mut() {
+            Some(session) if !session.is_full => session,
+            _ => return,
+        };
+
+        let mut line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(err) => {
+                log::warn!("Unable to serialize crawl session entry: {}", err);
+                return;
+            }
+        };
+        line.push('\n');
+
+        if session.bytes_written + line.len() as u64 > max_bytes {
+            log::warn!(
+                "{} reached {} bytes, no longer recording crawls",
+                session.path.display(),
+                max_bytes
+            );
+            session.is_full = true;
+            return;
+        }
+
+        match session.file.write_all(line.as_bytes()).await {
+            Ok(_) => session.bytes_written += line.len() as u64,
+            Err(err) => log::warn!("Unable to record crawl: {}", err),
+        }
+    }
+
+    async fn create_session(dir: &Path) -> std::io::Result<SessionFile> {
+        tokio::fs::create_dir_all(dir).await?;
+        let path = dir.join(format!("{}.jsonl", Utc::now().format("%Y%m%d-%H%M%S%.3f")));
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+
+        Ok(SessionFile {
+            path,
+            file,
+            bytes_written: 0,
+            is_full: false,
+        })
+    }
+}
+
+/// Reads the entries of a recorded session file.
+pub fn read_session(path: &Path) -> anyhow::Result<Vec<SessionEntry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_session, CrawlRecorder, RecordedResponse, SessionEntry};
+    use crate::crawler::CrawlResult;
+    use chrono::Utc;
+
+    fn entry(task_id: i64) -> SessionEntry {
+        SessionEntry {
+            task_id,
+            recorded_at: Utc::now(),
+            responses: vec![RecordedResponse {
+                url: "https://example.com".into(),
+                final_url: "https://example.com/".into(),
+                status: 200,
+                headers: vec![("content-type".into(), "text/html".into())],
+                body: "<html><title>Example</title></html>".into(),
+            }],
+            result: Some(CrawlResult {
+                title: Some("Example".into()),
+                url: "https://example.com/".into(),
+                ..Default::default()
+            }),
+            error: None,
+            outcome: "New".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_session() {
+        let dir = std::env::temp_dir().join(format!("crawl-session-{}", uuid::Uuid::new_v4()));
+        let recorder = CrawlRecorder::default();
+        let entry_size = serde_json::to_string(&entry(1)).unwrap().len() as u64 + 1;
+
+        // Stops recording once the file is full, leaving some slack as the
+        // timestamps may differ in length.
+        for task_id in 0..3 {
+            recorder
+                .record(&dir, entry_size * 5 / 2, &entry(task_id))
+                .await;
+        }
+
+        let sessions = std::fs::read_dir(&dir)
+            .expect("Unable to read session dir")
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_eq!(sessions.len(), 1);
+
+        let entries = read_session(&sessions[0].path()).expect("Unable to read session");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].task_id, 1);
+        assert_eq!(entries[1].responses[0].status, 200);
+        assert_eq!(
+            entries[1]
+                .result
+                .as_ref()
+                .and_then(|result| result.title.clone()),
+            Some("Example".to_string())
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,284 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use regex::Regex;
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use url::Url;
+
+use super::read_body;
+
+/// Most sitemap files fetched for a single site, index files included.
+const MAX_SITEMAPS: usize = 50;
+/// Largest a sitemap can be once decompressed, the limit set by the protocol.
+const MAX_SITEMAP_BYTES: u64 = 50 * 1024 * 1024;
+
+/// A `<url>` or `<sitemap>` entry in a sitemap.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: Option<DateTime<Utc>>,
+}
+
+impl SitemapEntry {
+    /// Whether the entry may have changed since `since`. Entries w/o a
+    /// `<lastmod>` always might have.
+    pub fn is_modified_since(&self, since: Option<DateTime<Utc>>) -> bool {
+        match (self.lastmod, since) {
+            (Some(lastmod), Some(since)) => lastmod > since,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedSitemap {
+    /// Pages listed in a `<urlset>`.
+    pub urls: Vec<SitemapEntry>,
+    /// Other sitemaps listed in a `<sitemapindex>`.
+    pub sitemaps: Vec<SitemapEntry>,
+}
+
+/// Parses the `<url>` & `<sitemap>` entries of a sitemap or sitemap index.
+/// Entries w/o a valid `<loc>` are skipped.
+pub fn parse_sitemap(xml: &str) -> ParsedSitemap {
+    let entry_re = Regex::new(r"(?is)<(url|sitemap)\b[^>]*>(.*?)</(?:url|sitemap)\s*>")
+        .expect("Invalid sitemap entry regex");
+    let loc_re = Regex::new(r"(?is)<loc\b[^>]*>(.*?)</loc\s*>").expect("Invalid loc regex");
+    let lastmod_re =
+        Regex::new(r"(?is)<lastmod\b[^>]*>(.*?)</lastmod\s*>").expect("Invalid lastmod regex");
+
+    let mut parsed = ParsedSitemap::default();
+    for cap in entry_re.captures_iter(xml) {
+        let body = &cap[2];
+        let loc = loc_re
+            .captures(body)
+            .map(|loc| xml_text(&loc[1]))
+            .and_then(|loc| Url::parse(&loc).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"));
+        let loc = match loc {
+            Some(loc) => loc.to_string(),
+            None => continue,
+        };
+
+        let lastmod = lastmod_re
+            .captures(body)
+            .and_then(|lastmod| parse_lastmod(&xml_text(&lastmod[1])));
+
+        let entry = SitemapEntry { loc, lastmod };
+        if cap[1].eq_ignore_ascii_case("url") {
+            parsed.urls.push(entry);
+        } else {
+            parsed.sitemaps.push(entry);
+        }
+    }
+
+    parsed
+}
+
+/// Text of an XML element, w/o any CDATA wrapper & w/ entities decoded.
+fn xml_text(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+        .map(|text| text.to_string())
+        .unwrap_or_else(|| {
+            text.replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&amp;", "&")
+        });
+    text.trim().to_string()
+}
+
+/// Parses a W3C datetime, e.g. `2023-11-30T10:00:00+00:00`. Anything w/ less
+/// precision than a full timestamp is treated as the start of its day.
+fn parse_lastmod(lastmod: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(lastmod) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    let day = lastmod.get(..10)?;
+    NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .ok()
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|day| DateTime::from_naive_utc_and_offset(day, Utc))
+}
+
+/// Decompresses gzipped sitemaps (e.g. `sitemap.xml.gz`), plain sitemaps are
+/// returned as is.
+pub fn decode_sitemap(body: &[u8]) -> Option<String> {
+    let mut xml = String::new();
+    if body.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(body)
+            .take(MAX_SITEMAP_BYTES)
+            .read_to_string(&mut xml)
+            .ok()?;
+    } else {
+        body.take(MAX_SITEMAP_BYTES).read_to_string(&mut xml).ok()?;
+    }
+
+    Some(xml)
+}
+
+async fn fetch_sitemap(client: &reqwest::Client, url: &Url) -> Option<ParsedSitemap> {
+    let resp = match client.get(url.as_str()).send().await {
+        Ok(resp) if resp.status().is_success() => resp,
+        Ok(resp) => {
+            log::debug!("no sitemap at {}: {}", url, resp.status());
+            return None;
+        }
+        Err(err) => {
+            log::debug!("Unable to fetch sitemap {}: {}", url, err);
+            return None;
+        }
+    };
+
+    // A compressed sitemap is never larger than the decompressed limit either.
+    let body = match read_body(resp, MAX_SITEMAP_BYTES as usize).await {
+        Ok(body) => body,
+        Err(err) => {
+            log::debug!("Unable to read sitemap {}: {}", url, err);
+            return None;
+        }
+    };
+    let xml = tokio::task::spawn_blocking(move || decode_sitemap(&body))
+        .await
+        .ok()??;
+    Some(parse_sitemap(&xml))
+}
+
+/// Fetches the pages listed in a site's `/sitemap.xml` (or `/sitemap.xml.gz`),
+/// following sitemap indexes on the same host. With `since`, pages & sitemaps
+/// last modified before then are skipped.
+pub async fn fetch_sitemap_urls(
+    client: &reqwest::Client,
+    root: &Url,
+    since: Option<DateTime<Utc>>,
+) -> Vec<SitemapEntry> {
+    let mut urls = Vec::new();
+    let (sitemap, sitemap_gz) = match (root.join("/sitemap.xml"), root.join("/sitemap.xml.gz")) {
+        (Ok(sitemap), Ok(sitemap_gz)) => (sitemap, sitemap_gz),
+        _ => return urls,
+    };
+
+    let mut to_fetch = VecDeque::from([sitemap.clone()]);
+    let mut seen = HashSet::from([sitemap.to_string()]);
+    let mut num_fetched = 0;
+    while let Some(url) = to_fetch.pop_front() {
+        if num_fetched >= MAX_SITEMAPS {
+            log::warn!("stopped after {} sitemaps for {}", MAX_SITEMAPS, root);
+            break;
+        }
+        num_fetched += 1;
+
+        let parsed = match fetch_sitemap(client, &url).await {
+            Some(parsed) => parsed,
+            None => {
+                // Some sites only serve a compressed sitemap
+                if url == sitemap && seen.insert(sitemap_gz.to_string()) {
+                    to_fetch.push_back(sitemap_gz.clone());
+                }
+                continue;
+            }
+        };
+
+        for entry in parsed.sitemaps {
+            if !entry.is_modified_since(since) {
+                continue;
+            }
+
+            if let Ok(child) = Url::parse(&entry.loc) {
+                if child.host_str() == root.host_str() && seen.insert(child.to_string()) {
+                    to_fetch.push_back(child);
+                }
+            }
+        }
+
+        urls.extend(
+            parsed
+                .urls
+                .into_iter()
+                .filter(|entry| entry.is_modified_since(since)),
+        );
+    }
+
+    urls
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_sitemap, parse_sitemap, SitemapEntry};
+    use chrono::{TimeZone, Utc};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse_sitemap() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>https://example.com/?a=1&amp;b=2</loc>
+    <lastmod>2023-11-30T10:00:00+01:00</lastmod>
+  </url>
+  <url><loc><![CDATA[https://example.com/about]]></loc><lastmod>2023-11-01</lastmod></url>
+  <url><loc>mailto:someone@example.com</loc></url>
+  <url><lastmod>2023-11-01</lastmod></url>
+</urlset>"#;
+
+        let parsed = parse_sitemap(xml);
+        assert!(parsed.sitemaps.is_empty());
+        assert_eq!(
+            parsed.urls,
+            vec![
+                SitemapEntry {
+                    loc: "https://example.com/?a=1&b=2".into(),
+                    lastmod: Some(Utc.with_ymd_and_hms(2023, 11, 30, 9, 0, 0).unwrap()),
+                },
+                SitemapEntry {
+                    loc: "https://example.com/about".into(),
+                    lastmod: Some(Utc.with_ymd_and_hms(2023, 11, 1, 0, 0, 0).unwrap()),
+                },
+            ]
+        );
+
+        let since = Some(Utc.with_ymd_and_hms(2023, 11, 15, 0, 0, 0).unwrap());
+        assert!(parsed.urls[0].is_modified_since(since));
+        assert!(!parsed.urls[1].is_modified_since(since));
+        assert!(parsed.urls[1].is_modified_since(None));
+    }
+
+    #[test]
+    fn test_parse_sitemap_index() {
+        let xml = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-posts.xml.gz</loc></sitemap>
+  <sitemap>
+    <loc>https://example.com/sitemap-pages.xml</loc>
+    <lastmod>2023-11-30T10:00Z</lastmod>
+  </sitemap>
+</sitemapindex>"#;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(xml.as_bytes()).unwrap();
+        let decoded = decode_sitemap(&encoder.finish().unwrap()).expect("Unable to decode");
+        assert_eq!(decoded, xml);
+
+        let parsed = parse_sitemap(&decoded);
+        assert!(parsed.urls.is_empty());
+        assert_eq!(
+            parsed.sitemaps,
+            vec![
+                SitemapEntry {
+                    loc: "https://example.com/sitemap-posts.xml.gz".into(),
+                    lastmod: None,
+                },
+                SitemapEntry {
+                    loc: "https://example.com/sitemap-pages.xml".into(),
+                    lastmod: Some(Utc.with_ymd_and_hms(2023, 11, 30, 0, 0, 0).unwrap()),
+                },
+            ]
+        );
+    }
+}
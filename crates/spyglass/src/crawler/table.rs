@@ -0,0 +1,227 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+
+use super::{html_fragment_to_text, TAG_RE};
+
+/// Cells spanning more columns than this are treated as spanning one.
+const MAX_COLSPAN: usize = 20;
+
+static CAPTION_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<caption\b[^>]*>(.*?)</caption\s*>").expect("Invalid caption regex")
+});
+static ROW_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<tr\b[^>]*>").expect("Invalid row regex"));
+static CELL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<(th|td)\b([^>]*)>").expect("Invalid cell regex"));
+static COLSPAN_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\bcolspan\s*=\s*["']?(\d+)"#).expect("Invalid colspan regex"));
+static STRUCTURE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)</?(?:td|th|tr|thead|tbody|tfoot)\b[^>]*>").expect("Invalid structure regex")
+});
+static TABLE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<(/?)table\b[^>]*>").expect("Invalid table regex"));
+
+/// A data table, its header row & the inner HTML of each row's cells.
+#[derive(Debug, PartialEq, Eq)]
+struct Table {
+    caption: Option<String>,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Splits the inner HTML of a `<table>` into rows of cells. Closing tags are
+/// optional for rows & cells, so each one runs until the next one starts.
+/// Only tables w/ a header row are parsed, tables without are most likely used
+/// for layout.
+fn parse_table(html: &str) -> Option<Table> {
+    let caption = CAPTION_RE
+        .captures(html)
+        .and_then(|cap| cap.get(1))
+        .map(|caption| html_fragment_to_text(&TAG_RE, caption.as_str()))
+        .filter(|caption| !caption.is_empty());
+
+    // (is header, cell HTML) for each row
+    let rows = ROW_RE
+        .split(html)
+        .skip(1)
+        .map(|row| {
+            let starts = CELL_RE.captures_iter(row).collect::<Vec<_>>();
+            let mut cells = Vec::new();
+            for (idx, cap) in starts.iter().enumerate() {
+                let (tag, name) = match (cap.get(0), cap.get(1)) {
+                    (Some(tag), Some(name)) => (tag, name),
+                    _ => continue,
+                };
+                let end = starts
+                    .get(idx + 1)
+                    .and_then(|next| next.get(0))
+                    .map_or(row.len(), |next| next.start());
+                let is_header = name.as_str().eq_ignore_ascii_case("th");
+                let html = STRUCTURE_RE
+                    .replace_all(&row[tag.end()..end], " ")
+                    .trim()
+                    .to_string();
+                let colspan = cap
+                    .get(2)
+                    .and_then(|attrs| COLSPAN_RE.captures(attrs.as_str()))
+                    .and_then(|span| span[1].parse::<usize>().ok())
+                    .filter(|span| (1..=MAX_COLSPAN).contains(span))
+                    .unwrap_or(1);
+
+                for _ in 0..colspan {
+                    cells.push((is_header, html.clone()));
+                }
+            }
+
+            cells
+        })
+        .filter(|cells| !cells.is_empty())
+        .collect::<Vec<_>>();
+
+    let (header_row, rows) = rows.split_first()?;
+    if rows.is_empty() || !header_row.iter().all(|(is_header, _)| *is_header) {
+        return None;
+    }
+
+    Some(Table {
+        caption,
+        headers: header_row
+            .iter()
+            .map(|(_, cell)| html_fragment_to_text(&TAG_RE, cell))
+            .collect(),
+        rows: rows
+            .iter()
+            .map(|cells| cells.iter().map(|(_, cell)| cell.clone()).collect())
+            .collect(),
+    })
+}
+
+/// Renders each row as a paragraph of `header: cell` pairs.
+fn render_table(table: &Table) -> String {
+    let mut html = String::new();
+    if let Some(caption) = &table.caption {
+        html.push_str(&format!("<p>{}</p>", escape(caption)));
+    }
+
+    for row in &table.rows {
+        let cells = row
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| !html_fragment_to_text(&TAG_RE, cell).is_empty())
+            .map(|(idx, cell)| match table.headers.get(idx) {
+                Some(header) if !header.is_empty() => format!("{}: {}", escape(header), cell),
+                _ => cell.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        if !cells.is_empty() {
+            html.push_str(&format!("<p>{}</p>", cells.join("; ")));
+        }
+    }
+
+    html
+}
+
+/// Rewrites the data tables of a page so each cell's text is preceded by its
+/// column header, e.g. a row of a pricing table becomes `Year: 2024; Price:
+/// $10`. Without this, extracted text runs all the headers together followed
+/// by all the cells & a query can't match a value with its column.
+///
+/// Tables containing other tables, without a header row or missing their
+/// closing tag are left as is.
+pub fn structure_tables(html: &str) -> Cow<'_, str> {
+    // (is opening tag, start, end) of each table tag
+    let tags = TABLE_RE
+        .captures_iter(html)
+        .filter_map(|cap| {
+            let tag = cap.get(0)?;
+            Some((cap[1].is_empty(), tag.start(), tag.end()))
+        })
+        .collect::<Vec<_>>();
+
+    let mut structured = String::new();
+    let mut last_end = 0;
+    // An opening tag followed by a closing one is a table w/o nested tables
+    for pair in tags.windows(2) {
+        let ((is_open, start, inner_start), (is_close, inner_end, end)) = (pair[0], pair[1]);
+        if !is_open || is_close {
+            continue;
+        }
+
+        if let Some(table) = parse_table(&html[inner_start..inner_end]) {
+            structured.push_str(&html[last_end..start]);
+            structured.push_str(&render_table(&table));
+            last_end = end;
+        }
+    }
+
+    if last_end == 0 {
+        return Cow::Borrowed(html);
+    }
+
+    structured.push_str(&html[last_end..]);
+    Cow::Owned(structured)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_table, structure_tables, Table};
+
+    #[test]
+    fn test_parse_table() {
+        let html = r#"
+            <caption>Plans</caption>
+            <thead><tr><th>Year</th><th colspan="2">Price</th></tr></thead>
+            <tbody>
+                <tr><td>2023<td>$8<td>monthly
+                <tr><td>2024</td><td><a href="/pricing">$10</a></td><td></td></tr>
+            </tbody>
+        "#;
+
+        assert_eq!(
+            parse_table(html),
+            Some(Table {
+                caption: Some("Plans".to_string()),
+                headers: vec!["Year".into(), "Price".into(), "Price".into()],
+                rows: vec![
+                    vec!["2023".into(), "$8".into(), "monthly".into()],
+                    vec![
+                        "2024".into(),
+                        r#"<a href="/pricing">$10</a>"#.into(),
+                        "".into()
+                    ],
+                ],
+            })
+        );
+
+        // Tables without a header row are left alone
+        assert_eq!(parse_table("<tr><td>Menu</td><td>Content</td></tr>"), None);
+    }
+
+    #[test]
+    fn test_structure_tables() {
+        let html = r#"<body><p>Pricing</p><table><tr><th>Year</th><th>Price</th></tr><tr><td>2024</td><td>$10</td></tr></table></body>"#;
+        assert_eq!(
+            structure_tables(html),
+            "<body><p>Pricing</p><p>Year: 2024; Price: $10</p></body>"
+        );
+
+        // Nested tables, only the inner data table is restructured
+        let html = r#"<table><tr><td><table><tr><th>A</th></tr><tr><td>1</td></tr></table></td></tr></table>"#;
+        assert_eq!(
+            structure_tables(html),
+            "<table><tr><td><p>A: 1</p></td></tr></table>"
+        );
+
+        // Layout & unclosed tables are left as is
+        let html = r#"<table><tr><td>Menu</td></tr></table><table><tr><th>A</th></tr>"#;
+        assert_eq!(structure_tables(html), html);
+    }
+}
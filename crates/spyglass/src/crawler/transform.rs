@@ -0,0 +1,278 @@
+use regex::{Regex, RegexBuilder};
+use shared::config::{ContentTransform, TransformRule};
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+/// Max number of content transforms that can be configured.
+pub const MAX_CONTENT_TRANSFORMS: usize = 50;
+/// Time the transforms may spend on a single page. Matching is linear in the
+/// size of the page, so pathological rules are caught by this between rules
+/// rather than hanging the worker, any rules left once it's used up are
+/// skipped.
+const TRANSFORM_BUDGET: Duration = Duration::from_millis(250);
+/// Max compiled size of a rule's regex.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+enum CompiledRule {
+    Replace(Regex, String),
+    RemoveElement(Regex),
+}
+
+/// The content transforms for a page, compiled & ready to be applied.
+pub struct ContentTransforms {
+    rules: Vec<CompiledRule>,
+    /// When the page's time budget runs out.
+    deadline: Instant,
+}
+
+impl ContentTransforms {
+    /// Compiles the transforms & starts the page's time budget. Invalid rules
+    /// are rejected when the settings are saved, any that slip through (e.g. a
+    /// hand edited settings file) are skipped.
+    pub fn new(transforms: &[ContentTransform]) -> Self {
+        let rules = transforms
+            .iter()
+            .take(MAX_CONTENT_TRANSFORMS)
+            .filter_map(|transform| match compile(transform) {
+                Ok(rule) => Some(rule),
+                Err(err) => {
+                    log::warn!("Skipping invalid content transform: {}", err);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            rules,
+            deadline: Instant::now() + TRANSFORM_BUDGET,
+        }
+    }
+
+    /// Removes matching elements from the raw HTML of a page.
+    pub fn remove_elements<'a>(&self, html: &'a str) -> Cow<'a, str> {
+        let mut html = Cow::Borrowed(html);
+        for rule in self.rules.iter() {
+            if let CompiledRule::RemoveElement(re) = rule {
+                if self.is_out_of_time() {
+                    break;
+                }
+
+                if let Cow::Owned(updated) = re.replace_all(&html, " ") {
+                    html = Cow::Owned(updated);
+                }
+            }
+        }
+
+        html
+    }
+
+    /// Applies the regex replacements to text extracted from a page.
+    pub fn replace_text(&self, text: String) -> String {
+        let mut text = text;
+        for rule in self.rules.iter() {
+            if let CompiledRule::Replace(re, replacement) = rule {
+                if self.is_out_of_time() {
+                    break;
+                }
+
+                if let Cow::Owned(updated) = re.replace_all(&text, replacement.as_str()) {
+                    text = updated;
+                }
+            }
+        }
+
+        text
+    }
+
+    fn is_out_of_time(&self) -> bool {
+        let out_of_time = Instant::now() > self.deadline;
+        if out_of_time {
+            log::warn!("Content transforms ran out of time, skipping the rest");
+        }
+
+        out_of_time
+    }
+}
+
+fn build_regex(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+/// Turns a `tag`, `tag.class` or `tag#id` selector into a regex matching the
+/// element. Like the boilerplate removal, a nested element with the same tag
+/// ends the match early.
+fn selector_regex(selector: &str) -> Result<Regex, String> {
+    let selector = selector.trim();
+    let (tag, attr) = match selector.find(['.', '#']) {
+        Some(idx) => (&selector[..idx], Some(selector.split_at(idx).1)),
+        None => (selector, None),
+    };
+
+    let is_name = |name: &str| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if !is_name(tag) || !tag.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(format!(
+            "Invalid selector `{selector}`, expected `tag`, `tag.class` or `tag#id`"
+        ));
+    }
+
+    let attr = match attr {
+        None => String::new(),
+        Some(attr) => {
+            let (kind, name) = attr.split_at(1);
+            if !is_name(name) {
+                return Err(format!(
+                    "Invalid selector `{selector}`, expected `tag`, `tag.class` or `tag#id`"
+                ));
+            }
+
+            if kind == "." {
+                format!(r#"[^>]*\bclass\s*=\s*["'](?:[^"']*\s)?{name}(?:\s[^"']*)?["']"#)
+            } else {
+                format!(r#"[^>]*\bid\s*=\s*["']{name}["']"#)
+            }
+        }
+    };
+
+    build_regex(&format!(r"(?is)<{tag}\b{attr}[^>]*>.*?</{tag}\s*>"))
+}
+
+fn compile(transform: &ContentTransform) -> Result<CompiledRule, String> {
+    match &transform.rule {
+        TransformRule::Replace(pattern, replacement) => build_regex(pattern)
+            .map(|re| CompiledRule::Replace(re, replacement.clone()))
+            .map_err(|err| format!("Invalid pattern `{pattern}`: {err}")),
+        TransformRule::RemoveElement(selector) => {
+            selector_regex(selector).map(CompiledRule::RemoveElement)
+        }
+    }
+}
+
+/// Checks the configured content transforms, returning the first problem found.
+pub fn validate(transforms: &[ContentTransform]) -> Result<(), String> {
+    if transforms.len() > MAX_CONTENT_TRANSFORMS {
+        return Err(format!(
+            "Too many content transforms, at most {MAX_CONTENT_TRANSFORMS} are allowed"
+        ));
+    }
+
+    for transform in transforms {
+        if let Some(domain) = &transform.domain {
+            if domain.trim().is_empty() {
+                return Err("Content transform domain can't be empty".to_string());
+            }
+        }
+
+        compile(transform)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{validate, ContentTransforms};
+    use shared::config::{ContentTransform, TransformRule, UserSettings};
+
+    fn for_host(settings: &UserSettings, host: &str) -> ContentTransforms {
+        let transforms = settings
+            .transforms_for_host(host)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        ContentTransforms::new(&transforms)
+    }
+
+    fn transform(domain: Option<&str>, rule: TransformRule) -> ContentTransform {
+        ContentTransform {
+            domain: domain.map(|d| d.to_string()),
+            rule,
+        }
+    }
+
+    #[test]
+    fn test_transforms_for_host() {
+        let settings = UserSettings {
+            content_transforms: vec![
+                transform(
+                    None,
+                    TransformRule::Replace(r"\s+".to_string(), " ".to_string()),
+                ),
+                transform(
+                    Some("example.com"),
+                    TransformRule::RemoveElement("div.cookie-banner".to_string()),
+                ),
+                transform(
+                    Some("example.com"),
+                    TransformRule::Replace("Accept cookies".to_string(), String::new()),
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let html = r#"<body><div class="notice cookie-banner">We use cookies</div><div class="cookie">Hello   world</div> Accept cookies</body>"#;
+
+        let transforms = for_host(&settings, "docs.example.com");
+        let html = transforms.remove_elements(html);
+        assert!(!html.contains("We use cookies"));
+        assert!(html.contains("Hello   world"));
+        assert_eq!(
+            transforms.replace_text("Hello   world Accept cookies".to_string()),
+            "Hello world "
+        );
+
+        // Only the global rule applies to other sites
+        let transforms = for_host(&settings, "other.com");
+        assert_eq!(transforms.remove_elements(html.as_ref()), html);
+        assert_eq!(
+            transforms.replace_text("Hello   world Accept cookies".to_string()),
+            "Hello world Accept cookies"
+        );
+    }
+
+    #[test]
+    fn test_remove_element_by_id() {
+        let settings = UserSettings {
+            content_transforms: vec![transform(
+                None,
+                TransformRule::RemoveElement("pre#setup".to_string()),
+            )],
+            ..Default::default()
+        };
+
+        let transforms = for_host(&settings, "example.com");
+        let html = transforms.remove_elements(r#"<pre id="setup">cargo run</pre><pre>ls</pre>"#);
+        assert_eq!(html, " <pre>ls</pre>");
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[transform(
+            Some("example.com"),
+            TransformRule::RemoveElement("pre".to_string())
+        )])
+        .is_ok());
+        assert!(validate(&[transform(
+            None,
+            TransformRule::Replace("(unclosed".to_string(), String::new())
+        )])
+        .is_err());
+        assert!(validate(&[transform(
+            None,
+            TransformRule::RemoveElement("div > p".to_string())
+        )])
+        .is_err());
+        assert!(validate(&[transform(
+            Some(" "),
+            TransformRule::RemoveElement("pre".to_string())
+        )])
+        .is_err());
+    }
+}
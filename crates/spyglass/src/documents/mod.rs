@@ -1,8 +1,9 @@
 use chrono::Utc;
 use entities::{
     models::{
-        crawl_queue,
+        crawl_queue, document_alias, document_content, document_version,
         indexed_document::{self, find_by_doc_ids},
+        link,
         tag::{self, TagPair},
     },
     sea_orm::{ActiveModelTrait, DatabaseConnection},
@@ -20,7 +21,7 @@ use entities::models::tag::TagType;
 use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
 use spyglass_searcher::{
     schema::{DocumentUpdate, ToDocument},
-    RetrievedDocument, WriteTrait,
+    RetrievedDocument, SearchTrait, WriteTrait,
 };
 
 /// Helper method to delete indexed documents, crawl queue items and search
@@ -62,6 +63,10 @@ pub async fn delete_documents_by_uri(state: &AppState, uri: Vec<String>) {
             log::warn!("Error deleting for indexed document store {:?}", error);
         }
 
+        if let Err(error) = document_version::delete_by_urls(&state.db, chunk).await {
+            log::warn!("Error deleting previous document versions {:?}", error);
+        }
+
         log::info!(
             "chunk: deleted {} ({}) docs from index",
             chunk.len(),
@@ -70,16 +75,81 @@ pub async fn delete_documents_by_uri(state: &AppState, uri: Vec<String>) {
     }
 }
 
+/// Line based diff between two versions of a document. Only changed lines are
+/// included, prefixed with "-" when removed & "+" when added.
+pub fn text_diff(previous: &str, current: &str) -> String {
+    diff::lines(previous, current)
+        .into_iter()
+        .filter_map(|line| match line {
+            diff::Result::Left(removed) => Some(format!("- {removed}")),
+            diff::Result::Right(added) => Some(format!("+ {added}")),
+            diff::Result::Both(_, _) => None,
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 #[derive(Default)]
 pub struct AddUpdateResult {
     pub num_added: usize,
     pub num_updated: usize,
+    /// Recrawled documents that were identical to the indexed version & left
+    /// untouched.
+    pub num_unchanged: usize,
+    /// New pages w/ the same content as an indexed document, recorded as an
+    /// alias instead of being indexed again.
+    pub num_aliased: usize,
+}
+
+/// How a recrawled document differs from the version already in the index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecrawlChange {
+    /// Nothing we index changed, the document doesn't need to be re-added.
+    None,
+    /// Same content, but the title, description and/or tags changed.
+    Metadata,
+    /// The content itself changed.
+    Content,
+}
+
+/// Compares a crawl result (and the tags it will be indexed with) against the
+/// indexed version of the document. Anchor text isn't stored in the index, so
+/// new anchor text for an otherwise unchanged document is picked up the next
+/// time the document itself changes.
+pub fn classify_recrawl(
+    previous: &RetrievedDocument,
+    result: &CrawlResult,
+    tags: &[i64],
+) -> RecrawlChange {
+    let content = result.content.as_deref().unwrap_or_default();
+    let body = result.body.as_deref().unwrap_or_default();
+    if previous.content.trim() != content.trim() || previous.body.trim() != body.trim() {
+        return RecrawlChange::Content;
+    }
+
+    let mut previous_tags = previous.tags.clone();
+    previous_tags.sort_unstable();
+    previous_tags.dedup();
+    let mut new_tags = tags.iter().map(|tag| *tag as u64).collect::<Vec<_>>();
+    new_tags.sort_unstable();
+    new_tags.dedup();
+
+    let title = result.title.as_deref().unwrap_or_default();
+    let description = result.description.as_deref().unwrap_or_default();
+    if previous.title != title
+        || previous.description.trim() != description.trim()
+        || previous_tags != new_tags
+    {
+        RecrawlChange::Metadata
+    } else {
+        RecrawlChange::None
+    }
 }
 
 /// Process a list of crawl results. The following steps will be taken:
 /// 1. Find all urls that already have been processed in the database
-/// 2. Remove any documents that already exist from the index
-/// 3. Add all new results to the index
+/// 2. Remove any documents that already exist & changed from the index
+/// 3. Add all new & changed results to the index
 /// 4. Insert all new documents to the indexed document database
 pub async fn process_crawl_results(
     state: &AppState,
@@ -112,11 +182,37 @@ pub async fn process_crawl_results(
         model_map.insert(model.doc_id.to_string(), model.clone());
     }
 
-    // build a list of doc ids to delete from the index
-    let doc_id_list = id_map.values().cloned().collect::<Vec<String>>();
+    // Hang on to the indexed version so we can tell what changed in the recrawl
+    let mut previous_docs = HashMap::new();
+    for model in &existing {
+        if let Some(doc) = state.index.get(&model.doc_id).await {
+            previous_docs.insert(model.url.to_string(), doc);
+        }
+    }
 
-    // Delete existing docs
-    let _ = state.index.delete_many_by_id(&doc_id_list).await;
+    // Sites often serve the same page under multiple URLs (trailing slashes,
+    // tracking params, www vs non-www). New web pages w/ the same title &
+    // content as an indexed document from the same site are only recorded as
+    // an alias of it.
+    let mut canonical_map: HashMap<(String, String), String> = HashMap::new();
+    let new_hashes = results
+        .iter()
+        .filter(|result| !id_map.contains_key(&result.url))
+        .filter_map(dedupe_hash)
+        .collect::<Vec<String>>();
+    if !new_hashes.is_empty() {
+        let duplicates = indexed_document::Entity::find()
+            .filter(indexed_document::Column::ContentHash.is_in(new_hashes))
+            .all(&state.db)
+            .await
+            .unwrap_or_default();
+        for model in duplicates {
+            if let Some(hash) = model.content_hash {
+                let site = document_alias::alias_site(&model.domain).to_string();
+                canonical_map.entry((site, hash)).or_insert(model.url);
+            }
+        }
+    }
 
     // Find/create the tags for this crawl.
     let mut tag_map: HashMap<String, Vec<i64>> = HashMap::new();
@@ -125,39 +221,150 @@ pub async fn process_crawl_results(
     // Grab tags that applies to all crawl results.
     let global_tids = _get_tag_ids(&state.db, global_tags, &mut tag_cache).await;
 
+    let mut changes = HashMap::new();
+    for crawl_result in results {
+        let mut tags_for_crawl = _get_tag_ids(&state.db, &crawl_result.tags, &mut tag_cache).await;
+        tags_for_crawl.extend(global_tids.clone());
+
+        if let Some(previous) = previous_docs.get(&crawl_result.url) {
+            changes.insert(
+                crawl_result.url.clone(),
+                classify_recrawl(previous, crawl_result, &tags_for_crawl),
+            );
+        }
+        tag_map.insert(crawl_result.url.clone(), tags_for_crawl);
+    }
+
+    // Delete existing docs that will be re-added. Unchanged docs are left as
+    // is, tantivy can only update a document by deleting & re-adding it.
+    let doc_id_list = id_map
+        .iter()
+        .filter(|(url, _)| changes.get(*url) != Some(&RecrawlChange::None))
+        .map(|(_, doc_id)| doc_id.clone())
+        .collect::<Vec<String>>();
+    let _ = state.index.delete_many_by_id(&doc_id_list).await;
+
     // Keep track of document upserts
     let mut inserts = Vec::new();
     let mut updates = Vec::new();
     let mut added_docs = Vec::new();
 
+    let mut num_unchanged = 0;
+    let mut num_aliased = 0;
+    let (index_code_blocks, store_content) = {
+        let settings = state.user_settings.load();
+        (settings.index_code_blocks, settings.store_document_content)
+    };
+
     let tx = state.db.begin().await?;
     for crawl_result in results {
-        // Fetch the tag ids to apply to this crawl.
-        let mut tags_for_crawl = _get_tag_ids(&state.db, &crawl_result.tags, &mut tag_cache).await;
-        tags_for_crawl.extend(global_tids.clone());
-        tag_map.insert(crawl_result.url.clone(), tags_for_crawl.clone());
+        let tags_for_crawl = tag_map.get(&crawl_result.url).cloned().unwrap_or_default();
+        let change = changes.get(&crawl_result.url).copied();
 
-        // Add document to index
         let url = Url::parse(&crawl_result.url)?;
         let url_host = url.host_str().unwrap_or("");
+        let content_hash = dedupe_hash(crawl_result);
+
+        if change == Some(RecrawlChange::None) {
+            // Nothing to re-index, only touch the existing model so we know
+            // it's been checked recently.
+            num_unchanged += 1;
+            if let Some(model) = id_map
+                .get(&crawl_result.url)
+                .and_then(|doc_id| model_map.get(doc_id))
+            {
+                let mut update: indexed_document::ActiveModel = model.to_owned().into();
+                update.updated_at = Set(Utc::now());
+                update.etag = Set(crawl_result.etag.clone());
+                update.last_modified = Set(crawl_result.last_modified.clone());
+                update.content_hash = Set(content_hash);
+                updates.push(update);
+            }
+            continue;
+        }
+
+        if !id_map.contains_key(&crawl_result.url) {
+            if let Some(hash) = content_hash.clone() {
+                let key = (document_alias::alias_site(url_host).to_string(), hash);
+                match canonical_map.get(&key) {
+                    Some(canonical) if canonical != &crawl_result.url => {
+                        document_alias::add(&tx, &crawl_result.url, canonical).await?;
+                        num_aliased += 1;
+                        continue;
+                    }
+                    Some(_) => {}
+                    None => {
+                        canonical_map.insert(key, crawl_result.url.clone());
+                    }
+                }
+            }
+        }
+
+        // Metadata-only changes re-use the indexed text as is, only a content
+        // change needs the new text & a version of the previous content.
+        let previous = previous_docs.get(&crawl_result.url);
+        let (content, body) = match (change, previous) {
+            (Some(RecrawlChange::Metadata), Some(previous)) => (
+                previous.content.clone(),
+                Some(previous.body.clone()).filter(|body| !body.is_empty()),
+            ),
+            _ => (
+                crawl_result.content.clone().unwrap_or_default(),
+                crawl_result.body.clone(),
+            ),
+        };
+
+        // Describe the document using the text of links pointing to it
+        let anchor_text = link::get_anchor_text(&state.db, url.as_str())
+            .await
+            .unwrap_or_default();
+        let title = crawl_result.title.clone().unwrap_or_default();
+        let description = crawl_result.description.clone().unwrap_or_default();
+        let code: &[String] = if index_code_blocks {
+            &crawl_result.code
+        } else {
+            &[]
+        };
         // Add document to index
         let doc_id = state
             .index
             .upsert(
                 &DocumentUpdate {
                     doc_id: id_map.get(&crawl_result.url).cloned(),
-                    title: &crawl_result.title.clone().unwrap_or_default(),
+                    title: &title,
+                    description: &description,
                     domain: url_host,
                     url: url.as_str(),
-                    content: &crawl_result.content.clone().unwrap_or_default(),
-                    tags: &tags_for_crawl.clone(),
+                    content: &content,
+                    tags: &tags_for_crawl,
+                    anchor_text: &anchor_text,
+                    code,
+                    body: body.as_deref(),
                     published_at: None,
-                    last_modified: None,
+                    last_modified: Some(Utc::now()),
                 }
                 .to_document(),
             )
             .await?;
 
+        if store_content {
+            let new_content = document_content::NewContent {
+                title: &title,
+                description: &description,
+                content: &content,
+                body: body.as_deref(),
+                code,
+                published_at: None,
+            };
+            document_content::save(&tx, url.as_str(), &new_content).await?;
+        }
+
+        if let (Some(RecrawlChange::Content), Some(previous)) = (change, previous) {
+            if previous.content.trim() != content.trim() {
+                document_version::save_previous(&tx, url.as_str(), &previous.content).await?;
+            }
+        }
+
         if !id_map.contains_key(&doc_id) {
             added_docs.push(url.to_string());
             inserts.push(indexed_document::ActiveModel {
@@ -165,25 +372,36 @@ pub async fn process_crawl_results(
                 url: Set(url.to_string()),
                 open_url: Set(crawl_result.open_url.clone()),
                 doc_id: Set(doc_id),
+                depth: Set(crawl_result.depth),
                 updated_at: Set(Utc::now()),
+                etag: Set(crawl_result.etag.clone()),
+                last_modified: Set(crawl_result.last_modified.clone()),
+                content_hash: Set(content_hash),
                 ..Default::default()
             });
         } else if let Some(model) = model_map.get(&doc_id) {
             // Touch the existing model so we know it's been checked recently.
             let mut update: indexed_document::ActiveModel = model.to_owned().into();
             update.updated_at = Set(Utc::now());
+            update.etag = Set(crawl_result.etag.clone());
+            update.last_modified = Set(crawl_result.last_modified.clone());
+            update.content_hash = Set(content_hash);
             updates.push(update);
         }
     }
 
-    // Insert docs & save everything.
+    // Insert docs & save everything. Pages that used to be an alias are their
+    // own document now.
     indexed_document::insert_many(&tx, &inserts).await?;
+    document_alias::remove_many(&tx, &added_docs).await?;
     for update in updates {
         let _ = update.save(&tx).await;
     }
 
     tx.commit().await?;
-    let _ = state.index.save().await;
+    if num_unchanged + num_aliased < results.len() {
+        let _ = state.index.save().await;
+    }
 
     // Find the recently added docs & apply the tags to them.
     let added_entries: Vec<indexed_document::Model> = indexed_document::Entity::find()
@@ -208,13 +426,37 @@ pub async fn process_crawl_results(
         now.elapsed().as_millis()
     );
 
-    let num_updates = existing.len();
+    let num_updates = existing.len() - num_unchanged;
     Ok(AddUpdateResult {
         num_added: num_entries - num_updates,
         num_updated: num_updates,
+        num_unchanged,
+        num_aliased,
     })
 }
 
+/// Content hash used to spot duplicate pages. Only web pages w/ some content
+/// are deduplicated, identical files & connection documents are kept apart.
+fn dedupe_hash(result: &CrawlResult) -> Option<String> {
+    let is_web = Url::parse(&result.url)
+        .map(|url| url.scheme() == "http" || url.scheme() == "https")
+        .unwrap_or_default();
+    let has_content = result
+        .content
+        .as_deref()
+        .map(|content| !content.trim().is_empty())
+        == Some(true);
+
+    if is_web && has_content {
+        Some(document_alias::content_hash(
+            result.title.as_deref().unwrap_or_default(),
+            result.content.as_deref().unwrap_or_default(),
+        ))
+    } else {
+        None
+    }
+}
+
 // Process a list of crawl results. The following steps will be taken:
 // 1. Find all urls that already have been processed in the database
 // 2. Remove any documents that already exist from the index
@@ -250,6 +492,14 @@ pub async fn process_records(
         .map(|x| x.to_owned())
         .collect::<Vec<String>>();
 
+    // Records don't include code blocks, keep the ones already indexed
+    let mut code_map = HashMap::new();
+    for doc_id in &doc_id_list {
+        if let Some(doc) = state.index.get(doc_id).await {
+            code_map.insert(doc_id.clone(), doc.code);
+        }
+    }
+
     let _ = state.index.delete_many_by_id(&doc_id_list).await;
 
     // Grab tags from the lens.
@@ -281,20 +531,33 @@ pub async fn process_records(
             match Url::parse(canonical_url_str) {
                 Ok(url) => {
                     let url_host = url.host_str().unwrap_or("");
+                    let anchor_text = link::get_anchor_text(&state.db, url.as_str())
+                        .await
+                        .unwrap_or_default();
+                    let existing_id = id_map.get(canonical_url_str).cloned();
+                    let code = existing_id
+                        .as_ref()
+                        .and_then(|doc_id| code_map.get(doc_id))
+                        .cloned()
+                        .unwrap_or_default();
                     // Add document to index
                     let doc_id: Option<String> = {
                         match state
                             .index
                             .upsert(
                                 &DocumentUpdate {
-                                    doc_id: id_map.get(&canonical_url_str.clone()).cloned(),
+                                    doc_id: existing_id,
                                     title: &crawl_result.title.clone().unwrap_or_default(),
+                                    description: "",
                                     domain: url_host,
                                     url: url.as_str(),
                                     content: &crawl_result.content,
                                     tags: &tag_list,
+                                    anchor_text: &anchor_text,
+                                    code: &code,
+                                    body: None,
                                     published_at: None,
-                                    last_modified: None,
+                                    last_modified: Some(Utc::now()),
                                 }
                                 .to_document(),
                             )
@@ -334,9 +597,7 @@ pub async fn process_records(
     // Save the data
     indexed_document::insert_many(&transaction, &updates).await?;
     transaction.commit().await?;
-    if let Ok(mut writer) = state.index.lock_writer() {
-        let _ = writer.commit();
-    }
+    let _ = state.index.save().await;
 
     let added_entries: Vec<indexed_document::Model> = indexed_document::Entity::find()
         .filter(indexed_document::Column::Url.is_in(added_docs))
@@ -443,18 +704,25 @@ pub async fn update_tags(
 
         log::debug!("Tag map generated {}", tag_map.len());
         for (_, (doc, ids)) in tag_map.iter() {
+            let anchor_text = link::get_anchor_text(&state.db, &doc.url)
+                .await
+                .unwrap_or_default();
             let _doc_id = state
                 .index
                 .upsert(
                     &DocumentUpdate {
                         doc_id: Some(doc.doc_id.clone()),
                         title: &doc.title,
+                        description: &doc.description,
                         domain: &doc.domain,
                         url: &doc.url,
                         content: &doc.content,
                         tags: ids,
-                        published_at: None,
-                        last_modified: None,
+                        anchor_text: &anchor_text,
+                        code: &doc.code,
+                        body: Some(&doc.body),
+                        published_at: doc.published_at,
+                        last_modified: doc.last_modified,
                     }
                     .to_document(),
                 )
@@ -465,6 +733,37 @@ pub async fn update_tags(
     Ok(())
 }
 
+/// Adds a document that was removed from the index back as it was, e.g. when
+/// the db changes that went along w/ removing it couldn't be saved.
+pub async fn restore_document(state: &AppState, doc: &RetrievedDocument) -> anyhow::Result<()> {
+    let tags = doc.tags.iter().map(|id| *id as i64).collect::<Vec<i64>>();
+    let anchor_text = link::get_anchor_text(&state.db, &doc.url)
+        .await
+        .unwrap_or_default();
+    state
+        .index
+        .upsert(
+            &DocumentUpdate {
+                doc_id: Some(doc.doc_id.clone()),
+                title: &doc.title,
+                description: &doc.description,
+                domain: &doc.domain,
+                url: &doc.url,
+                content: &doc.content,
+                tags: &tags,
+                anchor_text: &anchor_text,
+                code: &doc.code,
+                body: Some(&doc.body),
+                published_at: doc.published_at,
+                last_modified: doc.last_modified,
+            }
+            .to_document(),
+        )
+        .await?;
+
+    Ok(())
+}
+
 /// Helper method used to get the tag ids for a specific crawl result. The tag map and the tag cache
 /// will be modified as results are processed. The tag map contains the url to tag it mapping used
 /// for insertion to the database. The tag_cache is used to avoid additional loops for common tags
@@ -542,3 +841,91 @@ async fn _get_tag_ids(
 
     tids
 }
+
+#[cfg(test)]
+mod test {
+    use super::{classify_recrawl, text_diff, RecrawlChange};
+    use crate::crawler::CrawlResult;
+    use spyglass_searcher::RetrievedDocument;
+
+    fn indexed_doc() -> RetrievedDocument {
+        RetrievedDocument {
+            doc_id: "doc".into(),
+            domain: "example.com".into(),
+            title: "Example".into(),
+            description: String::new(),
+            content: "Some page content".into(),
+            body: String::new(),
+            url: "https://example.com/".into(),
+            tags: vec![1, 2],
+            code: Vec::new(),
+            published_at: None,
+            last_modified: None,
+        }
+    }
+
+    fn recrawl(title: &str, content: &str) -> CrawlResult {
+        CrawlResult {
+            title: Some(title.into()),
+            content: Some(content.into()),
+            url: "https://example.com/".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_text_diff() {
+        let previous = "title\nold line\nfooter";
+        let current = "title\nnew line\nfooter\nextra";
+        assert_eq!(
+            text_diff(previous, current),
+            "- old line\n+ new line\n+ extra"
+        );
+        assert_eq!(text_diff(previous, previous), "");
+    }
+
+    #[test]
+    fn test_classify_recrawl() {
+        let previous = indexed_doc();
+
+        // Tag order doesn't matter
+        assert_eq!(
+            classify_recrawl(&previous, &recrawl("Example", "Some page content"), &[2, 1]),
+            RecrawlChange::None
+        );
+        assert_eq!(
+            classify_recrawl(
+                &previous,
+                &recrawl("New title", "Some page content"),
+                &[1, 2]
+            ),
+            RecrawlChange::Metadata
+        );
+        assert_eq!(
+            classify_recrawl(
+                &previous,
+                &recrawl("Example", "Some page content"),
+                &[1, 2, 3]
+            ),
+            RecrawlChange::Metadata
+        );
+        assert_eq!(
+            classify_recrawl(&previous, &recrawl("New title", "Updated content"), &[1, 2]),
+            RecrawlChange::Content
+        );
+
+        let mut with_description = recrawl("Example", "Some page content");
+        with_description.description = Some("A page about examples".into());
+        assert_eq!(
+            classify_recrawl(&previous, &with_description, &[1, 2]),
+            RecrawlChange::Metadata
+        );
+
+        let mut with_body = recrawl("Example", "Some page content");
+        with_body.body = Some("Main article".into());
+        assert_eq!(
+            classify_recrawl(&previous, &with_body, &[1, 2]),
+            RecrawlChange::Content
+        );
+    }
+}
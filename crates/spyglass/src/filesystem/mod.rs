@@ -814,6 +814,7 @@ async fn _process_file_and_dir(
             is_recrawl: true,
             tags,
             force_allow: true,
+            ..Default::default()
         };
         if let Err(error) =
             crawl_queue::enqueue_local_files(&state.db, &enqueue_list, &enqueue_settings, None)
@@ -867,6 +868,82 @@ pub fn build_file_tags(path: &Path) -> Vec<TagPair> {
     tags
 }
 
+/// Walks `root` and queues every readable file for indexing under `lens`. Only
+/// files with one of the given `extensions` are queued, or any supported file
+/// type when no extensions are given. Files are queued in batches as they're
+/// found so large directories never need to be listed in full. Returns the
+/// number of files queued.
+pub async fn index_folder(
+    state: &AppState,
+    lens: &str,
+    root: &Path,
+    extensions: &HashSet<String>,
+) -> anyhow::Result<usize> {
+    let extensions = extensions
+        .iter()
+        .map(|ext| ext.trim_start_matches('.').to_lowercase())
+        .collect::<HashSet<_>>();
+
+    let enqueue_settings = EnqueueSettings {
+        crawl_type: CrawlType::Normal,
+        is_recrawl: true,
+        tags: vec![(TagType::Lens, lens.to_string())],
+        force_allow: true,
+        ..Default::default()
+    };
+
+    let mut num_queued = 0;
+    let mut batch = Vec::new();
+    for entry in WalkBuilder::new(root).build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::debug!("Skipping unreadable path: {}", err);
+                continue;
+            }
+        };
+
+        let path = entry.path();
+        if !entry.file_type().map(|ft| ft.is_file()).unwrap_or_default() {
+            continue;
+        }
+
+        let ext = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_lowercase())
+            .unwrap_or_default();
+        let is_match = if extensions.is_empty() {
+            SupportedExt::from_ext(&ext) != SupportedExt::NotSupported
+        } else {
+            extensions.contains(&ext)
+        };
+
+        if !is_match {
+            continue;
+        }
+
+        if let Err(err) = std::fs::File::open(path) {
+            log::debug!("Skipping unreadable file {:?}: {}", path, err);
+            continue;
+        }
+
+        batch.push(utils::path_to_uri(path));
+        if batch.len() >= BATCH_SIZE {
+            crawl_queue::enqueue_local_files(&state.db, &batch, &enqueue_settings, None).await?;
+            num_queued += batch.len();
+            batch.clear();
+        }
+    }
+
+    if !batch.is_empty() {
+        crawl_queue::enqueue_local_files(&state.db, &batch, &enqueue_settings, None).await?;
+        num_queued += batch.len();
+    }
+
+    Ok(num_queued)
+}
+
 // Helper method used process files
 async fn _process_general_file(state: &AppState, file_uri: &[String]) {
     let crawl_results = file_uri
@@ -912,15 +989,76 @@ fn _path_to_result(url: &Url, path: &Path) -> Option<CrawlResult> {
         Some(CrawlResult {
             content_hash: Some(content_hash),
             content: Some(file_name.clone()),
+            body: None,
             // Does a file have a description? Pull the first part of the file
             description: None,
             title: Some(title),
             url: url.to_string(),
             open_url: Some(url.to_string()),
             links: Default::default(),
+            anchors: Vec::new(),
             tags,
+            depth: 0,
+            is_feed: false,
+            bytes_fetched: 0,
+            next_page: None,
+            prev_page: None,
+            etag: None,
+            last_modified: None,
+            code: Vec::new(),
         })
     } else {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use entities::models::crawl_queue;
+    use entities::test::setup_test_db;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    use super::{index_folder, utils};
+    use crate::state::AppState;
+
+    #[tokio::test]
+    async fn test_index_folder() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db).build();
+
+        #[cfg(target_os = "windows")]
+        let test_folder = Path::new("C:\\tmp\\index_folder");
+        #[cfg(not(target_os = "windows"))]
+        let test_folder = Path::new("/tmp/index_folder");
+
+        std::fs::create_dir_all(test_folder.join("nested")).expect("Unable to create test dir");
+        for name in ["a.txt", "b.md", "c.unknown", "nested/d.txt"] {
+            std::fs::write(test_folder.join(name), "test_content")
+                .expect("Unable to write test file");
+        }
+
+        // Only supported file types are queued by default
+        let num_queued = index_folder(&state, "docs", test_folder, &HashSet::new())
+            .await
+            .unwrap();
+        assert_eq!(num_queued, 3);
+
+        let queued = crawl_queue::queued_by_lens(&state.db, "docs")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|task| task.url)
+            .collect::<HashSet<_>>();
+        assert!(queued.contains(&utils::path_to_uri(&test_folder.join("nested/d.txt"))));
+        assert!(!queued.contains(&utils::path_to_uri(&test_folder.join("c.unknown"))));
+
+        let extensions = HashSet::from([".MD".to_string()]);
+        let num_queued = index_folder(&state, "notes", test_folder, &extensions)
+            .await
+            .unwrap();
+        assert_eq!(num_queued, 1);
+
+        std::fs::remove_dir_all(test_folder).expect("Unable to clean up test folder");
+    }
+}
@@ -1,5 +1,5 @@
 use super::PipelineContext;
-use crate::crawler::{CrawlResult, Crawler};
+use crate::crawler::{CrawlResult, Crawler, HttpClients};
 
 pub trait PipelineCollector {
     fn collect(
@@ -48,6 +48,13 @@ impl DefaultCollector {
             crawler: Crawler::default(),
         }
     }
+
+    /// Creates a collector that reuses existing HTTP clients.
+    pub fn with_clients(clients: HttpClients) -> Self {
+        Self {
+            crawler: Crawler::with_clients(clients, 10),
+        }
+    }
 }
 
 impl Default for DefaultCollector {
@@ -2,6 +2,7 @@ use crate::pipeline::collector::DefaultCollector;
 use crate::pipeline::PipelineContext;
 use crate::state::AppState;
 use crate::task::CrawlTask;
+use entities::models::crawl_queue::{TaskError, TaskErrorType};
 use entities::models::{crawl_queue, indexed_document};
 use entities::sea_orm::prelude::*;
 use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
@@ -28,7 +29,7 @@ pub async fn pipeline_loop(
     let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
     log::debug!("Default Pipeline Loop Started for Pipeline: {:?}", pipeline);
 
-    let collector = DefaultCollector::new();
+    let collector = DefaultCollector::with_clients(state.http_clients.clone());
     let parser = DefaultParser::new();
     loop {
         log::debug!("Running pipeline loop");
@@ -130,6 +131,12 @@ async fn start_crawl(
                             let _ = indexed_document::delete_many_by_id(&state.db, &[doc.id]).await;
                         }
 
+                        let code = if state.user_settings.load().index_code_blocks {
+                            crawl_result.code
+                        } else {
+                            Vec::new()
+                        };
+
                         // Add document to index
                         let doc_id: Option<String> = {
                             match state
@@ -138,12 +145,16 @@ async fn start_crawl(
                                     &DocumentUpdate {
                                         doc_id: existing.clone().map(|f| f.doc_id),
                                         title: &crawl_result.title.unwrap_or_default(),
+                                        description: &crawl_result.description.unwrap_or_default(),
                                         domain: url_host,
                                         url: url.as_str(),
                                         content: &content,
                                         tags: &[],
+                                        anchor_text: &[],
+                                        code: &code,
+                                        body: None,
                                         published_at: None,
-                                        last_modified: None,
+                                        last_modified: Some(chrono::Utc::now()),
                                     }
                                     .to_document(),
                                 )
@@ -178,14 +189,26 @@ async fn start_crawl(
                 Err(err) => {
                     log::info!("Unable to crawl id: {} - {:?}", task.id, err);
                     // mark crawl as failed
-                    crawl_queue::mark_failed(&state.db, task.id, false).await;
+                    crawl_queue::mark_failed(
+                        &state.db,
+                        task.id,
+                        0,
+                        TaskError::new(TaskErrorType::Parse, &err),
+                    )
+                    .await;
                 }
             }
         }
         Err(err) => {
             log::info!("Unable to crawl id: {} - {:?}", task.id, err);
             // mark crawl as failed
-            crawl_queue::mark_failed(&state.db, task.id, false).await;
+            crawl_queue::mark_failed(
+                &state.db,
+                task.id,
+                0,
+                TaskError::new(TaskErrorType::Collect, &err),
+            )
+            .await;
         }
     }
 }
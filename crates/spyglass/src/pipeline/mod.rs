@@ -5,7 +5,7 @@ pub mod parser;
 
 use crate::state::AppState;
 use crate::task::{lens::read_lenses, CrawlTask};
-use entities::models::crawl_queue;
+use entities::models::crawl_queue::{self, TaskError, TaskErrorType};
 use shared::config::Config;
 use shared::config::PipelineConfiguration;
 use std::collections::HashMap;
@@ -122,7 +122,8 @@ pub async fn initialize_pipelines(
                         }
                         None => {
                             log::warn!("No pipeline configuration found for pipeline {:?}, failing crawl id: {}", &pipeline, task.id);
-                            fail_crawl_cmd(&app_state, task.id).await;
+                            let msg = format!("No pipeline configuration found for {pipeline}");
+                            fail_crawl_cmd(&app_state, task.id, &msg).await;
                         }
                     }
                 }
@@ -145,9 +146,10 @@ pub async fn initialize_pipelines(
 }
 
 // Helper function used to set any crawl failures with the status of failed.
-pub async fn fail_crawl_cmd(state: &AppState, task_uid: i64) {
+pub async fn fail_crawl_cmd(state: &AppState, task_uid: i64, msg: &str) {
     // mark crawl as failed
-    crawl_queue::mark_failed(&state.db, task_uid, false).await;
+    let error = TaskError::new(TaskErrorType::Collect, msg);
+    crawl_queue::mark_failed(&state.db, task_uid, 0, error).await;
 }
 
 /// Read pipelines into the AppState
@@ -80,7 +80,9 @@ async fn handle_plugin_cmd_request(
             env.app_state.index.delete_many_by_id(&doc_ids).await?;
         }
         // Enqueue a list of URLs to be crawled
-        PluginCommandRequest::Enqueue { urls } => handle_plugin_enqueue(env, urls),
+        PluginCommandRequest::Enqueue { urls, priority } => {
+            handle_plugin_enqueue(env, urls, *priority)
+        }
         PluginCommandRequest::QueryDocuments { query, subscribe } => {
             if *subscribe {
                 tokio::spawn(query_document_and_send_loop(env.clone(), query.clone()));
@@ -443,7 +445,7 @@ fn _handle_sync_file(env: &PluginEnv, dst: &str, src: &str) {
     }
 }
 
-fn handle_plugin_enqueue(env: &PluginEnv, urls: &Vec<String>) {
+fn handle_plugin_enqueue(env: &PluginEnv, urls: &Vec<String>, priority: i32) {
     log::info!("{} enqueuing {} urls", env.name, urls.len());
     let state = env.app_state.clone();
     // Grab a handle to the plugin manager runtime
@@ -459,6 +461,7 @@ fn handle_plugin_enqueue(env: &PluginEnv, urls: &Vec<String>) {
             &state.user_settings.load(),
             &EnqueueSettings {
                 force_allow: true,
+                priority,
                 ..Default::default()
             },
             Option::None,
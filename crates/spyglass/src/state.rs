@@ -5,14 +5,19 @@ use entities::sea_orm::DatabaseConnection;
 use spyglass_rpc::RpcEvent;
 use spyglass_searcher::schema::DocFields;
 use spyglass_searcher::schema::SearchDocument;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Instant;
 use tantivy::schema::Schema;
 use tokio::sync::mpsc::error::SendError;
-use tokio::sync::Mutex;
 use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{Mutex, RwLock};
 
+use crate::crawler::{recrawl_cache::RecrawlCache, session::CrawlRecorder, HttpClients};
 use crate::filesystem::SpyglassFileWatcher;
-use crate::task::{AppShutdown, UserSettingsChange};
+use crate::task::{
+    circuit_breaker::CrawlCircuitBreaker, recrawl_job::RecrawlJobs, AppShutdown, UserSettingsChange,
+};
 use crate::{
     pipeline::PipelineCommand,
     plugin::{PluginCommand, PluginManager},
@@ -62,6 +67,9 @@ pub struct AppState {
     pub pipelines: Arc<DashMap<String, PipelineConfiguration>>,
     pub user_settings: Arc<ArcSwap<UserSettings>>,
     pub index: Searcher,
+    // HTTP clients shared by all crawlers so connections are pooled across
+    // workers, see `http_client`.
+    pub http_clients: HttpClients,
     pub metrics: Metrics,
     pub config: Config,
     // Task scheduler command/control
@@ -80,10 +88,41 @@ pub struct AppState {
     pub file_watcher: Arc<Mutex<Option<SpyglassFileWatcher>>>,
     // Keep track of in-flight tasks
     pub fetch_limits: Arc<DashMap<FetchLimitType, usize>>,
+    // Recently crawled pages, so rapid recrawls of unchanged pages are skipped.
+    pub recrawl_cache: RecrawlCache,
+    // Pauses web crawling while most fetches are failing.
+    pub crawl_breaker: CrawlCircuitBreaker,
+    // Saves crawls to a session file when `record_crawls` is enabled.
+    pub crawl_recorder: CrawlRecorder,
+    // When a crawl of each domain was last sent to the workers, used to space
+    // out fetches from the same domain.
+    pub domain_last_fetched: Arc<DashMap<String, Instant>>,
+    // Web crawls sent to the workers that haven't finished yet, keyed by task
+    // id w/ the domain they're fetching from.
+    pub inflight_crawls: Arc<DashMap<i64, String>>,
+    // Domains whose sitemap has been queued since the app started.
+    pub sitemap_domains: Arc<DashMap<String, Instant>>,
+    // Progress of full lens recrawls, updated as the workers finish tasks.
+    pub recrawl_jobs: RecrawlJobs,
+    // Workers hold a read lock while writing crawl results, database
+    // maintenance (e.g. VACUUM) takes the write lock so it doesn't compete
+    // with them.
+    pub db_maintenance: Arc<RwLock<()>>,
+    pub is_vacuuming: Arc<AtomicBool>,
+    // Vacuum requested outside of the maintenance window, run once it opens.
+    pub vacuum_deferred: Arc<AtomicBool>,
+    pub is_reindexing: Arc<AtomicBool>,
     pub readonly_mode: bool,
 }
 
 impl AppState {
+    /// Client for requests made outside of a crawl (e.g. sitemaps), sent
+    /// through the current crawl proxy.
+    pub fn http_client(&self) -> reqwest::Client {
+        let proxy = self.user_settings.load().crawl_proxy.clone();
+        self.http_clients.client_or_direct(proxy.as_deref())
+    }
+
     pub async fn new(config: &Config, readonly_mode: bool) -> Self {
         let db_connection_result = create_connection(config, false).await;
         if let Err(error) = &db_connection_result {
@@ -92,13 +131,17 @@ impl AppState {
 
         let db = db_connection_result.expect("Unable to connect to database");
 
-        AppStateBuilder::new()
-            .with_db(db)
-            .with_index(
-                &IndexBackend::LocalPath(config.index_dir()),
-                DocFields::as_schema(),
-                readonly_mode,
-            )
+        let mut builder = AppStateBuilder::new();
+        builder.with_db(db).with_index(
+            &IndexBackend::LocalPath(config.index_dir()),
+            DocFields::as_schema(),
+            readonly_mode,
+        );
+        if config.user_settings.lens_indexes && !readonly_mode {
+            builder.with_lens_indexes(&IndexBackend::LocalPath(config.lens_index_dir()));
+        }
+
+        builder
             .with_lenses(&config.lenses.values().cloned().collect())
             .with_pipelines(
                 &config
@@ -151,6 +194,7 @@ impl AppState {
 pub struct AppStateBuilder {
     db: Option<DatabaseConnection>,
     index: Option<Searcher>,
+    lens_indexes: Option<IndexBackend>,
     lenses: Option<Vec<LensConfig>>,
     pipelines: Option<Vec<PipelineConfiguration>>,
     user_settings: Option<UserSettings>,
@@ -173,12 +217,17 @@ impl AppStateBuilder {
             }
         }
 
-        let index = if let Some(index) = &self.index {
+        let mut index = if let Some(index) = &self.index {
             index.to_owned()
         } else {
             Searcher::with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
                 .expect("Unable to open search index")
         };
+        if let Some(lens_indexes) = &self.lens_indexes {
+            index = index
+                .with_lens_indexes(lens_indexes)
+                .expect("Unable to open lens indexes");
+        }
 
         let user_settings = if let Some(settings) = &self.user_settings {
             settings.to_owned()
@@ -194,6 +243,7 @@ impl AppStateBuilder {
             app_state: Arc::new(DashMap::new()),
             db: self.db.as_ref().expect("Must set db").to_owned(),
             index,
+            http_clients: HttpClients::default(),
             lenses: Arc::new(lenses),
             manager_cmd_tx: Arc::new(Mutex::new(None)),
             metrics: Metrics::new(
@@ -212,6 +262,17 @@ impl AppStateBuilder {
             file_watcher: Arc::new(Mutex::new(None)),
             user_settings: Arc::new(ArcSwap::from_pointee(user_settings)),
             fetch_limits: Arc::new(DashMap::new()),
+            recrawl_cache: RecrawlCache::default(),
+            recrawl_jobs: RecrawlJobs::default(),
+            crawl_breaker: CrawlCircuitBreaker::default(),
+            crawl_recorder: CrawlRecorder::default(),
+            domain_last_fetched: Arc::new(DashMap::new()),
+            inflight_crawls: Arc::new(DashMap::new()),
+            sitemap_domains: Arc::new(DashMap::new()),
+            db_maintenance: Arc::new(RwLock::new(())),
+            is_vacuuming: Arc::new(AtomicBool::new(false)),
+            vacuum_deferred: Arc::new(AtomicBool::new(false)),
+            is_reindexing: Arc::new(AtomicBool::new(false)),
             readonly_mode: self.readonly_mode.unwrap_or_default(),
         }
     }
@@ -260,4 +321,10 @@ impl AppStateBuilder {
         self.index = Some(searcher.expect("Unable to open index"));
         self
     }
+
+    /// Keep a separate index for each lens, see `Searcher::with_lens_indexes`
+    pub fn with_lens_indexes(&mut self, index: &IndexBackend) -> &mut Self {
+        self.lens_indexes = Some(index.to_owned());
+        self
+    }
 }
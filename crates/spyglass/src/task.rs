@@ -1,14 +1,15 @@
 use anyhow::anyhow;
 use entities::models::crawl_queue::CrawlStatus;
-use entities::models::{bootstrap_queue, connection, crawl_queue};
+use entities::models::{bootstrap_queue, connection, crawl_queue, url_pattern};
 use entities::sea_orm::{sea_query::Expr, ColumnTrait, Condition, EntityTrait, QueryFilter};
-use futures::StreamExt;
+use futures::{FutureExt, StreamExt};
 use notify::event::ModifyKind;
 use notify::{EventKind, RecursiveMode, Watcher};
 use shared::config::{Config, LensConfig, UserSettings, UserSettingsDiff};
 use spyglass_rpc::{ModelDownloadStatusPayload, RpcEvent, RpcEventType};
 use std::fs::File;
 use std::io::Write;
+use std::panic::AssertUnwindSafe;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
@@ -24,11 +25,31 @@ use crate::task::worker::FetchResult;
 use diff::Diff;
 use spyglass_processor::utils::extensions::AudioExt;
 
+pub mod circuit_breaker;
+pub mod crash;
 pub mod lens;
+pub mod maintenance;
 mod manager;
+pub mod recrawl_job;
+pub mod saved_query;
 pub mod worker;
 use lens::{load_lenses, read_lenses};
 
+/// How often to check for expired tasks in the crawl queue
+const QUEUE_EXPIRE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often to check for saved queries that are due to run
+const SAVED_QUERY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often to try a test fetch while crawling is paused due to failures
+const CRAWL_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+/// How often to check whether deferred maintenance can run
+const MAINTENANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often to check whether any lens feeds are due to be polled
+const FEED_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// How often to check for indexed pages that are due to be recrawled
+const RECRAWL_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How long to wait before restarting a task that panicked
+const TASK_RESTART_DELAY: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Clone)]
 pub struct CrawlTask {
     pub id: i64,
@@ -104,17 +125,51 @@ pub enum AppShutdown {
 }
 
 /// Manages the worker pool, scheduling tasks based on type/priority/etc.
+/// Restarts the manager if it panics.
 #[tracing::instrument(skip_all)]
 pub async fn manager_task(
     state: AppState,
     queue: mpsc::Sender<WorkerCommand>,
     manager_cmd_tx: mpsc::UnboundedSender<ManagerCommand>,
     mut manager_cmd_rx: mpsc::UnboundedReceiver<ManagerCommand>,
+) {
+    loop {
+        let res = AssertUnwindSafe(run_manager(
+            &state,
+            &queue,
+            &manager_cmd_tx,
+            &mut manager_cmd_rx,
+        ))
+        .catch_unwind()
+        .await;
+
+        match res {
+            Ok(()) => return,
+            Err(panic) => {
+                crash::record_panic(&state, "manager", panic);
+                tokio::time::sleep(TASK_RESTART_DELAY).await;
+                log::info!("restarting manager");
+            }
+        }
+    }
+}
+
+async fn run_manager(
+    state: &AppState,
+    queue: &mpsc::Sender<WorkerCommand>,
+    manager_cmd_tx: &mpsc::UnboundedSender<ManagerCommand>,
+    manager_cmd_rx: &mut mpsc::UnboundedReceiver<ManagerCommand>,
 ) {
     log::info!("manager started");
 
     let mut queue_check_interval = tokio::time::interval(Duration::from_millis(100));
     let mut commit_check_interval = tokio::time::interval(Duration::from_secs(10));
+    let mut queue_expire_interval = tokio::time::interval(QUEUE_EXPIRE_INTERVAL);
+    let mut saved_query_interval = tokio::time::interval(SAVED_QUERY_CHECK_INTERVAL);
+    let mut crawl_probe_interval = tokio::time::interval(CRAWL_PROBE_INTERVAL);
+    let mut maintenance_interval = tokio::time::interval(MAINTENANCE_CHECK_INTERVAL);
+    let mut recrawl_interval = tokio::time::interval(RECRAWL_CHECK_INTERVAL);
+    let mut feed_poll_interval = tokio::time::interval(FEED_POLL_INTERVAL);
     let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
     // Startup filesystem watcher
     filesystem::configure_watcher(state.clone()).await;
@@ -137,7 +192,10 @@ pub async fn manager_task(
                             }
                         },
                         ManagerCommand::CheckForJobs => {
-                            if !manager::check_for_jobs(&state, &queue).await {
+                            // Hold off on new jobs while the database is being vacuumed
+                            let has_jobs = !state.is_vacuuming.load(Ordering::Relaxed)
+                                && manager::check_for_jobs(state, queue).await;
+                            if !has_jobs {
                                 // If no jobs were queue, sleep longer. This will keep
                                 // CPU usage low when there is nothing going on and
                                 // let the manager process jobs as quickly as possible
@@ -158,6 +216,61 @@ pub async fn manager_task(
             _ = commit_check_interval.tick() => {
                 let _ = queue.send(WorkerCommand::CommitIndex).await;
             }
+            // Expire tasks that have been queued for too long
+            _ = queue_expire_interval.tick() => {
+                let ttl_days = state.user_settings.load().queue_item_ttl_days;
+                if ttl_days > 0 && maintenance::can_run_maintenance(&state) {
+                    match crawl_queue::expire_tasks(&state.db, ttl_days).await {
+                        Ok(0) => {}
+                        Ok(count) => {
+                            log::info!("expired {} queued tasks older than {} days", count, ttl_days)
+                        }
+                        Err(err) => log::error!("Unable to expire queued tasks: {}", err),
+                    }
+                }
+                if maintenance::can_run_maintenance(&state) {
+                    // Forget old crawl trap counts so sites get another chance
+                    if let Err(err) = url_pattern::delete_stale(&state.db).await {
+                        log::error!("Unable to reset url patterns: {}", err);
+                    }
+                }
+            }
+            // Run maintenance deferred until the maintenance window
+            _ = maintenance_interval.tick() => {
+                maintenance::run_deferred(&state);
+            }
+            // Fetch lens feeds again to pick up new entries
+            _ = feed_poll_interval.tick() => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    lens::poll_lens_feeds(&state).await;
+                });
+            }
+            // Queue pages that haven't been crawled in a while
+            _ = recrawl_interval.tick() => {
+                if maintenance::can_run_maintenance(&state) {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        maintenance::queue_stale_documents(&state).await;
+                    });
+                }
+            }
+            // Run saved queries that are due & export any new results
+            _ = saved_query_interval.tick() => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    saved_query::run_due_queries(&state).await;
+                });
+            }
+            // Check whether sites can be reached again after widespread failures
+            _ = crawl_probe_interval.tick() => {
+                if state.crawl_breaker.is_tripped() {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        circuit_breaker::probe(&state).await;
+                    });
+                }
+            }
             // If we're not handling anything, continually poll for jobs.
             _ = queue_check_interval.tick() => {
                 if let Err(err) = manager_cmd_tx.send(ManagerCommand::CheckForJobs) {
@@ -322,6 +435,28 @@ pub async fn worker_task(
     config: Config,
     mut queue: mpsc::Receiver<WorkerCommand>,
     mut pause_rx: broadcast::Receiver<AppPause>,
+) {
+    loop {
+        let res = AssertUnwindSafe(run_worker(&state, &config, &mut queue, &mut pause_rx))
+            .catch_unwind()
+            .await;
+
+        match res {
+            Ok(()) => return,
+            Err(panic) => {
+                crash::record_panic(&state, "worker", panic);
+                tokio::time::sleep(TASK_RESTART_DELAY).await;
+                log::info!("restarting worker");
+            }
+        }
+    }
+}
+
+async fn run_worker(
+    state: &AppState,
+    config: &Config,
+    queue: &mut mpsc::Receiver<WorkerCommand>,
+    pause_rx: &mut broadcast::Receiver<AppPause>,
 ) {
     log::info!("worker started");
     let mut is_paused = false;
@@ -412,7 +547,7 @@ pub async fn worker_task(
                             }
                         },
                         WorkerCommand::CleanupDatabase(cleanup_task) => {
-                            let _ = worker::cleanup_database(&state, cleanup_task).await;
+                            let _ = worker::cleanup_database(state, cleanup_task).await;
                         }
                         WorkerCommand::CommitIndex => {
                             let state = state.clone();
@@ -429,11 +564,16 @@ pub async fn worker_task(
                             let state = state.clone();
                             let updated_docs = updated_docs.clone();
                             tokio::spawn(async move {
-                                match worker::handle_fetch(state, CrawlTask { id }).await {
-                                    FetchResult::New | FetchResult::Updated => {
+                                let res = AssertUnwindSafe(worker::handle_fetch(state.clone(), CrawlTask { id }))
+                                    .catch_unwind()
+                                    .await;
+                                state.inflight_crawls.remove(&id);
+                                match res {
+                                    Ok(FetchResult::New | FetchResult::Updated) => {
                                         updated_docs.fetch_add(1, Ordering::Relaxed);
                                     }
-                                    _ => {}
+                                    Ok(_) => {}
+                                    Err(panic) => crash::record_crawl_panic(&state, id, panic).await,
                                 }
                             });
                         }
@@ -441,7 +581,18 @@ pub async fn worker_task(
                             let state = state.clone();
                             let updated_docs = updated_docs.clone();
                             tokio::spawn(async move {
-                                match worker::handle_fetch(state.clone(), CrawlTask { id }).await {
+                                let res = AssertUnwindSafe(worker::handle_fetch(state.clone(), CrawlTask { id }))
+                                    .catch_unwind()
+                                    .await;
+                                let res = match res {
+                                    Ok(res) => res,
+                                    Err(panic) => {
+                                        crash::record_crawl_panic(&state, id, panic).await;
+                                        return;
+                                    }
+                                };
+
+                                match res {
                                     FetchResult::New | FetchResult::Updated => {
                                         updated_docs.fetch_add(1, Ordering::Relaxed);
                                     }
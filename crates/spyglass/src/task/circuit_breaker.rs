@@ -0,0 +1,177 @@
+use entities::models::crawl_queue::{self, CrawlStatus};
+use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::state::AppState;
+
+/// Fewer fetches than this in the window is too little to go on, e.g. a
+/// single failure would be a 100% failure rate.
+const MIN_FETCHES: usize = 20;
+/// How long the test fetch gets before it's considered failed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    /// When each fetch in the window finished & whether it succeeded.
+    fetches: VecDeque<(Instant, bool)>,
+    tripped_at: Option<Instant>,
+}
+
+/// Pauses web crawling when most fetches across all domains are failing,
+/// e.g. the network is down. Otherwise the workers would churn through the
+/// queue marking everything as failed.
+#[derive(Clone, Debug, Default)]
+pub struct CrawlCircuitBreaker {
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl CrawlCircuitBreaker {
+    /// Records the outcome of a fetch. Returns true when this fetch pushed the
+    /// failure rate over `max_failure_percent` & tripped the breaker. A
+    /// `max_failure_percent` of 0 disables the breaker.
+    pub fn record(&self, success: bool, max_failure_percent: u8, window: Duration) -> bool {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return false,
+        };
+
+        let now = Instant::now();
+        state.fetches.push_back((now, success));
+        while let Some((finished_at, _)) = state.fetches.front() {
+            if now.duration_since(*finished_at) <= window {
+                break;
+            }
+            state.fetches.pop_front();
+        }
+
+        if max_failure_percent == 0 || state.tripped_at.is_some() {
+            return false;
+        }
+
+        let num_fetches = state.fetches.len();
+        if num_fetches < MIN_FETCHES {
+            return false;
+        }
+
+        let num_failed = state.fetches.iter().filter(|(_, success)| !success).count();
+        if num_failed * 100 >= num_fetches * max_failure_percent as usize {
+            state.tripped_at = Some(now);
+            return true;
+        }
+
+        false
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.state
+            .lock()
+            .map(|state| state.tripped_at.is_some())
+            .unwrap_or_default()
+    }
+
+    /// Resumes crawling, starting over w/ a clean window.
+    pub fn reset(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.fetches.clear();
+            state.tripped_at = None;
+        }
+    }
+}
+
+/// Tries a single fetch while the breaker is tripped, resuming crawling if it
+/// gets a response. Any response counts, it's only checking whether sites can
+/// be reached again.
+pub async fn probe(state: &AppState) {
+    if !state.crawl_breaker.is_tripped() {
+        return;
+    }
+
+    let task = crawl_queue::Entity::find()
+        .filter(crawl_queue::Column::Status.eq(CrawlStatus::Queued))
+        .filter(crawl_queue::Column::Url.starts_with("http"))
+        .order_by_asc(crawl_queue::Column::UpdatedAt)
+        .one(&state.db)
+        .await;
+
+    let url = match task {
+        Ok(Some(task)) => task.url,
+        // Nothing left to crawl, nothing to protect
+        Ok(None) => {
+            state.crawl_breaker.reset();
+            return;
+        }
+        Err(err) => {
+            log::warn!("Unable to find a task to probe with: {}", err);
+            return;
+        }
+    };
+
+    match state
+        .http_client()
+        .head(&url)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(_) => {
+            log::info!("Fetched {}, resuming crawling", url);
+            state.crawl_breaker.reset();
+        }
+        Err(err) => log::debug!("Crawling still paused, unable to fetch {}: {}", url, err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CrawlCircuitBreaker, MIN_FETCHES};
+    use std::time::Duration;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_trips_on_widespread_failures() {
+        let breaker = CrawlCircuitBreaker::default();
+        // Not enough fetches to go on yet
+        for _ in 0..MIN_FETCHES - 1 {
+            assert!(!breaker.record(false, 90, WINDOW));
+        }
+        assert!(!breaker.is_tripped());
+
+        assert!(breaker.record(false, 90, WINDOW));
+        assert!(breaker.is_tripped());
+        // Only reported once
+        assert!(!breaker.record(false, 90, WINDOW));
+
+        breaker.reset();
+        assert!(!breaker.is_tripped());
+    }
+
+    #[test]
+    fn test_ignores_some_failures() {
+        let breaker = CrawlCircuitBreaker::default();
+        for idx in 0..MIN_FETCHES * 2 {
+            assert!(!breaker.record(idx % 2 == 0, 90, WINDOW));
+        }
+
+        // Disabled
+        let breaker = CrawlCircuitBreaker::default();
+        for _ in 0..MIN_FETCHES * 2 {
+            assert!(!breaker.record(false, 0, WINDOW));
+        }
+        assert!(!breaker.is_tripped());
+    }
+
+    #[test]
+    fn test_old_fetches_drop_out_of_window() {
+        let window = Duration::from_millis(10);
+        let breaker = CrawlCircuitBreaker::default();
+        for _ in 0..MIN_FETCHES - 1 {
+            breaker.record(false, 90, window);
+        }
+
+        std::thread::sleep(window * 2);
+        assert!(!breaker.record(false, 90, window));
+    }
+}
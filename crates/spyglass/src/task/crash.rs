@@ -0,0 +1,67 @@
+use std::any::Any;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use entities::models::crawl_queue;
+use entities::sea_orm::EntityTrait;
+
+use crate::state::AppState;
+
+/// Local crash log, written to the logs folder so users can inspect what
+/// went wrong.
+pub const CRASH_LOG: &str = "crash.log";
+
+/// Pull a readable message out of a caught panic payload.
+pub fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(msg) = panic.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = panic.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Log a panic caught in a background task. When telemetry is enabled the
+/// panic is also appended to the local crash log & reported to sentry.
+pub fn record_panic(state: &AppState, context: &str, panic: Box<dyn Any + Send>) {
+    let msg = panic_message(&*panic);
+    log::error!("🔥 panic in {}: {}", context, msg);
+
+    if state.user_settings.load().disable_telemetry {
+        return;
+    }
+
+    let path = state.config.logs_dir().join(CRASH_LOG);
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(mut file) => {
+            let _ = writeln!(
+                file,
+                "[{}] panic in {}: {}",
+                chrono::Utc::now().to_rfc3339(),
+                context,
+                msg
+            );
+        }
+        Err(err) => log::error!("Unable to write to crash log {:?}: {}", path, err),
+    }
+
+    sentry::capture_message(&format!("panic in {context}: {msg}"), sentry::Level::Fatal);
+}
+
+/// Record a panic that happened while crawling a task. The task is marked as
+/// failed so it doesn't stay stuck in processing.
+pub async fn record_crawl_panic(state: &AppState, id: i64, panic: Box<dyn Any + Send>) {
+    let url = match crawl_queue::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(task)) => task.url,
+        _ => "<unknown>".to_string(),
+    };
+
+    let error = crawl_queue::TaskError::new(
+        crawl_queue::TaskErrorType::Collect,
+        &format!("panic while crawling: {}", panic_message(&*panic)),
+    );
+    record_panic(state, &format!("crawl (id: {id}, url: {url})"), panic);
+    crawl_queue::mark_failed(&state.db, id, 0, error).await;
+    state.recrawl_jobs.record(id, true);
+}
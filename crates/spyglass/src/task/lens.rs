@@ -1,8 +1,11 @@
 use dashmap::DashMap;
-use entities::models::lens;
+use entities::models::crawl_queue::{self, CrawlStatus, EnqueueSettings};
+use entities::models::{lens, tag};
+use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use shared::response::InstallableLens;
 use std::fs;
 use std::path::PathBuf;
+use url::Url;
 
 use crate::{
     state::AppState,
@@ -10,7 +13,7 @@ use crate::{
 };
 use reqwest::Client;
 use shared::config::{Config, LensConfig, LensSource};
-use shared::constants;
+use shared::{collapse_equivalent_url, constants};
 
 /// Loop through lenses in the AppState. Update our internal db & bootstrap anything
 /// that hasn't been bootstrapped.
@@ -54,9 +57,140 @@ pub async fn load_lenses(lens_map: &DashMap<String, LensConfig>, state: AppState
             .await;
     }
 
+    open_lens_indexes(&state).await;
     log::info!("✅ finished lens checks")
 }
 
+/// Opens the separate index of each lens when lens indexes are enabled. Lens
+/// indexes that don't exist yet are filled from the shared index.
+pub async fn open_lens_indexes(state: &AppState) {
+    if state.index.lens_indexes.is_none() {
+        return;
+    }
+
+    // Lenses that haven't indexed anything yet won't have a tag
+    let names = state
+        .lenses
+        .iter()
+        .map(|lens| lens.key().clone())
+        .collect::<Vec<_>>();
+    for name in names {
+        if let Err(err) = tag::get_or_create(&state.db, tag::TagType::Lens, &name).await {
+            log::warn!("Unable to create tag for lens {}: {}", name, err);
+        }
+    }
+
+    let lens_tags = match tag::Entity::find()
+        .filter(tag::Column::Label.eq(tag::TagType::Lens.to_string()))
+        .all(&state.db)
+        .await
+    {
+        Ok(tags) => tags,
+        Err(err) => {
+            log::error!("Unable to find lens tags: {}", err);
+            return;
+        }
+    };
+
+    for lens_tag in lens_tags {
+        if let Err(err) = state
+            .index
+            .open_lens_index(&lens_tag.value, lens_tag.id as u64)
+            .await
+        {
+            log::error!("Unable to open index for lens {}: {}", lens_tag.value, err);
+        }
+    }
+}
+
+/// Queues the feeds of loaded lenses that haven't been fetched in their poll
+/// interval, returning the number of feeds queued. Feeds waiting to be crawled
+/// are left alone.
+pub async fn poll_lens_feeds(state: &AppState) -> usize {
+    let lenses = state
+        .lenses
+        .iter()
+        .filter(|entry| !entry.value().feeds.is_empty())
+        .map(|entry| entry.value().clone())
+        .collect::<Vec<_>>();
+    let settings = state.user_settings.load_full();
+    let now = chrono::Utc::now();
+
+    let mut queued = 0;
+    for lens in lenses {
+        let mut due = Vec::new();
+        for feed in &lens.feeds {
+            // Looked up the way enqueue_all stores it, or it'd never be found
+            // & the feed would be queued again on every poll.
+            let url = match Url::parse(&feed.url) {
+                Ok(mut url) => {
+                    if settings.collapse_equivalent_urls {
+                        collapse_equivalent_url(&mut url);
+                    }
+                    url.to_string()
+                }
+                Err(err) => {
+                    log::warn!("Invalid feed url {}: {}", feed.url, err);
+                    continue;
+                }
+            };
+
+            let last_poll = match crawl_queue::Entity::find()
+                .filter(crawl_queue::Column::Url.eq(url.as_str()))
+                .one(&state.db)
+                .await
+            {
+                Ok(task) => task,
+                Err(err) => {
+                    log::error!("Unable to check feed {}: {}", feed.url, err);
+                    continue;
+                }
+            };
+
+            let poll_interval = chrono::Duration::minutes(feed.poll_interval_minutes as i64);
+            let is_due = match last_poll {
+                Some(task) => {
+                    (task.status == CrawlStatus::Completed || task.status == CrawlStatus::Failed)
+                        && task.updated_at + poll_interval <= now
+                }
+                None => true,
+            };
+            if is_due {
+                due.push(url);
+            }
+        }
+
+        if due.is_empty() {
+            continue;
+        }
+
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            is_recrawl: true,
+            tags: vec![(tag::TagType::Lens, lens.name.clone())],
+            ..Default::default()
+        };
+        match crawl_queue::enqueue_all(
+            &state.db,
+            &due,
+            std::slice::from_ref(&lens),
+            &settings,
+            &overrides,
+            None,
+        )
+        .await
+        {
+            Ok(()) => {
+                log::debug!("polling {} feeds for lens {}", due.len(), lens.name);
+                queued += due.len();
+            }
+            Err(err) => log::error!("Unable to poll feeds for lens {}: {}", lens.name, err),
+        }
+    }
+
+    queued
+}
+
 /// Installs a new lens or updates the current lens. The requested lens will be
 /// downloaded from the lens store and added to the database. The actually lens
 /// loading will happen through the normal file system watch mechanism.
@@ -184,3 +318,66 @@ pub async fn read_lenses(config: &Config) -> anyhow::Result<DashMap<String, Lens
 
     Ok(lens_map)
 }
+
+#[cfg(test)]
+mod test {
+    use super::poll_lens_feeds;
+    use crate::state::AppState;
+    use entities::models::crawl_queue::{self, CrawlStatus};
+    use entities::sea_orm::{ActiveModelTrait, EntityTrait, Set};
+    use entities::test::setup_test_db;
+    use shared::config::{LensConfig, LensFeed};
+
+    #[tokio::test]
+    async fn test_poll_lens_feeds() {
+        let db = setup_test_db().await;
+        let lens = LensConfig {
+            name: "feeds".into(),
+            domains: vec!["example.com".into()],
+            feeds: vec![
+                LensFeed {
+                    url: "https://example.com/feed.xml".into(),
+                    poll_interval_minutes: 30,
+                    include_summary: true,
+                },
+                // Matched to the queued URL once normalized
+                LensFeed {
+                    url: "https://EXAMPLE.com/atom.xml".into(),
+                    poll_interval_minutes: 30,
+                    include_summary: true,
+                },
+            ],
+            ..Default::default()
+        };
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_lenses(&vec![lens])
+            .build();
+
+        // Polled a few minutes ago, not due yet
+        crawl_queue::ActiveModel {
+            domain: Set("example.com".into()),
+            url: Set("https://example.com/atom.xml".into()),
+            status: Set(CrawlStatus::Completed),
+            updated_at: Set(chrono::Utc::now() - chrono::Duration::minutes(5)),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert task");
+
+        assert_eq!(poll_lens_feeds(&state).await, 1);
+        let queued = crawl_queue::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|task| task.status == CrawlStatus::Queued)
+            .map(|task| task.url)
+            .collect::<Vec<_>>();
+        assert_eq!(queued, vec!["https://example.com/feed.xml".to_string()]);
+
+        // Still waiting to be crawled
+        assert_eq!(poll_lens_feeds(&state).await, 0);
+    }
+}
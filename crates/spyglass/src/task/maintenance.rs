@@ -0,0 +1,484 @@
+use chrono::Timelike;
+use entities::models::crawl_queue::{self, EnqueueSettings};
+use entities::models::{document_content, document_tag, indexed_document, link, vacuum};
+use entities::sea_orm::{
+    ColumnTrait, DbErr, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use shared::config::UserSettings;
+use shared::response::VacuumStatus;
+use spyglass_rpc::{ReindexProgressPayload, RpcEvent, RpcEventType};
+use spyglass_searcher::schema::{DocumentUpdate, ToDocument};
+use spyglass_searcher::{SearchTrait, WriteTrait};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use crate::state::AppState;
+
+/// Indexed documents checked per batch when reindexing.
+const REINDEX_BATCH_SIZE: u64 = 500;
+
+/// Size of the database file on disk, if it can be read.
+fn db_file_size(state: &AppState) -> Option<u64> {
+    std::fs::metadata(state.config.data_dir().join("db.sqlite"))
+        .ok()
+        .map(|meta| meta.len())
+}
+
+/// Whether heavy maintenance can run at `hour` (local time). Always true
+/// w/o a maintenance window.
+pub fn in_maintenance_window(settings: &UserSettings, hour: u32) -> bool {
+    settings
+        .maintenance_window
+        .as_ref()
+        .map(|window| window.contains(hour))
+        .unwrap_or(true)
+}
+
+/// Whether heavy maintenance can run right now.
+pub fn can_run_maintenance(state: &AppState) -> bool {
+    in_maintenance_window(&state.user_settings.load(), chrono::Local::now().hour())
+}
+
+/// Vacuums the database now if we're in the maintenance window, otherwise
+/// it's deferred until the window opens. Returns None if a vacuum is already
+/// running or deferred.
+pub fn vacuum_or_defer(state: &AppState) -> Option<VacuumStatus> {
+    if can_run_maintenance(state) {
+        return start_vacuum(state).then_some(VacuumStatus::Started);
+    }
+
+    if state.is_vacuuming.load(Ordering::SeqCst)
+        || state.vacuum_deferred.swap(true, Ordering::SeqCst)
+    {
+        return None;
+    }
+
+    log::info!("deferring database vacuum until the maintenance window");
+    Some(VacuumStatus::Deferred)
+}
+
+/// Runs any maintenance deferred until the maintenance window, once it opens.
+pub fn run_deferred(state: &AppState) {
+    if can_run_maintenance(state) && state.vacuum_deferred.swap(false, Ordering::SeqCst) {
+        start_vacuum(state);
+    }
+}
+
+/// Stale documents queued per check, so a large backlog is spread out.
+const MAX_RECRAWLS_PER_CHECK: u64 = 500;
+
+/// Queues web pages that haven't been crawled in `recrawl_interval_days` to
+/// be crawled again, returning the number of URLs queued. Pages that are
+/// already queued are left alone.
+pub async fn queue_stale_documents(state: &AppState) -> usize {
+    let settings = state.user_settings.load_full();
+    if settings.recrawl_interval_days == 0 {
+        return 0;
+    }
+
+    let before = chrono::Utc::now() - chrono::Duration::days(settings.recrawl_interval_days as i64);
+    let urls = match indexed_document::find_stale(&state.db, before, MAX_RECRAWLS_PER_CHECK).await {
+        Ok(docs) => docs.into_iter().map(|doc| doc.url).collect::<Vec<_>>(),
+        Err(err) => {
+            log::error!("Unable to find stale documents: {}", err);
+            return 0;
+        }
+    };
+    if urls.is_empty() {
+        return 0;
+    }
+
+    let overrides = EnqueueSettings {
+        force_allow: true,
+        is_recrawl: true,
+        ..Default::default()
+    };
+    match crawl_queue::enqueue_all(&state.db, &urls, &[], &settings, &overrides, None).await {
+        Ok(()) => {
+            log::info!("queued {} stale documents to be recrawled", urls.len());
+            urls.len()
+        }
+        Err(err) => {
+            log::error!("Unable to queue stale documents: {}", err);
+            0
+        }
+    }
+}
+
+/// Starts vacuuming the database in the background, a `DatabaseVacuumed` event
+/// is published once it's done. Returns false if a vacuum is already running.
+///
+/// Crawl results aren't written while the vacuum runs & no new crawls are
+/// started until it's done.
+pub fn start_vacuum(state: &AppState) -> bool {
+    if state.is_vacuuming.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        let payload = {
+            // Wait for in-flight crawls to finish writing
+            let _maintenance = state.db_maintenance.write().await;
+            let size_before = db_file_size(&state);
+            let start = Instant::now();
+
+            log::info!("vacuuming database");
+            match vacuum(&state.db).await {
+                Ok(()) => {
+                    let reclaimed = size_before
+                        .zip(db_file_size(&state))
+                        .map(|(before, after)| before.saturating_sub(after))
+                        .unwrap_or_default();
+                    log::info!(
+                        "vacuumed database in {}ms, reclaimed {} bytes",
+                        start.elapsed().as_millis(),
+                        reclaimed
+                    );
+                    format!("Reclaimed {:.1} MB", reclaimed as f64 / (1024.0 * 1024.0))
+                }
+                Err(err) => {
+                    log::error!("Unable to vacuum database: {}", err);
+                    format!("Unable to compact database: {err}")
+                }
+            }
+        };
+
+        state.is_vacuuming.store(false, Ordering::SeqCst);
+        state
+            .publish_event(&RpcEvent {
+                event_type: RpcEventType::DatabaseVacuumed,
+                payload,
+            })
+            .await;
+    });
+
+    true
+}
+
+/// Outcome of `reindex_documents`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReindexResult {
+    pub checked: u64,
+    /// Documents rebuilt in the index from their stored content.
+    pub restored: u64,
+    /// Web pages missing from the index w/o any stored content, queued to be
+    /// crawled again.
+    pub requeued: u64,
+}
+
+/// Rebuilds the search index from the database, e.g. after a schema change or
+/// the index was corrupted. Every document w/ stored content is removed from
+/// the index & added back from it. Documents w/o stored content are left as
+/// they are, unless they're missing from the index & are web pages, which are
+/// queued to be crawled again.
+pub async fn reindex_documents(state: &AppState) -> Result<ReindexResult, DbErr> {
+    let total = indexed_document::Entity::find().count(&state.db).await?;
+    let overrides = EnqueueSettings {
+        force_allow: true,
+        is_recrawl: true,
+        ..Default::default()
+    };
+
+    let mut result = ReindexResult::default();
+    let mut last_id = 0;
+    loop {
+        let docs = indexed_document::Entity::find()
+            .filter(indexed_document::Column::Id.gt(last_id))
+            .order_by_asc(indexed_document::Column::Id)
+            .limit(REINDEX_BATCH_SIZE)
+            .all(&state.db)
+            .await?;
+        last_id = match docs.last() {
+            Some(doc) => doc.id,
+            None => break,
+        };
+        result.checked += docs.len() as u64;
+
+        let mut missing = Vec::new();
+        let mut num_restored = 0;
+        for doc in docs {
+            if rebuild_document(state, &doc).await? {
+                num_restored += 1;
+            } else if doc.url.starts_with("http") && state.index.get(&doc.doc_id).await.is_none() {
+                // Local files & connection (api://) docs aren't crawled from
+                // the web, they're picked up again by their own syncs.
+                missing.push(doc.url);
+            }
+        }
+
+        if num_restored > 0 {
+            let _ = state.index.save().await;
+            result.restored += num_restored;
+        }
+
+        if !missing.is_empty() {
+            let settings = state.user_settings.load_full();
+            crawl_queue::enqueue_all(&state.db, &missing, &[], &settings, &overrides, None).await?;
+            result.requeued += missing.len() as u64;
+        }
+
+        publish_reindex_progress(
+            state,
+            ReindexProgressPayload::InProgress {
+                checked: result.checked,
+                total,
+            },
+        )
+        .await;
+    }
+
+    Ok(result)
+}
+
+/// Replaces a document in the index w/ one built from its stored content.
+/// Returns false if there's no stored content or it couldn't be added.
+async fn rebuild_document(state: &AppState, doc: &indexed_document::Model) -> Result<bool, DbErr> {
+    let stored = match document_content::get(&state.db, &doc.url).await? {
+        Some(stored) => stored,
+        None => return Ok(false),
+    };
+
+    let tags = document_tag::Entity::find()
+        .filter(document_tag::Column::IndexedDocumentId.eq(doc.id))
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|tag| tag.tag_id)
+        .collect::<Vec<_>>();
+    let anchor_text = link::get_anchor_text(&state.db, &doc.url)
+        .await
+        .unwrap_or_default();
+
+    let update = DocumentUpdate {
+        doc_id: Some(doc.doc_id.clone()),
+        title: &stored.title,
+        description: &stored.description,
+        domain: &doc.domain,
+        url: &doc.url,
+        content: &stored.content,
+        body: stored.body.as_deref(),
+        tags: &tags,
+        anchor_text: &anchor_text,
+        code: &stored.code.0,
+        published_at: stored.published_at,
+        last_modified: Some(doc.updated_at),
+    };
+    if let Err(err) = state.index.delete(&doc.doc_id).await {
+        log::warn!("Unable to remove {} from the index: {}", doc.url, err);
+        return Ok(false);
+    }
+    match state.index.upsert(&update.to_document()).await {
+        Ok(_) => Ok(true),
+        Err(err) => {
+            log::warn!("Unable to restore {} to the index: {}", doc.url, err);
+            Ok(false)
+        }
+    }
+}
+
+/// Starts reindexing in the background, see `reindex_documents`. Progress is
+/// published as `ReindexProgress` events. Returns false if a reindex is
+/// already running.
+pub fn start_reindex(state: &AppState) -> bool {
+    if state.is_reindexing.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        log::info!("rebuilding the search index from the database");
+        let payload = match reindex_documents(&state).await {
+            Ok(result) => {
+                log::info!(
+                    "checked {} documents, {} rebuilt, {} queued to be crawled again",
+                    result.checked,
+                    result.restored,
+                    result.requeued
+                );
+                ReindexProgressPayload::Finished {
+                    checked: result.checked,
+                    restored: result.restored,
+                    requeued: result.requeued,
+                }
+            }
+            Err(err) => {
+                log::error!("Unable to reindex documents: {}", err);
+                ReindexProgressPayload::Error {
+                    msg: err.to_string(),
+                }
+            }
+        };
+
+        state.is_reindexing.store(false, Ordering::SeqCst);
+        publish_reindex_progress(&state, payload).await;
+    });
+
+    true
+}
+
+async fn publish_reindex_progress(state: &AppState, payload: ReindexProgressPayload) {
+    state
+        .publish_event(&RpcEvent {
+            event_type: RpcEventType::ReindexProgress,
+            payload: serde_json::to_string(&payload).unwrap_or_default(),
+        })
+        .await;
+}
+
+#[cfg(test)]
+mod test {
+    use super::{in_maintenance_window, queue_stale_documents, reindex_documents, ReindexResult};
+    use crate::state::AppState;
+    use entities::models::crawl_queue::{self, CrawlStatus};
+    use entities::models::{document_content, indexed_document};
+    use entities::sea_orm::{ActiveModelTrait, EntityTrait, Set};
+    use entities::test::setup_test_db;
+    use shared::config::{MaintenanceWindow, UserSettings};
+    use spyglass_searcher::schema::{DocumentUpdate, ToDocument};
+    use spyglass_searcher::{SearchTrait, WriteTrait};
+
+    #[test]
+    fn test_in_maintenance_window() {
+        let mut settings = UserSettings::default();
+        assert!((0..24).all(|hour| in_maintenance_window(&settings, hour)));
+
+        settings.maintenance_window = Some(MaintenanceWindow {
+            start_hour: 1,
+            end_hour: 5,
+        });
+        assert!(!in_maintenance_window(&settings, 0));
+        assert!(in_maintenance_window(&settings, 1));
+        assert!(in_maintenance_window(&settings, 4));
+        assert!(!in_maintenance_window(&settings, 5));
+
+        // Wraps around midnight
+        settings.maintenance_window = Some(MaintenanceWindow {
+            start_hour: 22,
+            end_hour: 6,
+        });
+        assert!(in_maintenance_window(&settings, 23));
+        assert!(in_maintenance_window(&settings, 0));
+        assert!(!in_maintenance_window(&settings, 6));
+        assert!(!in_maintenance_window(&settings, 12));
+    }
+
+    #[tokio::test]
+    async fn test_queue_stale_documents() {
+        let db = setup_test_db().await;
+        let settings = UserSettings {
+            recrawl_interval_days: 7,
+            ..Default::default()
+        };
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&settings)
+            .build();
+
+        let now = chrono::Utc::now();
+        for (idx, days_old) in [(0, 10), (1, 1)] {
+            indexed_document::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(format!("https://example.com/{idx}")),
+                doc_id: Set(format!("doc_{idx}")),
+                updated_at: Set(now - chrono::Duration::days(days_old)),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert doc");
+        }
+
+        assert_eq!(queue_stale_documents(&state).await, 1);
+        let tasks = crawl_queue::Entity::find().all(&db).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].url, "https://example.com/0");
+        assert_eq!(tasks[0].status, CrawlStatus::Queued);
+
+        // Already queued, not queued again
+        assert_eq!(queue_stale_documents(&state).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reindex_documents() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        state
+            .index
+            .upsert(
+                &DocumentUpdate {
+                    doc_id: Some("doc_0".into()),
+                    title: "test title",
+                    description: "",
+                    domain: "example.com",
+                    url: "https://example.com/0",
+                    content: "test content",
+                    tags: &[],
+                    anchor_text: &[],
+                    code: &[],
+                    body: None,
+                    published_at: None,
+                    last_modified: None,
+                }
+                .to_document(),
+            )
+            .await
+            .expect("Unable to add doc");
+        let _ = state.index.save().await;
+
+        // Only the first document made it into the index. Both it & the second
+        // are rebuilt from their stored content.
+        for idx in 0..2 {
+            let new_content = document_content::NewContent {
+                title: "stored title",
+                description: "stored description",
+                content: "stored content",
+                ..Default::default()
+            };
+            document_content::save(&db, &format!("https://example.com/{idx}"), &new_content)
+                .await
+                .expect("Unable to store content");
+        }
+        for idx in 0..3 {
+            indexed_document::ActiveModel {
+                domain: Set("example.com".into()),
+                url: Set(format!("https://example.com/{idx}")),
+                doc_id: Set(format!("doc_{idx}")),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to insert doc");
+        }
+        // Not a web page, so never queued as a crawl
+        indexed_document::ActiveModel {
+            domain: Set("localhost".into()),
+            url: Set("file:///tmp/notes.txt".into()),
+            doc_id: Set("doc_file".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to insert doc");
+
+        let result = reindex_documents(&state).await.expect("Unable to reindex");
+        assert_eq!(
+            result,
+            ReindexResult {
+                checked: 4,
+                restored: 2,
+                requeued: 1,
+            }
+        );
+        let rebuilt = state.index.get("doc_0").await.expect("doc_0 in the index");
+        assert_eq!(rebuilt.title, "stored title");
+        assert_eq!(rebuilt.description, "stored description");
+        assert!(state.index.get("doc_1").await.is_some());
+
+        let tasks = crawl_queue::Entity::find().all(&db).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].url, "https://example.com/2");
+        assert_eq!(tasks[0].status, CrawlStatus::Queued);
+    }
+}
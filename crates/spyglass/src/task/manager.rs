@@ -1,17 +1,70 @@
-use entities::models::{connection, crawl_queue};
+use entities::models::connection;
+use entities::models::crawl_queue::{self, CrawlType};
+use shared::config::Limit;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 use super::{CollectTask, CrawlTask, ManagerCommand, WorkerCommand};
 use crate::pipeline::PipelineCommand;
 use crate::state::AppState;
 
+/// Domains fetched from within the last `delay`. Domains fetched before that
+/// are forgotten.
+fn recently_fetched_domains(state: &AppState, delay: Duration) -> Vec<String> {
+    state
+        .domain_last_fetched
+        .retain(|_, fetched_at| fetched_at.elapsed() < delay);
+    state
+        .domain_last_fetched
+        .iter()
+        .map(|entry| entry.key().clone())
+        .collect()
+}
+
+/// Domains w/ at least `limit` crawls sent to the workers that haven't
+/// finished yet.
+fn domains_at_inflight_limit(state: &AppState, limit: u32) -> Vec<String> {
+    let mut inflight: HashMap<String, u32> = HashMap::new();
+    for entry in state.inflight_crawls.iter() {
+        *inflight.entry(entry.value().clone()).or_default() += 1;
+    }
+
+    inflight
+        .into_iter()
+        .filter(|(_, count)| *count >= limit)
+        .map(|(domain, _)| domain)
+        .collect()
+}
+
 // Check for new jobs in the crawl queue and add them to the worker queue.
 #[tracing::instrument(skip(state, queue))]
 pub async fn check_for_jobs(state: &AppState, queue: &mpsc::Sender<WorkerCommand>) -> bool {
     let mut started_task = None;
-    // Do we have any crawl tasks?
-    match crawl_queue::dequeue(&state.db, &state.user_settings.load()).await {
+    // Do we have any crawl tasks? Web crawls are held off while most fetches
+    // are failing, local files are still crawled.
+    let next_task = if state.crawl_breaker.is_tripped() {
+        Ok(None)
+    } else {
+        let settings = state.user_settings.load();
+        let mut skip_domains = recently_fetched_domains(
+            state,
+            Duration::from_millis(settings.domain_crawl_delay_ms as u64),
+        );
+        // Leave domains that are already being fetched from as much as allowed
+        if let Limit::Finite(limit) = settings.inflight_domain_limit {
+            skip_domains.extend(domains_at_inflight_limit(state, limit));
+        }
+        crawl_queue::dequeue_except(&state.db, &settings, &skip_domains).await
+    };
+    match next_task {
         Ok(Some(task)) => {
+            if task.crawl_type != CrawlType::Bootstrap {
+                state
+                    .domain_last_fetched
+                    .insert(task.domain.clone(), Instant::now());
+            }
+
             match &task.pipeline {
                 Some(pipeline) => {
                     if let Some(pipeline_tx) = state.pipeline_cmd_tx.lock().await.as_mut() {
@@ -28,9 +81,13 @@ pub async fn check_for_jobs(state: &AppState, queue: &mpsc::Sender<WorkerCommand
                 }
                 None => {
                     // Send to worker
+                    if task.crawl_type != CrawlType::Bootstrap {
+                        state.inflight_crawls.insert(task.id, task.domain.clone());
+                    }
                     let cmd = WorkerCommand::Crawl { id: task.id };
                     if queue.send(cmd).await.is_err() {
                         log::error!("unable to send command to worker");
+                        state.inflight_crawls.remove(&task.id);
                     }
                     started_task = Some(true);
                 }
@@ -105,6 +162,7 @@ mod test {
     use entities::models::crawl_queue::{self, CrawlStatus, CrawlType};
     use entities::sea_orm::{ActiveModelTrait, Set};
     use entities::test::setup_test_db;
+    use shared::config::{Limit, UserSettings};
 
     #[tokio::test]
     async fn test_check_for_jobs() {
@@ -133,4 +191,93 @@ mod test {
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_check_for_jobs_domain_delay() {
+        let db = setup_test_db().await;
+        let settings = UserSettings {
+            domain_crawl_delay_ms: 60_000,
+            ..Default::default()
+        };
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&settings)
+            .build();
+
+        for (domain, url) in [
+            ("example.com", "https://example.com/a"),
+            ("example.com", "https://example.com/b"),
+            ("example.org", "https://example.org/"),
+        ] {
+            let task = crawl_queue::ActiveModel {
+                url: Set(url.to_owned()),
+                domain: Set(domain.to_owned()),
+                crawl_type: Set(CrawlType::Normal),
+                status: Set(CrawlStatus::Queued),
+                ..Default::default()
+            };
+            task.save(&db).await.expect("Unable to save dummy task");
+        }
+
+        // Each domain gets a turn, the second example.com task waits
+        let (sender, mut recv) = mpsc::channel(10);
+        assert!(check_for_jobs(&state, &sender).await);
+        assert!(check_for_jobs(&state, &sender).await);
+        assert!(!check_for_jobs(&state, &sender).await);
+
+        let mut dispatched = 0;
+        while recv.try_recv().is_ok() {
+            dispatched += 1;
+        }
+        assert_eq!(dispatched, 2);
+
+        let queued = crawl_queue::num_queued(&db, CrawlStatus::Queued)
+            .await
+            .expect("Unable to count queued tasks");
+        assert_eq!(queued, 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_for_jobs_inflight_limit() {
+        let db = setup_test_db().await;
+        let settings = UserSettings {
+            inflight_domain_limit: Limit::Finite(1),
+            ..Default::default()
+        };
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&settings)
+            .build();
+
+        let task = crawl_queue::ActiveModel {
+            url: Set("https://example.com/a".to_owned()),
+            domain: Set("example.com".to_owned()),
+            crawl_type: Set(CrawlType::Normal),
+            status: Set(CrawlStatus::Queued),
+            ..Default::default()
+        };
+        task.save(&db).await.expect("Unable to save dummy task");
+
+        // Already fetching from example.com
+        state
+            .inflight_crawls
+            .insert(1000, "example.com".to_string());
+        let (sender, mut recv) = mpsc::channel(10);
+        assert!(!check_for_jobs(&state, &sender).await);
+        assert!(recv.try_recv().is_err());
+
+        // Dispatched once that crawl finishes & tracked until it does
+        state.inflight_crawls.remove(&1000);
+        assert!(check_for_jobs(&state, &sender).await);
+        let message = recv.recv().await.expect("no WorkerCommand in channel");
+        match message {
+            WorkerCommand::Crawl { id } => {
+                assert_eq!(
+                    state.inflight_crawls.get(&id).map(|domain| domain.clone()),
+                    Some("example.com".to_string())
+                );
+            }
+            _ => panic!("Expected a crawl"),
+        }
+    }
 }
@@ -0,0 +1,252 @@
+use dashmap::DashMap;
+use shared::response::LensRecrawlProgress;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a finished job's progress is kept around.
+const DEFAULT_RETENTION: Duration = Duration::from_secs(60 * 60);
+/// Unfinished jobs w/o any progress for this long are dropped, their tasks
+/// were likely removed some other way.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Clone, Debug)]
+struct RecrawlJob {
+    lens: String,
+    started_at: Instant,
+    total: usize,
+    /// Tasks that haven't been crawled yet.
+    pending: HashSet<i64>,
+    crawled: usize,
+    failed: usize,
+    /// Last time a task in the job was done with.
+    updated_at: Instant,
+    finished_at: Option<Instant>,
+}
+
+impl RecrawlJob {
+    fn progress(&self, job_id: &str) -> LensRecrawlProgress {
+        let done = self.crawled + self.failed;
+        let remaining = self.pending.len();
+        let percent_complete = if self.total == 0 {
+            100.0
+        } else {
+            done as f32 / self.total as f32 * 100.0
+        };
+
+        // Assumes the remaining tasks go as fast as the ones done so far
+        let eta_secs = if remaining == 0 {
+            Some(0)
+        } else if done == 0 {
+            None
+        } else {
+            let per_task = self.started_at.elapsed().as_secs_f64() / done as f64;
+            Some((per_task * remaining as f64).ceil() as u64)
+        };
+
+        LensRecrawlProgress {
+            job_id: job_id.to_string(),
+            lens: self.lens.clone(),
+            total: self.total as u64,
+            crawled: self.crawled as u64,
+            failed: self.failed as u64,
+            remaining: remaining as u64,
+            percent_complete,
+            eta_secs,
+            is_finished: self.finished_at.is_some(),
+        }
+    }
+}
+
+/// In-memory progress of lens recrawls. The workers report each task they
+/// finish, jobs are dropped a while after their last task is done.
+#[derive(Clone, Debug)]
+pub struct RecrawlJobs {
+    jobs: Arc<DashMap<String, RecrawlJob>>,
+    retention: Duration,
+}
+
+impl Default for RecrawlJobs {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETENTION)
+    }
+}
+
+impl RecrawlJobs {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            jobs: Arc::new(DashMap::new()),
+            retention,
+        }
+    }
+
+    /// Starts tracking a recrawl of `task_ids`, returning the job id. A lens
+    /// that's already being recrawled keeps its current job.
+    pub fn start(&self, lens: &str, task_ids: &[i64]) -> String {
+        self.prune();
+        if let Some(job) = self
+            .jobs
+            .iter()
+            .find(|job| job.lens == lens && job.finished_at.is_none())
+        {
+            return job.key().clone();
+        }
+
+        let pending = task_ids.iter().copied().collect::<HashSet<_>>();
+        let job = RecrawlJob {
+            lens: lens.to_string(),
+            started_at: Instant::now(),
+            total: pending.len(),
+            finished_at: pending.is_empty().then(Instant::now),
+            pending,
+            crawled: 0,
+            failed: 0,
+            updated_at: Instant::now(),
+        };
+
+        let job_id = uuid::Uuid::new_v4().as_hyphenated().to_string();
+        self.jobs.insert(job_id.clone(), job);
+        job_id
+    }
+
+    /// Records a task being done with, for whichever job it's part of.
+    pub fn record(&self, task_id: i64, failed: bool) {
+        for mut job in self.jobs.iter_mut() {
+            if !job.pending.remove(&task_id) {
+                continue;
+            }
+
+            if failed {
+                job.failed += 1;
+            } else {
+                job.crawled += 1;
+            }
+
+            job.updated_at = Instant::now();
+            if job.pending.is_empty() {
+                job.finished_at = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Drops tasks that were removed from the crawl queue before being
+    /// crawled, they no longer count towards their job.
+    pub fn remove(&self, task_ids: &[i64]) {
+        for mut job in self.jobs.iter_mut() {
+            let before = job.pending.len();
+            for task_id in task_ids {
+                job.pending.remove(task_id);
+            }
+
+            let removed = before - job.pending.len();
+            if removed == 0 {
+                continue;
+            }
+
+            job.total -= removed;
+            job.updated_at = Instant::now();
+            if job.pending.is_empty() && job.finished_at.is_none() {
+                job.finished_at = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Finishes any running job for a lens, e.g. when it's uninstalled.
+    pub fn abandon(&self, lens: &str) {
+        for mut job in self.jobs.iter_mut() {
+            if job.lens == lens && job.finished_at.is_none() {
+                job.pending.clear();
+                job.finished_at = Some(Instant::now());
+            }
+        }
+    }
+
+    pub fn progress(&self, job_id: &str) -> Option<LensRecrawlProgress> {
+        self.prune();
+        self.jobs.get(job_id).map(|job| job.progress(job_id))
+    }
+
+    /// Drops finished jobs past the retention period & stalled ones.
+    fn prune(&self) {
+        self.jobs.retain(|_, job| match job.finished_at {
+            Some(finished_at) => finished_at.elapsed() < self.retention,
+            None => job.updated_at.elapsed() < STALE_AFTER,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RecrawlJobs;
+    use std::time::Duration;
+
+    #[test]
+    fn test_recrawl_progress() {
+        let jobs = RecrawlJobs::default();
+        let job_id = jobs.start("test", &[1, 2, 3, 4]);
+        // Recrawling again while the job is running returns the same job
+        assert_eq!(jobs.start("test", &[1, 2, 3, 4]), job_id);
+
+        let progress = jobs.progress(&job_id).expect("job should exist");
+        assert_eq!(progress.total, 4);
+        assert_eq!(progress.remaining, 4);
+        assert_eq!(progress.eta_secs, None);
+
+        jobs.record(1, false);
+        jobs.record(2, true);
+        // Tasks outside the job are ignored
+        jobs.record(10, false);
+
+        let progress = jobs.progress(&job_id).expect("job should exist");
+        assert_eq!(progress.crawled, 1);
+        assert_eq!(progress.failed, 1);
+        assert_eq!(progress.remaining, 2);
+        assert_eq!(progress.percent_complete, 50.0);
+        assert!(progress.eta_secs.is_some());
+        assert!(!progress.is_finished);
+
+        jobs.record(3, false);
+        jobs.record(4, false);
+        let progress = jobs.progress(&job_id).expect("job should exist");
+        assert_eq!(progress.remaining, 0);
+        assert_eq!(progress.eta_secs, Some(0));
+        assert!(progress.is_finished);
+
+        // Finished, so a new recrawl gets a new job
+        assert_ne!(jobs.start("test", &[1]), job_id);
+    }
+
+    #[test]
+    fn test_finished_jobs_are_dropped() {
+        let jobs = RecrawlJobs::new(Duration::ZERO);
+        let job_id = jobs.start("test", &[1]);
+        assert!(jobs.progress(&job_id).is_some());
+
+        jobs.record(1, false);
+        assert!(jobs.progress(&job_id).is_none());
+    }
+
+    #[test]
+    fn test_removed_tasks() {
+        let jobs = RecrawlJobs::default();
+        let job_id = jobs.start("test", &[1, 2, 3]);
+
+        jobs.record(1, false);
+        jobs.remove(&[2, 10]);
+        let progress = jobs.progress(&job_id).expect("job should exist");
+        assert_eq!(progress.total, 2);
+        assert_eq!(progress.remaining, 1);
+        assert!(!progress.is_finished);
+
+        jobs.remove(&[3]);
+        let progress = jobs.progress(&job_id).expect("job should exist");
+        assert_eq!(progress.remaining, 0);
+        assert!(progress.is_finished);
+
+        let job_id = jobs.start("test", &[4, 5]);
+        jobs.abandon("test");
+        let progress = jobs.progress(&job_id).expect("job should exist");
+        assert!(progress.is_finished);
+        assert_eq!(progress.remaining, 0);
+    }
+}
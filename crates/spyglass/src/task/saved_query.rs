@@ -0,0 +1,298 @@
+use entities::models::{saved_query, saved_query_seen, tag};
+use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Serialize;
+use spyglass_searcher::{Boost, QueryBoost, RetrievedDocument, SearchTrait};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Component, PathBuf};
+use std::time::Duration;
+use url::{Host, Url};
+
+use crate::state::AppState;
+
+/// Max number of results looked at each time a saved query is run.
+const MAX_RESULTS_PER_RUN: usize = 100;
+/// How long to wait on a webhook before giving up until the next run.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the new results of a saved query are delivered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Destination {
+    /// Results are POSTed to the URL as NDJSON.
+    Webhook(Url),
+    /// Results are appended to the file as NDJSON, relative to the exports
+    /// folder.
+    File(PathBuf),
+}
+
+impl Destination {
+    pub fn parse(destination: &str) -> Result<Self, String> {
+        let destination = destination.trim();
+        if destination.starts_with("http://") || destination.starts_with("https://") {
+            let url =
+                Url::parse(destination).map_err(|err| format!("Invalid webhook URL: {err}"))?;
+            if is_local_host(&url) {
+                return Err("Webhooks can't be sent to local or private addresses".to_string());
+            }
+
+            return Ok(Destination::Webhook(url));
+        }
+
+        // Files can't be written outside of the exports folder
+        let path = PathBuf::from(destination);
+        let in_exports = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !destination.is_empty() && in_exports {
+            Ok(Destination::File(path))
+        } else {
+            Err(
+                "Destination must be a http(s) URL or a file name in the exports folder"
+                    .to_string(),
+            )
+        }
+    }
+}
+
+/// Loopback, private network & link-local addresses, which webhooks could use
+/// to reach services that aren't meant to be public.
+fn is_local_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_local_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local (fc00::/7) & link-local (fe80::/10)
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80
+            }
+        },
+    }
+}
+
+fn is_local_host(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => is_local_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_local_ip(IpAddr::V6(ip)),
+        None => true,
+    }
+}
+
+/// Checks the addresses the webhook's host resolves to, it may have been
+/// pointed at a local address since it was added.
+async fn check_webhook_host(url: &Url) -> anyhow::Result<()> {
+    let (host, port) = match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => (host, port),
+        _ => return Err(anyhow::anyhow!("Invalid webhook URL {}", url)),
+    };
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    for addr in tokio::net::lookup_host((host, port)).await? {
+        if is_local_ip(addr.ip()) {
+            return Err(anyhow::anyhow!(
+                "Webhook {} resolves to a local or private address",
+                url
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A single new match, serialized as one NDJSON line.
+#[derive(Debug, Serialize)]
+struct ExportedResult<'a> {
+    saved_query: &'a str,
+    query: &'a str,
+    doc_id: &'a str,
+    title: &'a str,
+    description: &'a str,
+    url: &'a str,
+    domain: &'a str,
+    found_at: String,
+}
+
+fn to_ndjson(query: &saved_query::Model, docs: &[&RetrievedDocument]) -> String {
+    let found_at = chrono::Utc::now().to_rfc3339();
+    docs.iter()
+        .filter_map(|doc| {
+            serde_json::to_string(&ExportedResult {
+                saved_query: &query.name,
+                query: &query.query,
+                doc_id: &doc.doc_id,
+                title: &doc.title,
+                description: &doc.description,
+                url: &doc.url,
+                domain: &doc.domain,
+                found_at: found_at.clone(),
+            })
+            .ok()
+        })
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+async fn deliver(
+    state: &AppState,
+    destination: &Destination,
+    ndjson: String,
+) -> anyhow::Result<()> {
+    match destination {
+        Destination::Webhook(url) => {
+            check_webhook_host(url).await?;
+            state
+                .http_client()
+                .post(url.clone())
+                .timeout(WEBHOOK_TIMEOUT)
+                .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+                .body(ndjson)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        Destination::File(path) => {
+            let path = state.config.exports_dir().join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(ndjson.as_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs a saved query & delivers any results that weren't delivered by an
+/// earlier run. Returns the number of new results.
+pub async fn run_saved_query(
+    state: &AppState,
+    query: &saved_query::Model,
+) -> anyhow::Result<usize> {
+    let destination = Destination::parse(&query.destination).map_err(anyhow::Error::msg)?;
+
+    let filters = if query.filters.lenses.is_empty() {
+        Vec::new()
+    } else {
+        tag::Entity::find()
+            .filter(tag::Column::Label.eq(tag::TagType::Lens.to_string()))
+            .filter(tag::Column::Value.is_in(query.filters.lenses.clone()))
+            .all(&state.db)
+            .await?
+            .iter()
+            .map(|model| QueryBoost::new(Boost::Tag(model.id as u64)))
+            .collect()
+    };
+
+    let results = state
+        .index
+        .search(&query.query, &filters, &[], MAX_RESULTS_PER_RUN)
+        .await;
+
+    let urls = results
+        .documents
+        .iter()
+        .map(|(_, doc)| doc.url.clone())
+        .collect::<Vec<_>>();
+    let unseen = saved_query_seen::unseen(&state.db, query.id, &urls).await?;
+    if unseen.is_empty() {
+        return Ok(0);
+    }
+
+    let new_docs = results
+        .documents
+        .iter()
+        .map(|(_, doc)| doc)
+        .filter(|doc| unseen.contains(&doc.url))
+        .collect::<Vec<_>>();
+
+    deliver(state, &destination, to_ndjson(query, &new_docs)).await?;
+    // Only mark results as seen once they've been delivered so failed
+    // deliveries are retried on the next run.
+    saved_query_seen::mark_seen(&state.db, query.id, &unseen).await?;
+
+    Ok(unseen.len())
+}
+
+/// Runs any saved queries that are due.
+pub async fn run_due_queries(state: &AppState) {
+    let due = match saved_query::due(&state.db).await {
+        Ok(due) => due,
+        Err(err) => {
+            log::error!("Unable to check for saved queries: {}", err);
+            return;
+        }
+    };
+
+    for query in due {
+        // Claimed before running so overlapping checks don't deliver the same
+        // results twice. Failed runs wait for the next interval as well, so a
+        // broken destination isn't retried in a tight loop.
+        match saved_query::claim_run(&state.db, &query).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(err) => {
+                log::error!("Unable to update saved query \"{}\": {}", query.name, err);
+                continue;
+            }
+        }
+
+        match run_saved_query(state, &query).await {
+            Ok(0) => {}
+            Ok(count) => log::info!("delivered {} new results for \"{}\"", count, query.name),
+            Err(err) => log::warn!("Unable to run saved query \"{}\": {}", query.name, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Destination;
+
+    #[test]
+    fn test_parse_destination() {
+        assert_eq!(
+            Destination::parse("https://example.com/hook"),
+            Ok(Destination::Webhook(
+                url::Url::parse("https://example.com/hook").unwrap()
+            ))
+        );
+
+        assert_eq!(
+            Destination::parse("cves/results.ndjson"),
+            Ok(Destination::File(std::path::PathBuf::from(
+                "cves/results.ndjson"
+            )))
+        );
+
+        // Files stay in the exports folder
+        assert!(Destination::parse("/tmp/results.ndjson").is_err());
+        assert!(Destination::parse("../results.ndjson").is_err());
+        assert!(Destination::parse("cves/../../results.ndjson").is_err());
+        assert!(Destination::parse("").is_err());
+
+        // Webhooks can't reach local services
+        assert!(Destination::parse("http://localhost:4664/").is_err());
+        assert!(Destination::parse("http://127.0.0.1/hook").is_err());
+        assert!(Destination::parse("http://192.168.1.10/hook").is_err());
+        assert!(Destination::parse("http://169.254.169.254/latest").is_err());
+        assert!(Destination::parse("http://[::1]/hook").is_err());
+        assert!(Destination::parse("http://[::ffff:10.0.0.1]/hook").is_err());
+        assert!(Destination::parse("https://").is_err());
+    }
+}
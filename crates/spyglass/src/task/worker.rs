@@ -1,21 +1,27 @@
-use entities::models::crawl_queue::EnqueueSettings;
+use entities::models::crawl_queue::{EnqueueSettings, TaskError, TaskErrorType};
 
+use entities::models::lens_stats::{self, CrawlUsage};
 use entities::models::{
-    bootstrap_queue, crawl_queue, crawl_tag, indexed_document,
-    tag::{self, TagPair},
+    bootstrap_queue, crawl_queue, crawl_tag, fetch_history, indexed_document, link,
+    tag::{self, TagPair, TagType},
+    url_blocklist,
 };
 use entities::sea_orm::prelude::*;
 use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+use shared::collapse_equivalent_url;
 use shared::config::{Config, LensConfig, LensSource};
 use spyglass_searcher::{SearchTrait, WriteTrait};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use url::Url;
 
 use super::{bootstrap, CollectTask, ManagerCommand};
 use super::{CleanupTask, CrawlTask};
 
 use crate::state::AppState;
 use crate::{
-    crawler::{CrawlError, CrawlResult, Crawler},
-    documents::process_crawl_results,
+    crawler::{favicon, session::SessionEntry, sitemap, CrawlError, CrawlResult, Crawler},
+    documents::{self, process_crawl_results},
 };
 
 /// Handles bootstrapping a lens. If the lens is remote we attempt to process the cache.
@@ -147,11 +153,71 @@ pub enum FetchResult {
     Updated,
 }
 
+/// Switches an `http://` URL to `https://`. URLs w/ an explicit port are left
+/// alone, the port is likely specific to http.
+fn upgrade_insecure_url(url: &mut Url) -> bool {
+    url.scheme() == "http" && url.port().is_none() && url.set_scheme("https").is_ok()
+}
+
+/// Upgrades `http://` links to `https://` and/or drops `http://` links found on
+/// `https://` pages. Returns the links to crawl along w/ those of them that
+/// were upgraded. Links that were also found as `https://` don't count as
+/// upgraded.
+fn secure_links(
+    page: &Url,
+    links: Vec<String>,
+    upgrade: bool,
+    skip: bool,
+) -> (Vec<String>, HashSet<String>) {
+    if !upgrade && !skip {
+        return (links, HashSet::new());
+    }
+
+    let found: HashSet<&str> = links.iter().map(|link| link.as_str()).collect();
+    let mut upgraded = HashSet::new();
+    let mut seen = HashSet::new();
+    let secured = links
+        .iter()
+        .filter_map(|link| {
+            let mut link_url = match Url::parse(link) {
+                Ok(link_url) if link_url.scheme() == "http" => link_url,
+                _ => return Some(link.clone()),
+            };
+
+            if upgrade && upgrade_insecure_url(&mut link_url) {
+                let link_url = link_url.to_string();
+                if !found.contains(link_url.as_str()) {
+                    upgraded.insert(link_url.clone());
+                }
+                Some(link_url)
+            } else if skip && page.scheme() == "https" {
+                log::debug!("skipping insecure link {} from {}", link, page);
+                None
+            } else {
+                Some(link.clone())
+            }
+        })
+        // Upgrading can turn a link into one that's already in the list
+        .filter(|link| seen.insert(link.clone()))
+        .collect();
+
+    (secured, upgraded)
+}
+
 pub async fn process_crawl(
     state: &AppState,
     task_id: i64,
     crawl_result: &CrawlResult,
 ) -> anyhow::Result<FetchResult, CrawlError> {
+    // Collapse equivalent URLs (trailing slashes, index files) so they dedupe
+    let mut crawl_result = crawl_result.clone();
+    if state.user_settings.load().collapse_equivalent_urls {
+        if let Ok(mut url) = Url::parse(&crawl_result.url) {
+            collapse_equivalent_url(&mut url);
+            crawl_result.url = url.to_string();
+        }
+    }
+
     // Update job status
     let task =
         match crawl_queue::mark_done(&state.db, task_id, Some(crawl_result.tags.clone())).await {
@@ -175,6 +241,50 @@ pub async fn process_crawl(
         };
     }
 
+    // Pages the user has blocked are never indexed, drop any earlier version
+    let urls = vec![task.url.clone(), crawl_result.url.clone()];
+    match url_blocklist::remove_blocked(&state.db, urls.clone()).await {
+        Ok(allowed) if allowed.len() < urls.len() => {
+            log::debug!("skipping blocked url {}", crawl_result.url);
+            let blocked = urls
+                .into_iter()
+                .filter(|url| !allowed.contains(url))
+                .collect::<Vec<_>>();
+            documents::delete_documents_by_uri(state, blocked).await;
+            return Ok(FetchResult::Ignore);
+        }
+        Err(err) => log::error!("Unable to check url blocklist: {}", err),
+        _ => {}
+    }
+
+    // The page says it's a duplicate of another page, skip it if that page is
+    // already indexed rather than indexing it again. Unless that's this page's
+    // own earlier crawl (e.g. after a redirect or w/ a tracking param), which
+    // still needs to be refreshed.
+    let is_same_page = task.canonical_url().as_deref() == Some(crawl_result.url.as_str());
+    if task.url != crawl_result.url
+        && !is_same_page
+        && state.user_settings.load().skip_indexed_canonicals
+    {
+        let canonical = indexed_document::Entity::find()
+            .filter(indexed_document::Column::Url.eq(crawl_result.url.as_str()))
+            .one(&state.db)
+            .await;
+        match canonical {
+            Ok(Some(_)) => {
+                log::debug!(
+                    "skipping {}, canonical {} already indexed",
+                    task.url,
+                    crawl_result.url
+                );
+                documents::delete_documents_by_uri(state, vec![task.url.clone()]).await;
+                return Ok(FetchResult::Ignore);
+            }
+            Err(err) => log::error!("Unable to check for canonical url: {}", err),
+            _ => {}
+        }
+    }
+
     let task_tags = task
         .find_related(tag::Entity)
         .all(&state.db)
@@ -185,7 +295,30 @@ pub async fn process_crawl(
         .collect::<Vec<TagPair>>();
 
     // Add all valid, non-duplicate, non-indexed links found to crawl queue
-    let to_enqueue: Vec<String> = crawl_result.links.clone().into_iter().collect();
+    let mut to_enqueue: Vec<String> = crawl_result.links.clone().into_iter().collect();
+
+    let (stay_on_domain, merge_series, upgrade_insecure, skip_insecure) = {
+        let settings = state.user_settings.load();
+        (
+            settings.stay_on_domain,
+            settings.merge_paginated_series,
+            settings.upgrade_insecure_links,
+            settings.skip_insecure_links,
+        )
+    };
+
+    // Later pages of a paginated series are indexed as part of the first
+    // page, only the way back to it is followed.
+    let in_series = merge_series && crawl_result.prev_page.is_some();
+    if in_series {
+        to_enqueue = crawl_result.prev_page.clone().into_iter().collect();
+    }
+
+    let mut upgraded = HashSet::new();
+    if let Ok(page_url) = Url::parse(&crawl_result.url) {
+        (to_enqueue, upgraded) =
+            secure_links(&page_url, to_enqueue, upgrade_insecure, skip_insecure);
+    }
 
     // Grab enabled lenses
     let lenses: Vec<LensConfig> = state
@@ -195,20 +328,94 @@ pub async fn process_crawl(
         .map(|entry| entry.value().clone())
         .collect();
 
-    if let Err(err) = crawl_queue::enqueue_all(
-        &state.db,
-        &to_enqueue,
-        &lenses,
-        &state.user_settings.load_full(),
-        &EnqueueSettings {
-            tags: task_tags.clone(),
-            ..Default::default()
-        },
-        None,
-    )
-    .await
+    // Links are checked against the site of the lens source being crawled
+    let seed_host = if stay_on_domain {
+        crawl_queue::suburl_seed_host(&lenses, &task.url)
+    } else {
+        None
+    };
+
+    // Upgraded links are queued separately, so they're the only ones that can
+    // fall back to http when crawled.
+    let (to_upgrade, to_enqueue): (Vec<String>, Vec<String>) = to_enqueue
+        .into_iter()
+        .partition(|link| upgraded.contains(link));
+    for (links, upgraded_from_http) in [(to_enqueue, false), (to_upgrade, true)] {
+        if links.is_empty() {
+            continue;
+        }
+
+        if let Err(err) = crawl_queue::enqueue_all(
+            &state.db,
+            &links,
+            &lenses,
+            &state.user_settings.load_full(),
+            &EnqueueSettings {
+                tags: task_tags.clone(),
+                depth: task.depth + 1,
+                // Feeds are recrawled to pick up new entries, anything seen
+                // before doesn't need to be crawled again.
+                new_only: crawl_result.is_feed,
+                seed_host: seed_host.clone(),
+                upgraded_from_http,
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        {
+            log::error!("error enqueuing all: {}", err);
+        }
+    }
+
+    if in_series {
+        log::debug!("skipping {}, part of a paginated series", crawl_result.url);
+        documents::delete_documents_by_uri(state, vec![crawl_result.url.clone()]).await;
+        return Ok(FetchResult::Ignore);
+    }
+
+    // Grab the favicon the first time we see a site
+    if let Ok(page_url) = Url::parse(&crawl_result.url) {
+        let is_web = page_url.scheme() == "http" || page_url.scheme() == "https";
+        if is_web
+            && page_url
+                .host_str()
+                .map_or(false, |domain| favicon::needs_fetch(&state.config, domain))
+        {
+            let client = state.http_client();
+            let config = state.config.clone();
+            tokio::spawn(async move {
+                favicon::fetch_favicon(&client, &config, &page_url).await;
+            });
+        }
+    }
+
+    // Pick up pages that aren't linked from anywhere through the site's sitemap
+    if task.use_sitemap {
+        queue_sitemap(state, &task.url, task_tags.clone(), task.depth + 1);
+    }
+
+    // Keep the text of outgoing links around until the pages they point to
+    // are crawled & indexed, under the same URLs the links are crawled with.
+    if upgrade_insecure {
+        for (link, _) in crawl_result.anchors.iter_mut() {
+            if let Ok(mut link_url) = Url::parse(link) {
+                if upgrade_insecure_url(&mut link_url) {
+                    *link = link_url.to_string();
+                }
+            }
+        }
+    }
+    if let Err(err) = link::save_anchors(&state.db, &crawl_result.url, &crawl_result.anchors).await
+    {
+        log::error!("error saving anchor text: {}", err);
+    }
+
+    if crawl_result.is_feed
+        && (!state.user_settings.load().index_feed_summaries
+            || !includes_feed_summary(state, &crawl_result.url))
     {
-        log::error!("error enqueuing all: {}", err);
+        return Ok(FetchResult::Ignore);
     }
 
     // Add / update search index w/ crawl result.
@@ -216,9 +423,19 @@ pub async fn process_crawl(
         return Err(CrawlError::ParseError("No content found".to_string()));
     }
 
-    match process_crawl_results(state, &[crawl_result.clone()], &task_tags).await {
+    // Keep track of how far this document was from the seed URL.
+    let crawl_result = CrawlResult {
+        depth: task.depth,
+        ..crawl_result
+    };
+
+    match process_crawl_results(state, &[crawl_result], &task_tags).await {
         Ok(res) => {
-            if res.num_updated > 0 {
+            if res.num_unchanged > 0 || res.num_aliased > 0 {
+                // Same as the indexed version or another indexed page,
+                // nothing new to commit.
+                Ok(FetchResult::Ignore)
+            } else if res.num_updated > 0 {
                 Ok(FetchResult::Updated)
             } else {
                 Ok(FetchResult::New)
@@ -228,27 +445,235 @@ pub async fn process_crawl(
     }
 }
 
+/// Whether the feed's own summary should be indexed, i.e. no lens polling
+/// the feed turned `include_summary` off.
+fn includes_feed_summary(state: &AppState, url: &str) -> bool {
+    !state.lenses.iter().any(|lens| {
+        lens.feeds.iter().any(|feed| {
+            !feed.include_summary
+                && Url::parse(&feed.url).map_or(false, |feed_url| feed_url.as_str() == url)
+        })
+    })
+}
+
+/// Queues the pages listed in the sitemap of the site `url` is on, unless that
+/// was already done since the app started. Pages last modified before the
+/// site's sitemap was last queued are skipped.
+fn queue_sitemap(state: &AppState, url: &str, tags: Vec<TagPair>, depth: i32) {
+    let root = match Url::parse(url) {
+        Ok(root) if matches!(root.scheme(), "http" | "https") => root,
+        _ => return,
+    };
+    let (domain, sitemap_url) = match (root.host_str(), root.join("/sitemap.xml")) {
+        (Some(domain), Ok(sitemap_url)) => (domain.to_string(), sitemap_url),
+        _ => return,
+    };
+
+    if state
+        .sitemap_domains
+        .insert(domain.clone(), Instant::now())
+        .is_some()
+    {
+        return;
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        let since = fetch_history::find_by_url(&state.db, &sitemap_url)
+            .await
+            .ok()
+            .flatten()
+            .map(|history| history.updated_at);
+        let entries = sitemap::fetch_sitemap_urls(&state.http_client(), &root, since).await;
+        if entries.is_empty() {
+            return;
+        }
+
+        log::debug!("queueing {} urls from {} sitemap", entries.len(), domain);
+        let _ = fetch_history::upsert(&state.db, &domain, sitemap_url.path(), None, 200).await;
+
+        // Pages known to have changed since they were last queued are
+        // crawled again, anything else only if it hasn't been seen before.
+        let (changed, unknown): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|entry| since.is_some() && entry.lastmod.is_some());
+
+        let lenses: Vec<LensConfig> = state
+            .lenses
+            .iter()
+            .filter(|entry| entry.value().pipeline.is_none())
+            .map(|entry| entry.value().clone())
+            .collect();
+        let settings = state.user_settings.load_full();
+        for (entries, is_recrawl) in [(changed, true), (unknown, false)] {
+            if entries.is_empty() {
+                continue;
+            }
+
+            let urls = entries
+                .into_iter()
+                .map(|entry| entry.loc)
+                .collect::<Vec<_>>();
+            if let Err(err) = crawl_queue::enqueue_all(
+                &state.db,
+                &urls,
+                &lenses,
+                &settings,
+                &EnqueueSettings {
+                    tags: tags.clone(),
+                    depth,
+                    is_recrawl,
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            {
+                log::error!("Unable to queue {} sitemap: {}", domain, err);
+            }
+        }
+    });
+}
+
+/// Names of the lenses a crawl task was queued for.
+async fn task_lenses(state: &AppState, task_id: i64) -> Vec<String> {
+    let task = match crawl_queue::Entity::find_by_id(task_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(task)) => task,
+        _ => return Vec::new(),
+    };
+
+    task.find_related(tag::Entity)
+        .all(&state.db)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|tag| tag.label == TagType::Lens.to_string())
+        .map(|tag| tag.value)
+        .collect()
+}
+
 #[tracing::instrument(skip(state))]
 pub async fn handle_fetch(state: AppState, task: CrawlTask) -> FetchResult {
-    let crawler = Crawler::new(state.user_settings.load().domain_crawl_limit.value());
+    let started = Instant::now();
+    // Grab these before crawling, the task may be merged into another when
+    // its canonical URL is already queued.
+    let lenses = task_lenses(&state, task.id).await;
+
+    let (crawler, record_crawls) = {
+        let settings = state.user_settings.load();
+        let crawler = Crawler::with_clients(
+            state.http_clients.clone(),
+            settings.domain_crawl_limit.value(),
+        )
+        .with_proxy(settings.crawl_proxy.clone())
+        .with_code_blocks(settings.index_code_blocks);
+        if settings.record_crawls {
+            (crawler.with_recording(), true)
+        } else {
+            (crawler, false)
+        }
+    };
     let result = crawler.fetch_by_job(&state, task.id, true).await;
+    // Kept until the outcome is known, see `record_session`
+    let recorded = if record_crawls {
+        Some(SessionEntry {
+            task_id: task.id,
+            recorded_at: chrono::Utc::now(),
+            responses: crawler.take_recorded(),
+            result: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+            outcome: String::new(),
+        })
+    } else {
+        None
+    };
+    // Track web fetches that failed to reach the site at all, pausing
+    // crawling when most of them are failing.
+    let reached_site = match &result {
+        Err(CrawlError::FetchError(_) | CrawlError::Timeout | CrawlError::Unreachable(_)) => {
+            Some(false)
+        }
+        Ok(crawl_result) if crawl_result.url.starts_with("http") => Some(true),
+        _ => None,
+    };
+    if let Some(success) = reached_site {
+        let (max_failure_percent, window) = {
+            let settings = state.user_settings.load();
+            (
+                settings.crawl_failure_pause_percent,
+                Duration::from_secs(settings.crawl_failure_window_secs as u64),
+            )
+        };
+        if state
+            .crawl_breaker
+            .record(success, max_failure_percent, window)
+        {
+            log::warn!(
+                "Over {}% of fetches failed in the last {:?}, pausing crawling",
+                max_failure_percent,
+                window
+            );
+        }
+    }
 
-    match result {
-        Ok(crawl_result) => match process_crawl(&state, task.id, &crawl_result).await {
-            Ok(res) => {
-                log::debug!("Crawled task id: {} - {:?}", task.id, res);
-                res
-            }
-            Err(err) => {
-                log::warn!("Unable to crawl id: {} - {:?}", task.id, err);
-                FetchResult::Error(err.to_string())
+    let bytes_fetched = result
+        .as_ref()
+        .map(|crawl_result| crawl_result.bytes_fetched)
+        .unwrap_or_default();
+
+    // Timeouts are retried, so the task isn't done with yet
+    let mut requeued = false;
+    let fetch_result = match result {
+        Ok(crawl_result) => {
+            // Wait on any database maintenance before writing the results
+            let _maintenance = state.db_maintenance.read().await;
+            match process_crawl(&state, task.id, &crawl_result).await {
+                Ok(res) => {
+                    log::debug!("Crawled task id: {} - {:?}", task.id, res);
+                    res
+                }
+                Err(err) => {
+                    log::warn!("Unable to crawl id: {} - {:?}", task.id, err);
+                    crawl_queue::mark_failed(
+                        &state.db,
+                        task.id,
+                        0,
+                        TaskError::new(TaskErrorType::Collect, &err.to_string()),
+                    )
+                    .await;
+                    FetchResult::Error(err.to_string())
+                }
             }
-        },
+        }
         Err(err) => {
             log::warn!("Unable to crawl id: {} - {:?}", task.id, err);
             match err {
+                // Nothing to re-index, only note the document was checked
+                CrawlError::NotModified => {
+                    if let Ok(Some(model)) = crawl_queue::Entity::find_by_id(task.id)
+                        .one(&state.db)
+                        .await
+                    {
+                        // The page is indexed under its canonical URL, which
+                        // may not be the URL it was crawled w/.
+                        if let Ok(Some(doc)) =
+                            indexed_document::find_by_crawl_url(&state.db, &model.url).await
+                        {
+                            if let Err(err) =
+                                indexed_document::touch_by_url(&state.db, &doc.url).await
+                            {
+                                log::warn!("Unable to update {}: {}", doc.url, err);
+                            }
+                        }
+                    }
+                    let _ = crawl_queue::mark_done(&state.db, task.id, None).await;
+                    FetchResult::Ignore
+                }
                 // Ignore skips, recently fetched crawls, or not found
-                CrawlError::Denied(_) | CrawlError::NotModified | CrawlError::RecentlyFetched => {
+                CrawlError::Denied(_) | CrawlError::RecentlyFetched => {
                     let _ = crawl_queue::mark_done(&state.db, task.id, None).await;
                     FetchResult::Ignore
                 }
@@ -256,10 +681,18 @@ pub async fn handle_fetch(state: AppState, task: CrawlTask) -> FetchResult {
                     let _ = crawl_queue::mark_done(&state.db, task.id, None).await;
                     FetchResult::NotFound
                 }
-                // Retry timeouts, might be a network issue
-                CrawlError::Timeout => {
+                // Retry timeouts & sites that are down or unreachable for
+                // now, might be a network issue
+                CrawlError::Timeout | CrawlError::Unavailable(_) | CrawlError::Unreachable(_) => {
                     log::info!("Retrying task {} if possible", task.id);
-                    crawl_queue::mark_failed(&state.db, task.id, true).await;
+                    let max_retries = state.user_settings.load().max_retries;
+                    requeued = crawl_queue::mark_failed(
+                        &state.db,
+                        task.id,
+                        max_retries,
+                        err.to_task_error(),
+                    )
+                    .await;
                     FetchResult::Error(err.to_string())
                 }
                 // No need to retry these, mark as failed.
@@ -269,12 +702,51 @@ pub async fn handle_fetch(state: AppState, task: CrawlTask) -> FetchResult {
                 | CrawlError::Unsupported(_)
                 | CrawlError::Other(_) => {
                     // mark crawl as failed
-                    crawl_queue::mark_failed(&state.db, task.id, false).await;
+                    crawl_queue::mark_failed(&state.db, task.id, 0, err.to_task_error()).await;
                     FetchResult::Error(err.to_string())
                 }
             }
         }
+    };
+
+    if let Some(entry) = recorded {
+        record_session(
+            &state,
+            SessionEntry {
+                outcome: format!("{fetch_result:?}"),
+                ..entry
+            },
+        )
+        .await;
+    }
+
+    if !requeued {
+        state
+            .recrawl_jobs
+            .record(task.id, matches!(fetch_result, FetchResult::Error(_)));
+    }
+
+    if !lenses.is_empty() {
+        let usage = CrawlUsage {
+            bytes_fetched,
+            crawl_time_ms: started.elapsed().as_millis() as u64,
+            indexed: matches!(fetch_result, FetchResult::New | FetchResult::Updated),
+        };
+        if let Err(err) = lens_stats::record_crawl(&state.db, &lenses, usage).await {
+            log::warn!("Unable to record crawl stats: {}", err);
+        }
     }
+
+    fetch_result
+}
+
+/// Appends a crawl to the current session file, see `record_crawls`.
+async fn record_session(state: &AppState, entry: SessionEntry) {
+    let max_bytes = state.user_settings.load().crawl_recording_max_mb as u64 * 1_000_000;
+    state
+        .crawl_recorder
+        .record(&state.config.crawl_sessions_dir(), max_bytes, &entry)
+        .await;
 }
 
 #[tracing::instrument(skip(state))]
@@ -318,15 +790,22 @@ mod test {
     use crate::crawler::CrawlResult;
     use entities::models::crawl_queue::{self, CrawlStatus, CrawlType};
     use entities::models::tag::{self, TagType};
-    use entities::models::{bootstrap_queue, indexed_document};
-    use entities::sea_orm::{ActiveModelTrait, EntityTrait, ModelTrait, Set};
+    use entities::models::{bootstrap_queue, document_alias, indexed_document};
+    use entities::sea_orm::{
+        ActiveModelTrait, ColumnTrait, EntityTrait, ModelTrait, QueryFilter, Set,
+    };
     use entities::test::setup_test_db;
-    use shared::config::{LensConfig, UserSettings};
+    use shared::config::{LensConfig, LensFeed, UserSettings};
     use spyglass_searcher::schema::DocFields;
     use spyglass_searcher::schema::SearchDocument;
     use spyglass_searcher::IndexBackend;
+    use std::collections::HashSet;
 
-    use super::{handle_cdx_collection, process_crawl, AppState, FetchResult};
+    use super::{
+        handle_cdx_collection, includes_feed_summary, process_crawl, secure_links, AppState,
+        FetchResult,
+    };
+    use url::Url;
 
     #[tokio::test]
     async fn test_handle_cdx_collection() {
@@ -398,6 +877,122 @@ mod test {
         assert_eq!(docs.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_process_crawl_skip_indexed_canonical() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&UserSettings {
+                skip_indexed_canonicals: true,
+                ..Default::default()
+            })
+            .with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .build();
+
+        let mut task_ids = Vec::new();
+        for url in [
+            "https://example.com/post",
+            "https://example.com/post?ref=feed",
+        ] {
+            let task = crawl_queue::ActiveModel {
+                domain: Set("example.com".to_owned()),
+                url: Set(url.to_owned()),
+                status: Set(CrawlStatus::Processing),
+                crawl_type: Set(CrawlType::Normal),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to save model");
+            task_ids.push(task.id);
+        }
+
+        let crawl_result = CrawlResult {
+            content: Some("fake content".to_owned()),
+            title: Some("Title".to_owned()),
+            url: "https://example.com/post".to_owned(),
+            ..Default::default()
+        };
+
+        let result = process_crawl(&state, task_ids[0], &crawl_result)
+            .await
+            .expect("success");
+        assert_eq!(result, FetchResult::New);
+
+        // Declares the same canonical URL, which is now indexed
+        let result = process_crawl(&state, task_ids[1], &crawl_result)
+            .await
+            .expect("success");
+        assert_eq!(result, FetchResult::Ignore);
+
+        let indexed = indexed_document::Entity::find()
+            .all(&db)
+            .await
+            .expect("Unable to query indexed docs");
+        assert_eq!(indexed.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_process_crawl_duplicate_content() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&UserSettings::default())
+            .with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .build();
+
+        // Same title & content on the same site is collapsed, a different title
+        // or site is its own document.
+        let pages = [
+            ("https://example.com/post", "Title"),
+            ("https://www.example.com/post/", "Title"),
+            ("https://example.com/login", "Log in"),
+            ("https://other.com/post", "Title"),
+        ];
+        let expected = [
+            FetchResult::New,
+            FetchResult::Ignore,
+            FetchResult::New,
+            FetchResult::New,
+        ];
+        for ((url, title), expected) in pages.iter().zip(expected) {
+            let url = Url::parse(url).expect("Invalid URL");
+            let task = crawl_queue::ActiveModel {
+                domain: Set(url.host_str().unwrap_or_default().to_owned()),
+                url: Set(url.to_string()),
+                status: Set(CrawlStatus::Processing),
+                crawl_type: Set(CrawlType::Normal),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .expect("Unable to save model");
+
+            let crawl_result = CrawlResult::new(&url, None, "fake content", title, None);
+            let result = process_crawl(&state, task.id, &crawl_result)
+                .await
+                .expect("success");
+            assert_eq!(result, expected);
+        }
+
+        // Only the www page is an alias, of the first page.
+        let indexed = indexed_document::Entity::find()
+            .all(&db)
+            .await
+            .expect("Unable to query indexed docs");
+        assert_eq!(indexed.len(), 3);
+        assert!(indexed.iter().all(|doc| doc.content_hash.is_some()));
+        assert_eq!(state.index.reader.searcher().num_docs(), 3);
+
+        let aliases = document_alias::Entity::find()
+            .all(&db)
+            .await
+            .expect("Unable to query aliases");
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases[0].url, pages[1].0);
+        assert_eq!(aliases[0].canonical_url, pages[0].0);
+    }
+
     #[tokio::test]
     async fn test_process_crawl_update() {
         let db = setup_test_db().await;
@@ -598,4 +1193,140 @@ mod test {
             .unwrap_or_default();
         assert_eq!(task_tags.len(), 3);
     }
+
+    #[tokio::test]
+    async fn test_process_crawl_tracks_depth() {
+        let db = setup_test_db().await;
+        let lens = LensConfig {
+            name: "example".to_owned(),
+            domains: vec!["example.com".to_owned()],
+            ..Default::default()
+        };
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_lenses(&vec![lens])
+            .with_user_settings(&UserSettings::default())
+            .with_index(&IndexBackend::Memory, DocFields::as_schema(), false)
+            .build();
+
+        let task = crawl_queue::ActiveModel {
+            domain: Set("example.com".to_owned()),
+            url: Set("https://example.com/test".to_owned()),
+            status: Set(CrawlStatus::Processing),
+            crawl_type: Set(CrawlType::Normal),
+            depth: Set(2),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to save model");
+
+        let crawl_result = CrawlResult {
+            content: Some("fake content".to_owned()),
+            title: Some("Title".to_owned()),
+            url: "https://example.com/test".to_owned(),
+            links: ["https://example.com/next".to_owned()]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+
+        process_crawl(&state, task.id, &crawl_result)
+            .await
+            .expect("success");
+
+        // Indexed doc should keep the depth it was discovered at
+        let docs = indexed_document::Entity::find()
+            .all(&db)
+            .await
+            .unwrap_or_default();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].depth, 2);
+
+        // Links found should be one level deeper
+        let next = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Url.eq("https://example.com/next"))
+            .one(&db)
+            .await
+            .expect("Unable to query crawl task")
+            .expect("Link should be enqueued");
+        assert_eq!(next.depth, 3);
+    }
+
+    #[tokio::test]
+    async fn test_includes_feed_summary() {
+        let db = setup_test_db().await;
+        let lens = LensConfig {
+            name: "feeds".into(),
+            feeds: vec![LensFeed {
+                url: "https://EXAMPLE.com/feed.xml".into(),
+                poll_interval_minutes: 60,
+                include_summary: false,
+            }],
+            ..Default::default()
+        };
+        let state = AppState::builder()
+            .with_db(db)
+            .with_lenses(&vec![lens])
+            .build();
+
+        assert!(!includes_feed_summary(
+            &state,
+            "https://example.com/feed.xml"
+        ));
+        assert!(includes_feed_summary(
+            &state,
+            "https://example.com/atom.xml"
+        ));
+    }
+
+    #[test]
+    fn test_secure_links() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let links = vec![
+            "http://example.com/about".to_string(),
+            "https://example.com/about".to_string(),
+            "http://example.com:8080/admin".to_string(),
+            "mailto:hello@example.com".to_string(),
+        ];
+
+        assert_eq!(
+            secure_links(&page, links.clone(), false, false),
+            (links.clone(), HashSet::new())
+        );
+        let (secured, upgraded) = secure_links(&page, links.clone(), true, false);
+        assert_eq!(
+            secured,
+            vec![
+                "https://example.com/about".to_string(),
+                "http://example.com:8080/admin".to_string(),
+                "mailto:hello@example.com".to_string(),
+            ]
+        );
+        // Also linked over https, so it was never only an http link
+        assert!(upgraded.is_empty());
+
+        let links = vec!["http://example.com/contact".to_string()];
+        let (secured, upgraded) = secure_links(&page, links, true, false);
+        assert_eq!(secured, vec!["https://example.com/contact".to_string()]);
+        assert!(upgraded.contains("https://example.com/contact"));
+
+        let links = vec![
+            "http://example.com/about".to_string(),
+            "https://example.com/about".to_string(),
+            "http://example.com:8080/admin".to_string(),
+            "mailto:hello@example.com".to_string(),
+        ];
+        assert_eq!(
+            secure_links(&page, links.clone(), false, true).0,
+            vec![
+                "https://example.com/about".to_string(),
+                "mailto:hello@example.com".to_string(),
+            ]
+        );
+
+        // Insecure pages can link to insecure pages
+        let page = Url::parse("http://example.com/").unwrap();
+        assert_eq!(secure_links(&page, links.clone(), false, true).0, links);
+    }
 }
@@ -161,6 +161,7 @@ pub async fn search_docs<'r>(
         let data = request::SearchParam {
             lenses,
             query: query.to_string(),
+            ..Default::default()
         };
 
         let rpc = rpc.lock().await;
@@ -200,10 +201,21 @@ pub async fn search_lenses<'r>(
 }
 
 #[tauri::command]
-pub async fn delete_doc<'r>(window: tauri::Window, id: &str) -> Result<(), String> {
+pub async fn delete_doc<'r>(
+    window: tauri::Window,
+    id: &str,
+    block: Option<bool>,
+) -> Result<(), String> {
     if let Some(rpc) = window.app_handle().try_state::<rpc::RpcMutex>() {
         let rpc = rpc.lock().await;
-        match rpc.client.delete_document(id.to_string()).await {
+        // Optionally block the URL so a recrawl doesn't add the doc back
+        let res = if block.unwrap_or_default() {
+            rpc.client.block_document(id.to_string()).await
+        } else {
+            rpc.client.delete_document(id.to_string()).await
+        };
+
+        match res {
             Ok(_) => {
                 let _ = window.emit(ClientEvent::RefreshSearchResults.as_ref(), true);
             }
@@ -251,6 +263,77 @@ pub async fn recrawl_domain(win: tauri::Window, domain: &str) -> Result<(), Stri
     Ok(())
 }
 
+#[tauri::command]
+pub async fn crawl_url_now(
+    win: tauri::Window,
+    url: &str,
+) -> Result<response::CrawlUrlResult, String> {
+    log::info!("crawling {} now", url);
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        match rpc.client.crawl_url_now(url.to_string()).await {
+            Ok(res) => Ok(res),
+            Err(err) => {
+                log::error!("crawl_url_now err: {}", err);
+                Err(err.to_string())
+            }
+        }
+    } else {
+        Err("Unable to reach backend".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn delete_queue_entry(win: tauri::Window, id: i64) -> Result<(), String> {
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        match rpc.client.delete_queue_entry(id).await {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::error!("delete_queue_entry err: {}", err);
+                Err(err.to_string())
+            }
+        }
+    } else {
+        Err("Unable to reach backend".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn failed_crawls(
+    win: tauri::Window,
+    domain: Option<String>,
+) -> Result<Vec<response::FailedCrawl>, String> {
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        match rpc.client.failed_crawls(domain).await {
+            Ok(res) => Ok(res),
+            Err(err) => {
+                log::error!("failed_crawls err: {}", err);
+                Err(err.to_string())
+            }
+        }
+    } else {
+        Err("Unable to reach backend".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn retry_failed_crawl(win: tauri::Window, id: i64) -> Result<(), String> {
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        match rpc.client.retry_failed_crawl(id).await {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::error!("retry_failed_crawl err: {}", err);
+                Err(err.to_string())
+            }
+        }
+    } else {
+        Err("Unable to reach backend".to_string())
+    }
+}
+
 #[tauri::command]
 pub async fn get_library_stats(
     win: tauri::Window,
@@ -314,6 +397,37 @@ pub async fn toggle_plugin(window: tauri::Window, name: &str, enabled: bool) ->
     Ok(())
 }
 
+/// Compacts the database in the background, the user is notified once it's done.
+/// Returns whether it started or was deferred until the maintenance window.
+#[tauri::command]
+pub async fn vacuum_database(win: tauri::Window) -> Result<Option<response::VacuumStatus>, String> {
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        return match rpc.client.vacuum_database().await {
+            Ok(status) => Ok(Some(status)),
+            Err(err) => {
+                log::error!("vacuum_database err: {}", err);
+                Err(err.to_string())
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+#[tauri::command]
+pub async fn reindex(win: tauri::Window) -> Result<(), String> {
+    if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        if let Err(err) = rpc.client.reindex().await {
+            log::error!("reindex err: {}", err);
+            return Err(err.to_string());
+        }
+    }
+
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn update_and_restart(window: tauri::Window) -> Result<(), String> {
     let app_handle = window.app_handle();
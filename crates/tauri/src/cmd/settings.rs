@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use shared::config::FileSystemSettings;
 use shared::config::UserActionSettings;
 use tauri::Manager;
 use tauri::State;
 
-use shared::config::{Config, Limit, UserSettings};
+use shared::config::{Config, Limit, MaintenanceWindow, QueryStringUrls, UserSettings};
 use shared::form::SettingOpts;
 
 #[tauri::command]
@@ -19,6 +20,11 @@ pub async fn save_user_settings(
     let mut current_settings =
         Config::load_user_settings().unwrap_or_else(|_| config.user_settings.clone());
     let orig_settings = current_settings.clone();
+    // Some settings are only read on startup, restart even if the UI didn't ask to.
+    let restart = restart
+        || settings
+            .keys()
+            .any(|key| UserSettings::requires_restart(key));
 
     let config_list: Vec<(String, SettingOpts)> = config.user_settings.clone().into();
     let setting_configs: HashMap<String, SettingOpts> = config_list.into_iter().collect();
@@ -58,6 +64,123 @@ pub async fn save_user_settings(
                                         current_settings.disable_telemetry =
                                             serde_json::from_str(value).unwrap_or_default();
                                     }
+                                    "stay_on_domain" => {
+                                        current_settings.stay_on_domain =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "stay_on_domain_subdomains" => {
+                                        current_settings.stay_on_domain_subdomains =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "index_feed_summaries" => {
+                                        current_settings.index_feed_summaries =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "index_code_blocks" => {
+                                        current_settings.index_code_blocks =
+                                            serde_json::from_str(value).unwrap_or_else(|_| {
+                                                UserSettings::default_index_code_blocks()
+                                            });
+                                    }
+                                    "store_document_content" => {
+                                        current_settings.store_document_content =
+                                            serde_json::from_str(value).unwrap_or_else(|_| {
+                                                UserSettings::default_store_document_content()
+                                            });
+                                    }
+                                    "upgrade_insecure_links" => {
+                                        current_settings.upgrade_insecure_links =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "skip_insecure_links" => {
+                                        current_settings.skip_insecure_links =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "remove_lens_documents" => {
+                                        current_settings.remove_lens_documents =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "lens_indexes" => {
+                                        current_settings.lens_indexes =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "merge_paginated_series" => {
+                                        current_settings.merge_paginated_series =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "skip_indexed_canonicals" => {
+                                        current_settings.skip_indexed_canonicals =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "record_crawls" => {
+                                        current_settings.record_crawls =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "crawl_recording_max_mb" => {
+                                        current_settings.crawl_recording_max_mb =
+                                            serde_json::from_str(value).unwrap_or_else(|_| {
+                                                UserSettings::default_crawl_recording_max_mb()
+                                            });
+                                    }
+                                    "total_external_domain_limit" => {
+                                        let limit: u32 = serde_json::from_str(value).unwrap_or(0);
+                                        current_settings.total_external_domain_limit = if limit == 0
+                                        {
+                                            Limit::Infinite
+                                        } else {
+                                            Limit::Finite(limit)
+                                        };
+                                    }
+                                    "crawl_trap_limit" => {
+                                        let limit: u32 = serde_json::from_str(value).unwrap_or(0);
+                                        current_settings.crawl_trap_limit = if limit == 0 {
+                                            Limit::Infinite
+                                        } else {
+                                            Limit::Finite(limit)
+                                        };
+                                    }
+                                    "max_sources_per_lens" => {
+                                        let limit: u32 = serde_json::from_str(value).unwrap_or(0);
+                                        current_settings.max_sources_per_lens = if limit == 0 {
+                                            Limit::Infinite
+                                        } else {
+                                            Limit::Finite(limit)
+                                        };
+                                    }
+                                    "index_query_string_urls" => {
+                                        match QueryStringUrls::from_str(&val) {
+                                            Ok(setting) => {
+                                                current_settings.index_query_string_urls = setting;
+                                            }
+                                            Err(_) => {
+                                                errors.insert(
+                                                    key.to_string(),
+                                                    "Expected Always, Never or OnlyWhitelisted"
+                                                        .to_string(),
+                                                );
+                                            }
+                                        }
+                                    }
+                                    "query_string_whitelist" => {
+                                        current_settings.query_string_whitelist =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "domain_crawl_delay_ms" => {
+                                        current_settings.domain_crawl_delay_ms =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "recrawl_interval_days" => {
+                                        current_settings.recrawl_interval_days =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
+                                    "max_retries" => {
+                                        current_settings.max_retries = serde_json::from_str(value)
+                                            .unwrap_or_else(|_| UserSettings::default_max_retries());
+                                    }
+                                    "trusted_domains" => {
+                                        current_settings.trusted_domains =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
                                     "inflight_crawl_limit" => {
                                         let limit: u32 = serde_json::from_str(value).unwrap_or(10);
                                         current_settings.inflight_crawl_limit =
@@ -68,6 +191,49 @@ pub async fn save_user_settings(
                                         current_settings.inflight_domain_limit =
                                             Limit::Finite(limit);
                                     }
+                                    "maintenance_window.start_hour"
+                                    | "maintenance_window.end_hour" => {
+                                        // Both hours are read together, so the order
+                                        // they're saved in doesn't matter.
+                                        let saved = orig_settings.maintenance_window.as_ref();
+                                        let hour = |name: &str, saved_hour: u8| match settings
+                                            .get(&format!("_.maintenance_window.{name}"))
+                                        {
+                                            Some(value) => serde_json::from_str::<u8>(value)
+                                                .ok()
+                                                .filter(|hour| *hour < 24),
+                                            None => Some(saved_hour),
+                                        };
+                                        let start_hour = hour(
+                                            "start_hour",
+                                            saved.map_or(0, |window| window.start_hour),
+                                        );
+                                        let end_hour = hour(
+                                            "end_hour",
+                                            saved.map_or(0, |window| window.end_hour),
+                                        );
+                                        match (start_hour, end_hour) {
+                                            // Same start & end is open all day
+                                            (Some(start_hour), Some(end_hour))
+                                                if start_hour == end_hour =>
+                                            {
+                                                current_settings.maintenance_window = None;
+                                            }
+                                            (Some(start_hour), Some(end_hour)) => {
+                                                current_settings.maintenance_window =
+                                                    Some(MaintenanceWindow {
+                                                        start_hour,
+                                                        end_hour,
+                                                    });
+                                            }
+                                            _ => {
+                                                errors.insert(
+                                                    key.to_string(),
+                                                    "Expected an hour between 0 and 23".to_string(),
+                                                );
+                                            }
+                                        }
+                                    }
                                     "port" => {
                                         current_settings.port = serde_json::from_str(value)
                                             .unwrap_or_else(|_| UserSettings::default_port());
@@ -154,6 +320,22 @@ pub async fn save_user_settings(
     }
 }
 
+/// Returns the pending setting changes that will restart Spyglass once saved,
+/// so the UI can warn before the user commits to them.
+#[tauri::command]
+pub async fn preview_settings_restart(
+    _: tauri::Window,
+    settings: HashMap<String, String>,
+) -> Result<Vec<String>, String> {
+    let mut restarts = settings
+        .into_keys()
+        .filter(|key| UserSettings::requires_restart(key))
+        .collect::<Vec<String>>();
+    restarts.sort();
+
+    Ok(restarts)
+}
+
 #[tauri::command]
 pub async fn load_action_settings(
     _: tauri::Window,
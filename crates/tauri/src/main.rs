@@ -106,9 +106,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             cmd::authorize_connection,
             cmd::choose_folder,
             cmd::copy_to_clipboard,
+            cmd::crawl_url_now,
             cmd::default_indices,
             cmd::delete_doc,
+            cmd::delete_queue_entry,
             cmd::escape,
+            cmd::failed_crawls,
             cmd::get_library_stats,
             cmd::get_shortcut,
             cmd::list_connections,
@@ -122,15 +125,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             cmd::open_plugins_folder,
             cmd::open_result,
             cmd::open_settings_folder,
+            cmd::preview_settings_restart,
             cmd::recrawl_domain,
+            cmd::reindex,
             cmd::resize_window,
             cmd::resync_connection,
+            cmd::retry_failed_crawl,
             cmd::revoke_connection,
             cmd::save_user_settings,
             cmd::search_docs,
             cmd::search_lenses,
             cmd::toggle_plugin,
             cmd::update_and_restart,
+            cmd::vacuum_database,
             cmd::wizard_finished,
         ])
         .menu(menu::get_app_menu())
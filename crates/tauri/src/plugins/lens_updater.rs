@@ -10,7 +10,7 @@ use tokio::time::{self, Duration};
 
 use crate::{constants, rpc, AppEvent};
 use serde_json::Value;
-use shared::response::{InstallableLens, LensResult};
+use shared::response::{ImportBookmarksResult, InstallableLens, LensResetResult, LensResult};
 use shared::{
     event::ClientEvent,
     metrics::{Event, Metrics},
@@ -22,10 +22,16 @@ pub struct LensWatcherHandle(JoinHandle<()>);
 pub fn init() -> TauriPlugin<Wry> {
     Builder::new("lens-updater")
         .invoke_handler(tauri::generate_handler![
+            add_lens_folder,
+            import_bookmarks,
             install_lens,
             list_installable_lenses,
             list_installed_lenses,
+            pause_lens,
+            reset_lens,
             run_lens_updater,
+            set_lens_source_boost,
+            set_lens_weight,
             uninstall_lens,
         ])
         .on_event(|app_handle, event| match event {
@@ -239,6 +245,148 @@ pub async fn run_lens_updater(win: tauri::Window) -> Result<(), String> {
     }
 }
 
+/// Pause/resume crawling for a single lens
+#[tauri::command]
+pub async fn pause_lens(win: tauri::Window, name: &str, is_paused: bool) -> Result<(), String> {
+    let app_handle = win.app_handle();
+    if let Some(rpc) = app_handle.try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        if let Err(err) = rpc.client.pause_lens(name.to_string(), is_paused).await {
+            log::error!("Unable to pause lens: {}", err.to_string());
+            return Err(err.to_string());
+        }
+
+        let _ = app_handle.emit_all(ClientEvent::RefreshLensLibrary.as_ref(), Value::Null);
+    }
+
+    Ok(())
+}
+
+/// Boost/bury a lens' documents in search results
+#[tauri::command]
+pub async fn set_lens_weight(win: tauri::Window, name: &str, weight: u32) -> Result<(), String> {
+    let app_handle = win.app_handle();
+    if let Some(rpc) = app_handle.try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        if let Err(err) = rpc.client.set_lens_weight(name.to_string(), weight).await {
+            log::error!("Unable to set lens weight: {}", err.to_string());
+            return Err(err.to_string());
+        }
+
+        let _ = app_handle.emit_all(ClientEvent::RefreshLensLibrary.as_ref(), Value::Null);
+    }
+
+    Ok(())
+}
+
+/// Boost/bury documents from one of a lens' sources when searching the lens
+#[tauri::command]
+pub async fn set_lens_source_boost(
+    win: tauri::Window,
+    name: &str,
+    domain: &str,
+    boost: f32,
+) -> Result<(), String> {
+    let app_handle = win.app_handle();
+    if let Some(rpc) = app_handle.try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        if let Err(err) = rpc
+            .client
+            .set_lens_source_boost(name.to_string(), domain.to_string(), boost)
+            .await
+        {
+            log::error!("Unable to set lens source boost: {}", err.to_string());
+            return Err(err.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Index a local folder as part of a lens
+#[tauri::command]
+pub async fn add_lens_folder(
+    win: tauri::Window,
+    name: &str,
+    path: &str,
+    extensions: Vec<String>,
+) -> Result<(), String> {
+    let app_handle = win.app_handle();
+    if let Some(rpc) = app_handle.try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        if let Err(err) = rpc
+            .client
+            .add_lens_folder(name.to_string(), path.to_string(), extensions)
+            .await
+        {
+            log::error!("Unable to add folder to lens: {}", err.to_string());
+            return Err(err.to_string());
+        }
+
+        let _ = app_handle.emit_all(ClientEvent::RefreshLensLibrary.as_ref(), Value::Null);
+    }
+
+    Ok(())
+}
+
+/// Queue the links in a bookmarks file for crawling into a lens
+#[tauri::command]
+pub async fn import_bookmarks(
+    win: tauri::Window,
+    name: &str,
+    path: &str,
+) -> Result<ImportBookmarksResult, String> {
+    let app_handle = win.app_handle();
+    if let Some(rpc) = app_handle.try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        match rpc
+            .client
+            .import_bookmarks(name.to_string(), path.to_string())
+            .await
+        {
+            Ok(result) => {
+                let _ = app_handle.emit_all(ClientEvent::RefreshLensLibrary.as_ref(), Value::Null);
+                Ok(result)
+            }
+            Err(err) => {
+                log::error!("Unable to import bookmarks: {}", err.to_string());
+                Err(err.to_string())
+            }
+        }
+    } else {
+        Err("Unable to reach backend".to_string())
+    }
+}
+
+/// Clear the crawl state of a lens & crawl its sources again
+#[tauri::command]
+pub async fn reset_lens(
+    win: tauri::Window,
+    name: &str,
+    remove_documents: bool,
+) -> Result<LensResetResult, String> {
+    let app_handle = win.app_handle();
+    if let Some(rpc) = app_handle.try_state::<rpc::RpcMutex>() {
+        let rpc = rpc.lock().await;
+        match rpc
+            .client
+            .reset_lens(name.to_string(), remove_documents)
+            .await
+        {
+            Ok(result) => {
+                let _ = app_handle.emit_all(ClientEvent::RefreshLensLibrary.as_ref(), Value::Null);
+                Ok(result)
+            }
+            Err(err) => {
+                log::error!("Unable to reset lens: {}", err.to_string());
+                Err(err.to_string())
+            }
+        }
+    } else {
+        Err("Unable to reach backend".to_string())
+    }
+}
+
 /// Uninstall lens from the backend
 #[tauri::command]
 pub async fn uninstall_lens(win: tauri::Window, name: &str) -> Result<(), String> {
@@ -2,7 +2,9 @@ use crate::window::notify;
 use crate::{rpc, AppEvent};
 use anyhow::anyhow;
 use jsonrpsee::core::client::Subscription;
-use spyglass_rpc::{ModelDownloadStatusPayload, RpcClient, RpcEvent, RpcEventType};
+use spyglass_rpc::{
+    ModelDownloadStatusPayload, ReindexProgressPayload, RpcClient, RpcEvent, RpcEventType,
+};
 use tauri::{
     async_runtime::JoinHandle,
     plugin::{Builder, TauriPlugin},
@@ -43,9 +45,11 @@ async fn _subscribe(app: &AppHandle) -> anyhow::Result<Subscription<RpcEvent>> {
         .client
         .subscribe_events(vec![
             RpcEventType::ConnectionSyncFinished,
+            RpcEventType::DatabaseVacuumed,
             RpcEventType::LensInstalled,
             RpcEventType::LensUninstalled,
             RpcEventType::ModelDownloadStatus,
+            RpcEventType::ReindexProgress,
         ])
         .await?;
 
@@ -86,6 +90,7 @@ async fn setup_notification_handler(app: AppHandle) {
                         log::debug!("received event: {:?}", event);
                         let notif: Option<(String, String)> = match &event.event_type {
                             RpcEventType::ConnectionSyncFinished => Some(("Sync Completed".into(), event.payload)),
+                            RpcEventType::DatabaseVacuumed => Some(("Database Compacted".into(), event.payload)),
                             RpcEventType::LensInstalled => Some(("Lens Installed".into(), event.payload)),
                             RpcEventType::LensUninstalled => Some(("Lens Removed".into(), event.payload)),
                             RpcEventType::ModelDownloadStatus => {
@@ -116,6 +121,23 @@ async fn setup_notification_handler(app: AppHandle) {
                                     None
                                 }
                             }
+                            RpcEventType::ReindexProgress => {
+                                match serde_json::de::from_str::<ReindexProgressPayload>(&event.payload) {
+                                    Ok(ReindexProgressPayload::Finished { checked, restored, requeued }) => Some((
+                                        "Reindex Finished".into(),
+                                        format!("Checked {checked} documents, {restored} rebuilt & {requeued} queued to be crawled again")
+                                    )),
+                                    Ok(ReindexProgressPayload::Error { msg }) => Some((
+                                        "Reindex Failed".into(),
+                                        msg
+                                    )),
+                                    Ok(ReindexProgressPayload::InProgress { checked, total }) => {
+                                        log::info!("reindexing: {} / {}", checked, total);
+                                        None
+                                    }
+                                    Err(_) => None,
+                                }
+                            }
                         };
 
                         if let Some((title, blurb)) = notif {